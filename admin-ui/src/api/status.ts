@@ -48,6 +48,14 @@ export interface BackendStatus {
   connections: ConnectionStatus
   ingress: TrafficStatus
   egress: TrafficStatus
+  handshake_latency?: HandshakeLatencyStatus | null
+}
+
+export interface HandshakeLatencyStatus {
+  p50_ms: number
+  p95_ms: number
+  p99_ms: number
+  samples: number
 }
 
 export interface StatusSnapshot {
@@ -76,6 +84,8 @@ export interface StatusSnapshot {
     ports: Array<{ listen: number; backend: number }>
     directional_flow_entries: number
     flow_map_complete: boolean
+    packet_outcomes?: DataplaneCounter[]
+    errors?: DataplaneCounter[]
   }
   connections: ConnectionStatus
   ingress: TrafficStatus
@@ -84,6 +94,12 @@ export interface StatusSnapshot {
   backends: BackendStatus[]
 }
 
+export interface DataplaneCounter {
+  name: string
+  per_second: number
+  total: number
+}
+
 export async function fetchStatus(signal?: AbortSignal): Promise<StatusSnapshot> {
   const response = await fetch('/api/v1/status', {
     cache: 'no-store',
@@ -95,3 +111,50 @@ export async function fetchStatus(signal?: AbortSignal): Promise<StatusSnapshot>
   if (status.schema_version !== 1) throw new Error(`Unsupported status schema ${status.schema_version}`)
   return status
 }
+
+export type EventKind =
+  | 'backend_added'
+  | 'backend_removed'
+  | 'backend_drained'
+  | 'backend_disabled'
+  | 'backend_enabled'
+  | 'readiness_changed'
+  | 'xdp_attached'
+  | 'outlier_ejected'
+  | 'outlier_restored'
+  | 'priority_tier_changed'
+  | 'invariant_violation'
+  | 'config_reloaded'
+  | 'config_reload_failed'
+  | 'shutdown_started'
+export type EventSeverity = 'info' | 'warning'
+
+export interface LifecycleEvent {
+  id: number
+  at_unix_ms: number
+  kind: EventKind
+  severity: EventSeverity
+  message: string
+  backend: string | null
+}
+
+export interface EventPage {
+  schema_version: number
+  events: LifecycleEvent[]
+  has_more: boolean
+  oldest_id: number | null
+  newest_id: number | null
+}
+
+export async function fetchEvents(after: number | null, signal?: AbortSignal): Promise<EventPage> {
+  const query = after === null ? '' : `?after=${after}`
+  const response = await fetch(`/api/v1/events${query}`, {
+    cache: 'no-store',
+    headers: { Accept: 'application/json' },
+    signal,
+  })
+  if (!response.ok) throw new Error(`Events API returned HTTP ${response.status}`)
+  const page = (await response.json()) as EventPage
+  if (page.schema_version !== 1) throw new Error(`Unsupported events schema ${page.schema_version}`)
+  return page
+}
@@ -27,6 +27,22 @@ export const demoStatus: StatusSnapshot = {
     ports: [{ listen: 80, backend: 8080 }],
     directional_flow_entries: 368_438,
     flow_map_complete: true,
+    packet_outcomes: [
+      { name: 'pass', per_second: 412, total: 2_918_442 },
+      { name: 'drop', per_second: 3.4, total: 48_119 },
+      { name: 'reply', per_second: 0, total: 0 },
+      { name: 'forward', per_second: 318_420, total: 2_184_291_844_210 },
+      { name: 'aborted', per_second: 0, total: 0 },
+    ],
+    errors: [
+      { name: 'malformed_eth', per_second: 0, total: 12 },
+      { name: 'malformed_ip', per_second: 0.2, total: 1_904 },
+      { name: 'malformed_proto', per_second: 0, total: 388 },
+      { name: 'no_backends', per_second: 0, total: 0 },
+      { name: 'map_insert_failed', per_second: 0, total: 0 },
+      { name: 'no_ephemeral_ports', per_second: 0, total: 0 },
+      { name: 'orphaned_flow', per_second: 1.3, total: 41_207 },
+    ],
   },
   connections: {
     active: 184_219,
@@ -86,5 +102,13 @@ export const demoStatus: StatusSnapshot = {
       bytes_per_second: (backend.egressMbps * 1_000_000) / 8,
       bytes_total: backend.egressBytesTotal,
     },
+    handshake_latency: backend.handshakeLatency
+      ? {
+          p50_ms: backend.handshakeLatency.p50Ms,
+          p95_ms: backend.handshakeLatency.p95Ms,
+          p99_ms: backend.handshakeLatency.p99Ms,
+          samples: backend.handshakeLatency.samples,
+        }
+      : null,
   })),
 }
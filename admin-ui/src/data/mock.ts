@@ -47,10 +47,22 @@ export const backends: Backend[] = definitions.map((definition, index) => {
     closedTotal: 8170000 + index * 519000,
     ingressBytesTotal: 1490000000000 + index * 91000000000,
     egressBytesTotal: 1010000000000 + index * 72000000000,
+    handshakeLatency:
+      state === 'draining'
+        ? null
+        : {
+            p50Ms: Number((0.21 + (index % 4) * 0.03).toFixed(2)),
+            p95Ms: Number((0.48 + (index % 5) * 0.07).toFixed(2)),
+            p99Ms: Number((1.12 + (index % 3) * 0.41).toFixed(2)),
+            samples: 131_000 + ((index * 17_483) % 62_000),
+          },
     ingressHistory: wave(ingress, 68, index * 0.47),
     egressHistory: wave(egress, 51, index * 0.39),
     openedHistory: wave(state === 'draining' ? 0 : 2180 + ((index * 293) % 1040), 270, index * 0.31),
     closedHistory: wave(closed, 240, index * 0.55),
+    handshakeP50History: state === 'draining' ? [] : wave(0.21 + (index % 4) * 0.03, 0.02, index * 0.43),
+    handshakeP95History: state === 'draining' ? [] : wave(0.48 + (index % 5) * 0.07, 0.06, index * 0.43),
+    handshakeP99History: state === 'draining' ? [] : wave(1.12 + (index % 3) * 0.41, 0.18, index * 0.43),
   }
 })
 
@@ -31,6 +31,8 @@ export const unavailableStatus: StatusSnapshot = {
     ports: [],
     directional_flow_entries: 0,
     flow_map_complete: false,
+    packet_outcomes: [],
+    errors: [],
   },
   connections: {
     active: 0,
@@ -0,0 +1,90 @@
+import { computed, readonly, ref } from 'vue'
+import { fetchEvents } from '../api/status'
+import type { LifecycleEvent } from '../api/status'
+
+const MAX_EVENTS = 500
+const EVENT_POLL_INTERVAL_MS = 5_000
+const demoMode = import.meta.env.MODE === 'demo'
+
+// Newest first.
+const events = ref<LifecycleEvent[]>([])
+const error = ref<string | null>(null)
+const loaded = ref(false)
+const polling = ref(false)
+
+let cursor: number | null = null
+let timer: number | undefined
+let controller: AbortController | undefined
+
+const poll = async () => {
+  if (controller) return
+  const ownController = new AbortController()
+  controller = ownController
+  try {
+    const page = await fetchEvents(cursor, ownController.signal)
+    if (ownController.signal.aborted || !polling.value) return
+    // A restarted instance numbers its events from 1 again.
+    if (cursor !== null && page.newest_id !== null && page.newest_id < cursor) {
+      cursor = null
+      events.value = []
+      return
+    }
+    if (page.events.length > 0) {
+      cursor = page.events[page.events.length - 1].id
+      events.value = [...page.events].reverse().concat(events.value).slice(0, MAX_EVENTS)
+    } else if (cursor === null && page.newest_id !== null) {
+      cursor = page.newest_id
+    }
+    error.value = null
+    loaded.value = true
+  } catch (cause) {
+    if (ownController.signal.aborted) return
+    error.value = cause instanceof Error ? cause.message : 'Events API request failed'
+  } finally {
+    if (controller === ownController) controller = undefined
+  }
+}
+
+const start = () => {
+  if (demoMode || polling.value) return
+  polling.value = true
+  void poll()
+  timer = window.setInterval(() => void poll(), EVENT_POLL_INTERVAL_MS)
+}
+
+const stop = () => {
+  polling.value = false
+  if (timer !== undefined) window.clearInterval(timer)
+  timer = undefined
+  controller?.abort()
+  controller = undefined
+}
+
+const recentEvents = computed(() => events.value.slice(0, 5))
+
+export const useEventsStore = () => ({
+  events: readonly(events),
+  recentEvents,
+  error: readonly(error),
+  loaded: readonly(loaded),
+  demoMode,
+  start,
+  stop,
+})
+
+export const eventKindLabel: Record<LifecycleEvent['kind'], string> = {
+  backend_added: 'Backend added',
+  backend_removed: 'Backend removed',
+  backend_drained: 'Backend drained',
+  backend_disabled: 'Backend disabled',
+  backend_enabled: 'Backend enabled',
+  readiness_changed: 'Readiness',
+  xdp_attached: 'XDP attached',
+  outlier_ejected: 'Outlier ejected',
+  outlier_restored: 'Outlier restored',
+  priority_tier_changed: 'Priority tier',
+  invariant_violation: 'Invariant violation',
+  config_reloaded: 'Config reloaded',
+  config_reload_failed: 'Config reload failed',
+  shutdown_started: 'Shutdown',
+}
@@ -47,6 +47,9 @@ interface BackendHistory {
   egressMbps: number[]
   openedPerSecond: number[]
   closedPerSecond: number[]
+  handshakeP50Ms: Array<number | null>
+  handshakeP95Ms: Array<number | null>
+  handshakeP99Ms: Array<number | null>
 }
 
 const backendHistories = reactive(new Map<string, BackendHistory>())
@@ -72,6 +75,9 @@ const appendBackendSamples = (status: StatusSnapshot) => {
         egressMbps: [],
         openedPerSecond: [],
         closedPerSecond: [],
+        handshakeP50Ms: [],
+        handshakeP95Ms: [],
+        handshakeP99Ms: [],
       }
       backendHistories.set(backend.address, values)
     }
@@ -79,6 +85,10 @@ const appendBackendSamples = (status: StatusSnapshot) => {
     appendNumber(values.egressMbps, backend.egress.megabits_per_second, MAX_BACKEND_HISTORY_POINTS)
     appendNumber(values.openedPerSecond, backend.connections.opened_per_second, MAX_BACKEND_HISTORY_POINTS)
     appendNumber(values.closedPerSecond, backend.connections.closed_per_second, MAX_BACKEND_HISTORY_POINTS)
+    const latency = backend.handshake_latency ?? null
+    appendResource(values.handshakeP50Ms, latency?.p50_ms ?? null, MAX_BACKEND_HISTORY_POINTS)
+    appendResource(values.handshakeP95Ms, latency?.p95_ms ?? null, MAX_BACKEND_HISTORY_POINTS)
+    appendResource(values.handshakeP99Ms, latency?.p99_ms ?? null, MAX_BACKEND_HISTORY_POINTS)
   }
 
   for (const address of backendHistories.keys()) {
@@ -115,6 +125,9 @@ const emptyHistory = (): BackendHistory => ({
   egressMbps: [],
   openedPerSecond: [],
   closedPerSecond: [],
+  handshakeP50Ms: [],
+  handshakeP95Ms: [],
+  handshakeP99Ms: [],
 })
 
 const liveBackend = (backend: StatusSnapshot['backends'][number]): Backend => {
@@ -137,10 +150,21 @@ const liveBackend = (backend: StatusSnapshot['backends'][number]): Backend => {
     closedTotal: backend.connections.closed_total,
     ingressBytesTotal: backend.ingress.bytes_total,
     egressBytesTotal: backend.egress.bytes_total,
+    handshakeLatency: backend.handshake_latency
+      ? {
+          p50Ms: backend.handshake_latency.p50_ms,
+          p95Ms: backend.handshake_latency.p95_ms,
+          p99Ms: backend.handshake_latency.p99_ms,
+          samples: backend.handshake_latency.samples,
+        }
+      : null,
     ingressHistory: values.ingressMbps,
     egressHistory: values.egressMbps,
     openedHistory: values.openedPerSecond,
     closedHistory: values.closedPerSecond,
+    handshakeP50History: values.handshakeP50Ms,
+    handshakeP95History: values.handshakeP95Ms,
+    handshakeP99History: values.handshakeP99Ms,
   }
 }
 
@@ -27,10 +27,21 @@ export interface Backend {
   closedTotal: number
   ingressBytesTotal: number
   egressBytesTotal: number
+  handshakeLatency: HandshakeLatency | null
   ingressHistory: number[]
   egressHistory: number[]
   openedHistory: number[]
   closedHistory: number[]
+  handshakeP50History: Array<number | null>
+  handshakeP95History: Array<number | null>
+  handshakeP99History: Array<number | null>
+}
+
+export interface HandshakeLatency {
+  p50Ms: number
+  p95Ms: number
+  p99Ms: number
+  samples: number
 }
 
 export interface ChartSeries {
@@ -7,6 +7,11 @@ test.beforeEach(async ({ page }) => {
       json: { ...demoStatus, sampled_at_unix_ms: Date.now(), sample_age_ms: 0 },
     }),
   )
+  await page.route('**/api/v1/events*', (route) =>
+    route.fulfill({
+      json: { schema_version: 1, events: [], has_more: false, oldest_id: null, newest_id: null },
+    }),
+  )
 })
 
 test('primary routes preserve the local-instance information hierarchy', async ({ page }) => {
@@ -59,7 +64,8 @@ test('backend pool supports pagination, filtering, sorting, and drill-down', asy
   await expect(page.getByRole('button', { name: 'Close backend details' })).toBeFocused()
   await expect(page.getByRole('button', { name: 'Close backend details' })).toBeVisible()
   await expect(drawer.getByRole('button', { name: /Close breakdown.*Coming soon/ })).toBeDisabled()
-  await expect(drawer.getByRole('button', { name: /Latency.*Coming soon/ })).toBeDisabled()
+  await expect(drawer.getByRole('button', { name: 'Latency' })).toBeEnabled()
+  await expect(drawer.locator('.detail-list dt').filter({ hasText: 'p95' })).toBeVisible()
   await expect(drawer.locator('.detail-list dt').filter({ hasText: 'Time in pool' })).toBeVisible()
   await expect(drawer.locator('.detail-list').getByText('1d 0h', { exact: true })).toBeVisible()
   await expect(drawer.getByText('Client ended connection')).toHaveCount(0)
@@ -157,6 +163,7 @@ test('connection lifecycle disables unavailable close attribution', async ({ pag
   await expect(page.getByText('Opened and closed volume share one scale.')).toBeVisible()
   await expect(page.getByRole('button', { name: /Close breakdown.*Coming soon/ })).toBeDisabled()
   await expect(page.getByText('Client ended connection')).toHaveCount(0)
+  await expect(page.locator('.latency-table tbody tr')).toHaveCount(11)
 
   const plot = page.locator('.connection-panel .u-over')
   const bounds = await plot.boundingBox()
@@ -210,6 +217,16 @@ test('diagnostics reports the actual XDP attachment mode', async ({ page }) => {
   await expect(dataplane.getByText('XDP attachment mode', { exact: true })).toBeVisible()
 })
 
+test('diagnostics groups dataplane failure counters', async ({ page }) => {
+  await page.goto('./diagnostics')
+
+  const failures = page.locator('.diagnostic-failures')
+  await expect(failures.getByText('Malformed packets', { exact: true })).toBeVisible()
+  await expect(failures.getByText(/^2,304 since load/)).toBeVisible()
+  await expect(failures.getByText('Port exhaustion', { exact: true })).toBeVisible()
+  await expect(failures.getByText('3.4/s dropped in total')).toBeVisible()
+})
+
 test('diagnostics tolerates status payloads from before attachment modes were reported', async ({ page }) => {
   await page.unroute('**/api/v1/status')
   await page.route('**/api/v1/status', (route) => {
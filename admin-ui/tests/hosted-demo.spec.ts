@@ -99,7 +99,7 @@ test('unfinished features remain empty and explicit in demo mode', async ({ page
 
   await page.goto('./connections')
   await expect(page.getByRole('button', { name: /Close breakdown.*Coming soon/ })).toBeDisabled()
-  await expect(page.getByText('Passive backend latency is not collected yet')).toBeVisible()
+  await expect(page.locator('.latency-table')).toContainText('bidder-api-7f8797d8f4-2kq9p')
   await expect(page.locator('.close-breakdown')).toHaveCount(0)
 
   await page.goto('./events')
@@ -114,9 +114,8 @@ test('unfinished features remain empty and explicit in demo mode', async ({ page
   await expect(drawer.getByText('Demo sample', { exact: true })).toBeVisible()
 
   await expect(drawer.getByRole('button', { name: /Close breakdown.*Coming soon/ })).toBeDisabled()
-  await expect(drawer.getByRole('button', { name: /Latency.*Coming soon/ })).toBeDisabled()
-  await expect(drawer.getByText('Passive TCP handshake latency is not collected yet')).toBeVisible()
-  await expect(drawer.locator('.latency-detail')).toHaveCount(0)
+  await expect(drawer.getByRole('button', { name: 'Latency' })).toBeEnabled()
+  await expect(drawer.locator('.detail-list dt').filter({ hasText: 'p99' })).toBeVisible()
   await expect(drawer.locator('.close-breakdown')).toHaveCount(0)
   await expect(drawer.locator('.uplot')).toBeVisible()
   await page.evaluate(
@@ -1,4 +1,5 @@
 use crate::config::routing::RoutingMode;
+use crate::consts::MAX_PORT_MAPPINGS;
 use crate::net::{IpVersion, Proto};
 use crate::types::PortMapping;
 
@@ -23,7 +24,7 @@ pub struct EbpfConfig {
     pub ip_ver: IpVersion,
     pub proto: Proto,
     pub shutdown: bool, // only state field.. do we want to split this out?
-    pub port_mappings: [PortMapping; 8],
+    pub port_mappings: [PortMapping; MAX_PORT_MAPPINGS],
 }
 
 impl EbpfConfig {
@@ -38,7 +39,7 @@ impl EbpfConfig {
             port_mappings: [PortMapping {
                 local_port: 0,
                 remote_port: 0,
-            }; 8],
+            }; MAX_PORT_MAPPINGS],
         }
     }
 }
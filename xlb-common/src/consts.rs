@@ -1,5 +1,24 @@
 /// Max number of supported backends at any given time
 pub const MAX_BACKENDS: u32 = 4096;
+/// Selection weight of a backend receiving its full round-robin share.
+/// Lower weights skip the backend for a proportional share of new flows.
+pub const MAX_BACKEND_WEIGHT: u16 = 1000;
 pub const MAX_ACTIVE_FLOWS: u32 = 1_000_000;
+/// Max number of listen-to-backend port mappings
+pub const MAX_PORT_MAPPINGS: usize = 8;
+/// Max number of distinct per-backend destination ports outside the
+/// configured port mappings
+pub const MAX_RETURN_PORTS: u32 = 256;
+/// Max number of load-balancer VIPs served besides the listen address
+pub const MAX_VIPS: u32 = 64;
+/// Max number of backends in the pool of one load-balancer VIP
+pub const MAX_VIP_BACKENDS: u32 = 64;
+/// Max number of backends with a handshake latency histogram; the least
+/// recently updated is evicted beyond this
+pub const MAX_HANDSHAKE_BACKENDS: u32 = 1024;
+/// Number of power-of-two microsecond buckets in a handshake latency
+/// histogram. The last bucket holds every handshake of 2^24 µs (~16.8 s) or
+/// longer.
+pub const HANDSHAKE_LATENCY_BUCKETS: usize = 26;
 
 pub const LOCALHOST_IP_U32: u32 = 0x7f000001;
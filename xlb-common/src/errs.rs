@@ -29,3 +29,54 @@ pub enum XlbErr {
     /// Unable to find available ephemeral port
     ErrNoEphemeralPorts,
 }
+
+impl XlbErr {
+    /// Number of variants, the length of the `DATAPLANE_ERRORS` map.
+    pub const COUNT: u32 = 12;
+
+    /// Every variant, ordered by discriminant.
+    pub const ALL: [XlbErr; Self::COUNT as usize] = [
+        Self::ErrParseHdrEth,
+        Self::ErrParseHdrIp,
+        Self::ErrParseHdrProto,
+        Self::ErrInvalidOp,
+        Self::ErrNotYetImpl,
+        Self::ErrInvalidIpVal,
+        Self::ErrUnexpectedSyn,
+        Self::ErrNoBackends,
+        Self::ErrFibLookupFailed,
+        Self::ErrOrphanedFlow,
+        Self::ErrMapInsertFailed,
+        Self::ErrNoEphemeralPorts,
+    ];
+
+    /// Stable name of this error in metrics and the status API.
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::ErrParseHdrEth => "malformed_eth",
+            Self::ErrParseHdrIp => "malformed_ip",
+            Self::ErrParseHdrProto => "malformed_proto",
+            Self::ErrInvalidOp => "invalid_op",
+            Self::ErrNotYetImpl => "not_yet_implemented",
+            Self::ErrInvalidIpVal => "invalid_ip",
+            Self::ErrUnexpectedSyn => "unexpected_syn",
+            Self::ErrNoBackends => "no_backends",
+            Self::ErrFibLookupFailed => "fib_lookup_failed",
+            Self::ErrOrphanedFlow => "orphaned_flow",
+            Self::ErrMapInsertFailed => "map_insert_failed",
+            Self::ErrNoEphemeralPorts => "no_ephemeral_ports",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::XlbErr;
+
+    #[test]
+    fn all_errors_are_ordered_by_discriminant() {
+        for (index, err) in XlbErr::ALL.iter().enumerate() {
+            assert_eq!(*err as usize, index, "{err:?}");
+        }
+    }
+}
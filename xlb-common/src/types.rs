@@ -1,3 +1,4 @@
+use crate::consts::{HANDSHAKE_LATENCY_BUCKETS, MAX_PORT_MAPPINGS};
 use crate::net::IpVersion;
 use serde::Deserialize;
 use strum::IntoStaticStr;
@@ -25,6 +26,21 @@ pub struct PortMapping {
 #[cfg(feature = "user")]
 unsafe impl aya::Pod for PortMapping {}
 
+/// A load-balancer VIP served besides the listen address, with its own
+/// port mappings and backend pool.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Vip {
+    /// Slot of the VIP's pool in the VIP backend array, which holds
+    /// [`MAX_VIP_BACKENDS`](crate::consts::MAX_VIP_BACKENDS) entries per slot
+    pub slot: u32,
+    /// Listen-to-backend port mappings; unused entries are zero
+    pub port_mappings: [PortMapping; MAX_PORT_MAPPINGS],
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for Vip {}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Backend {
@@ -40,6 +56,12 @@ pub struct Backend {
     pub src_iface_ifindex: u16,
     /// Aggregate count of live connections
     pub conns: u16,
+    /// Share of round-robin turns accepted for new flows, out of
+    /// [`MAX_BACKEND_WEIGHT`](crate::consts::MAX_BACKEND_WEIGHT)
+    pub weight: u16,
+    /// Destination port for each port mapping, by index. Zero uses the
+    /// mapping's `remote_port`.
+    pub ports: [u16; MAX_PORT_MAPPINGS],
     /// The ip protovol ver
     pub ip_ver: IpVersion,
 }
@@ -62,6 +84,112 @@ pub enum FlowDirection {
 #[cfg(feature = "user")]
 unsafe impl aya::Pod for FlowDirection {}
 
+/// Verdict the XDP program returned for a packet, indexing the
+/// `PACKET_OUTCOMES` map. Packets that fail with an
+/// [`XlbErr`](crate::XlbErr) are dropped and count as `Drop`.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketOutcome {
+    /// Handed to the kernel network stack
+    Pass,
+    Drop,
+    /// Answered out of the ingress interface
+    Reply,
+    /// Redirected toward a backend or client
+    Forward,
+    /// The program could not run, e.g. its config was missing
+    Aborted,
+}
+
+impl PacketOutcome {
+    /// Number of variants, the length of the `PACKET_OUTCOMES` map.
+    pub const COUNT: u32 = 5;
+
+    /// Every variant, ordered by discriminant.
+    pub const ALL: [PacketOutcome; Self::COUNT as usize] = [
+        Self::Pass,
+        Self::Drop,
+        Self::Reply,
+        Self::Forward,
+        Self::Aborted,
+    ];
+
+    /// Stable name of this outcome in metrics and the status API.
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Pass => "pass",
+            Self::Drop => "drop",
+            Self::Reply => "reply",
+            Self::Forward => "forward",
+            Self::Aborted => "aborted",
+        }
+    }
+}
+
+/// Count of backend TCP handshakes by duration, from the forwarded client
+/// SYN to the backend's first SYN-ACK. Bucket `0` holds handshakes under
+/// 1 µs and bucket `b` those of `2^(b-1)` up to `2^b` µs, except the last,
+/// which has no upper bound.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HandshakeLatency {
+    pub buckets: [u64; HANDSHAKE_LATENCY_BUCKETS],
+}
+
+impl HandshakeLatency {
+    /// Bucket of a handshake lasting `micros` microseconds.
+    ///
+    /// Branches on fixed shifts rather than `leading_zeros` so the eBPF
+    /// program needs no bit-counting instruction.
+    pub const fn bucket(micros: u64) -> usize {
+        let mut value = micros;
+        let mut bits = 0;
+        if value >= 1 << 32 {
+            value >>= 32;
+            bits += 32;
+        }
+        if value >= 1 << 16 {
+            value >>= 16;
+            bits += 16;
+        }
+        if value >= 1 << 8 {
+            value >>= 8;
+            bits += 8;
+        }
+        if value >= 1 << 4 {
+            value >>= 4;
+            bits += 4;
+        }
+        if value >= 1 << 2 {
+            value >>= 2;
+            bits += 2;
+        }
+        if value >= 1 << 1 {
+            value >>= 1;
+            bits += 1;
+        }
+        bits += value as usize;
+        if bits < HANDSHAKE_LATENCY_BUCKETS {
+            bits
+        } else {
+            HANDSHAKE_LATENCY_BUCKETS - 1
+        }
+    }
+
+    /// Exclusive upper bound of `bucket` in microseconds, or `None` for the
+    /// last bucket.
+    pub const fn upper_bound_micros(bucket: usize) -> Option<u64> {
+        if bucket + 1 < HANDSHAKE_LATENCY_BUCKETS {
+            Some(1 << bucket)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for HandshakeLatency {}
+
 /// A directional connection entry, which defines
 /// the verbatim rewrite recipe to properly
 /// reroute a packet. This does *not* record
@@ -151,18 +279,32 @@ pub struct Flow {
     pub pair_invalid: bool,
     /// Both directional entries have been installed and may be reused.
     pub pair_ready: bool,
-    /// Explicit bytes keep the following pair tag aligned without implicit,
-    /// potentially uninitialized padding.
-    #[doc(hidden)]
-    pub _reserved: [u8; 1],
+    /// The dataplane answered this side with a RST because the
+    /// backend's drain deadline passed. Also keeps the following pair
+    /// tag aligned without implicit, potentially uninitialized padding.
+    pub force_reset: bool,
     /// Generation shared by both directional entries of this flow pair.
     pub pair_tag: u32,
+    /// Sequence number following the last segment forwarded in this
+    /// direction, which the receiver expects next. Lets userspace craft
+    /// an in-window RST from this entry's rewrite recipe.
+    pub next_seq: u32,
+    /// Whether `next_seq` has been observed for this direction.
+    pub seq_known: bool,
+    /// The backend's first SYN-ACK was timed against `created_at_ns`, the
+    /// forwarded client SYN. Only set on ToClient entries; later SYN-ACKs
+    /// are retransmissions and are not timed again.
+    pub handshake_timed: bool,
+    /// Explicit tail bytes keep the entry size a multiple of its alignment
+    /// without implicit, potentially uninitialized padding.
+    #[doc(hidden)]
+    pub _reserved: [u8; 10],
 }
 
 #[cfg(feature = "user")]
 unsafe impl aya::Pod for Flow {}
 
-const _: [(); 160] = [(); core::mem::size_of::<Flow>()];
+const _: [(); 176] = [(); core::mem::size_of::<Flow>()];
 const _: [(); 16] = [(); core::mem::align_of::<Flow>()];
 
 /// Exact, fixed-layout identity for an IPv4 TCP flow direction.
@@ -219,11 +361,12 @@ const _: [(); 4] = [(); core::mem::align_of::<FlowKeyV4>()];
 
 #[cfg(test)]
 mod tests {
-    use super::{Flow, FlowDirection, FlowKeyV4};
+    use super::{Flow, FlowDirection, FlowKeyV4, HandshakeLatency, PacketOutcome};
+    use crate::consts::HANDSHAKE_LATENCY_BUCKETS;
 
     #[test]
     fn flow_has_padding_free_stable_layout() {
-        assert_eq!(core::mem::size_of::<Flow>(), 160);
+        assert_eq!(core::mem::size_of::<Flow>(), 176);
         assert_eq!(core::mem::align_of::<Flow>(), 16);
         assert_eq!(core::mem::offset_of!(Flow, client_ip), 0);
         assert_eq!(core::mem::offset_of!(Flow, backend_ip), 16);
@@ -247,8 +390,41 @@ mod tests {
         assert_eq!(core::mem::offset_of!(Flow, rst_is_src), 152);
         assert_eq!(core::mem::offset_of!(Flow, pair_invalid), 153);
         assert_eq!(core::mem::offset_of!(Flow, pair_ready), 154);
-        assert_eq!(core::mem::offset_of!(Flow, _reserved), 155);
+        assert_eq!(core::mem::offset_of!(Flow, force_reset), 155);
         assert_eq!(core::mem::offset_of!(Flow, pair_tag), 156);
+        assert_eq!(core::mem::offset_of!(Flow, next_seq), 160);
+        assert_eq!(core::mem::offset_of!(Flow, seq_known), 164);
+        assert_eq!(core::mem::offset_of!(Flow, handshake_timed), 165);
+        assert_eq!(core::mem::offset_of!(Flow, _reserved), 166);
+    }
+
+    #[test]
+    fn handshake_buckets_are_powers_of_two_microseconds() {
+        assert_eq!(HandshakeLatency::bucket(0), 0);
+        assert_eq!(HandshakeLatency::bucket(1), 1);
+        assert_eq!(HandshakeLatency::bucket(3), 2);
+        assert_eq!(HandshakeLatency::bucket(4), 3);
+        assert_eq!(HandshakeLatency::bucket(1_000), 10);
+        assert_eq!(
+            HandshakeLatency::bucket(u64::MAX),
+            HANDSHAKE_LATENCY_BUCKETS - 1
+        );
+        for bucket in 0..HANDSHAKE_LATENCY_BUCKETS - 1 {
+            let upper = HandshakeLatency::upper_bound_micros(bucket).expect("bounded bucket");
+            assert_eq!(HandshakeLatency::bucket(upper - 1), bucket);
+            assert_eq!(HandshakeLatency::bucket(upper), bucket + 1);
+        }
+        assert_eq!(
+            HandshakeLatency::upper_bound_micros(HANDSHAKE_LATENCY_BUCKETS - 1),
+            None
+        );
+    }
+
+    #[test]
+    fn packet_outcomes_are_ordered_by_discriminant() {
+        for (index, outcome) in PacketOutcome::ALL.iter().enumerate() {
+            assert_eq!(*outcome as usize, index, "{outcome:?}");
+        }
     }
 
     #[test]
@@ -1,13 +1,34 @@
 use crate::balancing::roundrobin;
+use aya_ebpf::macros::map;
 use aya_ebpf::maps::Array;
 use xlb_common::config::ebpf::Strategy;
+use xlb_common::consts;
 use xlb_common::types::Backend;
 
+/// Backend pools of the load-balancer VIPs, one run of
+/// `MAX_VIP_BACKENDS` entries per VIP slot
+#[map(name = "VIP_BACKENDS")]
+static VIP_BACKENDS: Array<Backend> =
+    Array::with_max_entries(consts::MAX_VIPS * consts::MAX_VIP_BACKENDS, 0);
+
+/// Select a backend from `pool`: 0 is the listen address's `backends`,
+/// any other pool is one past the slot of a load-balancer VIP.
 pub fn select_backend(
     strategy: &Strategy,
     backends: &'static Array<Backend>,
+    pool: u32,
 ) -> Option<&'static Backend> {
+    let (backends, first, len) = if pool == 0 {
+        (backends, 0, consts::MAX_BACKENDS)
+    } else {
+        (
+            &VIP_BACKENDS,
+            (pool - 1) * consts::MAX_VIP_BACKENDS,
+            consts::MAX_VIP_BACKENDS,
+        )
+    };
+
     match strategy {
-        Strategy::RoundRobin => roundrobin::select_backend(backends),
+        Strategy::RoundRobin => roundrobin::select_backend(backends, pool, first, len),
     }
 }
@@ -1,23 +1,37 @@
+use aya_ebpf::helpers::bpf_get_prandom_u32;
 use aya_ebpf::macros::map;
 use aya_ebpf::maps::Array;
 use xlb_common::consts;
 use xlb_common::types::Backend;
 
+/// Next round-robin position of each pool, relative to the pool's first entry
 #[map(name = "RR_COUNTER")]
-static RR_COUNTER: Array<u32> = Array::with_max_entries(1, 0);
+static RR_COUNTER: Array<u32> = Array::with_max_entries(1 + consts::MAX_VIPS, 0);
 
-pub fn select_backend(backends: &'static Array<Backend>) -> Option<&'static Backend> {
-    let start_idx = RR_COUNTER.get(0).map(|v| *v).unwrap_or(0);
+/// Select from the `len` entries of `backends` starting at `first`.
+pub fn select_backend(
+    backends: &'static Array<Backend>,
+    pool: u32,
+    first: u32,
+    len: u32,
+) -> Option<&'static Backend> {
+    let start_idx = RR_COUNTER.get(pool).copied().unwrap_or(0) % len;
+    // First live backend seen, used when every candidate declined its turn
+    // so a pool of only ramping backends still accepts new flows.
+    let mut fallback: Option<(u32, &'static Backend)> = None;
 
     // Search up to 64 backends starting from current position
     for offset in 0..64 {
-        let idx = (start_idx + offset) % consts::MAX_BACKENDS;
+        let idx = (start_idx + offset) % len;
 
-        if let Some(entry) = backends.get(idx) {
-            if entry.ip != 0 {
-                // Update counter for next selection
-                let next_idx = (idx + 1) % consts::MAX_BACKENDS;
-                let _ = RR_COUNTER.set(0, &next_idx, 0);
+        if let Some(entry) = backends.get(first + idx)
+            && entry.ip != 0
+        {
+            if fallback.is_none() {
+                fallback = Some((idx, entry));
+            }
+            if accepts_turn(entry) {
+                advance(pool, idx, len);
                 return Some(entry);
             }
         }
@@ -26,15 +40,40 @@ pub fn select_backend(backends: &'static Array<Backend>) -> Option<&'static Back
     // Nothing found in range starting from start_idx, try from beginning if we didn't start there
     if start_idx != 0 {
         for idx in 0..64 {
-            if let Some(entry) = backends.get(idx) {
-                if entry.ip != 0 {
-                    let next_idx = (idx + 1) % consts::MAX_BACKENDS;
-                    let _ = RR_COUNTER.set(0, &next_idx, 0);
+            if let Some(entry) = backends.get(first + idx)
+                && entry.ip != 0
+            {
+                if fallback.is_none() {
+                    fallback = Some((idx, entry));
+                }
+                if accepts_turn(entry) {
+                    advance(pool, idx, len);
                     return Some(entry);
                 }
             }
         }
     }
 
-    None
+    let (idx, entry) = fallback?;
+    advance(pool, idx, len);
+    Some(entry)
+}
+
+/// Update counter for next selection
+#[inline(always)]
+fn advance(pool: u32, idx: u32, len: u32) {
+    let next_idx = (idx + 1) % len;
+    let _ = RR_COUNTER.set(pool, &next_idx, 0);
+}
+
+/// Whether a backend takes its round-robin turn, with probability
+/// proportional to its weight (e.g. while slow-start ramps it up).
+#[inline(always)]
+fn accepts_turn(backend: &Backend) -> bool {
+    if backend.weight >= consts::MAX_BACKEND_WEIGHT {
+        return true;
+    }
+
+    let draw = unsafe { bpf_get_prandom_u32() } % u32::from(consts::MAX_BACKEND_WEIGHT);
+    draw < u32::from(backend.weight)
 }
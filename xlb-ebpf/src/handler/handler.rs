@@ -32,7 +32,7 @@ impl PacketHandler {
         flow_map: &'static HashMap<FlowKeyV4, Flow>,
         shutdown: bool,
     ) -> Result<PacketEvent, XlbErr> {
-        let (direction, port_map) = match utils::should_process_packet(config, packet) {
+        let (direction, listener) = match utils::should_process_packet(config, packet) {
             Some(result) => result,
             None => return Ok(PacketEvent::Pass),
         };
@@ -54,11 +54,15 @@ impl PacketHandler {
                     backends,
                     flow_map,
                     &config.strategy,
-                    port_map.remote_port,
+                    &listener,
                 )? {
                     TcpOutcome::Pass => Ok(PacketEvent::Pass),
                     TcpOutcome::Drop => Ok(PacketEvent::Drop),
                     TcpOutcome::Reply => Ok(PacketEvent::Reply),
+                    TcpOutcome::Reset => {
+                        packet.rst()?;
+                        Ok(PacketEvent::Reply)
+                    }
                     TcpOutcome::Forward(flow) => {
                         packet.reroute(
                             &MacAddr::new(flow.src_mac),
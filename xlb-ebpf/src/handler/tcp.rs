@@ -1,15 +1,29 @@
-use crate::handler::types::{PacketFlow, TcpOutcome};
+use crate::handler::types::{Listener, PacketFlow, TcpOutcome};
 use crate::handler::utils;
 use crate::net::packet::Packet;
 use crate::net::types::ProtoHeader;
 use crate::{packet_log_debug, packet_log_trace};
-use aya_ebpf::maps::{Array, HashMap};
+use aya_ebpf::bindings::BPF_NOEXIST;
+use aya_ebpf::macros::map;
+use aya_ebpf::maps::{Array, HashMap, LruPerCpuHashMap, PerCpuArray};
 use xlb_common::XlbErr;
 use xlb_common::config::ebpf::Strategy;
-use xlb_common::types::{Backend, Flow, FlowDirection, FlowKeyV4};
+use xlb_common::consts::MAX_HANDSHAKE_BACKENDS;
+use xlb_common::types::{Backend, Flow, FlowDirection, FlowKeyV4, HandshakeLatency};
 
+mod force_close;
 mod syn;
 
+/// Per-CPU handshake latency histogram of each IPv4 backend
+#[map(name = "HANDSHAKE_LATENCY")]
+static HANDSHAKE_LATENCY: LruPerCpuHashMap<u32, HandshakeLatency> =
+    LruPerCpuHashMap::with_max_entries(MAX_HANDSHAKE_BACKENDS, 0);
+
+/// Empty histogram a backend's first handshake is inserted from. It is too
+/// large for the BPF stack; the kernel zeroes the entry and nothing writes it.
+#[map(name = "HANDSHAKE_LATENCY_EMPTY")]
+static HANDSHAKE_LATENCY_EMPTY: PerCpuArray<HandshakeLatency> = PerCpuArray::with_max_entries(1, 0);
+
 #[derive(Clone, Copy)]
 enum CloseKind {
     Fin,
@@ -28,15 +42,15 @@ const fn is_new_client_syn(syn: bool, ack: bool, direction: FlowDirection) -> bo
 /// - `direction`: Detected [`FlowDirection`] for this packet.
 /// - `backends`: Backends available to the selection strategy.
 /// - `strategy`: Configured backend selection strategy.
-/// - `port_map_dest`: Backend destination port; for example, client-facing
-///   port 80 may map to backend port 8080.
+/// - `listener`: Matched backend pool and port mapping; for example,
+///   client-facing port 80 may map to backend port 8080.
 pub fn handle_tcp_packet(
     packet: &mut Packet,
     direction: &FlowDirection,
     backends: &'static Array<Backend>,
     flow_map: &'static HashMap<FlowKeyV4, Flow>,
     strategy: &Strategy,
-    port_map_dest: u16,
+    listener: &Listener,
 ) -> Result<TcpOutcome, XlbErr> {
     let tcp = match packet.proto_hdr() {
         ProtoHeader::Tcp(tcp) => tcp,
@@ -54,8 +68,14 @@ pub fn handle_tcp_packet(
         return existing_flow(packet, direction, flow_map);
     }
 
+    // Checked before the SYN path so that a client reusing the tuple of a
+    // force-closed flow is reset rather than handed the closed pair.
+    if force_close::reset_requested(packet, direction, flow_map) {
+        return Ok(TcpOutcome::Reset);
+    }
+
     if is_new_client_syn(tcp_syn, tcp_ack, *direction) {
-        return syn::handle_syn(packet, backends, flow_map, strategy, port_map_dest);
+        return syn::handle_syn(packet, backends, flow_map, strategy, listener);
     }
 
     if tcp_fin {
@@ -168,9 +188,12 @@ pub(super) fn existing_flow(
 
     packet_log_trace!(packet, "Recognized flow");
 
+    let now_ns = utils::monotonic_time_ns();
     flow.bytes_transfer += packet.size();
     flow.packets_transfer += 1;
-    flow.last_seen_ns = utils::monotonic_time_ns();
+    flow.last_seen_ns = now_ns;
+    record_next_seq(flow, packet);
+    record_handshake(flow, packet, now_ns);
 
     Ok(TcpOutcome::Forward(PacketFlow {
         iface: utils::flow_to_iface(flow),
@@ -183,9 +206,68 @@ pub(super) fn existing_flow(
     }))
 }
 
+/// Remember the receiver's next expected sequence number so userspace can
+/// reset the connection at shutdown. Retransmitted and reordered segments
+/// never move it backwards.
+#[inline(always)]
+fn record_next_seq(flow: &mut Flow, packet: &Packet) {
+    if let Some(next_seq) = packet.tcp_next_seq()
+        && (!flow.seq_known || seq_after(next_seq, flow.next_seq))
+    {
+        flow.next_seq = next_seq;
+        flow.seq_known = true;
+    }
+}
+
+/// Time the backend's first SYN-ACK against the forwarded client SYN, which
+/// created the flow pair. Client SYN retransmissions reuse the pair and
+/// never move its creation time; SYN-ACK retransmissions find the flow
+/// already timed.
+#[inline(always)]
+fn record_handshake(flow: &mut Flow, packet: &Packet, now_ns: u64) {
+    if flow.handshake_timed || flow.direction != FlowDirection::ToClient {
+        return;
+    }
+    let ProtoHeader::Tcp(tcp) = packet.proto_hdr() else {
+        return;
+    };
+    if !tcp.is_syn() || !tcp.is_ack() {
+        return;
+    }
+    flow.handshake_timed = true;
+
+    let backend = flow.backend_ip as u32;
+    let bucket = HandshakeLatency::bucket(now_ns.saturating_sub(flow.created_at_ns) / 1_000);
+    let histogram_ptr = match HANDSHAKE_LATENCY.get_ptr_mut(backend) {
+        Some(ptr) => ptr,
+        None => {
+            let Some(empty) = HANDSHAKE_LATENCY_EMPTY.get(0) else {
+                return;
+            };
+            // Losing a racing insert on another CPU is harmless: both
+            // entries start empty and the lookup below finds the winner.
+            let _ = HANDSHAKE_LATENCY.insert(backend, empty, BPF_NOEXIST as u64);
+            let Some(ptr) = HANDSHAKE_LATENCY.get_ptr_mut(backend) else {
+                return;
+            };
+            ptr
+        }
+    };
+    let histogram = unsafe { &mut *histogram_ptr };
+    if let Some(count) = histogram.buckets.get_mut(bucket) {
+        *count = count.wrapping_add(1);
+    }
+}
+
+/// Serial-number comparison of TCP sequence numbers (RFC 1982).
+#[inline(always)]
+const fn seq_after(seq: u32, other: u32) -> bool {
+    (seq.wrapping_sub(other) as i32) > 0
+}
+
 #[cfg(test)]
 mod tests {
-    use super::is_new_client_syn;
+    use super::{is_new_client_syn, seq_after};
     use xlb_common::types::FlowDirection;
 
     #[test]
@@ -195,4 +277,13 @@ mod tests {
         assert!(!is_new_client_syn(true, false, FlowDirection::ToClient));
         assert!(!is_new_client_syn(false, false, FlowDirection::ToServer));
     }
+
+    #[test]
+    fn sequence_comparison_handles_wraparound() {
+        assert!(seq_after(2, 1));
+        assert!(!seq_after(1, 2));
+        assert!(!seq_after(7, 7));
+        assert!(seq_after(5, u32::MAX - 5));
+        assert!(!seq_after(u32::MAX - 5, 5));
+    }
 }
@@ -0,0 +1,54 @@
+use crate::handler::utils;
+use crate::net::packet::Packet;
+use crate::packet_log_debug;
+use aya_ebpf::macros::map;
+use aya_ebpf::maps::HashMap;
+use xlb_common::consts;
+use xlb_common::types::{Flow, FlowDirection, FlowKeyV4};
+
+/// Backends whose drain deadline has passed, keyed by packed backend IP.
+/// Userspace inserts a backend when its deadline expires and removes it once
+/// the drain ends.
+#[map(name = "FORCE_CLOSE")]
+static FORCE_CLOSE: HashMap<u128, u8> = HashMap::with_max_entries(consts::MAX_BACKENDS, 0);
+
+/// Whether this packet belongs to a flow of a force-closed backend and must
+/// be answered with a RST instead of forwarded.
+///
+/// The first reset marks the whole pair reset, so a client SYN cannot reuse
+/// it and userspace cleanup reclaims it. Until then each side is answered
+/// with a RST on its next packet.
+#[inline(always)]
+pub fn reset_requested(
+    packet: &Packet,
+    direction: &FlowDirection,
+    flow_map: &'static HashMap<FlowKeyV4, Flow>,
+) -> bool {
+    let flow_key = utils::get_flow_key(packet, direction);
+    let Some(flow_ptr) = flow_map.get_ptr_mut(flow_key) else {
+        return false;
+    };
+    let flow = unsafe { &mut *flow_ptr };
+
+    if unsafe { FORCE_CLOSE.get(flow.backend_ip) }.is_none() {
+        return false;
+    }
+
+    packet_log_debug!(packet, "Backend drain deadline passed, sending RST");
+    flow.force_reset = true;
+    if flow.rst_ns > 0 {
+        return true;
+    }
+
+    let now_ns = utils::monotonic_time_ns();
+    flow.rst_ns = now_ns;
+    if let Some(counter_ptr) = flow_map.get_ptr_mut(flow.counter_flow_key) {
+        let counter = unsafe { &mut *counter_ptr };
+        // A different tag belongs to a newer pair on the same tuple.
+        if counter.pair_tag == flow.pair_tag {
+            counter.rst_ns = now_ns;
+        }
+    }
+
+    true
+}
@@ -1,7 +1,7 @@
 use super::existing_flow;
 use crate::balancing;
 use crate::handler::iface::Iface;
-use crate::handler::types::{PacketFlow, TcpOutcome};
+use crate::handler::types::{Listener, PacketFlow, TcpOutcome};
 use crate::handler::utils;
 use crate::net::packet::Packet;
 use crate::packet_log_debug;
@@ -57,7 +57,7 @@ pub fn handle_syn(
     backends: &'static Array<Backend>,
     flow_map: &'static HashMap<FlowKeyV4, Flow>,
     strategy: &Strategy,
-    port_map_dest: u16,
+    listener: &Listener,
 ) -> Result<TcpOutcome, XlbErr> {
     match prepare_existing_syn(packet, flow_map) {
         ExistingSyn::Reuse => {
@@ -68,9 +68,12 @@ pub fn handle_syn(
     }
 
     packet_log_debug!(packet, "New TCP SYN");
-    let backend = balancing::select_backend(strategy, backends).ok_or(XlbErr::ErrNoBackends)?;
+    let backend = balancing::select_backend(strategy, backends, listener.pool)
+        .ok_or(XlbErr::ErrNoBackends)?;
 
-    match install_flow_pair(packet, backend, port_map_dest, flow_map) {
+    let dest_port = utils::backend_dest_port(backend, listener.mapping, listener.remote_port);
+
+    match install_flow_pair(packet, backend, dest_port, flow_map) {
         Ok(flow) => Ok(TcpOutcome::Forward(flow)),
         Err(InstallError::ForwardConflict) => match prepare_existing_syn(packet, flow_map) {
             ExistingSyn::Reuse => existing_flow(packet, &FlowDirection::ToServer, flow_map),
@@ -377,6 +380,7 @@ fn new_flow_to_server(
     now_ns: u64,
     pair_tag: u32,
 ) -> Flow {
+    let next_seq = packet.tcp_next_seq();
     Flow {
         direction: FlowDirection::ToServer,
         client_ip: packet.src_ip(),
@@ -401,7 +405,11 @@ fn new_flow_to_server(
         pair_ready: false,
         pair_tag,
         counter_flow_key: *client_flow_key,
-        _reserved: [0; 1],
+        force_reset: false,
+        next_seq: next_seq.unwrap_or_default(),
+        seq_known: next_seq.is_some(),
+        handshake_timed: false,
+        _reserved: [0; 10],
     }
 }
 
@@ -437,7 +445,11 @@ fn new_flow_to_client(
         pair_ready: true,
         pair_tag,
         counter_flow_key,
-        _reserved: [0; 1],
+        force_reset: false,
+        next_seq: 0,
+        seq_known: false,
+        handshake_timed: false,
+        _reserved: [0; 10],
     }
 }
 
@@ -489,7 +501,11 @@ mod tests {
             rst_is_src: false,
             pair_invalid: false,
             pair_ready: true,
-            _reserved: [0; 1],
+            force_reset: false,
+            next_seq: 0,
+            seq_known: false,
+            handshake_timed: false,
+            _reserved: [0; 10],
             pair_tag: 7,
         }
     }
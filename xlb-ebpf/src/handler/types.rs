@@ -19,6 +19,20 @@ pub enum TcpOutcome {
     Drop,
     /// Transmit the packet back through its ingress interface.
     Reply,
+    /// Answer the sender with a TCP reset through its ingress interface.
+    Reset,
     /// Rewrite and redirect the packet using the stored flow recipe.
     Forward(PacketFlow),
 }
+
+/// Listener matched by a packet toward a load-balanced service.
+#[derive(Clone, Copy)]
+pub struct Listener {
+    /// Backend pool of new flows: 0 for the listen address, otherwise one
+    /// past the slot of a load-balancer VIP.
+    pub pool: u32,
+    /// Index of the matched port mapping.
+    pub mapping: usize,
+    /// Backend port configured for the matched mapping.
+    pub remote_port: u16,
+}
@@ -1,53 +1,123 @@
 use crate::handler::iface::Iface;
+use crate::handler::types::Listener;
 use crate::net::packet::Packet;
 use aya_ebpf::helpers::bpf_ktime_get_ns;
+use aya_ebpf::macros::map;
+use aya_ebpf::maps::HashMap;
 use xlb_common::config::ebpf::EbpfConfig;
-use xlb_common::types::{Flow, FlowDirection, FlowKeyV4, PortMapping};
+use xlb_common::consts;
+use xlb_common::types::{Backend, Flow, FlowDirection, FlowKeyV4, Vip};
+
+/// Backend ports that may differ from every mapping's `remote_port`, such as
+/// ports resolved per backend from Kubernetes EndpointSlices or the port of
+/// open connections whose mapping a reload changed. Replies from these
+/// source ports are classified as traffic toward the client.
+#[map(name = "RETURN_PORTS")]
+static RETURN_PORTS: HashMap<u16, u8> = HashMap::with_max_entries(consts::MAX_RETURN_PORTS, 0);
+
+/// Load-balancer VIPs served besides the listen address, keyed by IPv4
+/// address. Userspace programs one per allocated Kubernetes Service.
+#[map(name = "VIPS")]
+static VIPS: HashMap<u32, Vip> = HashMap::with_max_entries(consts::MAX_VIPS, 0);
 
 /// Checks whether a packet is of interest to this XDP instance.
 /// We only care about packets that match our IP version, protocol, and port mappings.
 /// For incoming traffic (ToServer), we also verify it's actually destined for our configured
-/// listen IP - otherwise it's for some other service and we pass it through
+/// listen IP or one of the load-balancer VIPs - otherwise it's for some other service and we pass
+/// it through.
 pub fn should_process_packet(
     config: &EbpfConfig,
     packet: &Packet,
-) -> Option<(FlowDirection, PortMapping)> {
+) -> Option<(FlowDirection, Listener)> {
     if packet.ip_version() != config.ip_ver || packet.proto() != config.proto {
         return None;
     }
 
-    let (direction, port_map) = get_direction_port_map(config, packet)?;
+    if packet.dst_ip() != config.ip_addr
+        && let Some(listener) = vip_listener(packet)
+    {
+        return Some((FlowDirection::ToServer, listener));
+    }
+
+    let (direction, listener) = get_direction_port_map(config, packet)?;
 
     if direction == FlowDirection::ToServer && packet.dst_ip() != config.ip_addr {
         return None;
     }
 
-    Some((direction, port_map))
+    Some((direction, listener))
 }
 
 #[inline(always)]
 fn get_direction_port_map(
     config: &'_ EbpfConfig,
     packet: &Packet,
-) -> Option<(FlowDirection, PortMapping)> {
+) -> Option<(FlowDirection, Listener)> {
     let src_port = packet.src_port();
     let dst_port = packet.dst_port();
 
     for i in 0..config.port_mappings.len() {
         let port_map = config.port_mappings[i];
+        let listener = Listener {
+            pool: 0,
+            mapping: i,
+            remote_port: port_map.remote_port,
+        };
 
         if dst_port == port_map.local_port {
-            return Some((FlowDirection::ToServer, port_map));
+            return Some((FlowDirection::ToServer, listener));
         }
 
         if src_port == port_map.remote_port {
-            return Some((FlowDirection::ToClient, port_map));
+            return Some((FlowDirection::ToClient, listener));
         }
     }
 
+    // Replies never consult the listener; the flow entry holds the rewrite.
+    if unsafe { RETURN_PORTS.get(src_port) }.is_some() {
+        return Some((
+            FlowDirection::ToClient,
+            Listener {
+                pool: 0,
+                mapping: 0,
+                remote_port: src_port,
+            },
+        ));
+    }
+
     None
 }
 
+/// Match a packet toward a load-balancer VIP against the VIP's port mappings.
+#[inline(always)]
+fn vip_listener(packet: &Packet) -> Option<Listener> {
+    let vip = unsafe { VIPS.get(packet.dst_ip() as u32) }?;
+    let dst_port = packet.dst_port();
+
+    for i in 0..vip.port_mappings.len() {
+        let port_map = vip.port_mappings[i];
+        if port_map.local_port != 0 && dst_port == port_map.local_port {
+            return Some(Listener {
+                pool: vip.slot + 1,
+                mapping: i,
+                remote_port: port_map.remote_port,
+            });
+        }
+    }
+
+    None
+}
+
+/// Destination port of a new flow to `backend` through port mapping
+/// `index`: the port discovered for the backend, else `remote_port`.
+#[inline(always)]
+pub fn backend_dest_port(backend: &Backend, index: usize, remote_port: u16) -> u16 {
+    match backend.ports.get(index) {
+        Some(port) if *port != 0 => *port,
+        _ => remote_port,
+    }
+}
+
 /// Build the exact IPv4/TCP flow key from an unmodified packet.
 #[inline(always)]
 pub fn get_flow_key(packet: &Packet, direction: &FlowDirection) -> FlowKeyV4 {
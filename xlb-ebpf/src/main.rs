@@ -12,7 +12,7 @@ use crate::handler::{PacketEvent, PacketHandler};
 use crate::net::packet::Packet;
 use aya_ebpf::helpers::bpf_redirect;
 use aya_ebpf::macros::map;
-use aya_ebpf::maps::{Array, HashMap};
+use aya_ebpf::maps::{Array, HashMap, PerCpuArray};
 use aya_ebpf::{bindings::xdp_action, macros::xdp, programs::XdpContext};
 use aya_log_ebpf::warn;
 #[cfg(feature = "verbose-logs")]
@@ -20,7 +20,7 @@ use aya_log_ebpf::{debug, trace};
 use xlb_common::XlbErr;
 use xlb_common::config::ebpf::EbpfConfig;
 use xlb_common::consts;
-use xlb_common::types::{Backend, Flow, FlowKeyV4};
+use xlb_common::types::{Backend, Flow, FlowKeyV4, PacketOutcome};
 
 /// Shared global state config stored in a map for runtime updates
 #[map(name = "CONFIG")]
@@ -38,21 +38,58 @@ static mut FLOW_MAP: HashMap<FlowKeyV4, Flow> =
 #[map(name = "SHUTDOWN")]
 static SHUTDOWN: Array<u8> = Array::with_max_entries(1, 0);
 
+/// Per-CPU count of packets by [`PacketOutcome`]
+#[map(name = "PACKET_OUTCOMES")]
+static PACKET_OUTCOMES: PerCpuArray<u64> = PerCpuArray::with_max_entries(PacketOutcome::COUNT, 0);
+
+/// Per-CPU count of packets that failed by [`XlbErr`]
+#[map(name = "DATAPLANE_ERRORS")]
+static DATAPLANE_ERRORS: PerCpuArray<u64> = PerCpuArray::with_max_entries(XlbErr::COUNT, 0);
+
+#[inline(always)]
+fn increment(counters: &PerCpuArray<u64>, index: u32) {
+    if let Some(count_ptr) = counters.get_ptr_mut(index) {
+        let count = unsafe { &mut *count_ptr };
+        *count = count.wrapping_add(1);
+    }
+}
+
+#[inline(always)]
+fn record_error(err: XlbErr) {
+    increment(&DATAPLANE_ERRORS, err as u32);
+}
+
 #[xdp]
 pub fn xlb(ctx: XdpContext) -> u32 {
-    let mut packet = match Packet::new(&ctx) {
+    let action = handle(&ctx);
+    let outcome = match action {
+        xdp_action::XDP_PASS => PacketOutcome::Pass,
+        xdp_action::XDP_DROP => PacketOutcome::Drop,
+        xdp_action::XDP_TX => PacketOutcome::Reply,
+        xdp_action::XDP_REDIRECT => PacketOutcome::Forward,
+        _ => PacketOutcome::Aborted,
+    };
+    increment(&PACKET_OUTCOMES, outcome as u32);
+
+    action
+}
+
+#[inline(always)]
+fn handle(ctx: &XdpContext) -> u32 {
+    let mut packet = match Packet::new(ctx) {
         Ok(Some(packet)) => packet,
         Ok(None) => {
             #[cfg(feature = "verbose-logs")]
-            trace!(&ctx, "Valid packet but misc protos, passing");
+            trace!(ctx, "Valid packet but misc protos, passing");
 
             return xdp_action::XDP_PASS;
         }
-        Err(_err) => {
+        Err(err) => {
+            record_error(err);
             #[cfg(feature = "verbose-logs")]
             {
-                let err_str: &'static str = _err.into();
-                debug!(&ctx, "Dropping malformed packet: {}", err_str);
+                let err_str: &'static str = err.into();
+                debug!(ctx, "Dropping malformed packet: {}", err_str);
             }
 
             return xdp_action::XDP_DROP;
@@ -62,7 +99,7 @@ pub fn xlb(ctx: XdpContext) -> u32 {
     let config = match CONFIG.get_ptr(0) {
         Some(ptr) => unsafe { &*ptr },
         None => {
-            warn!(&ctx, "CONFIG map is empty");
+            warn!(ctx, "CONFIG map is empty");
             return xdp_action::XDP_ABORTED;
         }
     };
@@ -96,6 +133,7 @@ pub fn xlb(ctx: XdpContext) -> u32 {
                 }
             },
             Err(XlbErr::ErrOrphanedFlow) => {
+                record_error(XlbErr::ErrOrphanedFlow);
                 // A flow may legitimately disappear after its idle timeout. Logging every
                 // subsequent packet at warn level can overwhelm the userspace log reader.
                 packet_log_debug!(packet, "Packet belongs to an expired flow");
@@ -103,6 +141,7 @@ pub fn xlb(ctx: XdpContext) -> u32 {
                 xdp_action::XDP_DROP
             }
             Err(xlb_err) => {
+                record_error(xlb_err);
                 let err_str: &'static str = xlb_err.into();
                 packet_log_warn!(packet, "Failed to handle packet: {}", err_str);
 
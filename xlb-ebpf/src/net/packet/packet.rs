@@ -155,6 +155,16 @@ impl<'a> Packet<'a> {
         }
     }
 
+    /// Sequence number following this TCP segment.
+    pub fn tcp_next_seq(&self) -> Option<u32> {
+        match (&self.ip_hdr, &self.proto_hdr) {
+            (IpHeader::Ipv4(ip), ProtoHeader::Tcp(tcp)) => {
+                Some(tcp.next_seq(ip.total_len(), ip.header_len_ihl()))
+            }
+            _ => None,
+        }
+    }
+
     /// Logs major packet details for debugging
     #[allow(dead_code)]
     pub fn dump(&self, label: &str) {
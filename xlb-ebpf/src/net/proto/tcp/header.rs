@@ -53,6 +53,13 @@ impl<'a> TcpHeader<'a> {
         self.hdr.rst() != 0
     }
 
+    /// Sequence number following this segment, i.e. the receiver's next
+    /// expected sequence number once the segment is accepted.
+    pub fn next_seq(&self, ip_total_len_bytes: u16, ip_hdr_len_bytes: u8) -> u32 {
+        let seg_len = calculate_segment_length(self.hdr, ip_total_len_bytes, ip_hdr_len_bytes);
+        u32::from_be_bytes(self.hdr.seq).wrapping_add(seg_len)
+    }
+
     /// Set both source and destination ports without updating checksum.
     ///
     /// Use this when you plan to fully recalculate the checksum afterwards.
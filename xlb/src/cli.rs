@@ -0,0 +1,551 @@
+use crate::build_provider;
+use crate::config::XlbConfig;
+use crate::health;
+use crate::status::{AdminAuth, AdminTls};
+use crate::system;
+use anyhow::{Context, Result, anyhow, bail};
+use schemars::schema_for;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration;
+use xlb_common::types::Backend;
+
+const DEFAULT_CONFIG_PATH: &str = "xlb.yaml";
+const DEFAULT_ADMIN_URL: &str = "http://127.0.0.1:9090";
+const ADMIN_PASSWORD_ENV: &str = "XLB_ADMIN_PASSWORD";
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const USAGE: &str = "\
+Usage: xlb [command] [options]
+
+Commands:
+  run [--config <path>]     Load the configuration and serve traffic (default)
+  validate [<path>]         Check a configuration and the routes to its backends
+                            without attaching XDP
+  status [--url <url>] [--user <name>] [--json]
+         [--ca-cert <path>] [--client-cert <path> --client-key <path>]
+                            Print the status of a running instance
+  schema                    Print the JSON schema of the configuration file
+  help                      Print this message
+
+The configuration path defaults to xlb.yaml. `status` reads the admin
+password from XLB_ADMIN_PASSWORD when the instance requires one. Over
+https://, it also trusts the PEM CAs in --ca-cert and presents the PEM
+certificate and key in --client-cert and --client-key.";
+
+/// What the `xlb` binary was asked to do.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Run {
+        config: PathBuf,
+    },
+    Validate {
+        config: PathBuf,
+    },
+    Status {
+        url: String,
+        user: String,
+        json: bool,
+        tls: StatusTls,
+    },
+    Schema,
+    Help,
+}
+
+/// TLS files `status` uses to reach an HTTPS admin API.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct StatusTls {
+    /// CA certificates trusted in addition to the system roots.
+    pub ca_cert: Option<PathBuf>,
+    /// Client certificate and key, presented to an `admin.tls.client_ca`
+    /// instance.
+    pub client: Option<(PathBuf, PathBuf)>,
+}
+
+impl Command {
+    /// Parse the arguments following the program name. No arguments runs
+    /// the load balancer with `xlb.yaml`, as the container image does.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut args = args.into_iter();
+        let command = match args.next().as_deref() {
+            None | Some("run") => {
+                let mut config = PathBuf::from(DEFAULT_CONFIG_PATH);
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--config" | "-c" => config = value(&mut args, &arg)?.into(),
+                        _ => return unexpected(&arg),
+                    }
+                }
+                Command::Run { config }
+            }
+            Some("validate") => {
+                let config = args.next().unwrap_or_else(|| DEFAULT_CONFIG_PATH.into());
+                if let Some(arg) = args.next() {
+                    return unexpected(&arg);
+                }
+                Command::Validate {
+                    config: config.into(),
+                }
+            }
+            Some("status") => {
+                let mut url = DEFAULT_ADMIN_URL.to_string();
+                let mut user = "admin".to_string();
+                let mut json = false;
+                let mut ca_cert = None;
+                let mut client_cert = None;
+                let mut client_key = None;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--url" => url = value(&mut args, &arg)?,
+                        "--user" => user = value(&mut args, &arg)?,
+                        "--json" => json = true,
+                        "--ca-cert" => ca_cert = Some(value(&mut args, &arg)?.into()),
+                        "--client-cert" => client_cert = Some(value(&mut args, &arg)?.into()),
+                        "--client-key" => client_key = Some(value(&mut args, &arg)?.into()),
+                        _ => return unexpected(&arg),
+                    }
+                }
+                let client = match (client_cert, client_key) {
+                    (Some(cert), Some(key)) => Some((cert, key)),
+                    (None, None) => None,
+                    _ => {
+                        bail!("`--client-cert` and `--client-key` must be used together\n\n{USAGE}")
+                    }
+                };
+                Command::Status {
+                    url,
+                    user,
+                    json,
+                    tls: StatusTls { ca_cert, client },
+                }
+            }
+            Some("schema") => Command::Schema,
+            Some("help" | "--help" | "-h") => Command::Help,
+            Some(command) => bail!("unknown command `{command}`\n\n{USAGE}"),
+        };
+        if let Some(arg) = args.next() {
+            return unexpected(&arg);
+        }
+        Ok(command)
+    }
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String> {
+    args.next()
+        .ok_or_else(|| anyhow!("`{flag}` requires a value\n\n{USAGE}"))
+}
+
+fn unexpected<T>(arg: &str) -> Result<T> {
+    bail!("unexpected argument `{arg}`\n\n{USAGE}")
+}
+
+/// Print the configuration schema that `xtask gendocs` renders into the
+/// reference documentation.
+pub fn schema() -> Result<()> {
+    let schema = serde_json::to_string_pretty(&schema_for!(XlbConfig))?;
+    println!("{schema}");
+    Ok(())
+}
+
+/// Check everything `run` checks before attaching XDP, then resolve a
+/// route to every backend the provider discovers.
+pub async fn validate(path: PathBuf) -> Result<()> {
+    let config = XlbConfig::load(path.clone())
+        .with_context(|| format!("Invalid configuration {}", path.display()))?;
+    let iface = system::get_listen_iface(&config.listen)?;
+    config.validate_listen_ip(iface.ip)?;
+    println!("listen    {} on {}", iface.ip, iface.name);
+
+    if let Some(auth) = &config.admin.auth {
+        AdminAuth::from_env(auth.username.clone())?;
+    }
+    if let Some(auth) = &config.admin.metrics.auth {
+        AdminAuth::metrics_from_env(auth.username.clone())?;
+    }
+    if let Some(tls) = &config.admin.tls {
+        AdminTls::load(tls).context("Failed to load admin TLS certificates")?;
+    }
+    if let Some(health_check) = &config.health_check {
+        config
+            .health_check_port()
+            .context("Health checks require a backend port")?;
+        health::probe_from_config(&health_check.probe)
+            .context("Failed to configure health checks")?;
+    }
+    if let Some(load_balancer) = &config.load_balancer {
+        load_balancer.address_ranges()?;
+    }
+
+    let (provider, kind) = build_provider(&config.provider, &mut None);
+    provider
+        .start()
+        .await
+        .context("Failed to start backend provider")?;
+    let hosts = provider.get_backends();
+    let provider_error = (!provider.is_healthy()).then(|| provider.last_error());
+    provider
+        .shutdown()
+        .await
+        .context("Failed to shutdown backend provider")?;
+    if let Some(error) = provider_error {
+        bail!(
+            "Backend provider {kind:?} is unhealthy: {}",
+            error.as_deref().unwrap_or("no error reported")
+        );
+    }
+    println!("provider  {kind:?}, {} backends", hosts.len());
+
+    let mut unreachable = 0;
+    for host in &hosts {
+        let mut backend = Backend::from(host);
+        match system::populate_backend_route(&mut backend).await {
+            Ok(()) => println!("route     {} ({}): ok", host.name, host.ip),
+            Err(err) => {
+                unreachable += 1;
+                println!("route     {} ({}): {err:#}", host.name, host.ip);
+            }
+        }
+    }
+    if unreachable > 0 {
+        bail!(
+            "{unreachable} of {} backends have no usable route",
+            hosts.len()
+        );
+    }
+    println!("Configuration {} is valid", path.display());
+    Ok(())
+}
+
+/// Fields of `/api/v1/status` the summary prints. Others are ignored so
+/// the command keeps working against newer instances.
+#[derive(Debug, Deserialize)]
+struct StatusSummary {
+    schema_version: u16,
+    service: String,
+    version: String,
+    lifecycle: String,
+    uptime_seconds: u64,
+    health: Condition,
+    readiness: Condition,
+    provider: ProviderSummary,
+    dataplane: DataplaneSummary,
+    connections: ConnectionSummary,
+    ingress: TrafficSummary,
+    egress: TrafficSummary,
+    backends: Vec<BackendSummary>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Condition {
+    reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProviderSummary {
+    kind: String,
+    healthy: bool,
+    last_error: Option<String>,
+    discovered_backends: usize,
+    routable_backends: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct DataplaneSummary {
+    listen_address: String,
+    listen_interface: String,
+    protocol: String,
+    routing_mode: String,
+    ports: Vec<PortSummary>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PortSummary {
+    listen: u16,
+    backend: u16,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConnectionSummary {
+    active: u32,
+    active_clients: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrafficSummary {
+    megabits_per_second: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BackendSummary {
+    name: String,
+    address: String,
+    available_for_new_connections: bool,
+    connections: ConnectionSummary,
+}
+
+/// Fetch `/api/v1/status` from a running instance and print it.
+pub async fn status(url: &str, user: &str, json: bool, tls: &StatusTls) -> Result<()> {
+    let endpoint = format!("{}/api/v1/status", url.trim_end_matches('/'));
+    let mut request = status_client(tls)?.get(&endpoint).timeout(STATUS_TIMEOUT);
+    if let Ok(password) = std::env::var(ADMIN_PASSWORD_ENV) {
+        request = request.basic_auth(user, Some(password));
+    }
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {endpoint}"))?;
+    let code = response.status();
+    let body = response
+        .text()
+        .await
+        .with_context(|| format!("Failed to read {endpoint}"))?;
+    if !code.is_success() {
+        bail!("{endpoint} returned {code}: {}", body.trim());
+    }
+
+    let snapshot: serde_json::Value =
+        serde_json::from_str(&body).with_context(|| format!("{endpoint} returned invalid JSON"))?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&snapshot)?);
+        return Ok(());
+    }
+    let summary: StatusSummary = serde_json::from_value(snapshot)
+        .with_context(|| format!("{endpoint} returned an unsupported status schema"))?;
+    print!("{}", summarize(&summary));
+    Ok(())
+}
+
+fn status_client(tls: &StatusTls) -> Result<reqwest::Client> {
+    let read = |path: &PathBuf, kind: &str| {
+        std::fs::read(path).with_context(|| format!("Failed to read {kind} {}", path.display()))
+    };
+    let mut builder = reqwest::Client::builder();
+    if let Some(path) = &tls.ca_cert {
+        let certs = reqwest::Certificate::from_pem_bundle(&read(path, "CA certificate")?)
+            .with_context(|| format!("Invalid CA certificate {}", path.display()))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    if let Some((cert, key)) = &tls.client {
+        let mut pem = read(cert, "client certificate")?;
+        pem.extend(read(key, "client key")?);
+        let identity = reqwest::Identity::from_pem(&pem).with_context(|| {
+            format!(
+                "Invalid client certificate {} or key {}",
+                cert.display(),
+                key.display()
+            )
+        })?;
+        builder = builder.identity(identity);
+    }
+    builder.build().context("Failed to build the HTTP client")
+}
+
+fn summarize(status: &StatusSummary) -> String {
+    let ports = status
+        .dataplane
+        .ports
+        .iter()
+        .map(|port| format!("{}->{}", port.listen, port.backend))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut out = format!(
+        "service    {} {} (schema {}), {} for {}s\n\
+         health     {}\n\
+         readiness  {}\n\
+         provider   {}, {}, {} discovered, {} routable\n\
+         dataplane  {} on {}, {} {}, ports {}\n\
+         traffic    {} connections from {} clients, {:.2} Mbit/s in, {:.2} Mbit/s out\n",
+        status.service,
+        status.version,
+        status.schema_version,
+        status.lifecycle,
+        status.uptime_seconds,
+        status.health.reason,
+        status.readiness.reason,
+        status.provider.kind,
+        if status.provider.healthy {
+            "healthy"
+        } else {
+            "unhealthy"
+        },
+        status.provider.discovered_backends,
+        status.provider.routable_backends,
+        status.dataplane.listen_address,
+        status.dataplane.listen_interface,
+        status.dataplane.protocol,
+        status.dataplane.routing_mode,
+        ports,
+        status.connections.active,
+        status.connections.active_clients,
+        status.ingress.megabits_per_second,
+        status.egress.megabits_per_second,
+    );
+    if let Some(error) = &status.provider.last_error {
+        out.push_str(&format!("           last error: {error}\n"));
+    }
+    if !status.backends.is_empty() {
+        out.push_str(
+            "\nBACKEND                          ADDRESS          AVAILABLE  CONNECTIONS\n",
+        );
+        for backend in &status.backends {
+            out.push_str(&format!(
+                "{:<32} {:<16} {:<10} {}\n",
+                backend.name,
+                backend.address,
+                if backend.available_for_new_connections {
+                    "yes"
+                } else {
+                    "no"
+                },
+                backend.connections.active,
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command> {
+        Command::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn commands_default_to_running_with_the_working_directory_config() {
+        let run = Command::Run {
+            config: "xlb.yaml".into(),
+        };
+        assert_eq!(parse(&[]).expect("no arguments"), run);
+        assert_eq!(parse(&["run"]).expect("run"), run);
+        assert_eq!(
+            parse(&["run", "--config", "/etc/xlb/xlb.yaml"]).expect("run with config"),
+            Command::Run {
+                config: "/etc/xlb/xlb.yaml".into()
+            }
+        );
+        assert_eq!(
+            parse(&["validate", "staging.yaml"]).expect("validate"),
+            Command::Validate {
+                config: "staging.yaml".into()
+            }
+        );
+        assert_eq!(
+            parse(&["status", "--url", "http://10.0.0.5:9090", "--json"]).expect("status"),
+            Command::Status {
+                url: "http://10.0.0.5:9090".into(),
+                user: "admin".into(),
+                json: true,
+                tls: StatusTls::default(),
+            }
+        );
+        assert_eq!(
+            parse(&[
+                "status",
+                "--url",
+                "https://10.0.0.5:9090",
+                "--ca-cert",
+                "ca.crt",
+                "--client-cert",
+                "ops.crt",
+                "--client-key",
+                "ops.key",
+            ])
+            .expect("status over TLS"),
+            Command::Status {
+                url: "https://10.0.0.5:9090".into(),
+                user: "admin".into(),
+                json: false,
+                tls: StatusTls {
+                    ca_cert: Some("ca.crt".into()),
+                    client: Some(("ops.crt".into(), "ops.key".into())),
+                },
+            }
+        );
+        assert_eq!(parse(&["schema"]).expect("schema"), Command::Schema);
+        assert_eq!(parse(&["--help"]).expect("help"), Command::Help);
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected_with_usage() {
+        for args in [
+            &["serve"][..],
+            &["run", "--config"],
+            &["run", "xlb.yaml"],
+            &["validate", "a.yaml", "b.yaml"],
+            &["status", "--verbose"],
+            &["status", "--ca-cert"],
+            &["status", "--client-cert", "ops.crt"],
+            &["status", "--client-key", "ops.key"],
+            &["schema", "--pretty"],
+        ] {
+            let error = parse(args).expect_err("invalid arguments");
+            assert!(
+                error.to_string().contains("Usage: xlb"),
+                "{args:?}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn status_client_reports_the_file_it_cannot_use() {
+        let tls = StatusTls {
+            ca_cert: Some("/nonexistent/ca.crt".into()),
+            client: None,
+        };
+        let error = status_client(&tls).expect_err("a missing CA cannot be used");
+        assert!(error.to_string().contains("/nonexistent/ca.crt"), "{error}");
+        assert!(status_client(&StatusTls::default()).is_ok());
+    }
+
+    #[test]
+    fn status_summary_tolerates_fields_it_does_not_print() {
+        let summary: StatusSummary = serde_json::from_value(serde_json::json!({
+            "schema_version": 1,
+            "service": "edge",
+            "version": "0.1.0",
+            "lifecycle": "running",
+            "uptime_seconds": 42,
+            "health": { "healthy": true, "reason": "healthy" },
+            "readiness": { "ready": true, "reason": "ready" },
+            "provider": {
+                "kind": "static",
+                "healthy": true,
+                "last_error": null,
+                "discovered_backends": 1,
+                "routable_backends": 1,
+                "watches": []
+            },
+            "dataplane": {
+                "listen_address": "192.0.2.1",
+                "listen_interface": "eth0",
+                "protocol": "tcp",
+                "routing_mode": "nat",
+                "ports": [{ "listen": 80, "backend": 8080, "backend_name": null }]
+            },
+            "connections": { "active": 3, "active_clients": 2 },
+            "ingress": { "megabits_per_second": 1.5 },
+            "egress": { "megabits_per_second": 0.25 },
+            "config_reload": null,
+            "backends": [{
+                "name": "web-1",
+                "address": "10.0.0.1",
+                "available_for_new_connections": true,
+                "connections": { "active": 3, "active_clients": 2 }
+            }]
+        }))
+        .expect("status must deserialize");
+
+        let text = summarize(&summary);
+        assert!(
+            text.contains("edge 0.1.0 (schema 1), running for 42s"),
+            "{text}"
+        );
+        assert!(text.contains("ports 80->8080"), "{text}");
+        assert!(text.contains("1.50 Mbit/s in, 0.25 Mbit/s out"), "{text}");
+        assert!(text.contains("web-1"), "{text}");
+    }
+}
@@ -1,35 +1,350 @@
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
 use config::Config;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
 use xlb_common::config::routing::RoutingMode;
+use xlb_common::consts::MAX_PORT_MAPPINGS;
 use xlb_common::net::Proto;
 use xlb_common::types::PortMapping;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub struct Host {
     pub name: String,
     pub ip: IpAddr,
+    /// Selection weight out of `MAX_BACKEND_WEIGHT`, set by providers
+    /// that publish relative weights. Hosts without one get a full share.
+    #[serde(skip)]
+    pub weight: Option<u16>,
+    /// Failover tier; lower values are preferred. Backends in a higher
+    /// tier only receive new connections while every lower tier has fewer
+    /// eligible backends than `priority_tiers.min_eligible_backends`.
+    #[serde(default)]
+    pub priority: u32,
+    /// Destination ports by name, set by providers that discover them.
+    #[serde(skip)]
+    pub ports: BTreeMap<String, u16>,
 }
 
-#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum BackendSource {
-    Static {
-        backends: Vec<Host>,
-    },
-    #[allow(dead_code)]
-    Kubernetes {
-        namespace: String,
-        service: String,
-    },
+    Static { backends: Vec<Host> },
+    Kubernetes(KubernetesProviderConfig),
+    Dns(DnsProviderConfig),
+    File(FileProviderConfig),
+    Consul(ConsulProviderConfig),
+    Http(HttpProviderConfig),
+    Composite(CompositeProviderConfig),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsRecordType {
+    /// Every IPv4 address of the name is a backend
+    #[default]
+    A,
+    /// Targets of the lowest-priority SRV records are backends,
+    /// weighted by their SRV weight
+    Srv,
+}
+
+/// Backends discovered from the EndpointSlices of one or more Kubernetes
+/// Services, merged into one pool.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct KubernetesProviderConfig {
+    /// Namespace of `service`.
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// A single Service to watch.
+    #[serde(default)]
+    pub service: Option<String>,
+    /// Further Services to watch.
+    #[serde(default)]
+    pub services: Vec<KubernetesServiceRef>,
+    /// EndpointSlices matching a label selector in each listed namespace.
+    #[serde(default)]
+    pub selector: Option<KubernetesSelector>,
+    /// Preference for endpoints close to this XLB instance.
+    #[serde(default)]
+    pub topology: KubernetesTopologyConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct KubernetesServiceRef {
+    pub namespace: String,
+    pub service: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct KubernetesSelector {
+    pub namespaces: Vec<String>,
+    /// Label selector matched against EndpointSlices, which carry the
+    /// labels of their Service, e.g. `app=web`.
+    pub labels: String,
+}
+
+/// One EndpointSlice watch of the Kubernetes provider.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KubernetesWatch {
+    Service { namespace: String, service: String },
+    Selector { namespace: String, labels: String },
+}
+
+impl KubernetesWatch {
+    pub fn namespace(&self) -> &str {
+        match self {
+            KubernetesWatch::Service { namespace, .. }
+            | KubernetesWatch::Selector { namespace, .. } => namespace,
+        }
+    }
+}
+
+impl std::fmt::Display for KubernetesWatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KubernetesWatch::Service { namespace, service } => {
+                write!(f, "service {namespace}/{service}")
+            }
+            KubernetesWatch::Selector { namespace, labels } => {
+                write!(f, "selector '{labels}' in namespace {namespace}")
+            }
+        }
+    }
+}
+
+impl KubernetesProviderConfig {
+    /// Every watch in configuration order: `service`, then `services`,
+    /// then one per selector namespace.
+    pub fn watches(&self) -> Vec<KubernetesWatch> {
+        let single = self
+            .namespace
+            .iter()
+            .zip(&self.service)
+            .map(|(namespace, service)| (namespace.clone(), service.clone()));
+        let services = single
+            .chain(
+                self.services
+                    .iter()
+                    .map(|service| (service.namespace.clone(), service.service.clone())),
+            )
+            .map(|(namespace, service)| KubernetesWatch::Service { namespace, service });
+        let selected = self.selector.iter().flat_map(|selector| {
+            selector
+                .namespaces
+                .iter()
+                .map(|namespace| KubernetesWatch::Selector {
+                    namespace: namespace.clone(),
+                    labels: selector.labels.clone(),
+                })
+        });
+        services.chain(selected).collect()
+    }
+}
+
+/// Which Kubernetes endpoints receive new connections while enough of them
+/// are eligible.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TopologyPreference {
+    /// Every eligible endpoint
+    #[default]
+    None,
+    /// Endpoints in the zone of the node running XLB, or those the
+    /// EndpointSlice controller hinted for that zone
+    Zone,
+    /// Endpoints on the node running XLB
+    Node,
+}
+
+/// Preference for endpoints close to this XLB instance.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct KubernetesTopologyConfig {
+    #[serde(default)]
+    pub prefer: TopologyPreference,
+    /// Eligible local endpoints needed to keep new connections local.
+    /// Below it, endpoints in every zone are used.
+    #[serde(default = "default_min_local_backends")]
+    pub min_local_backends: u32,
+    /// Follow `hints.forZones` on EndpointSlices when every eligible
+    /// endpoint carries hints.
+    #[serde(default = "default_topology_hints")]
+    pub hints: bool,
+    /// Zone of this XLB instance. Defaults to the
+    /// `topology.kubernetes.io/zone` label of its node.
+    #[serde(default)]
+    pub zone: Option<String>,
+}
+
+impl Default for KubernetesTopologyConfig {
+    fn default() -> Self {
+        Self {
+            prefer: TopologyPreference::None,
+            min_local_backends: default_min_local_backends(),
+            hints: default_topology_hints(),
+            zone: None,
+        }
+    }
+}
+
+const fn default_min_local_backends() -> u32 {
+    1
+}
+
+const fn default_topology_hints() -> bool {
+    true
+}
+
+/// Backends discovered by periodically resolving a DNS name.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct DnsProviderConfig {
+    /// Name to resolve, e.g. `backends.example.internal`
+    /// or `_http._tcp.example.internal` for SRV.
+    pub name: String,
+    /// Record type to query.
+    #[serde(default)]
+    pub record_type: DnsRecordType,
+    /// Nameservers queried over UDP with TCP fallback.
+    /// Defaults to the system resolver configuration.
+    #[serde(default)]
+    pub nameservers: Vec<SocketAddr>,
+    /// Lower bound on the interval between resolutions,
+    /// also used as the retry interval after a failure.
+    #[serde(default = "default_dns_min_refresh_secs")]
+    pub min_refresh_secs: u64,
+    /// Upper bound on the interval between resolutions,
+    /// applied when record TTLs are longer.
+    #[serde(default = "default_dns_max_refresh_secs")]
+    pub max_refresh_secs: u64,
+}
+
+/// Backends read from a file that is watched for changes.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct FileProviderConfig {
+    /// Path to a `.yaml`, `.yml`, or `.json` file holding a
+    /// `backends` list in the same form as the static provider.
+    pub path: PathBuf,
+}
+
+/// Backends discovered from the passing instances of a Consul service.
+/// The ACL token, if any, is read from `CONSUL_HTTP_TOKEN`.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct ConsulProviderConfig {
+    /// Consul HTTP API address.
+    #[serde(default = "default_consul_address")]
+    pub address: String,
+    /// Service name as registered in the Consul catalog.
+    pub service: String,
+    /// Only instances carrying every one of these tags are backends.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Datacenter to query. Defaults to the agent's own datacenter.
+    #[serde(default)]
+    pub datacenter: Option<String>,
+    /// Node metadata key holding a numeric backend weight, scaled
+    /// relative to the heaviest instance. Instances without it weigh 1.
+    #[serde(default)]
+    pub weight_meta_key: Option<String>,
+    /// Longest time a blocking query waits for a change, at most 600.
+    #[serde(default = "default_consul_wait_secs")]
+    pub wait_secs: u64,
+}
+
+/// Backends pushed through the admin API, or polled from a URL, and
+/// persisted so a restart comes up with the last accepted set.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct HttpProviderConfig {
+    /// File holding the last accepted backend set, restored at startup.
+    #[serde(default = "default_http_state_path")]
+    pub state_path: PathBuf,
+    /// Poll this URL for the backend set instead of accepting pushes
+    /// through `PUT` and `PATCH /api/v1/backends`.
+    #[serde(default)]
+    pub poll: Option<HttpPollConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct HttpPollConfig {
+    /// URL returning a JSON document with a `backends` list.
+    pub url: String,
+    /// Interval between polls.
+    #[serde(default = "default_http_poll_interval_secs")]
+    pub interval_secs: u64,
+}
+
+/// Several providers merged into one backend pool.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct CompositeProviderConfig {
+    /// Providers whose backends are merged, which cannot themselves be
+    /// composite.
+    pub sources: Vec<CompositeSource>,
+    /// Which sources must be healthy for the merged pool to be healthy.
+    #[serde(default)]
+    pub health: CompositeHealth,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct CompositeSource {
+    pub provider: BackendSource,
+    /// Percentage of their selection weight this source's backends keep,
+    /// from 1 to 100.
+    #[serde(default = "default_composite_weight")]
+    pub weight: u8,
+    /// When several sources publish the same address, the source with the
+    /// lowest priority value supplies it. Its backends are placed in at
+    /// least this priority tier.
+    #[serde(default)]
+    pub priority: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CompositeHealth {
+    /// Healthy only while every source is healthy
+    #[default]
+    All,
+    /// Healthy while at least one source is healthy
+    Any,
+}
+
+/// Longest blocking query wait Consul accepts.
+pub const MAX_CONSUL_WAIT_SECS: u64 = 600;
+
+/// File extensions the file provider can parse.
+pub const BACKEND_FILE_EXTENSIONS: [&str; 3] = ["yaml", "yml", "json"];
+
+const fn default_dns_min_refresh_secs() -> u64 {
+    5
+}
+
+const fn default_dns_max_refresh_secs() -> u64 {
+    300
+}
+
+fn default_consul_address() -> String {
+    "http://127.0.0.1:8500".to_string()
+}
+
+const fn default_consul_wait_secs() -> u64 {
+    60
+}
+
+fn default_http_state_path() -> PathBuf {
+    PathBuf::from("/var/lib/xlb/backends.json")
+}
+
+const fn default_http_poll_interval_secs() -> u64 {
+    10
+}
+
+const fn default_composite_weight() -> u8 {
+    100
 }
 
 #[repr(C)]
-#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ListenAddr {
     /// Will attach to the interface and primary ip of
@@ -41,7 +356,7 @@ pub enum ListenAddr {
     Ip(String),
 }
 
-#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Default, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum OtelProtocol {
     #[default]
@@ -50,7 +365,7 @@ pub enum OtelProtocol {
 }
 
 /// OpenTelemetry configuration for metrics export
-#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
 pub struct OtelConfig {
     /// Enable/disable OTEL metrics export
     #[serde(default)]
@@ -73,7 +388,7 @@ const fn default_otel_export_interval() -> u64 {
 }
 
 /// Optional HTTP Basic authentication for administrative routes.
-#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
 pub struct AdminAuthConfig {
     /// Username accepted by the administrative UI and status API.
     #[serde(default = "default_admin_username")]
@@ -84,8 +399,23 @@ fn default_admin_username() -> String {
     "admin".to_owned()
 }
 
+/// HTTPS for the admin listener. The files are read again whenever they
+/// change, so renewed certificates apply without a restart.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct AdminTlsConfig {
+    /// PEM certificate chain served to clients, leaf first.
+    pub cert: PathBuf,
+    /// PEM private key of `cert`.
+    pub key: PathBuf,
+    /// PEM bundle of CAs whose client certificates authenticate
+    /// administrative requests, as an alternative to `auth`. Health and
+    /// readiness probes need no certificate.
+    #[serde(default)]
+    pub client_ca: Option<PathBuf>,
+}
+
 /// HTTP listener for health, readiness, and administrative status.
-#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
 pub struct AdminConfig {
     /// Address on which the admin API listens.
     #[serde(default = "default_admin_address")]
@@ -94,9 +424,20 @@ pub struct AdminConfig {
     #[serde(default = "default_admin_port")]
     pub port: u16,
     /// Protect the administrative UI and status API with HTTP Basic auth.
-    /// The password is read from `XLB_ADMIN_PASSWORD` at startup.
+    /// The password is read from `XLB_ADMIN_PASSWORD` at startup, and again
+    /// when a reload changes this setting.
     #[serde(default)]
     pub auth: Option<AdminAuthConfig>,
+    /// Serve the admin API over HTTPS instead of plain HTTP.
+    #[serde(default)]
+    pub tls: Option<AdminTlsConfig>,
+    /// Hide client addresses in the connections API. Filtering by client
+    /// CIDR still matches the real addresses.
+    #[serde(default = "default_redact_client_ips")]
+    pub redact_client_ips: bool,
+    /// Prometheus scrape endpoint at `/metrics`.
+    #[serde(default)]
+    pub metrics: AdminMetricsConfig,
 }
 
 impl Default for AdminConfig {
@@ -105,14 +446,58 @@ impl Default for AdminConfig {
             address: default_admin_address(),
             port: default_admin_port(),
             auth: None,
+            tls: None,
+            redact_client_ips: default_redact_client_ips(),
+            metrics: AdminMetricsConfig::default(),
         }
     }
 }
 
+/// Prometheus text exposition of the metrics XLB also exports over OTLP.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct AdminMetricsConfig {
+    /// Serve `/metrics`, whether or not OTLP export is enabled.
+    #[serde(default = "default_metrics_enabled")]
+    pub enabled: bool,
+    /// Backends labeled individually in each metric. Further backends, in
+    /// address order, are summed under `backend="other"`.
+    #[serde(default = "default_metrics_max_backends")]
+    pub max_backends: usize,
+    /// Protect `/metrics` with its own HTTP Basic credentials, independent
+    /// of `admin.auth`. The password is read from `XLB_METRICS_PASSWORD`
+    /// at startup. Without it, `/metrics` is unauthenticated.
+    #[serde(default)]
+    pub auth: Option<AdminAuthConfig>,
+}
+
+impl Default for AdminMetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_metrics_enabled(),
+            max_backends: default_metrics_max_backends(),
+            auth: None,
+        }
+    }
+}
+
+const fn default_metrics_enabled() -> bool {
+    true
+}
+
+const fn default_metrics_max_backends() -> usize {
+    100
+}
+
 impl AdminConfig {
     pub fn socket_addr(&self) -> SocketAddr {
         SocketAddr::new(self.address, self.port)
     }
+
+    /// Whether clients can authenticate to the admin API, with a password
+    /// or with a certificate issued by `tls.client_ca`.
+    pub fn authenticates(&self) -> bool {
+        self.auth.is_some() || self.tls.as_ref().is_some_and(|tls| tls.client_ca.is_some())
+    }
 }
 
 const fn default_admin_address() -> IpAddr {
@@ -123,8 +508,12 @@ const fn default_admin_port() -> u16 {
     9090
 }
 
+const fn default_redact_client_ips() -> bool {
+    true
+}
+
 /// Capacity inputs that cannot always be discovered from virtual hardware.
-#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Default, JsonSchema)]
 pub struct ResourceConfig {
     /// Per-interface network capacity in megabits per second.
     ///
@@ -135,8 +524,265 @@ pub struct ResourceConfig {
     pub network_capacity_mbps: Option<u64>,
 }
 
+/// TLS settings for gRPC health probes.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct GrpcProbeTlsConfig {
+    /// Name used for SNI and certificate verification.
+    /// Defaults to the backend IP address.
+    #[serde(default)]
+    pub server_name: Option<String>,
+    /// PEM bundle of trusted CAs. Defaults to the system roots.
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+}
+
+/// Settings for the standard `grpc.health.v1.Health/Check` probe.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, JsonSchema)]
+pub struct GrpcProbeConfig {
+    /// Service name sent with each check. Empty checks the
+    /// overall health of the backend server.
+    #[serde(default)]
+    pub service: String,
+    /// Connect to backends over TLS instead of plaintext HTTP/2.
+    #[serde(default)]
+    pub tls: Option<GrpcProbeTlsConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthProbeConfig {
+    /// Healthy when a TCP connection to the backend succeeds
+    Tcp,
+    /// Healthy only when the backend reports SERVING
+    Grpc(GrpcProbeConfig),
+}
+
+/// Active health checks gating which discovered backends
+/// may receive new connections.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct HealthCheckConfig {
+    /// Probe performed against every discovered backend.
+    pub probe: HealthProbeConfig,
+    /// Backend port to probe. Defaults to the remote
+    /// port of the first port mapping.
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Seconds between probe rounds.
+    #[serde(default = "default_health_check_interval_secs")]
+    pub interval_secs: u64,
+    /// Per-probe timeout in milliseconds, including connection setup.
+    #[serde(default = "default_health_check_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Consecutive successes required to return an
+    /// unhealthy backend to the pool.
+    #[serde(default = "default_healthy_threshold")]
+    pub healthy_threshold: u32,
+    /// Consecutive failures required to remove a
+    /// healthy backend from the pool.
+    #[serde(default = "default_unhealthy_threshold")]
+    pub unhealthy_threshold: u32,
+}
+
+const fn default_health_check_interval_secs() -> u64 {
+    5
+}
+
+const fn default_health_check_timeout_ms() -> u64 {
+    1_000
+}
+
+const fn default_healthy_threshold() -> u32 {
+    2
+}
+
+const fn default_unhealthy_threshold() -> u32 {
+    3
+}
+
+/// Passive ejection of backends whose connections fail at an elevated
+/// rate, measured from flows the dataplane already tracks.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct OutlierDetectionConfig {
+    /// Seconds of traffic aggregated into each evaluation.
+    #[serde(default = "default_outlier_interval_secs")]
+    pub interval_secs: u64,
+    /// New connections a backend must receive during an
+    /// interval before its failure rates are evaluated.
+    #[serde(default = "default_outlier_min_connections")]
+    pub min_connections: u32,
+    /// Percentage of new connections reset by the backend
+    /// that ejects it.
+    #[serde(default = "default_server_reset_percent")]
+    pub server_reset_percent: f64,
+    /// Percentage of new connections refused or left unanswered
+    /// by the backend that ejects it.
+    #[serde(default = "default_handshake_failure_percent")]
+    pub handshake_failure_percent: f64,
+    /// Duration of a first ejection. Each consecutive ejection
+    /// doubles it, up to `max_ejection_secs`.
+    #[serde(default = "default_base_ejection_secs")]
+    pub base_ejection_secs: u64,
+    /// Upper bound for a single ejection.
+    #[serde(default = "default_max_ejection_secs")]
+    pub max_ejection_secs: u64,
+    /// Maximum percentage of the backend pool ejected at once. Unless
+    /// zero, at least one backend may be ejected from a pool of two or more.
+    #[serde(default = "default_max_ejection_percent")]
+    pub max_ejection_percent: f64,
+}
+
+const fn default_outlier_interval_secs() -> u64 {
+    10
+}
+
+const fn default_outlier_min_connections() -> u32 {
+    10
+}
+
+const fn default_server_reset_percent() -> f64 {
+    50.0
+}
+
+const fn default_handshake_failure_percent() -> f64 {
+    20.0
+}
+
+const fn default_base_ejection_secs() -> u64 {
+    30
+}
+
+const fn default_max_ejection_secs() -> u64 {
+    300
+}
+
+const fn default_max_ejection_percent() -> f64 {
+    10.0
+}
+
+/// Handling of backends leaving the pool, either because the provider
+/// stopped publishing them or because an operator drained them.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct DrainConfig {
+    /// Seconds a draining backend's existing connections are
+    /// left to finish on their own.
+    #[serde(default = "default_drain_deadline_secs")]
+    pub deadline_secs: u64,
+    /// Reset connections still open when the deadline passes.
+    /// Each side receives a RST on its next packet.
+    #[serde(default)]
+    pub force_close: bool,
+}
+
+impl Default for DrainConfig {
+    fn default() -> Self {
+        Self {
+            deadline_secs: default_drain_deadline_secs(),
+            force_close: false,
+        }
+    }
+}
+
+const fn default_drain_deadline_secs() -> u64 {
+    300
+}
+
+/// One listen port and the backend port it maps to.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct PortMappingConfig {
+    /// Port XLB listens on.
+    pub local_port: u16,
+    /// Backend port, unless `port_name` resolves for a backend.
+    pub remote_port: u16,
+    /// Name of a Service port such as `http`. Backends whose EndpointSlice,
+    /// or SRV record of that service, publishes a port of this name receive
+    /// connections on its number.
+    #[serde(default)]
+    pub port_name: Option<String>,
+}
+
+impl From<&PortMappingConfig> for PortMapping {
+    fn from(value: &PortMappingConfig) -> Self {
+        PortMapping {
+            local_port: value.local_port,
+            remote_port: value.remote_port,
+        }
+    }
+}
+
+/// Kubernetes LoadBalancer controller mode.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct LoadBalancerControllerConfig {
+    /// `spec.loadBalancerClass` of the Services XLB implements.
+    pub class: String,
+    /// VIPs allocated to Services: IPv4 addresses, `first-last` ranges, or
+    /// CIDR blocks.
+    pub addresses: Vec<String>,
+}
+
+impl LoadBalancerControllerConfig {
+    /// Inclusive IPv4 ranges of the configured addresses, in order.
+    pub fn address_ranges(&self) -> Result<Vec<(Ipv4Addr, Ipv4Addr)>> {
+        self.addresses
+            .iter()
+            .map(|entry| parse_address_range(entry.trim()))
+            .collect()
+    }
+}
+
+fn parse_address_range(entry: &str) -> Result<(Ipv4Addr, Ipv4Addr)> {
+    let parse = |address: &str| {
+        address.trim().parse::<Ipv4Addr>().map_err(|_| {
+            anyhow!("Load balancer address '{entry}' is not an IPv4 address, range, or CIDR")
+        })
+    };
+
+    if let Some((network, prefix)) = entry.split_once('/') {
+        let network = parse(network)?;
+        let prefix = match prefix.parse::<u32>() {
+            Ok(prefix) if prefix <= 32 => prefix,
+            _ => bail!("Load balancer address '{entry}' has an invalid prefix length"),
+        };
+        let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+        let first = network.to_bits() & mask;
+        return Ok((
+            Ipv4Addr::from_bits(first),
+            Ipv4Addr::from_bits(first | !mask),
+        ));
+    }
+    if let Some((first, last)) = entry.split_once('-') {
+        let (first, last) = (parse(first)?, parse(last)?);
+        if first > last {
+            bail!("Load balancer address range '{entry}' ends before it starts");
+        }
+        return Ok((first, last));
+    }
+    let address = parse(entry)?;
+    Ok((address, address))
+}
+
+/// Failover between backend priority tiers.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct PriorityTierConfig {
+    /// Eligible backends a tier needs to receive new connections. Below
+    /// it, the next tier with enough eligible backends takes over.
+    #[serde(default = "default_min_eligible_backends")]
+    pub min_eligible_backends: u32,
+}
+
+impl Default for PriorityTierConfig {
+    fn default() -> Self {
+        Self {
+            min_eligible_backends: default_min_eligible_backends(),
+        }
+    }
+}
+
+const fn default_min_eligible_backends() -> u32 {
+    1
+}
+
 /// The user facing application config
-#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub struct XlbConfig {
     /// Optional service name attached to OTEL metrics.
@@ -154,7 +800,7 @@ pub struct XlbConfig {
     pub proto: Proto,
     /// The port mappings of inbound to backend dest
     /// ports. E.g. [80 -> 8080], [443 -> 443]
-    pub ports: Vec<PortMapping>,
+    pub ports: Vec<PortMappingConfig>,
     /// The source of backend hosts to load balance to
     pub provider: BackendSource,
     /// Routing mode of either nat or dsr, presently
@@ -172,6 +818,11 @@ pub struct XlbConfig {
     /// window receive a reset before XLB exits.
     #[serde(default = "default_shutdown_timeout")]
     pub shutdown_timeout: u32,
+    /// Send a RST to both sides of every live TCP connection
+    /// as soon as shutdown begins, so idle connections are
+    /// not silently abandoned.
+    #[serde(default = "default_shutdown_reset_connections")]
+    pub shutdown_reset_connections: bool,
     /// Optional OpenTelemetry metrics configuration
     #[serde(default)]
     pub otel: Option<OtelConfig>,
@@ -181,6 +832,29 @@ pub struct XlbConfig {
     /// Optional resource-capacity overrides for virtualized environments.
     #[serde(default)]
     pub resources: ResourceConfig,
+    /// Optional active health checks. When omitted every
+    /// discovered backend is eligible for new connections.
+    #[serde(default)]
+    pub health_check: Option<HealthCheckConfig>,
+    /// Optional passive outlier detection. When omitted
+    /// backends are never ejected for failing connections.
+    #[serde(default)]
+    pub outlier_detection: Option<OutlierDetectionConfig>,
+    /// Seconds over which a newly added backend's share of
+    /// new connections ramps linearly up to full. Zero
+    /// disables slow start.
+    #[serde(default)]
+    pub slow_start_secs: u64,
+    /// Draining of backends removed from the pool.
+    #[serde(default)]
+    pub drain: DrainConfig,
+    /// Failover between backend priority tiers.
+    #[serde(default)]
+    pub priority_tiers: PriorityTierConfig,
+    /// Optional implementation of Kubernetes `Service` `type: LoadBalancer`.
+    /// Each matching Service gets a VIP served besides the listen address.
+    #[serde(default)]
+    pub load_balancer: Option<LoadBalancerControllerConfig>,
 }
 
 pub const MIN_ORPHAN_TTL_SECS: u32 = 5 * 60;
@@ -191,6 +865,9 @@ const fn default_orphan_ttl_secs() -> u32 {
 const fn default_shutdown_timeout() -> u32 {
     15
 }
+const fn default_shutdown_reset_connections() -> bool {
+    true
+}
 
 impl XlbConfig {
     pub fn load(path: PathBuf) -> Result<XlbConfig> {
@@ -199,8 +876,8 @@ impl XlbConfig {
             .build()?
             .try_deserialize::<XlbConfig>()?;
 
-        if config.ports.is_empty() || config.ports.len() > 8 {
-            bail!("Number of port mappings must be between 1 and 8");
+        if config.ports.is_empty() || config.ports.len() > MAX_PORT_MAPPINGS {
+            bail!("Number of port mappings must be between 1 and {MAX_PORT_MAPPINGS}");
         }
 
         config.validate_supported()?;
@@ -218,10 +895,86 @@ impl XlbConfig {
         {
             bail!("Admin auth username must be non-empty and cannot contain ':'");
         }
+        if let Some(auth) = &self.admin.metrics.auth
+            && (auth.username.trim().is_empty() || auth.username.contains(':'))
+        {
+            bail!("Metrics auth username must be non-empty and cannot contain ':'");
+        }
+        if let Some(tls) = &self.admin.tls
+            && [Some(&tls.cert), Some(&tls.key), tls.client_ca.as_ref()]
+                .into_iter()
+                .flatten()
+                .any(|path| path.as_os_str().is_empty())
+        {
+            bail!("Admin TLS cert, key, and client_ca paths must be non-empty");
+        }
+        if self.ports.iter().any(|port| {
+            port.port_name
+                .as_ref()
+                .is_some_and(|name| name.trim().is_empty())
+        }) {
+            bail!("Port mapping port_name must be non-empty when set");
+        }
         if self.resources.network_capacity_mbps == Some(0) {
             bail!("Network capacity must be greater than zero megabits per second");
         }
 
+        if let Some(health_check) = &self.health_check {
+            if health_check.port == Some(0) {
+                bail!("Health check port must be between 1 and 65535");
+            }
+            if health_check.interval_secs == 0 || health_check.timeout_ms == 0 {
+                bail!("Health check interval and timeout must be greater than zero");
+            }
+            if health_check.healthy_threshold == 0 || health_check.unhealthy_threshold == 0 {
+                bail!("Health check thresholds must be at least 1");
+            }
+        }
+
+        if let Some(outliers) = &self.outlier_detection {
+            if outliers.interval_secs == 0 || outliers.base_ejection_secs == 0 {
+                bail!(
+                    "Outlier detection interval and base ejection time must be greater than zero"
+                );
+            }
+            if outliers.max_ejection_secs < outliers.base_ejection_secs {
+                bail!("Outlier detection max_ejection_secs must be at least base_ejection_secs");
+            }
+            let thresholds = [
+                outliers.server_reset_percent,
+                outliers.handshake_failure_percent,
+            ];
+            if thresholds
+                .iter()
+                .any(|percent| !(*percent > 0.0 && *percent <= 100.0))
+            {
+                bail!(
+                    "Outlier detection thresholds must be greater than 0 and at most 100 percent"
+                );
+            }
+            if !(0.0..=100.0).contains(&outliers.max_ejection_percent) {
+                bail!("Outlier detection max_ejection_percent must be between 0 and 100");
+            }
+        }
+
+        self.validate_provider(&self.provider)?;
+
+        if self.drain.deadline_secs == 0 {
+            bail!("Drain deadline must be greater than zero");
+        }
+        if self.priority_tiers.min_eligible_backends == 0 {
+            bail!("Priority tier min_eligible_backends must be at least 1");
+        }
+        if let Some(load_balancer) = &self.load_balancer {
+            if load_balancer.class.trim().is_empty() {
+                bail!("Load balancer class must not be empty");
+            }
+            if load_balancer.addresses.is_empty() {
+                bail!("Load balancer needs at least one address");
+            }
+            load_balancer.address_ranges()?;
+        }
+
         if self.proto != Proto::Tcp {
             bail!("Unsupported protocol 'udp': XLB currently supports only IPv4/TCP");
         }
@@ -234,19 +987,246 @@ impl XlbConfig {
             self.validate_listen_ip(listen_ip)?;
         }
 
-        if let BackendSource::Static { backends } = &self.provider
-            && let Some(backend) = backends.iter().find(|backend| backend.ip.is_ipv6())
-        {
-            bail!(
-                "Unsupported IPv6 backend '{}' ({}): XLB currently supports only IPv4 backends",
-                backend.name,
-                backend.ip
-            );
-        }
+        Ok(())
+    }
 
+    fn validate_provider(&self, provider: &BackendSource) -> Result<()> {
+        match provider {
+            BackendSource::Static { backends } => {
+                if let Some(backend) = backends.iter().find(|backend| backend.ip.is_ipv6()) {
+                    bail!(
+                        "Unsupported IPv6 backend '{}' ({}): XLB currently supports only IPv4 backends",
+                        backend.name,
+                        backend.ip
+                    );
+                }
+            }
+            BackendSource::Kubernetes(kubernetes) => {
+                if kubernetes.namespace.is_some() != kubernetes.service.is_some() {
+                    bail!("Kubernetes provider namespace and service must be set together");
+                }
+                if let Some(selector) = &kubernetes.selector {
+                    if selector.labels.trim().is_empty() {
+                        bail!("Kubernetes provider selector labels must not be empty");
+                    }
+                    if selector.namespaces.is_empty() {
+                        bail!("Kubernetes provider selector needs at least one namespace");
+                    }
+                }
+                let watches = kubernetes.watches();
+                if watches.is_empty() {
+                    bail!("Kubernetes provider needs a service, services, or a selector");
+                }
+                if let Some(watch) = watches.iter().find(|watch| {
+                    watch.namespace().trim().is_empty()
+                        || matches!(watch, KubernetesWatch::Service { service, .. } if service.trim().is_empty())
+                }) {
+                    bail!("Kubernetes provider {watch} needs a non-empty namespace and name");
+                }
+                let mut unique = HashSet::new();
+                if let Some(watch) = watches.iter().find(|watch| !unique.insert(*watch)) {
+                    bail!("Kubernetes provider watches {watch} more than once");
+                }
+                let topology = &kubernetes.topology;
+                if topology.min_local_backends == 0 {
+                    bail!("Kubernetes topology min_local_backends must be at least 1");
+                }
+                if topology
+                    .zone
+                    .as_ref()
+                    .is_some_and(|zone| zone.trim().is_empty())
+                {
+                    bail!("Kubernetes topology zone must not be empty");
+                }
+            }
+            BackendSource::Dns(dns) => {
+                if dns.name.trim().is_empty() {
+                    bail!("DNS provider name must not be empty");
+                }
+                if dns.min_refresh_secs == 0 {
+                    bail!("DNS provider min_refresh_secs must be greater than zero");
+                }
+                if dns.max_refresh_secs < dns.min_refresh_secs {
+                    bail!("DNS provider max_refresh_secs must be at least min_refresh_secs");
+                }
+            }
+            BackendSource::File(file) => {
+                if !file
+                    .path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .is_some_and(|extension| BACKEND_FILE_EXTENSIONS.contains(&extension))
+                {
+                    bail!(
+                        "File provider path '{}' must end in .yaml, .yml, or .json",
+                        file.path.display()
+                    );
+                }
+            }
+            BackendSource::Consul(consul) => {
+                if consul.service.trim().is_empty() {
+                    bail!("Consul provider service must not be empty");
+                }
+                if !(consul.address.starts_with("http://")
+                    || consul.address.starts_with("https://"))
+                {
+                    bail!(
+                        "Consul provider address '{}' must start with http:// or https://",
+                        consul.address
+                    );
+                }
+                if !(1..=MAX_CONSUL_WAIT_SECS).contains(&consul.wait_secs) {
+                    bail!("Consul provider wait_secs must be between 1 and {MAX_CONSUL_WAIT_SECS}");
+                }
+            }
+            BackendSource::Http(http) => match &http.poll {
+                Some(poll) => {
+                    if !(poll.url.starts_with("http://") || poll.url.starts_with("https://")) {
+                        bail!(
+                            "HTTP provider poll url '{}' must start with http:// or https://",
+                            poll.url
+                        );
+                    }
+                    if poll.interval_secs == 0 {
+                        bail!("HTTP provider poll interval_secs must be greater than zero");
+                    }
+                }
+                None if !self.admin.authenticates() => {
+                    bail!(
+                        "HTTP provider accepting backend pushes requires admin.auth or admin.tls.client_ca"
+                    );
+                }
+                None => {}
+            },
+            BackendSource::Composite(composite) => {
+                if composite.sources.is_empty() {
+                    bail!("Composite provider requires at least one source");
+                }
+                let mut http_sources = 0;
+                for source in &composite.sources {
+                    match source.provider {
+                        BackendSource::Composite(_) => {
+                            bail!("Composite provider sources cannot be composite providers");
+                        }
+                        BackendSource::Http(_) => http_sources += 1,
+                        _ => {}
+                    }
+                    if !(1..=100).contains(&source.weight) {
+                        bail!("Composite provider source weight must be between 1 and 100");
+                    }
+                    self.validate_provider(&source.provider)?;
+                }
+                if http_sources > 1 {
+                    bail!("Composite provider can include at most one HTTP source");
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Backend port probed by active health checks.
+    pub fn health_check_port(&self) -> Option<u16> {
+        let health_check = self.health_check.as_ref()?;
+        health_check
+            .port
+            .or_else(|| self.ports.first().map(|port| port.remote_port))
+    }
+
+    /// Settings that differ in `next`, split by whether a running instance
+    /// can apply them on reload. `live_listen_ports` are the listen ports
+    /// that open connections arrived on.
+    pub fn diff(&self, next: &XlbConfig, live_listen_ports: &HashSet<u16>) -> ConfigDiff {
+        let mut diff = ConfigDiff::default();
+        let mut compare = |name: &'static str, changed: bool, live: bool| {
+            if changed {
+                if live {
+                    diff.live.push(name);
+                } else {
+                    diff.restart.push(name);
+                }
+            }
+        };
+
+        compare("name", self.name != next.name, false);
+        compare("listen", self.listen != next.listen, false);
+        compare("proto", self.proto != next.proto, false);
+        // Open connections keep the backend port they were given, but their
+        // packets only reach the dataplane through a configured listen port.
+        let strands_connections = self.ports.iter().any(|port| {
+            live_listen_ports.contains(&port.local_port)
+                && !next
+                    .ports
+                    .iter()
+                    .any(|kept| kept.local_port == port.local_port)
+        });
+        compare("ports", self.ports != next.ports, !strands_connections);
+        // The admin API serves the HTTP provider's backend set, so the
+        // provider can only be swapped when neither side is HTTP.
+        compare(
+            "provider",
+            self.provider != next.provider,
+            !self.provider.accepts_pushes() && !next.provider.accepts_pushes(),
+        );
+        compare("mode", self.mode != next.mode, false);
+        compare(
+            "orphan_ttl_secs",
+            self.orphan_ttl_secs != next.orphan_ttl_secs,
+            true,
+        );
+        compare(
+            "shutdown_timeout",
+            self.shutdown_timeout != next.shutdown_timeout,
+            true,
+        );
+        compare(
+            "shutdown_reset_connections",
+            self.shutdown_reset_connections != next.shutdown_reset_connections,
+            false,
+        );
+        compare("otel", self.otel != next.otel, false);
+        // Credentials are swapped in the running admin API; its listener,
+        // TLS, and metrics endpoint are set up once.
+        compare(
+            "admin",
+            AdminConfig {
+                auth: next.admin.auth.clone(),
+                ..self.admin.clone()
+            } != next.admin,
+            false,
+        );
+        compare("admin.auth", self.admin.auth != next.admin.auth, true);
+        compare("resources", self.resources != next.resources, false);
+        // Defaulted from the first port mapping, so a port change can move it.
+        compare(
+            "health_check",
+            self.health_check != next.health_check
+                || self.health_check_port() != next.health_check_port(),
+            false,
+        );
+        compare(
+            "outlier_detection",
+            self.outlier_detection != next.outlier_detection,
+            false,
+        );
+        compare(
+            "slow_start_secs",
+            self.slow_start_secs != next.slow_start_secs,
+            false,
+        );
+        compare("drain", self.drain != next.drain, true);
+        compare(
+            "priority_tiers",
+            self.priority_tiers != next.priority_tiers,
+            false,
+        );
+        compare(
+            "load_balancer",
+            self.load_balancer != next.load_balancer,
+            false,
+        );
+        diff
+    }
+
     /// Validate an explicitly configured or auto-detected listen address.
     pub(crate) fn validate_listen_ip(&self, listen_ip: IpAddr) -> Result<()> {
         if listen_ip.is_ipv6() {
@@ -259,6 +1239,29 @@ impl XlbConfig {
     }
 }
 
+/// Top-level settings changed between two configurations.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ConfigDiff {
+    /// Settings a running instance applies without dropping flows.
+    pub live: Vec<&'static str>,
+    /// Settings that only take effect after a restart.
+    pub restart: Vec<&'static str>,
+}
+
+impl BackendSource {
+    /// Whether this source is, or includes, the HTTP provider.
+    fn accepts_pushes(&self) -> bool {
+        match self {
+            BackendSource::Http(_) => true,
+            BackendSource::Composite(composite) => composite
+                .sources
+                .iter()
+                .any(|source| source.provider.accepts_pushes()),
+            _ => false,
+        }
+    }
+}
+
 fn normalize_orphan_ttl_secs(orphan_ttl_secs: u32) -> u32 {
     if orphan_ttl_secs < MIN_ORPHAN_TTL_SECS {
         log::warn!(
@@ -414,6 +1417,26 @@ shutdown_timeout: 15
         assert_eq!(auth.username, "operator");
     }
 
+    #[test]
+    fn load_reads_admin_tls_with_optional_client_ca() {
+        let yaml = format!(
+            "{MINIMAL_CONFIG}\nadmin:\n  tls:\n    cert: /etc/xlb/tls.crt\n    key: /etc/xlb/tls.key\n"
+        );
+        let config = load_test_config("admin-tls", &yaml).expect("admin TLS must load");
+        let tls = config.admin.tls.expect("admin TLS is configured");
+
+        assert_eq!(tls.cert, PathBuf::from("/etc/xlb/tls.crt"));
+        assert_eq!(tls.key, PathBuf::from("/etc/xlb/tls.key"));
+        assert!(tls.client_ca.is_none());
+
+        let yaml = format!(
+            "{MINIMAL_CONFIG}\nadmin:\n  tls:\n    cert: /etc/xlb/tls.crt\n    key: \"\"\n"
+        );
+        let error = load_test_config("admin-tls-empty-key", &yaml)
+            .expect_err("an empty key path cannot be read");
+        assert!(error.to_string().contains("Admin TLS"));
+    }
+
     #[test]
     fn load_rejects_invalid_admin_auth_usernames() {
         for (name, username) in [("blank", "   "), ("colon", "ops:user")] {
@@ -423,6 +1446,703 @@ shutdown_timeout: 15
         }
     }
 
+    #[test]
+    fn load_defaults_grpc_health_check_to_first_backend_port() {
+        let yaml = format!(
+            "{MINIMAL_CONFIG}\nhealth_check:\n  probe:\n    grpc:\n      service: orders.v1.Orders\n"
+        );
+        let config = load_test_config("grpc-health", &yaml).expect("gRPC health check must load");
+        let health_check = config.health_check.as_ref().expect("health check");
+
+        assert_eq!(config.health_check_port(), Some(8080));
+        assert_eq!(health_check.interval_secs, 5);
+        assert_eq!(health_check.timeout_ms, 1_000);
+        assert_eq!(health_check.healthy_threshold, 2);
+        assert_eq!(health_check.unhealthy_threshold, 3);
+        match &health_check.probe {
+            HealthProbeConfig::Grpc(grpc) => {
+                assert_eq!(grpc.service, "orders.v1.Orders");
+                assert!(grpc.tls.is_none());
+            }
+            HealthProbeConfig::Tcp => panic!("expected a gRPC probe"),
+        }
+    }
+
+    #[test]
+    fn load_accepts_tls_grpc_health_check_on_explicit_port() {
+        let yaml = format!(
+            "{MINIMAL_CONFIG}\nhealth_check:\n  port: 9000\n  probe:\n    grpc:\n      tls:\n        server_name: backend.internal\n"
+        );
+        let config = load_test_config("grpc-tls-health", &yaml).expect("TLS probe must load");
+
+        assert_eq!(config.health_check_port(), Some(9000));
+        let Some(HealthProbeConfig::Grpc(grpc)) =
+            config.health_check.as_ref().map(|health| &health.probe)
+        else {
+            panic!("expected a gRPC probe");
+        };
+        let tls = grpc.tls.as_ref().expect("TLS configured");
+        assert_eq!(tls.server_name.as_deref(), Some("backend.internal"));
+        assert!(tls.ca_cert.is_none());
+    }
+
+    #[test]
+    fn load_rejects_invalid_health_check_settings() {
+        for (name, settings) in [
+            ("zero-port", "  port: 0\n"),
+            ("zero-interval", "  interval_secs: 0\n"),
+            ("zero-timeout", "  timeout_ms: 0\n"),
+            ("zero-threshold", "  healthy_threshold: 0\n"),
+        ] {
+            let yaml = format!("{MINIMAL_CONFIG}\nhealth_check:\n  probe: tcp\n{settings}");
+            let error = load_test_config(name, &yaml).expect_err("invalid health check must fail");
+            assert!(
+                error.to_string().contains("Health check"),
+                "{name}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn load_defaults_to_resetting_connections_at_shutdown() {
+        let config = load_test_config("shutdown-reset-default", MINIMAL_CONFIG)
+            .expect("minimal config must load");
+        assert!(config.shutdown_reset_connections);
+
+        let yaml = format!("{MINIMAL_CONFIG}\nshutdown_reset_connections: false\n");
+        let config = load_test_config("shutdown-reset", &yaml).expect("opt-out must load");
+        assert!(!config.shutdown_reset_connections);
+    }
+
+    #[test]
+    fn metrics_endpoint_is_enabled_by_default_with_bounded_backend_labels() {
+        let config =
+            load_test_config("metrics-default", MINIMAL_CONFIG).expect("minimal config must load");
+        assert!(config.admin.metrics.enabled);
+        assert_eq!(config.admin.metrics.max_backends, 100);
+        assert!(config.admin.metrics.auth.is_none());
+
+        let yaml = format!(
+            "{MINIMAL_CONFIG}\nadmin:\n  metrics:\n    max_backends: 20\n    auth:\n      username: prometheus\n"
+        );
+        let config = load_test_config("metrics-auth", &yaml).expect("metrics auth must load");
+        assert_eq!(config.admin.metrics.max_backends, 20);
+        assert_eq!(
+            config.admin.metrics.auth.map(|auth| auth.username),
+            Some("prometheus".to_string())
+        );
+
+        let yaml =
+            format!("{MINIMAL_CONFIG}\nadmin:\n  metrics:\n    auth:\n      username: \"a:b\"\n");
+        let error = load_test_config("metrics-auth-colon", &yaml).expect_err("colon must fail");
+        assert!(error.to_string().contains("Metrics auth username"));
+    }
+
+    #[test]
+    fn admin_redacts_client_addresses_unless_disabled() {
+        let config =
+            load_test_config("redact-default", MINIMAL_CONFIG).expect("minimal config must load");
+        assert!(config.admin.redact_client_ips);
+
+        let yaml = format!("{MINIMAL_CONFIG}\nadmin:\n  redact_client_ips: false\n");
+        let config = load_test_config("redact-disabled", &yaml).expect("opt-out must load");
+        assert!(!config.admin.redact_client_ips);
+    }
+
+    #[test]
+    fn load_defaults_drain_without_force_close() {
+        let config =
+            load_test_config("drain-default", MINIMAL_CONFIG).expect("minimal config must load");
+        assert_eq!(config.drain.deadline_secs, 300);
+        assert!(!config.drain.force_close);
+
+        let yaml = format!("{MINIMAL_CONFIG}\ndrain:\n  deadline_secs: 30\n  force_close: true\n");
+        let config = load_test_config("drain", &yaml).expect("drain settings must load");
+        assert_eq!(config.drain.deadline_secs, 30);
+        assert!(config.drain.force_close);
+    }
+
+    #[test]
+    fn load_rejects_zero_drain_deadline() {
+        let yaml = format!("{MINIMAL_CONFIG}\ndrain:\n  deadline_secs: 0\n");
+        let error = load_test_config("zero-drain-deadline", &yaml)
+            .expect_err("a zero deadline would reset connections immediately");
+
+        assert!(error.to_string().contains("Drain deadline"));
+    }
+
+    #[test]
+    fn load_reads_backend_priorities_and_tier_threshold() {
+        let config =
+            load_test_config("tiers-default", MINIMAL_CONFIG).expect("minimal config must load");
+        assert_eq!(config.priority_tiers.min_eligible_backends, 1);
+
+        let yaml = MINIMAL_CONFIG.replace(
+            "        ip: 127.0.0.1\n",
+            "        ip: 127.0.0.1\n      - name: standby-1\n        ip: 127.0.0.2\n        priority: 1\n",
+        ) + "\npriority_tiers:\n  min_eligible_backends: 2\n";
+        let config = load_test_config("tiers", &yaml).expect("priorities must load");
+        let BackendSource::Static { backends } = &config.provider else {
+            panic!("expected static provider");
+        };
+        assert_eq!(
+            backends
+                .iter()
+                .map(|host| host.priority)
+                .collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!(config.priority_tiers.min_eligible_backends, 2);
+
+        let zero = format!("{MINIMAL_CONFIG}\npriority_tiers:\n  min_eligible_backends: 0\n");
+        let error = load_test_config("tiers-zero", &zero).expect_err("zero threshold must fail");
+        assert!(error.to_string().contains("min_eligible_backends"));
+    }
+
+    #[test]
+    fn load_defaults_slow_start_to_disabled() {
+        let config = load_test_config("slow-start-default", MINIMAL_CONFIG)
+            .expect("minimal config must load");
+        assert_eq!(config.slow_start_secs, 0);
+
+        let yaml = format!("{MINIMAL_CONFIG}\nslow_start_secs: 45\n");
+        let config = load_test_config("slow-start", &yaml).expect("slow start must load");
+        assert_eq!(config.slow_start_secs, 45);
+    }
+
+    #[test]
+    fn load_defaults_outlier_detection() {
+        let yaml = format!("{MINIMAL_CONFIG}\noutlier_detection: {{}}\n");
+        let config = load_test_config("outliers", &yaml).expect("outlier detection must load");
+        let outliers = config.outlier_detection.expect("outlier detection");
+
+        assert_eq!(outliers.interval_secs, 10);
+        assert_eq!(outliers.min_connections, 10);
+        assert_eq!(outliers.server_reset_percent, 50.0);
+        assert_eq!(outliers.handshake_failure_percent, 20.0);
+        assert_eq!(outliers.base_ejection_secs, 30);
+        assert_eq!(outliers.max_ejection_secs, 300);
+        assert_eq!(outliers.max_ejection_percent, 10.0);
+    }
+
+    #[test]
+    fn load_rejects_invalid_outlier_detection_settings() {
+        for (name, settings) in [
+            ("zero-outlier-interval", "interval_secs: 0"),
+            (
+                "short-max-ejection",
+                "base_ejection_secs: 60, max_ejection_secs: 30",
+            ),
+            ("zero-reset-threshold", "server_reset_percent: 0"),
+            (
+                "large-handshake-threshold",
+                "handshake_failure_percent: 101",
+            ),
+            ("large-max-ejection", "max_ejection_percent: 150"),
+        ] {
+            let yaml = format!("{MINIMAL_CONFIG}\noutlier_detection: {{ {settings} }}\n");
+            let error = load_test_config(name, &yaml).expect_err("invalid outliers must fail");
+            assert!(
+                error.to_string().contains("Outlier detection"),
+                "{name}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn dns_provider_defaults_and_validation() {
+        let dns = MINIMAL_CONFIG.replace(
+            "  static:\n    backends:\n      - name: backend-1\n        ip: 127.0.0.1\n",
+            "  dns:\n    name: _http._tcp.example.internal\n    record_type: srv\n",
+        );
+        let config = load_test_config("dns", &dns).expect("DNS provider should load");
+        let BackendSource::Dns(provider) = &config.provider else {
+            panic!("expected DNS provider, got {:?}", config.provider);
+        };
+        assert_eq!(provider.record_type, DnsRecordType::Srv);
+        assert!(provider.nameservers.is_empty());
+        assert_eq!(provider.min_refresh_secs, 5);
+        assert_eq!(provider.max_refresh_secs, 300);
+
+        for (name, extra, message) in [
+            ("dns-min", "    min_refresh_secs: 0\n", "min_refresh_secs"),
+            (
+                "dns-max",
+                "    min_refresh_secs: 60\n    max_refresh_secs: 30\n",
+                "max_refresh_secs",
+            ),
+        ] {
+            let invalid = dns.replace("record_type: srv\n", &format!("record_type: srv\n{extra}"));
+            let error = load_test_config(name, &invalid).expect_err("invalid refresh must fail");
+            assert!(error.to_string().contains(message), "{name}: {error}");
+        }
+    }
+
+    #[test]
+    fn file_provider_requires_a_known_extension() {
+        let file = |path: &str| {
+            MINIMAL_CONFIG.replace(
+                "  static:\n    backends:\n      - name: backend-1\n        ip: 127.0.0.1\n",
+                &format!("  file:\n    path: {path}\n"),
+            )
+        };
+
+        let config = load_test_config("file", &file("/etc/xlb/backends.json"))
+            .expect("JSON backend file should load");
+        assert!(matches!(config.provider, BackendSource::File(_)));
+
+        let error = load_test_config("file-ext", &file("/etc/xlb/backends.txt"))
+            .expect_err("unknown extension must fail");
+        assert!(error.to_string().contains(".yaml, .yml, or .json"));
+    }
+
+    #[test]
+    fn consul_provider_defaults_and_validation() {
+        let consul = MINIMAL_CONFIG.replace(
+            "  static:\n    backends:\n      - name: backend-1\n        ip: 127.0.0.1\n",
+            "  consul:\n    service: web\n    tags: [primary]\n",
+        );
+        let config = load_test_config("consul", &consul).expect("Consul provider should load");
+        let BackendSource::Consul(provider) = &config.provider else {
+            panic!("expected Consul provider, got {:?}", config.provider);
+        };
+        assert_eq!(provider.address, "http://127.0.0.1:8500");
+        assert_eq!(provider.tags, vec!["primary".to_string()]);
+        assert_eq!(provider.datacenter, None);
+        assert_eq!(provider.weight_meta_key, None);
+        assert_eq!(provider.wait_secs, 60);
+
+        for (name, extra, message) in [
+            ("consul-address", "    address: consul:8500\n", "http://"),
+            ("consul-wait", "    wait_secs: 0\n", "wait_secs"),
+            ("consul-wait-max", "    wait_secs: 601\n", "wait_secs"),
+        ] {
+            let invalid = consul.replace("service: web\n", &format!("service: web\n{extra}"));
+            let error =
+                load_test_config(name, &invalid).expect_err("invalid Consul config must fail");
+            assert!(error.to_string().contains(message), "{name}: {error}");
+        }
+    }
+
+    #[test]
+    fn http_provider_requires_auth_unless_polling() {
+        let http = |provider: &str| {
+            MINIMAL_CONFIG.replace(
+                "  static:\n    backends:\n      - name: backend-1\n        ip: 127.0.0.1\n",
+                provider,
+            )
+        };
+        let push = http("  http: {}\n");
+
+        let error = load_test_config("http-push", &push).expect_err("pushes need admin auth");
+        assert!(error.to_string().contains("admin.auth"), "{error}");
+
+        let config = load_test_config(
+            "http-push-auth",
+            &format!("{push}\nadmin:\n  auth:\n    username: operator\n"),
+        )
+        .expect("authenticated push provider should load");
+        load_test_config(
+            "http-push-client-ca",
+            &format!(
+                "{push}\nadmin:\n  tls:\n    cert: /etc/xlb/tls.crt\n    key: /etc/xlb/tls.key\n    client_ca: /etc/xlb/clients.crt\n"
+            ),
+        )
+        .expect("client certificates authenticate pushes");
+        let error = load_test_config(
+            "http-push-tls",
+            &format!(
+                "{push}\nadmin:\n  tls:\n    cert: /etc/xlb/tls.crt\n    key: /etc/xlb/tls.key\n"
+            ),
+        )
+        .expect_err("TLS without client certificates does not authenticate pushes");
+        assert!(error.to_string().contains("admin.tls.client_ca"), "{error}");
+
+        let BackendSource::Http(provider) = &config.provider else {
+            panic!("expected HTTP provider, got {:?}", config.provider);
+        };
+        assert_eq!(
+            provider.state_path,
+            PathBuf::from("/var/lib/xlb/backends.json")
+        );
+        assert!(provider.poll.is_none());
+
+        let poll = http("  http:\n    poll:\n      url: https://orchestrator.internal/backends\n");
+        let config = load_test_config("http-poll", &poll).expect("poll provider should load");
+        let BackendSource::Http(provider) = &config.provider else {
+            panic!("expected HTTP provider, got {:?}", config.provider);
+        };
+        assert_eq!(
+            provider.poll.as_ref().map(|poll| poll.interval_secs),
+            Some(10)
+        );
+
+        let error = load_test_config(
+            "http-poll-url",
+            &poll.replace("https://orchestrator.internal", "orchestrator.internal"),
+        )
+        .expect_err("poll url needs a scheme");
+        assert!(error.to_string().contains("http://"), "{error}");
+    }
+
+    #[test]
+    fn diff_separates_live_settings_from_restart_only_ones() {
+        let running = load_test_config("diff-running", MINIMAL_CONFIG).expect("config must load");
+        assert_eq!(
+            running.diff(&running.clone(), &HashSet::new()),
+            ConfigDiff::default()
+        );
+
+        let live = MINIMAL_CONFIG
+            .replace("remote_port: 8080", "remote_port: 9090")
+            .replace("ip: 127.0.0.1", "ip: 127.0.0.2")
+            .replace(
+                "shutdown_timeout: 15",
+                "shutdown_timeout: 30\norphan_ttl_secs: 600",
+            );
+        let next = load_test_config("diff-live", &live).expect("config must load");
+        assert_eq!(
+            running.diff(&next, &HashSet::new()),
+            ConfigDiff {
+                live: vec!["ports", "provider", "orphan_ttl_secs", "shutdown_timeout"],
+                restart: vec![],
+            }
+        );
+
+        let restart = format!(
+            "{}\nadmin:\n  port: 9191\n",
+            MINIMAL_CONFIG.replace("listen: auto", "listen:\n  ip: 127.0.0.1")
+        );
+        let next = load_test_config("diff-restart", &restart).expect("config must load");
+        assert_eq!(
+            running.diff(&next, &HashSet::new()).restart,
+            vec!["listen", "admin"]
+        );
+
+        // A port change also moves a health check defaulted to the first port.
+        let checked = format!("{MINIMAL_CONFIG}\nhealth_check:\n  probe: tcp\n");
+        let running = load_test_config("diff-checked", &checked).expect("config must load");
+        let next = load_test_config(
+            "diff-checked-port",
+            &checked.replace("remote_port: 8080", "remote_port: 9090"),
+        )
+        .expect("config must load");
+        assert_eq!(
+            running.diff(&next, &HashSet::new()),
+            ConfigDiff {
+                live: vec!["ports"],
+                restart: vec!["health_check"],
+            }
+        );
+
+        // Swapping to or from the HTTP provider needs a restart.
+        let http = format!(
+            "{}\nadmin:\n  auth:\n    username: operator\n",
+            MINIMAL_CONFIG.replace(
+                "  static:\n    backends:\n      - name: backend-1\n        ip: 127.0.0.1\n",
+                "  http: {}\n",
+            )
+        );
+        let next = load_test_config("diff-http", &http).expect("config must load");
+        assert_eq!(
+            running.diff(&next, &HashSet::new()).restart,
+            vec!["provider", "health_check"]
+        );
+
+        // Credentials change in place; the rest of `admin` does not.
+        let authenticated = format!("{checked}\nadmin:\n  auth:\n    username: operator\n");
+        let next = load_test_config("diff-auth", &authenticated).expect("config must load");
+        assert_eq!(
+            running.diff(&next, &HashSet::new()),
+            ConfigDiff {
+                live: vec!["admin.auth"],
+                restart: vec![],
+            }
+        );
+        let relocated = authenticated.replace("admin:\n", "admin:\n  port: 9191\n");
+        let next = load_test_config("diff-auth-port", &relocated).expect("config must load");
+        assert_eq!(
+            running.diff(&next, &HashSet::new()),
+            ConfigDiff {
+                live: vec!["admin.auth"],
+                restart: vec!["admin"],
+            }
+        );
+    }
+
+    #[test]
+    fn diff_keeps_listen_ports_of_open_connections() {
+        let running = load_test_config("diff-open", MINIMAL_CONFIG).expect("config must load");
+        let open = HashSet::from([80]);
+
+        // The backend port can change under open connections.
+        let rerouted = load_test_config(
+            "diff-open-rerouted",
+            &MINIMAL_CONFIG.replace("remote_port: 8080", "remote_port: 9090"),
+        )
+        .expect("config must load");
+        assert_eq!(running.diff(&rerouted, &open).live, vec!["ports"]);
+
+        // Their listen port cannot go away until they close.
+        let moved = load_test_config(
+            "diff-open-moved",
+            &MINIMAL_CONFIG.replace("local_port: 80", "local_port: 81"),
+        )
+        .expect("config must load");
+        assert_eq!(running.diff(&moved, &open).restart, vec!["ports"]);
+        assert_eq!(running.diff(&moved, &HashSet::new()).live, vec!["ports"]);
+
+        let added = load_test_config(
+            "diff-open-added",
+            &MINIMAL_CONFIG.replace(
+                "    remote_port: 8080\n",
+                "    remote_port: 8080\n  - local_port: 81\n    remote_port: 8081\n",
+            ),
+        )
+        .expect("config must load");
+        assert_eq!(running.diff(&added, &open).live, vec!["ports"]);
+    }
+
+    #[test]
+    fn kubernetes_provider_lists_services_and_selectors() {
+        let kubernetes = |provider: &str| {
+            MINIMAL_CONFIG.replace(
+                "  static:\n    backends:\n      - name: backend-1\n        ip: 127.0.0.1\n",
+                &format!("  kubernetes:\n{provider}"),
+            )
+        };
+        let config = load_test_config(
+            "kubernetes-watches",
+            &kubernetes(concat!(
+                "    namespace: default\n    service: web\n",
+                "    services:\n      - namespace: shop\n        service: cart\n",
+                "    selector:\n      namespaces: [blue, green]\n      labels: app=api\n",
+            )),
+        )
+        .expect("kubernetes watches must load");
+        let BackendSource::Kubernetes(provider) = &config.provider else {
+            panic!("expected kubernetes provider, got {:?}", config.provider);
+        };
+        let watches: Vec<String> = provider.watches().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            watches,
+            vec![
+                "service default/web",
+                "service shop/cart",
+                "selector 'app=api' in namespace blue",
+                "selector 'app=api' in namespace green",
+            ]
+        );
+
+        for (name, provider, message) in [
+            ("kubernetes-none", "    topology: {}\n", "needs a service"),
+            (
+                "kubernetes-half",
+                "    service: web\n",
+                "namespace and service must be set together",
+            ),
+            (
+                "kubernetes-duplicate",
+                "    namespace: default\n    service: web\n    services:\n      - {namespace: default, service: web}\n",
+                "more than once",
+            ),
+            (
+                "kubernetes-selector",
+                "    selector:\n      namespaces: []\n      labels: app=api\n",
+                "at least one namespace",
+            ),
+        ] {
+            let error = load_test_config(name, &kubernetes(provider))
+                .expect_err("invalid kubernetes provider must fail");
+            assert!(error.to_string().contains(message), "{error:#}");
+        }
+    }
+
+    #[test]
+    fn port_mappings_accept_service_port_names() {
+        let config = load_test_config(
+            "port-name",
+            &MINIMAL_CONFIG.replace(
+                "    remote_port: 8080\n",
+                "    remote_port: 8080\n    port_name: http\n  - local_port: 443\n    remote_port: 8443\n",
+            ),
+        )
+        .expect("named port mapping must load");
+        assert_eq!(config.ports[0].port_name.as_deref(), Some("http"));
+        assert_eq!(config.ports[1].port_name, None);
+
+        let error = load_test_config(
+            "port-name-empty",
+            &MINIMAL_CONFIG.replace(
+                "    remote_port: 8080\n",
+                "    remote_port: 8080\n    port_name: \"\"\n",
+            ),
+        )
+        .expect_err("empty port name must be rejected");
+        assert!(error.to_string().contains("port_name"), "{error}");
+    }
+
+    #[test]
+    fn load_balancer_addresses_parse_as_ranges() {
+        let yaml = format!(
+            "{MINIMAL_CONFIG}load_balancer:\n  class: xlb.io/lb\n  addresses:\n    - 192.0.2.10\n    - 192.0.2.20-192.0.2.29\n    - 198.51.100.17/28\n"
+        );
+        let config = load_test_config("load-balancer", &yaml).expect("load balancer must load");
+        let load_balancer = config.load_balancer.expect("load balancer config");
+        assert_eq!(load_balancer.class, "xlb.io/lb");
+        let ip = |address: &str| address.parse::<Ipv4Addr>().expect("valid IPv4");
+        assert_eq!(
+            load_balancer.address_ranges().expect("valid addresses"),
+            vec![
+                (ip("192.0.2.10"), ip("192.0.2.10")),
+                (ip("192.0.2.20"), ip("192.0.2.29")),
+                (ip("198.51.100.16"), ip("198.51.100.31")),
+            ]
+        );
+
+        for (name, section, message) in [
+            (
+                "load-balancer-class",
+                "  class: \" \"\n  addresses: [192.0.2.10]\n",
+                "class",
+            ),
+            (
+                "load-balancer-empty",
+                "  class: xlb.io/lb\n  addresses: []\n",
+                "at least one address",
+            ),
+            (
+                "load-balancer-range",
+                "  class: xlb.io/lb\n  addresses: [192.0.2.20-192.0.2.10]\n",
+                "ends before it starts",
+            ),
+            (
+                "load-balancer-prefix",
+                "  class: xlb.io/lb\n  addresses: [192.0.2.0/33]\n",
+                "prefix length",
+            ),
+            (
+                "load-balancer-ipv6",
+                "  class: xlb.io/lb\n  addresses: [\"2001:db8::1\"]\n",
+                "not an IPv4 address",
+            ),
+        ] {
+            let yaml = format!("{MINIMAL_CONFIG}load_balancer:\n{section}");
+            let error = load_test_config(name, &yaml).expect_err("invalid load balancer");
+            assert!(error.to_string().contains(message), "{name}: {error}");
+        }
+    }
+
+    #[test]
+    fn kubernetes_topology_defaults_and_validation() {
+        let kubernetes = |topology: &str| {
+            MINIMAL_CONFIG.replace(
+                "  static:\n    backends:\n      - name: backend-1\n        ip: 127.0.0.1\n",
+                &format!("  kubernetes:\n    namespace: default\n    service: web\n{topology}"),
+            )
+        };
+        let config =
+            load_test_config("topology-default", &kubernetes("")).expect("kubernetes must load");
+        let BackendSource::Kubernetes(KubernetesProviderConfig { topology, .. }) = &config.provider
+        else {
+            panic!("expected kubernetes provider, got {:?}", config.provider);
+        };
+        assert_eq!(topology.prefer, TopologyPreference::None);
+        assert_eq!(topology.min_local_backends, 1);
+        assert!(topology.hints);
+        assert_eq!(topology.zone, None);
+
+        let config = load_test_config(
+            "topology",
+            &kubernetes(
+                "    topology:\n      prefer: zone\n      min_local_backends: 3\n      hints: false\n      zone: eu-west-1a\n",
+            ),
+        )
+        .expect("topology must load");
+        let BackendSource::Kubernetes(KubernetesProviderConfig { topology, .. }) = &config.provider
+        else {
+            panic!("expected kubernetes provider, got {:?}", config.provider);
+        };
+        assert_eq!(topology.prefer, TopologyPreference::Zone);
+        assert_eq!(topology.min_local_backends, 3);
+        assert!(!topology.hints);
+        assert_eq!(topology.zone.as_deref(), Some("eu-west-1a"));
+
+        for (name, topology, message) in [
+            (
+                "topology-min",
+                "    topology:\n      prefer: node\n      min_local_backends: 0\n",
+                "min_local_backends",
+            ),
+            (
+                "topology-zone",
+                "    topology:\n      prefer: zone\n      zone: \"\"\n",
+                "zone must not be empty",
+            ),
+        ] {
+            let error = load_test_config(name, &kubernetes(topology))
+                .expect_err("invalid topology must fail");
+            assert!(error.to_string().contains(message), "{error:#}");
+        }
+    }
+
+    #[test]
+    fn composite_provider_validates_its_sources() {
+        let composite = |sources: &str| {
+            MINIMAL_CONFIG.replace(
+                "  static:\n    backends:\n      - name: backend-1\n        ip: 127.0.0.1\n",
+                &format!("  composite:\n    health: any\n    sources:\n{sources}"),
+            )
+        };
+        let config = load_test_config(
+            "composite",
+            &composite(concat!(
+                "      - provider:\n          static:\n            backends:\n",
+                "              - name: legacy-1\n                ip: 10.0.0.1\n",
+                "        weight: 25\n        priority: 1\n",
+                "      - provider:\n          kubernetes:\n            namespace: default\n",
+                "            service: web\n",
+            )),
+        )
+        .expect("composite provider should load");
+        let BackendSource::Composite(provider) = &config.provider else {
+            panic!("expected composite provider, got {:?}", config.provider);
+        };
+        assert_eq!(provider.health, CompositeHealth::Any);
+        assert_eq!(provider.sources.len(), 2);
+        assert_eq!(
+            (provider.sources[0].weight, provider.sources[0].priority),
+            (25, 1)
+        );
+        assert_eq!(
+            (provider.sources[1].weight, provider.sources[1].priority),
+            (100, 0)
+        );
+
+        for (name, sources, message) in [
+            ("composite-empty", "      []\n", "at least one source"),
+            (
+                "composite-weight",
+                "      - provider:\n          kubernetes: {namespace: default, service: web}\n        weight: 0\n",
+                "weight",
+            ),
+            (
+                "composite-nested",
+                "      - provider:\n          composite:\n            sources: []\n",
+                "cannot be composite",
+            ),
+            (
+                "composite-child",
+                "      - provider:\n          static:\n            backends:\n              - name: v6\n                ip: \"2001:db8::1\"\n",
+                "IPv6",
+            ),
+        ] {
+            let error = load_test_config(name, &composite(sources))
+                .expect_err("invalid composite provider must fail");
+            assert!(error.to_string().contains(message), "{name}: {error}");
+        }
+    }
+
     #[test]
     fn load_rejects_unsupported_protocol_and_routing_mode() {
         let udp = MINIMAL_CONFIG.replace("proto: tcp", "proto: udp");
@@ -8,6 +8,7 @@ use aya::{Ebpf, EbpfLoader};
 use log::{info, warn};
 use std::net::IpAddr;
 use xlb_common::config::ebpf::{EbpfConfig, Strategy};
+use xlb_common::consts::MAX_PORT_MAPPINGS;
 use xlb_common::types::PortMapping;
 
 pub struct LoadedEbpf {
@@ -24,8 +25,10 @@ pub fn to_ebpf_config(cfg: &XlbConfig, iface: &ListenIface) -> EbpfConfig {
     let mut port_mappings = [PortMapping {
         local_port: 0,
         remote_port: 0,
-    }; 8];
-    port_mappings[..cfg.ports.len()].copy_from_slice(&cfg.ports);
+    }; MAX_PORT_MAPPINGS];
+    for (slot, port) in port_mappings.iter_mut().zip(&cfg.ports) {
+        *slot = port.into();
+    }
 
     EbpfConfig {
         strategy: Strategy::RoundRobin,
@@ -0,0 +1,239 @@
+use crate::config::Host;
+use crate::health::{HealthCheckSettings, HealthProbe, TargetHealth};
+use crate::provider::BackendProvider;
+use crate::status::{HealthCheckState, HealthCheckStatus};
+use log::{info, warn};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::{MissedTickBehavior, interval};
+
+/// Periodically probes every backend discovered by a provider and filters
+/// the provider's output down to backends that passed their health checks.
+///
+/// Probing runs on its own task so that a slow or unreachable backend never
+/// delays the maintenance loop; the loop only reads the latest results.
+pub struct HealthChecker {
+    probe: Arc<dyn HealthProbe>,
+    settings: HealthCheckSettings,
+    /// Latest health of each currently discovered backend.
+    targets: RwLock<HashMap<IpAddr, TargetHealth>>,
+    /// Handle to the probe task.
+    task: RwLock<Option<JoinHandle<()>>>,
+}
+
+impl HealthChecker {
+    pub fn new(probe: Arc<dyn HealthProbe>, settings: HealthCheckSettings) -> Self {
+        Self {
+            probe,
+            settings,
+            targets: RwLock::new(HashMap::new()),
+            task: RwLock::new(None),
+        }
+    }
+
+    /// Start probing the provider's backends every configured interval.
+    pub fn start(self: &Arc<Self>, provider: Arc<dyn BackendProvider>) {
+        let checker = self.clone();
+        let handle = tokio::spawn(async move {
+            let mut ticker = interval(checker.settings.interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                checker.check_hosts(&provider.get_backends()).await;
+            }
+        });
+
+        if let Some(previous) = self
+            .task
+            .write()
+            .expect("health check task lock poisoned")
+            .replace(handle)
+        {
+            previous.abort();
+        }
+    }
+
+    /// Probe each host once, concurrently, and record the results.
+    /// Hosts no longer present are forgotten so a returning backend
+    /// must pass a fresh probe before it is admitted again.
+    pub async fn check_hosts(&self, hosts: &[Host]) {
+        let present: HashSet<IpAddr> = hosts.iter().map(|host| host.ip).collect();
+        self.targets
+            .write()
+            .expect("health check lock poisoned")
+            .retain(|ip, _| present.contains(ip));
+
+        let probes = hosts.iter().map(|host| async move {
+            let target = SocketAddr::new(host.ip, self.settings.port);
+            let result =
+                match tokio::time::timeout(self.settings.timeout, self.probe.check(target)).await {
+                    Ok(Ok(())) => Ok(()),
+                    Ok(Err(error)) => Err(format!("{error:#}")),
+                    Err(_) => Err(format!(
+                        "Timed out after {}ms",
+                        self.settings.timeout.as_millis()
+                    )),
+                };
+            (host, result)
+        });
+        let results = futures::future::join_all(probes).await;
+
+        let mut targets = self.targets.write().expect("health check lock poisoned");
+        for (host, result) in results {
+            let target = targets.entry(host.ip).or_default();
+            let previous = target.record(result, &self.settings);
+            match (previous, target.state) {
+                (HealthCheckState::Healthy, HealthCheckState::Healthy) => {}
+                (_, HealthCheckState::Healthy) => {
+                    info!("Backend {} ({}) passed health checks", host.name, host.ip);
+                }
+                (HealthCheckState::Unhealthy, HealthCheckState::Unhealthy) => {}
+                (_, HealthCheckState::Unhealthy) => warn!(
+                    "Backend {} ({}) failed health checks: {}",
+                    host.name,
+                    host.ip,
+                    target.last_error.as_deref().unwrap_or("unknown error")
+                ),
+                (_, HealthCheckState::Pending) => {}
+            }
+        }
+    }
+
+    /// Hosts that may receive new connections, in provider order.
+    pub fn filter(&self, hosts: &[Host]) -> Vec<Host> {
+        let targets = self.targets.read().expect("health check lock poisoned");
+        hosts
+            .iter()
+            .filter(|host| targets.get(&host.ip).is_some_and(TargetHealth::is_healthy))
+            .cloned()
+            .collect()
+    }
+
+    /// Current health of every probed backend for the status API.
+    pub fn statuses(&self) -> BTreeMap<IpAddr, HealthCheckStatus> {
+        self.targets
+            .read()
+            .expect("health check lock poisoned")
+            .iter()
+            .map(|(ip, target)| (*ip, target.into()))
+            .collect()
+    }
+
+    pub fn shutdown(&self) {
+        if let Some(handle) = self
+            .task
+            .write()
+            .expect("health check task lock poisoned")
+            .take()
+        {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{Result, bail};
+    use async_trait::async_trait;
+    use std::time::Duration;
+
+    /// Healthy only for the listed addresses.
+    struct AllowList(HashSet<IpAddr>);
+
+    #[async_trait]
+    impl HealthProbe for AllowList {
+        async fn check(&self, target: SocketAddr) -> Result<()> {
+            if !self.0.contains(&target.ip()) {
+                bail!("refused");
+            }
+            Ok(())
+        }
+    }
+
+    /// Never completes, to exercise the probe timeout.
+    struct Hang;
+
+    #[async_trait]
+    impl HealthProbe for Hang {
+        async fn check(&self, _target: SocketAddr) -> Result<()> {
+            std::future::pending().await
+        }
+    }
+
+    fn settings() -> HealthCheckSettings {
+        HealthCheckSettings {
+            port: 8080,
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_millis(50),
+            healthy_threshold: 1,
+            unhealthy_threshold: 1,
+        }
+    }
+
+    fn host(name: &str, ip: &str) -> Host {
+        Host {
+            name: name.into(),
+            ip: ip.parse().expect("valid IP"),
+            weight: None,
+            priority: 0,
+            ports: BTreeMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn filter_excludes_pending_and_failed_backends() {
+        let healthy = host("healthy", "10.0.0.1");
+        let failing = host("failing", "10.0.0.2");
+        let checker =
+            HealthChecker::new(Arc::new(AllowList(HashSet::from([healthy.ip]))), settings());
+        let hosts = vec![healthy.clone(), failing.clone()];
+
+        assert!(checker.filter(&hosts).is_empty(), "unprobed hosts wait");
+
+        checker.check_hosts(&hosts).await;
+        assert_eq!(checker.filter(&hosts), vec![healthy.clone()]);
+
+        let statuses = checker.statuses();
+        assert_eq!(statuses[&healthy.ip].state, HealthCheckState::Healthy);
+        assert_eq!(statuses[&failing.ip].state, HealthCheckState::Unhealthy);
+        assert!(
+            statuses[&failing.ip]
+                .last_error
+                .as_deref()
+                .is_some_and(|error| error.contains("refused"))
+        );
+    }
+
+    #[tokio::test]
+    async fn removed_backends_are_forgotten() {
+        let backend = host("backend", "10.0.0.1");
+        let checker =
+            HealthChecker::new(Arc::new(AllowList(HashSet::from([backend.ip]))), settings());
+
+        checker.check_hosts(std::slice::from_ref(&backend)).await;
+        checker.check_hosts(&[]).await;
+
+        assert!(checker.statuses().is_empty());
+        assert!(checker.filter(&[backend]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn probes_that_exceed_the_timeout_fail() {
+        let backend = host("backend", "10.0.0.1");
+        let checker = HealthChecker::new(Arc::new(Hang), settings());
+
+        checker.check_hosts(std::slice::from_ref(&backend)).await;
+
+        let status = &checker.statuses()[&backend.ip];
+        assert_eq!(status.state, HealthCheckState::Unhealthy);
+        assert!(
+            status
+                .last_error
+                .as_deref()
+                .is_some_and(|error| error.contains("Timed out"))
+        );
+    }
+}
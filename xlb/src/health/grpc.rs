@@ -0,0 +1,213 @@
+use crate::config::GrpcProbeConfig;
+use crate::health::HealthProbe;
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint};
+use tonic_health::pb::HealthCheckRequest;
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+
+/// Calls `grpc.health.v1.Health/Check` on the backend. Only a `SERVING`
+/// response counts as healthy; `UNKNOWN`, `NOT_SERVING`, an unknown service
+/// name, and any RPC error all fail the probe.
+pub struct GrpcProbe {
+    service: String,
+    tls: Option<ClientTlsConfig>,
+}
+
+impl GrpcProbe {
+    pub fn new(config: &GrpcProbeConfig) -> Result<Self> {
+        let tls = config
+            .tls
+            .as_ref()
+            .map(|tls| -> Result<ClientTlsConfig> {
+                let mut client = match &tls.ca_cert {
+                    Some(path) => {
+                        let pem = std::fs::read(path).with_context(|| {
+                            format!("Failed to read gRPC health check CA {}", path.display())
+                        })?;
+                        ClientTlsConfig::new().ca_certificate(Certificate::from_pem(pem))
+                    }
+                    None => ClientTlsConfig::new().with_native_roots(),
+                };
+                if let Some(server_name) = &tls.server_name {
+                    client = client.domain_name(server_name);
+                }
+                Ok(client)
+            })
+            .transpose()?;
+
+        Ok(Self {
+            service: config.service.clone(),
+            tls,
+        })
+    }
+}
+
+#[async_trait]
+impl HealthProbe for GrpcProbe {
+    async fn check(&self, target: SocketAddr) -> Result<()> {
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        let mut endpoint = Endpoint::from_shared(format!("{scheme}://{target}"))?;
+        if let Some(tls) = &self.tls {
+            endpoint = endpoint.tls_config(tls.clone())?;
+        }
+
+        let channel = endpoint
+            .connect()
+            .await
+            .with_context(|| format!("gRPC connect to {target} failed"))?;
+        let response = HealthClient::new(channel)
+            .check(HealthCheckRequest {
+                service: self.service.clone(),
+            })
+            .await
+            .map_err(|status| {
+                anyhow!(
+                    "gRPC health check failed: {:?} {}",
+                    status.code(),
+                    status.message()
+                )
+            })?;
+
+        let status = response.into_inner().status();
+        if status != ServingStatus::Serving {
+            bail!("gRPC health check reported {}", status.as_str_name());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GrpcProbeTlsConfig;
+    use tokio::sync::oneshot;
+    use tonic::transport::Server;
+    use tonic::transport::server::TcpIncoming;
+    use tonic_health::server::HealthReporter;
+
+    struct TestServer {
+        addr: SocketAddr,
+        reporter: HealthReporter,
+        stop: Option<oneshot::Sender<()>>,
+    }
+
+    impl Drop for TestServer {
+        fn drop(&mut self) {
+            if let Some(stop) = self.stop.take() {
+                let _ = stop.send(());
+            }
+        }
+    }
+
+    async fn serve_health() -> TestServer {
+        let (reporter, service) = tonic_health::server::health_reporter();
+        let incoming =
+            TcpIncoming::bind("127.0.0.1:0".parse().expect("valid address")).expect("bind");
+        let addr = incoming.local_addr().expect("local address");
+        let (stop, stopped) = oneshot::channel();
+        tokio::spawn(
+            Server::builder()
+                .add_service(service)
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = stopped.await;
+                }),
+        );
+
+        TestServer {
+            addr,
+            reporter,
+            stop: Some(stop),
+        }
+    }
+
+    fn probe(service: &str) -> GrpcProbe {
+        GrpcProbe::new(&GrpcProbeConfig {
+            service: service.into(),
+            tls: None,
+        })
+        .expect("plaintext probe")
+    }
+
+    #[tokio::test]
+    async fn serving_server_is_healthy() {
+        let server = serve_health().await;
+
+        probe("")
+            .check(server.addr)
+            .await
+            .expect("overall server health is SERVING");
+    }
+
+    #[tokio::test]
+    async fn named_service_must_be_serving() {
+        let server = serve_health().await;
+        let probe = probe("orders.v1.Orders");
+
+        let unknown = probe
+            .check(server.addr)
+            .await
+            .expect_err("unregistered services are not healthy");
+        assert!(format!("{unknown:#}").contains("NotFound"));
+
+        server
+            .reporter
+            .set_service_status("orders.v1.Orders", tonic_health::ServingStatus::NotServing)
+            .await;
+        let not_serving = probe
+            .check(server.addr)
+            .await
+            .expect_err("NOT_SERVING is not healthy");
+        assert!(format!("{not_serving:#}").contains("NOT_SERVING"));
+
+        server
+            .reporter
+            .set_service_status("orders.v1.Orders", tonic_health::ServingStatus::Serving)
+            .await;
+        probe.check(server.addr).await.expect("SERVING is healthy");
+    }
+
+    #[tokio::test]
+    async fn unreachable_backend_is_unhealthy() {
+        let addr = serve_health().await.addr;
+        // Dropping the server stops it; give the listener a moment to close.
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        assert!(probe("").check(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn plaintext_probe_fails_against_tls_expectation() {
+        let server = serve_health().await;
+        let probe = GrpcProbe::new(&GrpcProbeConfig {
+            service: String::new(),
+            tls: Some(GrpcProbeTlsConfig {
+                server_name: Some("backend.internal".into()),
+                ca_cert: None,
+            }),
+        })
+        .expect("TLS probe with system roots");
+
+        assert!(
+            probe.check(server.addr).await.is_err(),
+            "a TLS probe must not accept a plaintext backend"
+        );
+    }
+
+    #[test]
+    fn missing_ca_bundle_fails_construction() {
+        let error = GrpcProbe::new(&GrpcProbeConfig {
+            service: String::new(),
+            tls: Some(GrpcProbeTlsConfig {
+                server_name: None,
+                ca_cert: Some("/nonexistent/xlb-health-ca.pem".into()),
+            }),
+        })
+        .err()
+        .expect("missing CA must fail");
+
+        assert!(error.to_string().contains("CA"));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::config::{HealthCheckConfig, HealthProbeConfig};
+use crate::health::{GrpcProbe, TcpProbe};
+use crate::status::{HealthCheckState, HealthCheckStatus};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A single kind of active check performed against a backend address.
+#[async_trait]
+pub trait HealthProbe: Send + Sync {
+    /// Probe the target once. `Ok` means the backend may receive
+    /// new connections; errors describe why it may not.
+    async fn check(&self, target: SocketAddr) -> Result<()>;
+}
+
+/// Build the probe selected by configuration.
+pub fn probe_from_config(config: &HealthProbeConfig) -> Result<Arc<dyn HealthProbe>> {
+    Ok(match config {
+        HealthProbeConfig::Tcp => Arc::new(TcpProbe),
+        HealthProbeConfig::Grpc(grpc) => Arc::new(GrpcProbe::new(grpc)?),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthCheckSettings {
+    /// Backend port every probe connects to.
+    pub port: u16,
+    pub interval: Duration,
+    pub timeout: Duration,
+    pub healthy_threshold: u32,
+    pub unhealthy_threshold: u32,
+}
+
+impl HealthCheckSettings {
+    pub fn new(config: &HealthCheckConfig, port: u16) -> Self {
+        Self {
+            port,
+            interval: Duration::from_secs(config.interval_secs),
+            timeout: Duration::from_millis(config.timeout_ms),
+            healthy_threshold: config.healthy_threshold,
+            unhealthy_threshold: config.unhealthy_threshold,
+        }
+    }
+}
+
+/// Consecutive probe results for one backend.
+///
+/// A newly discovered backend is pending until its first probe completes;
+/// a single success admits it and a single failure marks it unhealthy.
+/// After that, the configured thresholds provide hysteresis in both
+/// directions so one lost probe does not flap an otherwise healthy backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetHealth {
+    pub state: HealthCheckState,
+    pub consecutive_successes: u32,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+impl Default for TargetHealth {
+    fn default() -> Self {
+        Self {
+            state: HealthCheckState::Pending,
+            consecutive_successes: 0,
+            consecutive_failures: 0,
+            last_error: None,
+        }
+    }
+}
+
+impl TargetHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.state == HealthCheckState::Healthy
+    }
+
+    /// Apply one probe result and return the state before it was applied.
+    pub fn record(
+        &mut self,
+        result: Result<(), String>,
+        settings: &HealthCheckSettings,
+    ) -> HealthCheckState {
+        let previous = self.state;
+        match result {
+            Ok(()) => {
+                self.consecutive_successes = self.consecutive_successes.saturating_add(1);
+                self.consecutive_failures = 0;
+                self.last_error = None;
+                if previous == HealthCheckState::Pending
+                    || self.consecutive_successes >= settings.healthy_threshold
+                {
+                    self.state = HealthCheckState::Healthy;
+                }
+            }
+            Err(error) => {
+                self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+                self.consecutive_successes = 0;
+                self.last_error = Some(error);
+                if previous == HealthCheckState::Pending
+                    || self.consecutive_failures >= settings.unhealthy_threshold
+                {
+                    self.state = HealthCheckState::Unhealthy;
+                }
+            }
+        }
+        previous
+    }
+}
+
+impl From<&TargetHealth> for HealthCheckStatus {
+    fn from(value: &TargetHealth) -> Self {
+        Self {
+            state: value.state,
+            consecutive_successes: value.consecutive_successes,
+            consecutive_failures: value.consecutive_failures,
+            last_error: value.last_error.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> HealthCheckSettings {
+        HealthCheckSettings {
+            port: 8080,
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_millis(100),
+            healthy_threshold: 2,
+            unhealthy_threshold: 3,
+        }
+    }
+
+    #[test]
+    fn pending_target_is_decided_by_its_first_probe() {
+        let mut admitted = TargetHealth::default();
+        admitted.record(Ok(()), &settings());
+        assert!(admitted.is_healthy());
+
+        let mut rejected = TargetHealth::default();
+        rejected.record(Err("refused".into()), &settings());
+        assert_eq!(rejected.state, HealthCheckState::Unhealthy);
+        assert_eq!(rejected.last_error.as_deref(), Some("refused"));
+    }
+
+    #[test]
+    fn thresholds_apply_once_a_target_has_been_decided() {
+        let settings = settings();
+        let mut target = TargetHealth::default();
+        target.record(Ok(()), &settings);
+
+        target.record(Err("timeout".into()), &settings);
+        target.record(Err("timeout".into()), &settings);
+        assert!(target.is_healthy(), "two failures are below the threshold");
+        target.record(Err("timeout".into()), &settings);
+        assert_eq!(target.state, HealthCheckState::Unhealthy);
+
+        target.record(Ok(()), &settings);
+        assert_eq!(target.state, HealthCheckState::Unhealthy);
+        assert_eq!(target.last_error, None);
+        target.record(Ok(()), &settings);
+        assert!(target.is_healthy());
+    }
+
+    #[test]
+    fn a_success_resets_the_failure_streak() {
+        let settings = settings();
+        let mut target = TargetHealth::default();
+        target.record(Ok(()), &settings);
+
+        for _ in 0..5 {
+            target.record(Err("flaky".into()), &settings);
+            target.record(Err("flaky".into()), &settings);
+            target.record(Ok(()), &settings);
+        }
+
+        assert!(target.is_healthy());
+        assert_eq!(target.consecutive_failures, 0);
+    }
+}
@@ -0,0 +1,13 @@
+mod checker;
+mod grpc;
+// `health.rs` holds the shared API; its sibling modules are implementations.
+#[allow(clippy::module_inception)]
+mod health;
+mod outlier;
+mod tcp;
+
+pub use checker::*;
+pub use grpc::*;
+pub use health::*;
+pub use outlier::*;
+pub use tcp::*;
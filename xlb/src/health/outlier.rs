@@ -0,0 +1,422 @@
+use crate::config::OutlierDetectionConfig;
+use crate::r#loop::utils::{LbFlowStats, packed_ip};
+use crate::status::{OutlierReason, OutlierStatus};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use xlb_common::types::Backend;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlierSettings {
+    pub interval: Duration,
+    pub min_connections: u64,
+    /// Server-reset share of new connections, from 0 through 1.
+    pub server_reset_ratio: f64,
+    /// Handshake-failure share of new connections, from 0 through 1.
+    pub handshake_failure_ratio: f64,
+    pub base_ejection: Duration,
+    pub max_ejection: Duration,
+    /// Share of the pool that may be ejected at once, from 0 through 1.
+    pub max_ejection_ratio: f64,
+}
+
+impl From<&OutlierDetectionConfig> for OutlierSettings {
+    fn from(value: &OutlierDetectionConfig) -> Self {
+        Self {
+            interval: Duration::from_secs(value.interval_secs),
+            min_connections: u64::from(value.min_connections),
+            server_reset_ratio: value.server_reset_percent / 100.0,
+            handshake_failure_ratio: value.handshake_failure_percent / 100.0,
+            base_ejection: Duration::from_secs(value.base_ejection_secs),
+            max_ejection: Duration::from_secs(value.max_ejection_secs),
+            max_ejection_ratio: value.max_ejection_percent / 100.0,
+        }
+    }
+}
+
+/// A backend removed from the pool by the most recent evaluation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ejection {
+    pub address: IpAddr,
+    pub reason: OutlierReason,
+    pub failure_percent: f64,
+    pub duration: Duration,
+}
+
+/// Backends whose ejection started or ended during an observation.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct OutlierUpdate {
+    pub ejected: Vec<Ejection>,
+    pub restored: Vec<IpAddr>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct WindowCounts {
+    opened: u64,
+    server_resets: u64,
+    handshake_failures: u64,
+}
+
+#[derive(Debug, Default)]
+struct BackendOutliers {
+    window: WindowCounts,
+    ejected_until: Option<Instant>,
+    /// Consecutive ejections, decayed by one for each clean interval.
+    multiplier: u32,
+    ejections_total: u64,
+    last_reason: Option<OutlierReason>,
+}
+
+/// Envoy-style passive outlier detection over the per-backend closure
+/// counters the maintenance loop already aggregates from the flow map.
+///
+/// Counters accumulate across maintenance ticks and are evaluated once per
+/// interval. A backend whose server-reset or handshake-failure share of new
+/// connections crosses its threshold is ejected for an exponentially growing
+/// period, subject to a cap on how much of the pool may be ejected at once.
+pub struct OutlierDetector {
+    settings: OutlierSettings,
+    backends: HashMap<u128, BackendOutliers>,
+    window_started: Instant,
+}
+
+impl OutlierDetector {
+    pub fn new(settings: OutlierSettings, now: Instant) -> Self {
+        Self {
+            settings,
+            backends: HashMap::new(),
+            window_started: now,
+        }
+    }
+
+    /// Fold one maintenance sample into the current interval, expire
+    /// finished ejections, and evaluate the pool when the interval ends.
+    /// `pool` is every backend eligible for traffic before ejections apply.
+    pub fn observe(
+        &mut self,
+        stats: &LbFlowStats,
+        pool: &[Backend],
+        now: Instant,
+    ) -> OutlierUpdate {
+        let mut update = OutlierUpdate::default();
+
+        for (ip, backend) in &mut self.backends {
+            if backend.ejected_until.is_some_and(|until| until <= now) {
+                backend.ejected_until = None;
+                update.restored.push(packed_ip(*ip));
+            }
+        }
+
+        for (ip, aggregate) in &stats.backends {
+            let window = &mut self.backends.entry(*ip).or_default().window;
+            window.opened = window
+                .opened
+                .saturating_add(u64::from(aggregate.to_server.new_conns));
+            window.server_resets = window
+                .server_resets
+                .saturating_add(u64::from(aggregate.to_client.closed_rsts_by_server));
+            window.handshake_failures = window
+                .handshake_failures
+                .saturating_add(u64::from(aggregate.to_client.handshake_failures));
+        }
+
+        if now.saturating_duration_since(self.window_started) >= self.settings.interval {
+            update.ejected = self.evaluate(pool, now);
+            self.window_started = now;
+        }
+
+        update
+    }
+
+    fn evaluate(&mut self, pool: &[Backend], now: Instant) -> Vec<Ejection> {
+        let pool: HashSet<u128> = pool.iter().map(|backend| backend.ip).collect();
+        // Forget backends that left discovery once their ejection is over,
+        // so a returning address starts with a clean multiplier.
+        self.backends
+            .retain(|ip, backend| pool.contains(ip) || backend.ejected_until.is_some());
+
+        let max_ejected = max_ejected(pool.len(), self.settings.max_ejection_ratio);
+        let mut ejected_count = self
+            .backends
+            .iter()
+            .filter(|(ip, backend)| pool.contains(ip) && backend.ejected_until.is_some())
+            .count();
+
+        // Evaluate in address order so the ejection cap is applied
+        // deterministically when several backends fail at once.
+        let mut candidates: Vec<u128> = pool.iter().copied().collect();
+        candidates.sort_unstable();
+
+        let mut ejections = Vec::new();
+        for ip in candidates {
+            let backend = self.backends.entry(ip).or_default();
+            let window = std::mem::take(&mut backend.window);
+            if backend.ejected_until.is_some()
+                || window.opened == 0
+                || window.opened < self.settings.min_connections
+            {
+                continue;
+            }
+
+            let Some((reason, ratio)) = failure(&window, &self.settings) else {
+                // Only an interval with enough traffic to judge counts as
+                // evidence of recovery.
+                backend.multiplier = backend.multiplier.saturating_sub(1);
+                continue;
+            };
+            if ejected_count >= max_ejected {
+                continue;
+            }
+
+            backend.multiplier = backend.multiplier.saturating_add(1);
+            let duration = ejection_duration(&self.settings, backend.multiplier);
+            backend.ejected_until = Some(now + duration);
+            backend.ejections_total = backend.ejections_total.saturating_add(1);
+            backend.last_reason = Some(reason);
+            ejected_count += 1;
+            ejections.push(Ejection {
+                address: packed_ip(ip),
+                reason,
+                failure_percent: ratio * 100.0,
+                duration,
+            });
+        }
+
+        for backend in self.backends.values_mut() {
+            backend.window = WindowCounts::default();
+        }
+
+        ejections
+    }
+
+    /// Drop currently ejected backends from the set written to the dataplane.
+    pub fn retain_available(&self, backends: &mut Vec<Backend>, now: Instant) {
+        backends.retain(|backend| !self.is_ejected(backend.ip, now));
+    }
+
+    fn is_ejected(&self, ip: u128, now: Instant) -> bool {
+        self.backends
+            .get(&ip)
+            .and_then(|backend| backend.ejected_until)
+            .is_some_and(|until| until > now)
+    }
+
+    /// Outlier state of every backend that has been ejected at least once.
+    pub fn statuses(&self, now: Instant) -> BTreeMap<IpAddr, OutlierStatus> {
+        self.backends
+            .iter()
+            .filter(|(_, backend)| backend.ejections_total > 0)
+            .map(|(ip, backend)| {
+                let remaining = backend
+                    .ejected_until
+                    .map(|until| until.saturating_duration_since(now))
+                    .unwrap_or_default();
+                (
+                    packed_ip(*ip),
+                    OutlierStatus {
+                        ejected: !remaining.is_zero(),
+                        ejection_remaining_seconds: remaining.as_secs(),
+                        ejections_total: backend.ejections_total,
+                        last_reason: backend.last_reason,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+fn failure(window: &WindowCounts, settings: &OutlierSettings) -> Option<(OutlierReason, f64)> {
+    let opened = window.opened as f64;
+    let handshake_failures = window.handshake_failures as f64 / opened;
+    if handshake_failures >= settings.handshake_failure_ratio {
+        return Some((
+            OutlierReason::HandshakeFailures,
+            handshake_failures.min(1.0),
+        ));
+    }
+    let server_resets = window.server_resets as f64 / opened;
+    if server_resets >= settings.server_reset_ratio {
+        return Some((OutlierReason::ServerResets, server_resets.min(1.0)));
+    }
+    None
+}
+
+fn ejection_duration(settings: &OutlierSettings, multiplier: u32) -> Duration {
+    let factor = 1u32
+        .checked_shl(multiplier.saturating_sub(1))
+        .unwrap_or(u32::MAX);
+    settings
+        .base_ejection
+        .saturating_mul(factor)
+        .min(settings.max_ejection)
+}
+
+/// Ejection capacity for a pool. Unless ejection is disabled with a zero
+/// ratio, a pool of two or more may always lose one backend so that a small
+/// pool is not immune to detection, but the last backend is never ejected.
+fn max_ejected(pool_size: usize, max_ejection_ratio: f64) -> usize {
+    if pool_size < 2 || max_ejection_ratio <= 0.0 {
+        return 0;
+    }
+    let allowed = (pool_size as f64 * max_ejection_ratio).floor() as usize;
+    allowed.clamp(1, pool_size - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r#loop::utils::AggregateFlowStats;
+
+    fn settings() -> OutlierSettings {
+        OutlierSettings {
+            interval: Duration::from_secs(10),
+            min_connections: 10,
+            server_reset_ratio: 0.5,
+            handshake_failure_ratio: 0.2,
+            base_ejection: Duration::from_secs(30),
+            max_ejection: Duration::from_secs(100),
+            max_ejection_ratio: 0.5,
+        }
+    }
+
+    fn ip(last_octet: u8) -> u128 {
+        u128::from(u32::from_be_bytes([10, 0, 0, last_octet]))
+    }
+
+    fn pool(count: u8) -> Vec<Backend> {
+        (1..=count)
+            .map(|octet| Backend {
+                ip: ip(octet),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn traffic(entries: &[(u128, u32, u32, u32)]) -> LbFlowStats {
+        let mut stats = LbFlowStats::default();
+        for (ip, opened, resets, handshake_failures) in entries {
+            let mut aggregate = AggregateFlowStats::default();
+            aggregate.to_server.new_conns = *opened;
+            aggregate.to_client.closed_rsts_by_server = *resets;
+            aggregate.to_client.handshake_failures = *handshake_failures;
+            stats.backends.insert(*ip, aggregate);
+        }
+        stats
+    }
+
+    #[test]
+    fn failures_are_evaluated_only_at_the_end_of_an_interval() {
+        let start = Instant::now();
+        let mut detector = OutlierDetector::new(settings(), start);
+        let pool = pool(4);
+
+        let early = detector.observe(&traffic(&[(ip(1), 10, 10, 0)]), &pool, start);
+        assert!(early.ejected.is_empty());
+
+        let end = start + Duration::from_secs(10);
+        let update = detector.observe(&traffic(&[(ip(1), 10, 0, 0)]), &pool, end);
+        assert_eq!(update.ejected.len(), 1);
+        assert_eq!(update.ejected[0].reason, OutlierReason::ServerResets);
+        assert_eq!(update.ejected[0].failure_percent, 50.0);
+        assert_eq!(update.ejected[0].duration, Duration::from_secs(30));
+
+        let mut available = pool.clone();
+        detector.retain_available(&mut available, end);
+        assert_eq!(available.len(), 3);
+        assert!(available.iter().all(|backend| backend.ip != ip(1)));
+    }
+
+    #[test]
+    fn low_volume_backends_are_not_evaluated() {
+        let start = Instant::now();
+        let mut detector = OutlierDetector::new(settings(), start);
+
+        let update = detector.observe(
+            &traffic(&[(ip(1), 9, 9, 9)]),
+            &pool(4),
+            start + Duration::from_secs(10),
+        );
+
+        assert!(update.ejected.is_empty());
+    }
+
+    #[test]
+    fn handshake_failures_take_precedence_over_resets() {
+        let start = Instant::now();
+        let mut detector = OutlierDetector::new(settings(), start);
+
+        let update = detector.observe(
+            &traffic(&[(ip(1), 10, 10, 2)]),
+            &pool(4),
+            start + Duration::from_secs(10),
+        );
+
+        assert_eq!(update.ejected[0].reason, OutlierReason::HandshakeFailures);
+    }
+
+    #[test]
+    fn consecutive_ejections_grow_exponentially_up_to_the_maximum() {
+        let mut now = Instant::now();
+        let mut detector = OutlierDetector::new(settings(), now);
+        let pool = pool(4);
+        let mut durations = Vec::new();
+
+        for _ in 0..4 {
+            now += Duration::from_secs(10);
+            let update = detector.observe(&traffic(&[(ip(1), 10, 10, 0)]), &pool, now);
+            let ejection = update.ejected.first().expect("backend is ejected");
+            durations.push(ejection.duration.as_secs());
+
+            now += ejection.duration;
+            let restored = detector.observe(&LbFlowStats::default(), &pool, now);
+            assert_eq!(restored.restored, vec![packed_ip(ip(1))]);
+        }
+
+        assert_eq!(durations, vec![30, 60, 100, 100]);
+        assert_eq!(detector.statuses(now)[&packed_ip(ip(1))].ejections_total, 4);
+    }
+
+    #[test]
+    fn clean_intervals_decay_the_ejection_multiplier() {
+        let mut now = Instant::now();
+        let mut detector = OutlierDetector::new(settings(), now);
+        let pool = pool(4);
+
+        now += Duration::from_secs(10);
+        detector.observe(&traffic(&[(ip(1), 10, 10, 0)]), &pool, now);
+        now += Duration::from_secs(30);
+        detector.observe(&LbFlowStats::default(), &pool, now);
+        now += Duration::from_secs(10);
+        detector.observe(&traffic(&[(ip(1), 10, 0, 0)]), &pool, now);
+
+        now += Duration::from_secs(10);
+        let update = detector.observe(&traffic(&[(ip(1), 10, 10, 0)]), &pool, now);
+        assert_eq!(update.ejected[0].duration, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn max_ejection_percent_protects_the_pool() {
+        let start = Instant::now();
+        let mut detector = OutlierDetector::new(settings(), start);
+        let pool = pool(4);
+        let failing: Vec<_> = (1..=4).map(|octet| (ip(octet), 10, 10, 0)).collect();
+
+        let update = detector.observe(&traffic(&failing), &pool, start + Duration::from_secs(10));
+
+        assert_eq!(update.ejected.len(), 2, "50% of four backends");
+        let statuses = detector.statuses(start + Duration::from_secs(10));
+        assert!(statuses[&packed_ip(ip(1))].ejected);
+        assert!(statuses[&packed_ip(ip(2))].ejected);
+        assert!(!statuses.contains_key(&packed_ip(ip(3))));
+    }
+
+    #[test]
+    fn ejection_capacity_never_empties_the_pool() {
+        assert_eq!(max_ejected(0, 1.0), 0);
+        assert_eq!(max_ejected(1, 1.0), 0);
+        assert_eq!(max_ejected(2, 0.1), 1);
+        assert_eq!(max_ejected(2, 1.0), 1);
+        assert_eq!(max_ejected(20, 0.1), 2);
+        assert_eq!(max_ejected(20, 0.0), 0);
+    }
+}
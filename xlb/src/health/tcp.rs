@@ -0,0 +1,18 @@
+use crate::health::HealthProbe;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use tokio::net::TcpStream;
+
+/// Treats a backend as healthy when it accepts a TCP connection.
+pub struct TcpProbe;
+
+#[async_trait]
+impl HealthProbe for TcpProbe {
+    async fn check(&self, target: SocketAddr) -> Result<()> {
+        TcpStream::connect(target)
+            .await
+            .with_context(|| format!("TCP connect to {target} failed"))?;
+        Ok(())
+    }
+}
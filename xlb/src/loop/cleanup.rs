@@ -277,7 +277,11 @@ mod tests {
             rst_is_src: false,
             pair_invalid: false,
             pair_ready: true,
-            _reserved: [0; 1],
+            force_reset: false,
+            next_seq: 0,
+            seq_known: false,
+            handshake_timed: false,
+            _reserved: [0; 10],
             pair_tag: 1,
         }
     }
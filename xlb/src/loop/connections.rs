@@ -0,0 +1,689 @@
+use crate::r#loop::utils::{monotonic_now_ns, packed_ip};
+use crate::status::STATUS_SCHEMA_VERSION;
+use anyhow::{Result, anyhow, bail};
+use aya::maps::{HashMap, MapData};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::net::{IpAddr, SocketAddr};
+use xlb_common::types::{Flow, FlowDirection, FlowKeyV4};
+
+/// Connections returned by a page that does not request a limit.
+pub const DEFAULT_CONNECTION_PAGE: usize = 100;
+/// Largest page the connections API returns.
+pub const MAX_CONNECTION_PAGE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Open,
+    /// One side has sent a FIN.
+    HalfClosed,
+    /// Both sides have sent a FIN; the pair waits out its TIME_WAIT.
+    Closed,
+    Reset,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionSide {
+    Client,
+    Backend,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+pub struct TransferCounters {
+    pub bytes: u64,
+    pub packets: u64,
+}
+
+/// One paired flow of the dataplane.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Connection {
+    /// Stable identifier of the pair, also used as the paging cursor.
+    pub id: String,
+    /// Absent while client addresses are redacted.
+    pub client: Option<IpAddr>,
+    pub client_port: u16,
+    pub listen_port: u16,
+    pub backend: IpAddr,
+    pub backend_port: u16,
+    /// Source port XLB allocated toward the backend.
+    pub ephemeral_port: u16,
+    pub age_ms: u64,
+    /// Time since either direction last carried a packet.
+    pub idle_ms: u64,
+    pub state: ConnectionState,
+    /// Side that sent the first FIN or the RST, once the pair is closing.
+    pub closed_by: Option<ConnectionSide>,
+    pub to_backend: TransferCounters,
+    pub to_client: TransferCounters,
+}
+
+/// Filters and bounds of one page of the connections API. Connections are
+/// ordered by backend, backend port and ephemeral port.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+pub struct ConnectionQuery {
+    /// Client address or CIDR block.
+    pub client: Option<String>,
+    pub backend: Option<IpAddr>,
+    pub state: Option<ConnectionState>,
+    /// Only connections ordered after this connection ID.
+    pub after: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ConnectionPage {
+    pub schema_version: u16,
+    pub connections: Vec<Connection>,
+    /// Connections matching the filters, across all pages.
+    pub matched: usize,
+    pub has_more: bool,
+    /// Cursor of the next page, when there is one.
+    pub next_after: Option<String>,
+    pub client_ips_redacted: bool,
+    /// False when Aya could not produce a complete flow-map iteration, so
+    /// some connections may be missing.
+    pub complete: bool,
+}
+
+/// Read-only view of the flow map for the connections API. Holds its own
+/// handle to the map so listing never contends with the maintenance loop.
+pub struct ConnectionBrowser {
+    flows: HashMap<MapData, FlowKeyV4, Flow>,
+    redact_client_ips: bool,
+}
+
+impl ConnectionBrowser {
+    pub fn new(flows: HashMap<MapData, FlowKeyV4, Flow>, redact_client_ips: bool) -> Self {
+        Self {
+            flows,
+            redact_client_ips,
+        }
+    }
+
+    /// List paired connections matching the query. Walks the whole flow
+    /// map, so callers should run it off the async runtime.
+    pub fn list(&self, query: &ConnectionQuery) -> Result<ConnectionPage> {
+        let filter = ConnectionFilter::parse(query)?;
+        let mut complete = true;
+        let entries = self.flows.iter().filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(_) => {
+                complete = false;
+                None
+            }
+        });
+        let mut page = select_connections(
+            entries,
+            |key| self.flows.get(key, 0).ok(),
+            &filter,
+            monotonic_now_ns(),
+            self.redact_client_ips,
+        );
+        page.complete = complete;
+        Ok(page)
+    }
+}
+
+struct ConnectionFilter {
+    client: Option<ClientCidr>,
+    backend: Option<IpAddr>,
+    state: Option<ConnectionState>,
+    after: Option<ConnectionKey>,
+    limit: usize,
+}
+
+impl ConnectionFilter {
+    fn parse(query: &ConnectionQuery) -> Result<Self> {
+        Ok(Self {
+            client: query.client.as_deref().map(ClientCidr::parse).transpose()?,
+            backend: query.backend,
+            state: query.state,
+            after: query
+                .after
+                .as_deref()
+                .map(ConnectionKey::parse)
+                .transpose()?,
+            limit: query
+                .limit
+                .unwrap_or(DEFAULT_CONNECTION_PAGE)
+                .clamp(1, MAX_CONNECTION_PAGE),
+        })
+    }
+}
+
+struct ClientCidr {
+    network: IpAddr,
+    prefix: u32,
+}
+
+impl ClientCidr {
+    fn parse(value: &str) -> Result<Self> {
+        let invalid = || anyhow!("Client filter '{value}' is not an IP address or CIDR block");
+        let (address, prefix) = match value.trim().split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value.trim(), None),
+        };
+        let network: IpAddr = address.parse().map_err(|_| invalid())?;
+        let bits = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u32>().map_err(|_| invalid())?,
+            None => bits,
+        };
+        if prefix > bits {
+            bail!("Client filter '{value}' has an invalid prefix length");
+        }
+        Ok(Self { network, prefix })
+    }
+
+    fn contains(&self, address: IpAddr) -> bool {
+        match (self.network, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix).unwrap_or(0);
+                network.to_bits() & mask == address.to_bits() & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix).unwrap_or(0);
+                network.to_bits() & mask == address.to_bits() & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Backend socket and ephemeral port, which identify a pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct ConnectionKey {
+    backend: IpAddr,
+    backend_port: u16,
+    ephemeral_port: u16,
+}
+
+impl ConnectionKey {
+    fn parse(value: &str) -> Result<Self> {
+        let invalid = || anyhow!("Connection cursor '{value}' is not a connection ID");
+        let (backend, ephemeral_port) = value.rsplit_once('/').ok_or_else(invalid)?;
+        let backend: SocketAddr = backend.parse().map_err(|_| invalid())?;
+        Ok(Self {
+            backend: backend.ip(),
+            backend_port: backend.port(),
+            ephemeral_port: ephemeral_port.parse().map_err(|_| invalid())?,
+        })
+    }
+
+    fn id(&self) -> String {
+        format!(
+            "{}/{}",
+            SocketAddr::new(self.backend, self.backend_port),
+            self.ephemeral_port
+        )
+    }
+}
+
+/// Connection ranked by its key, so the page can be selected without
+/// holding every match in memory.
+struct Ranked(ConnectionKey, Connection);
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+/// Pair each backend-bound entry with its counterpart and keep the first
+/// page of matches. Entries without a ready counterpart are still being
+/// installed or torn down and are skipped.
+fn select_connections(
+    entries: impl Iterator<Item = (FlowKeyV4, Flow)>,
+    counterpart: impl Fn(&FlowKeyV4) -> Option<Flow>,
+    filter: &ConnectionFilter,
+    now_ns: u64,
+    redact_client_ips: bool,
+) -> ConnectionPage {
+    let mut matched = 0;
+    let mut page = BinaryHeap::with_capacity(filter.limit + 1);
+    for (_, to_server) in entries {
+        if to_server.direction != FlowDirection::ToServer || !to_server.pair_ready {
+            continue;
+        }
+        let client = packed_ip(to_server.client_ip);
+        let backend = packed_ip(to_server.backend_ip);
+        let key = ConnectionKey {
+            backend,
+            backend_port: to_server.dst_port,
+            ephemeral_port: to_server.src_port,
+        };
+        if filter
+            .client
+            .as_ref()
+            .is_some_and(|cidr| !cidr.contains(client))
+            || filter.backend.is_some_and(|wanted| wanted != backend)
+            || filter.after.is_some_and(|after| key <= after)
+        {
+            continue;
+        }
+        let Some(to_client) = counterpart(&to_server.counter_flow_key)
+            .filter(|flow| flow.direction == FlowDirection::ToClient)
+            .filter(|flow| flow.pair_tag == to_server.pair_tag)
+        else {
+            continue;
+        };
+        let (state, closed_by) = close_state(&to_server, &to_client);
+        if filter.state.is_some_and(|wanted| wanted != state) {
+            continue;
+        }
+
+        matched += 1;
+        page.push(Ranked(
+            key,
+            Connection {
+                id: key.id(),
+                client: (!redact_client_ips).then_some(client),
+                client_port: to_client.dst_port,
+                listen_port: to_client.src_port,
+                backend,
+                backend_port: key.backend_port,
+                ephemeral_port: key.ephemeral_port,
+                age_ms: now_ns.saturating_sub(to_server.created_at_ns) / 1_000_000,
+                idle_ms: now_ns.saturating_sub(to_server.last_seen_ns.max(to_client.last_seen_ns))
+                    / 1_000_000,
+                state,
+                closed_by,
+                to_backend: TransferCounters {
+                    bytes: to_server.bytes_transfer,
+                    packets: to_server.packets_transfer,
+                },
+                to_client: TransferCounters {
+                    bytes: to_client.bytes_transfer,
+                    packets: to_client.packets_transfer,
+                },
+            },
+        ));
+        if page.len() > filter.limit {
+            page.pop();
+        }
+    }
+
+    let connections: Vec<Connection> = page
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Ranked(_, connection)| connection)
+        .collect();
+    let has_more = matched > connections.len();
+    ConnectionPage {
+        schema_version: STATUS_SCHEMA_VERSION,
+        next_after: has_more
+            .then(|| connections.last().map(|connection| connection.id.clone()))
+            .flatten(),
+        connections,
+        matched,
+        has_more,
+        client_ips_redacted: redact_client_ips,
+        complete: true,
+    }
+}
+
+/// State of a pair and the side that started closing it. The backend-bound
+/// entry records what the client sent, its counterpart what the backend sent.
+fn close_state(to_server: &Flow, to_client: &Flow) -> (ConnectionState, Option<ConnectionSide>) {
+    let side = |client: bool| {
+        if client {
+            ConnectionSide::Client
+        } else {
+            ConnectionSide::Backend
+        }
+    };
+
+    if to_server.rst_ns > 0 || to_client.rst_ns > 0 {
+        let by = (to_server.rst_is_src || to_client.rst_is_src).then(|| side(to_server.rst_is_src));
+        return (ConnectionState::Reset, by);
+    }
+    let fin_by = (to_server.fin || to_client.fin).then(|| {
+        if to_server.fin && to_client.fin {
+            side(to_server.fin_is_src)
+        } else {
+            side(to_server.fin)
+        }
+    });
+    if to_server.fin_both_ns > 0 || to_client.fin_both_ns > 0 {
+        (ConnectionState::Closed, fin_by)
+    } else if fin_by.is_some() {
+        (ConnectionState::HalfClosed, fin_by)
+    } else {
+        (ConnectionState::Open, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap as StdHashMap;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    fn flow(direction: FlowDirection, counter_flow_key: FlowKeyV4, pair_tag: u32) -> Flow {
+        Flow {
+            client_ip: 0,
+            backend_ip: 0,
+            src_ip: 0,
+            dst_ip: 0,
+            bytes_transfer: 0,
+            packets_transfer: 0,
+            created_at_ns: 0,
+            last_seen_ns: 0,
+            fin_both_ns: 0,
+            rst_ns: 0,
+            counter_flow_key,
+            direction,
+            src_port: 0,
+            dst_port: 0,
+            src_iface_idx: 0,
+            dst_mac: [0; 6],
+            src_mac: [0; 6],
+            fin: false,
+            fin_is_src: false,
+            rst_is_src: false,
+            pair_invalid: false,
+            pair_ready: true,
+            force_reset: false,
+            pair_tag,
+            next_seq: 0,
+            seq_known: false,
+            handshake_timed: false,
+            _reserved: [0; 10],
+        }
+    }
+
+    /// Flow map holding one pair from `client` through `backend`.
+    fn pair(
+        flows: &mut StdHashMap<FlowKeyV4, Flow>,
+        client: [u8; 4],
+        backend: [u8; 4],
+        ephemeral_port: u16,
+        close: impl Fn(&mut Flow, &mut Flow),
+    ) {
+        let client_ip = u32::from_be_bytes(client);
+        let backend_ip = u32::from_be_bytes(backend);
+        let server_key =
+            FlowKeyV4::tcp(client_ip, 0x0a00_0001, 40_000, 80, FlowDirection::ToServer);
+        let client_key = FlowKeyV4::tcp(
+            backend_ip,
+            0x0a00_0001,
+            8080,
+            ephemeral_port,
+            FlowDirection::ToClient,
+        );
+        let pair_tag = u32::from(ephemeral_port);
+
+        let mut to_server = flow(FlowDirection::ToServer, client_key, pair_tag);
+        to_server.client_ip = u128::from(client_ip);
+        to_server.backend_ip = u128::from(backend_ip);
+        to_server.src_port = ephemeral_port;
+        to_server.dst_port = 8080;
+        to_server.bytes_transfer = 300;
+        to_server.packets_transfer = 3;
+        to_server.created_at_ns = 10 * SECOND;
+        to_server.last_seen_ns = 12 * SECOND;
+
+        let mut to_client = flow(FlowDirection::ToClient, server_key, pair_tag);
+        to_client.client_ip = u128::from(client_ip);
+        to_client.backend_ip = u128::from(backend_ip);
+        to_client.src_port = 80;
+        to_client.dst_port = 40_000;
+        to_client.bytes_transfer = 5000;
+        to_client.packets_transfer = 5;
+        to_client.created_at_ns = 10 * SECOND;
+        to_client.last_seen_ns = 15 * SECOND;
+
+        close(&mut to_server, &mut to_client);
+        flows.insert(server_key, to_server);
+        flows.insert(client_key, to_client);
+    }
+
+    fn select(
+        flows: &StdHashMap<FlowKeyV4, Flow>,
+        query: ConnectionQuery,
+        redact: bool,
+    ) -> ConnectionPage {
+        let filter = ConnectionFilter::parse(&query).expect("valid query");
+        select_connections(
+            flows.iter().map(|(key, flow)| (*key, *flow)),
+            |key| flows.get(key).copied(),
+            &filter,
+            20 * SECOND,
+            redact,
+        )
+    }
+
+    fn ids(page: &ConnectionPage) -> Vec<&str> {
+        page.connections
+            .iter()
+            .map(|connection| connection.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn pairs_directional_entries_into_connections() {
+        let mut flows = StdHashMap::new();
+        pair(&mut flows, [192, 0, 2, 1], [10, 0, 0, 7], 30_000, |_, _| {});
+
+        let page = select(&flows, ConnectionQuery::default(), false);
+
+        assert_eq!(
+            page.connections,
+            vec![Connection {
+                id: "10.0.0.7:8080/30000".to_string(),
+                client: Some(IpAddr::from([192, 0, 2, 1])),
+                client_port: 40_000,
+                listen_port: 80,
+                backend: IpAddr::from([10, 0, 0, 7]),
+                backend_port: 8080,
+                ephemeral_port: 30_000,
+                age_ms: 10_000,
+                idle_ms: 5_000,
+                state: ConnectionState::Open,
+                closed_by: None,
+                to_backend: TransferCounters {
+                    bytes: 300,
+                    packets: 3
+                },
+                to_client: TransferCounters {
+                    bytes: 5000,
+                    packets: 5
+                },
+            }]
+        );
+        assert_eq!(page.matched, 1);
+        assert!(!page.has_more);
+
+        let redacted = select(&flows, ConnectionQuery::default(), true);
+        assert_eq!(redacted.connections[0].client, None);
+        assert!(redacted.client_ips_redacted);
+    }
+
+    #[test]
+    fn skips_entries_without_a_ready_counterpart() {
+        let mut flows = StdHashMap::new();
+        pair(&mut flows, [192, 0, 2, 1], [10, 0, 0, 7], 30_000, |_, _| {});
+        pair(&mut flows, [192, 0, 2, 2], [10, 0, 0, 7], 30_001, |_, _| {});
+        pair(
+            &mut flows,
+            [192, 0, 2, 3],
+            [10, 0, 0, 7],
+            30_002,
+            |to_server, _| to_server.pair_ready = false,
+        );
+        flows.retain(|_, flow| {
+            !(flow.direction == FlowDirection::ToClient
+                && flow.client_ip == u128::from(u32::from_be_bytes([192, 0, 2, 2])))
+        });
+
+        let page = select(&flows, ConnectionQuery::default(), false);
+
+        assert_eq!(ids(&page), vec!["10.0.0.7:8080/30000"]);
+    }
+
+    #[test]
+    fn reports_close_state_and_initiator() {
+        let mut flows = StdHashMap::new();
+        pair(
+            &mut flows,
+            [192, 0, 2, 1],
+            [10, 0, 0, 7],
+            30_000,
+            |_, to_client| {
+                to_client.fin = true;
+            },
+        );
+        pair(
+            &mut flows,
+            [192, 0, 2, 2],
+            [10, 0, 0, 7],
+            30_001,
+            |to_server, to_client| {
+                to_server.fin = true;
+                to_server.fin_is_src = true;
+                to_client.fin = true;
+                to_server.fin_both_ns = 14 * SECOND;
+                to_client.fin_both_ns = 14 * SECOND;
+            },
+        );
+        pair(
+            &mut flows,
+            [192, 0, 2, 3],
+            [10, 0, 0, 7],
+            30_002,
+            |to_server, _| {
+                to_server.rst_ns = 14 * SECOND;
+                to_server.rst_is_src = true;
+            },
+        );
+
+        let page = select(&flows, ConnectionQuery::default(), false);
+        let states: Vec<_> = page
+            .connections
+            .iter()
+            .map(|connection| (connection.state, connection.closed_by))
+            .collect();
+
+        assert_eq!(
+            states,
+            vec![
+                (ConnectionState::HalfClosed, Some(ConnectionSide::Backend)),
+                (ConnectionState::Closed, Some(ConnectionSide::Client)),
+                (ConnectionState::Reset, Some(ConnectionSide::Client)),
+            ]
+        );
+
+        let reset = select(
+            &flows,
+            ConnectionQuery {
+                state: Some(ConnectionState::Reset),
+                ..Default::default()
+            },
+            false,
+        );
+        assert_eq!(ids(&reset), vec!["10.0.0.7:8080/30002"]);
+    }
+
+    #[test]
+    fn filters_by_client_cidr_and_backend_and_pages_in_order() {
+        let mut flows = StdHashMap::new();
+        pair(&mut flows, [192, 0, 2, 1], [10, 0, 0, 8], 30_000, |_, _| {});
+        pair(&mut flows, [192, 0, 2, 2], [10, 0, 0, 7], 30_002, |_, _| {});
+        pair(&mut flows, [192, 0, 2, 3], [10, 0, 0, 7], 30_001, |_, _| {});
+        pair(
+            &mut flows,
+            [198, 51, 100, 1],
+            [10, 0, 0, 7],
+            30_003,
+            |_, _| {},
+        );
+
+        let clients = select(
+            &flows,
+            ConnectionQuery {
+                client: Some("192.0.2.0/24".to_string()),
+                backend: Some(IpAddr::from([10, 0, 0, 7])),
+                ..Default::default()
+            },
+            true,
+        );
+        assert_eq!(
+            ids(&clients),
+            vec!["10.0.0.7:8080/30001", "10.0.0.7:8080/30002"]
+        );
+
+        let first = select(
+            &flows,
+            ConnectionQuery {
+                limit: Some(3),
+                ..Default::default()
+            },
+            false,
+        );
+        assert_eq!(
+            ids(&first),
+            vec![
+                "10.0.0.7:8080/30001",
+                "10.0.0.7:8080/30002",
+                "10.0.0.7:8080/30003"
+            ]
+        );
+        assert!(first.has_more);
+        assert_eq!(first.matched, 4);
+        let rest = select(
+            &flows,
+            ConnectionQuery {
+                after: first.next_after.clone(),
+                limit: Some(3),
+                ..Default::default()
+            },
+            false,
+        );
+        assert_eq!(ids(&rest), vec!["10.0.0.8:8080/30000"]);
+        assert!(!rest.has_more);
+        assert_eq!(rest.next_after, None);
+    }
+
+    #[test]
+    fn rejects_malformed_filters() {
+        for query in [
+            ConnectionQuery {
+                client: Some("192.0.2.0/33".to_string()),
+                ..Default::default()
+            },
+            ConnectionQuery {
+                client: Some("clients".to_string()),
+                ..Default::default()
+            },
+            ConnectionQuery {
+                after: Some("10.0.0.7/30000".to_string()),
+                ..Default::default()
+            },
+        ] {
+            assert!(ConnectionFilter::parse(&query).is_err(), "{query:?}");
+        }
+
+        let single = ClientCidr::parse("2001:db8::1").expect("bare address");
+        assert!(single.contains("2001:db8::1".parse().unwrap()));
+        assert!(!single.contains("2001:db8::2".parse().unwrap()));
+        assert!(!single.contains(IpAddr::from([192, 0, 2, 1])));
+    }
+}
@@ -0,0 +1,120 @@
+use aya::maps::{MapData, PerCpuArray};
+use log::warn;
+use xlb_common::XlbErr;
+use xlb_common::types::PacketOutcome;
+
+const OUTCOMES: usize = PacketOutcome::COUNT as usize;
+const ERRORS: usize = XlbErr::COUNT as usize;
+
+/// Cumulative value of one dataplane counter and its increase over the
+/// last maintenance interval.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CounterSample {
+    pub total: u64,
+    pub delta: u64,
+}
+
+/// Packet counters of the XDP program, summed across CPUs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DataplaneCounters {
+    /// Indexed by [`PacketOutcome`] discriminant.
+    pub outcomes: [CounterSample; OUTCOMES],
+    /// Indexed by [`XlbErr`] discriminant.
+    pub errors: [CounterSample; ERRORS],
+}
+
+/// Reads the per-CPU outcome and error counters the XDP program
+/// increments for every packet.
+pub struct DataplaneCounterMaps {
+    outcomes: PerCpuArray<MapData, u64>,
+    errors: PerCpuArray<MapData, u64>,
+    last: DataplaneCounters,
+    /// Suppresses repeated warnings while a counter remains unreadable.
+    read_error_reported: bool,
+}
+
+impl DataplaneCounterMaps {
+    pub fn new(outcomes: PerCpuArray<MapData, u64>, errors: PerCpuArray<MapData, u64>) -> Self {
+        Self {
+            outcomes,
+            errors,
+            last: DataplaneCounters::default(),
+            read_error_reported: false,
+        }
+    }
+
+    /// Read every counter and its increase since the previous read. An
+    /// unreadable counter keeps its last total and reports no increase.
+    pub fn sample(&mut self) -> DataplaneCounters {
+        let mut failed = false;
+        let mut read =
+            |map: &PerCpuArray<MapData, u64>, index: usize| match map.get(&(index as u32), 0) {
+                Ok(values) => Some(
+                    values
+                        .iter()
+                        .fold(0u64, |sum, count| sum.saturating_add(*count)),
+                ),
+                Err(err) => {
+                    if !failed {
+                        failed = true;
+                        if !self.read_error_reported {
+                            warn!("Failed to read dataplane packet counters: {err}");
+                        }
+                    }
+                    None
+                }
+            };
+
+        advance(&mut self.last.outcomes, |index| read(&self.outcomes, index));
+        advance(&mut self.last.errors, |index| read(&self.errors, index));
+        self.read_error_reported = failed;
+        self.last.clone()
+    }
+}
+
+fn advance(samples: &mut [CounterSample], mut read: impl FnMut(usize) -> Option<u64>) {
+    for (index, sample) in samples.iter_mut().enumerate() {
+        *sample = match read(index) {
+            Some(total) => CounterSample {
+                total,
+                delta: total.saturating_sub(sample.total),
+            },
+            None => CounterSample {
+                total: sample.total,
+                delta: 0,
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advance_reports_increases_and_holds_unreadable_counters() {
+        let mut samples = [CounterSample::default(); 3];
+        advance(&mut samples, |index| Some(10 * (index as u64 + 1)));
+        advance(&mut samples, |index| {
+            (index != 1).then_some(25 * (index as u64 + 1))
+        });
+
+        assert_eq!(
+            samples,
+            [
+                CounterSample {
+                    total: 25,
+                    delta: 15
+                },
+                CounterSample {
+                    total: 20,
+                    delta: 0
+                },
+                CounterSample {
+                    total: 75,
+                    delta: 45
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,460 @@
+use crate::config::{DrainConfig, Host};
+use crate::r#loop::utils::{LbFlowStats, packed_ip};
+use crate::status::{
+    AdminOverrideMode, AdminOverrideStatus, DrainState, DrainStatus, DrainTrigger, unix_time_ms,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use xlb_common::types::Backend;
+
+/// Backends an operator drained or disabled through the admin API,
+/// shared between the admin API and the maintenance loop. Overrides are
+/// keyed by address, so they outlive provider refreshes until cleared.
+#[derive(Debug, Default)]
+pub struct BackendOverrides {
+    backends: RwLock<BTreeMap<IpAddr, AdminOverrideStatus>>,
+}
+
+impl BackendOverrides {
+    /// Drain or disable a backend. Returns false if it already had this
+    /// override, which keeps its original author and time.
+    pub fn set(&self, address: IpAddr, mode: AdminOverrideMode, by: String) -> bool {
+        let mut backends = self.backends.write().expect("override lock poisoned");
+        if backends
+            .get(&address)
+            .is_some_and(|existing| existing.mode == mode)
+        {
+            return false;
+        }
+        backends.insert(
+            address,
+            AdminOverrideStatus {
+                mode,
+                by,
+                at_unix_ms: unix_time_ms(),
+            },
+        );
+        true
+    }
+
+    /// Return a backend to the provider's control, if its override is
+    /// `mode` or any override when `mode` is `None`. Returns the cleared
+    /// override.
+    pub fn clear(
+        &self,
+        address: IpAddr,
+        mode: Option<AdminOverrideMode>,
+    ) -> Option<AdminOverrideStatus> {
+        let mut backends = self.backends.write().expect("override lock poisoned");
+        let existing = backends.get(&address)?;
+        if mode.is_some_and(|mode| existing.mode != mode) {
+            return None;
+        }
+        backends.remove(&address)
+    }
+
+    fn snapshot(&self) -> BTreeMap<IpAddr, AdminOverrideStatus> {
+        self.backends
+            .read()
+            .expect("override lock poisoned")
+            .clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DrainSettings {
+    pub deadline: Duration,
+    pub force_close: bool,
+}
+
+impl From<&DrainConfig> for DrainSettings {
+    fn from(config: &DrainConfig) -> Self {
+        Self {
+            deadline: Duration::from_secs(config.deadline_secs),
+            force_close: config.force_close,
+        }
+    }
+}
+
+/// Dataplane changes produced by one drain evaluation.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DrainUpdate {
+    /// Backends whose remaining flows must be reset from now on.
+    pub force_close: Vec<IpAddr>,
+    /// Previously force-closed backends whose drain has ended.
+    pub release: Vec<IpAddr>,
+}
+
+#[derive(Debug)]
+struct Drain {
+    trigger: DrainTrigger,
+    started_at: Instant,
+    remaining: u32,
+    force_closing: bool,
+}
+
+/// Tracks backends leaving the pool until their connections finish or
+/// their deadline passes, when remaining connections are optionally reset.
+///
+/// Provider removal starts a drain that ends on its own; an admin override
+/// also keeps the backend out of the pool until it is cleared, and a
+/// disabled backend has its connections reset without waiting.
+pub struct DrainTracker {
+    settings: DrainSettings,
+    overrides: Arc<BackendOverrides>,
+    /// Overrides read by the last evaluation.
+    requested: BTreeMap<IpAddr, AdminOverrideStatus>,
+    discovered: HashSet<IpAddr>,
+    drains: BTreeMap<IpAddr, Drain>,
+}
+
+impl DrainTracker {
+    pub fn new(settings: DrainSettings, overrides: Arc<BackendOverrides>) -> Self {
+        Self {
+            settings,
+            overrides,
+            requested: BTreeMap::new(),
+            discovered: HashSet::new(),
+            drains: BTreeMap::new(),
+        }
+    }
+
+    /// Use new settings for every drain, including those in progress.
+    pub fn set_settings(&mut self, settings: DrainSettings) {
+        self.settings = settings;
+    }
+
+    /// Advance every drain from the hosts just discovered and the
+    /// connections still open per backend.
+    pub fn observe(&mut self, hosts: &[Host], stats: &LbFlowStats, now: Instant) -> DrainUpdate {
+        let discovered: HashSet<IpAddr> = hosts.iter().map(|host| host.ip).collect();
+        let requested = self.overrides.snapshot();
+        let active: HashMap<IpAddr, u32> = stats
+            .backends
+            .iter()
+            .map(|(ip, aggregate)| (packed_ip(*ip), aggregate.to_server.active_conns))
+            .collect();
+
+        for address in requested.keys() {
+            // An override takes over a drain the provider started, so the
+            // backend stays out when the provider publishes it again.
+            self.drains
+                .entry(*address)
+                .or_insert_with(|| Drain::new(DrainTrigger::Admin, now))
+                .trigger = DrainTrigger::Admin;
+        }
+        for address in self.discovered.difference(&discovered) {
+            self.drains
+                .entry(*address)
+                .or_insert_with(|| Drain::new(DrainTrigger::ProviderRemoved, now));
+        }
+
+        let mut update = DrainUpdate::default();
+        self.drains.retain(|address, drain| {
+            drain.remaining = active.get(address).copied().unwrap_or_default();
+            let expired = now.saturating_duration_since(drain.started_at) >= self.settings.deadline;
+            let disabled = requested
+                .get(address)
+                .is_some_and(|request| request.mode == AdminOverrideMode::Disable);
+            let keep = if requested.contains_key(address) {
+                true
+            } else if discovered.contains(address) {
+                // Undrained, or the provider published the backend again.
+                false
+            } else {
+                drain.remaining > 0 && (self.settings.force_close || !expired)
+            };
+
+            if !keep {
+                if drain.force_closing {
+                    update.release.push(*address);
+                }
+                return false;
+            }
+
+            let force_close = disabled || (expired && self.settings.force_close);
+            if force_close && drain.remaining > 0 && !drain.force_closing {
+                drain.force_closing = true;
+                update.force_close.push(*address);
+            }
+            true
+        });
+
+        self.discovered = discovered;
+        self.requested = requested;
+        update
+    }
+
+    /// Admin overrides in effect at the last evaluation.
+    pub fn overrides(&self) -> BTreeMap<IpAddr, AdminOverrideStatus> {
+        self.requested.clone()
+    }
+
+    /// Keep overridden backends out of the backends offered new connections.
+    pub fn retain_available(&self, backends: &mut Vec<Backend>) {
+        backends.retain(|backend| !self.requested.contains_key(&packed_ip(backend.ip)));
+    }
+
+    pub fn statuses(&self, now: Instant) -> BTreeMap<IpAddr, DrainStatus> {
+        self.drains
+            .iter()
+            .map(|(address, drain)| {
+                let remaining_time = self
+                    .settings
+                    .deadline
+                    .saturating_sub(now.saturating_duration_since(drain.started_at));
+                let state = if drain.remaining == 0 {
+                    DrainState::Drained
+                } else if drain.force_closing {
+                    DrainState::ForceClosing
+                } else if remaining_time.is_zero() {
+                    DrainState::Drained
+                } else {
+                    DrainState::Draining
+                };
+
+                (
+                    *address,
+                    DrainStatus {
+                        state,
+                        trigger: drain.trigger,
+                        remaining_connections: drain.remaining,
+                        deadline_remaining_seconds: remaining_time.as_secs(),
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+impl Drain {
+    fn new(trigger: DrainTrigger, started_at: Instant) -> Self {
+        Self {
+            trigger,
+            started_at,
+            remaining: 0,
+            force_closing: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r#loop::utils::{AggregateFlowStats, pack_ip};
+
+    const DEADLINE: Duration = Duration::from_secs(30);
+
+    fn address(last_octet: u8) -> IpAddr {
+        IpAddr::from([10, 0, 0, last_octet])
+    }
+
+    fn host(last_octet: u8) -> Host {
+        Host {
+            name: format!("backend-{last_octet}"),
+            ip: address(last_octet),
+            weight: None,
+            priority: 0,
+            ports: BTreeMap::new(),
+        }
+    }
+
+    fn stats(active: &[(u8, u32)]) -> LbFlowStats {
+        let mut stats = LbFlowStats::default();
+        for (last_octet, conns) in active {
+            let mut aggregate = AggregateFlowStats::default();
+            aggregate.to_server.active_conns = *conns;
+            stats
+                .backends
+                .insert(pack_ip(address(*last_octet)), aggregate);
+        }
+        stats
+    }
+
+    fn tracker(force_close: bool) -> (DrainTracker, Arc<BackendOverrides>) {
+        let requests = Arc::new(BackendOverrides::default());
+        let settings = DrainSettings {
+            deadline: DEADLINE,
+            force_close,
+        };
+        (DrainTracker::new(settings, requests.clone()), requests)
+    }
+
+    #[test]
+    fn provider_removal_drains_until_connections_finish() {
+        let (mut drains, _) = tracker(false);
+        let start = Instant::now();
+        drains.observe(&[host(1), host(2)], &stats(&[]), start);
+
+        drains.observe(&[host(1)], &stats(&[(2, 3)]), start);
+        let status = &drains.statuses(start)[&address(2)];
+        assert_eq!(status.state, DrainState::Draining);
+        assert_eq!(status.trigger, DrainTrigger::ProviderRemoved);
+        assert_eq!(status.remaining_connections, 3);
+        assert_eq!(status.deadline_remaining_seconds, 30);
+
+        drains.observe(&[host(1)], &stats(&[]), start + Duration::from_secs(5));
+        assert!(drains.statuses(start).is_empty());
+    }
+
+    #[test]
+    fn backend_without_connections_is_not_tracked() {
+        let (mut drains, _) = tracker(true);
+        let start = Instant::now();
+        drains.observe(&[host(1), host(2)], &stats(&[]), start);
+
+        let update = drains.observe(&[host(1)], &stats(&[]), start);
+
+        assert_eq!(update, DrainUpdate::default());
+        assert!(drains.statuses(start).is_empty());
+    }
+
+    #[test]
+    fn deadline_without_force_close_stops_tracking() {
+        let (mut drains, _) = tracker(false);
+        let start = Instant::now();
+        drains.observe(&[host(1)], &stats(&[]), start);
+        drains.observe(&[], &stats(&[(1, 2)]), start);
+
+        let update = drains.observe(&[], &stats(&[(1, 2)]), start + DEADLINE);
+
+        assert_eq!(update, DrainUpdate::default());
+        assert!(drains.statuses(start + DEADLINE).is_empty());
+    }
+
+    #[test]
+    fn deadline_with_force_close_resets_then_releases() {
+        let (mut drains, _) = tracker(true);
+        let start = Instant::now();
+        drains.observe(&[host(1)], &stats(&[]), start);
+        drains.observe(&[], &stats(&[(1, 2)]), start);
+
+        let update = drains.observe(&[], &stats(&[(1, 2)]), start + DEADLINE);
+        assert_eq!(update.force_close, vec![address(1)]);
+        assert_eq!(
+            drains.statuses(start + DEADLINE)[&address(1)].state,
+            DrainState::ForceClosing
+        );
+
+        let repeated = drains.observe(&[], &stats(&[(1, 1)]), start + DEADLINE);
+        assert!(
+            repeated.force_close.is_empty(),
+            "force-close is signalled once"
+        );
+
+        let done = drains.observe(&[], &stats(&[]), start + DEADLINE);
+        assert_eq!(done.release, vec![address(1)]);
+        assert!(drains.statuses(start + DEADLINE).is_empty());
+    }
+
+    #[test]
+    fn rediscovered_backend_cancels_its_drain() {
+        let (mut drains, _) = tracker(true);
+        let start = Instant::now();
+        drains.observe(&[host(1)], &stats(&[]), start);
+        drains.observe(&[], &stats(&[(1, 2)]), start);
+        drains.observe(&[], &stats(&[(1, 2)]), start + DEADLINE);
+
+        let update = drains.observe(&[host(1)], &stats(&[(1, 2)]), start + DEADLINE);
+
+        assert_eq!(update.release, vec![address(1)]);
+        assert!(drains.statuses(start + DEADLINE).is_empty());
+    }
+
+    #[test]
+    fn requested_drain_excludes_backend_until_undrained() {
+        let (mut drains, requests) = tracker(false);
+        let start = Instant::now();
+        let pool = || vec![Backend::from(&host(1)), Backend::from(&host(2))];
+
+        assert!(requests.set(address(2), AdminOverrideMode::Drain, "operator".to_string()));
+        assert!(!requests.set(address(2), AdminOverrideMode::Drain, "operator".to_string()));
+        drains.observe(&[host(1), host(2)], &stats(&[]), start);
+        let mut backends = pool();
+        drains.retain_available(&mut backends);
+        assert_eq!(backends.len(), 1);
+        assert_eq!(packed_ip(backends[0].ip), address(1));
+
+        // Requested drains outlive their connections and deadline.
+        drains.observe(&[host(1), host(2)], &stats(&[]), start + DEADLINE);
+        let status = &drains.statuses(start + DEADLINE)[&address(2)];
+        assert_eq!(status.trigger, DrainTrigger::Admin);
+        assert_eq!(status.state, DrainState::Drained);
+
+        assert!(
+            requests
+                .clear(address(2), Some(AdminOverrideMode::Disable))
+                .is_none()
+        );
+        assert!(
+            requests
+                .clear(address(2), Some(AdminOverrideMode::Drain))
+                .is_some()
+        );
+        drains.observe(&[host(1), host(2)], &stats(&[]), start + DEADLINE);
+        let mut backends = pool();
+        drains.retain_available(&mut backends);
+        assert_eq!(backends.len(), 2);
+    }
+
+    #[test]
+    fn admin_drain_of_a_removed_backend_outlives_its_rediscovery() {
+        let (mut drains, requests) = tracker(false);
+        let start = Instant::now();
+        drains.observe(&[host(1), host(2)], &stats(&[]), start);
+        drains.observe(&[host(1)], &stats(&[(2, 3)]), start);
+        assert_eq!(
+            drains.statuses(start)[&address(2)].trigger,
+            DrainTrigger::ProviderRemoved
+        );
+
+        assert!(requests.set(address(2), AdminOverrideMode::Drain, "operator".to_string()));
+        drains.observe(&[host(1)], &stats(&[(2, 3)]), start);
+        assert_eq!(
+            drains.statuses(start)[&address(2)].trigger,
+            DrainTrigger::Admin
+        );
+
+        drains.observe(&[host(1), host(2)], &stats(&[(2, 1)]), start);
+        let mut backends = vec![Backend::from(&host(1)), Backend::from(&host(2))];
+        drains.retain_available(&mut backends);
+        assert_eq!(backends.len(), 1);
+        assert_eq!(packed_ip(backends[0].ip), address(1));
+        assert_eq!(
+            drains.statuses(start)[&address(2)].trigger,
+            DrainTrigger::Admin
+        );
+    }
+
+    #[test]
+    fn disabled_backend_is_reset_at_once_and_keeps_its_author() {
+        let (mut drains, requests) = tracker(false);
+        let start = Instant::now();
+        drains.observe(&[host(1), host(2)], &stats(&[(2, 4)]), start);
+
+        assert!(requests.set(
+            address(2),
+            AdminOverrideMode::Disable,
+            "operator".to_string()
+        ));
+        let update = drains.observe(&[host(1), host(2)], &stats(&[(2, 4)]), start);
+        assert_eq!(update.force_close, vec![address(2)]);
+        assert_eq!(
+            drains.statuses(start)[&address(2)].state,
+            DrainState::ForceClosing
+        );
+        let applied = &drains.overrides()[&address(2)];
+        assert_eq!(applied.mode, AdminOverrideMode::Disable);
+        assert_eq!(applied.by, "operator");
+
+        // Survives later provider refreshes, then re-enabling releases it.
+        drains.observe(&[host(1), host(2)], &stats(&[]), start + DEADLINE);
+        assert!(drains.overrides().contains_key(&address(2)));
+        assert!(requests.clear(address(2), None).is_some());
+        let update = drains.observe(&[host(1), host(2)], &stats(&[]), start + DEADLINE);
+        assert_eq!(update.release, vec![address(2)]);
+        assert!(drains.overrides().is_empty());
+    }
+}
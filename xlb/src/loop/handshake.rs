@@ -0,0 +1,253 @@
+use aya::maps::{MapData, PerCpuHashMap};
+use log::warn;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use xlb_common::consts::HANDSHAKE_LATENCY_BUCKETS;
+use xlb_common::types::HandshakeLatency;
+
+/// Length of each window of recent handshakes. Percentiles cover the
+/// current window and the previous one, so between one and two windows.
+pub const HANDSHAKE_WINDOW: Duration = Duration::from_secs(60);
+
+type Buckets = [u64; HANDSHAKE_LATENCY_BUCKETS];
+
+/// Handshake latency of one backend, by [`HandshakeLatency`] bucket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HandshakeLatencySample {
+    /// Handshakes completed since the previous sample.
+    pub delta: Buckets,
+    /// Handshakes completed during the current and previous windows.
+    pub recent: Buckets,
+}
+
+impl HandshakeLatencySample {
+    pub fn recent_count(&self) -> u64 {
+        self.recent
+            .iter()
+            .fold(0u64, |sum, count| sum.saturating_add(*count))
+    }
+
+    /// Estimated latency in milliseconds below which `quantile` of recent
+    /// handshakes completed, interpolating linearly within a bucket.
+    pub fn recent_percentile_ms(&self, quantile: f64) -> Option<f64> {
+        percentile_micros(&self.recent, quantile).map(|micros| micros / 1_000.0)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct BackendHistory {
+    /// Cumulative count last read from the map.
+    last: Buckets,
+    current: Buckets,
+    previous: Buckets,
+}
+
+/// Reads the per-CPU handshake latency histograms the XDP program keeps for
+/// each backend and maintains each backend's recent windows.
+pub struct HandshakeLatencyMap {
+    map: PerCpuHashMap<MapData, u32, HandshakeLatency>,
+    backends: HashMap<u32, BackendHistory>,
+    window_started: Option<Instant>,
+    /// Suppresses repeated warnings while the map remains unreadable.
+    read_error_reported: bool,
+}
+
+impl HandshakeLatencyMap {
+    pub fn new(map: PerCpuHashMap<MapData, u32, HandshakeLatency>) -> Self {
+        Self {
+            map,
+            backends: HashMap::new(),
+            window_started: None,
+            read_error_reported: false,
+        }
+    }
+
+    /// Read every backend's histogram, keyed by the dataplane's packed
+    /// backend address. A backend evicted from the map drops out; an
+    /// unreadable map keeps the recent windows and reports no increase.
+    pub fn sample(&mut self, now: Instant) -> HashMap<u128, HandshakeLatencySample> {
+        let window_started = *self.window_started.get_or_insert(now);
+        let rotate = now.saturating_duration_since(window_started) >= HANDSHAKE_WINDOW;
+        if rotate {
+            self.window_started = Some(now);
+        }
+
+        let totals = self.read_totals();
+        if let Some(totals) = &totals {
+            self.backends
+                .retain(|backend, _| totals.contains_key(backend));
+            for backend in totals.keys() {
+                self.backends.entry(*backend).or_default();
+            }
+        }
+
+        self.backends
+            .iter_mut()
+            .map(|(backend, history)| {
+                let total = totals.as_ref().and_then(|totals| totals.get(backend));
+                let delta = advance(history, total, rotate);
+                (
+                    u128::from(*backend),
+                    HandshakeLatencySample {
+                        delta,
+                        recent: add(&history.current, &history.previous),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Cumulative histogram of every backend, summed across CPUs, or `None`
+    /// when the map could not be read completely.
+    fn read_totals(&mut self) -> Option<HashMap<u32, Buckets>> {
+        let mut totals = HashMap::new();
+        for entry in self.map.iter() {
+            match entry {
+                Ok((backend, per_cpu)) => {
+                    let mut buckets = [0u64; HANDSHAKE_LATENCY_BUCKETS];
+                    for histogram in per_cpu.iter() {
+                        for (sum, count) in buckets.iter_mut().zip(histogram.buckets) {
+                            *sum = sum.saturating_add(count);
+                        }
+                    }
+                    totals.insert(backend, buckets);
+                }
+                Err(err) => {
+                    if !self.read_error_reported {
+                        warn!("Failed to read handshake latency histograms: {err}");
+                    }
+                    self.read_error_reported = true;
+                    return None;
+                }
+            }
+        }
+        self.read_error_reported = false;
+        Some(totals)
+    }
+}
+
+/// Move `history` to the cumulative `total` just read, returning the
+/// handshakes completed since the previous read.
+fn advance(history: &mut BackendHistory, total: Option<&Buckets>, rotate: bool) -> Buckets {
+    if rotate {
+        history.previous = std::mem::take(&mut history.current);
+    }
+    let Some(total) = total else {
+        return [0; HANDSHAKE_LATENCY_BUCKETS];
+    };
+
+    // A count below the last one means the entry was evicted and
+    // recreated, so everything it holds is new.
+    let recreated = total
+        .iter()
+        .zip(&history.last)
+        .any(|(now, last)| now < last);
+    let delta: Buckets = if recreated {
+        *total
+    } else {
+        std::array::from_fn(|bucket| total[bucket] - history.last[bucket])
+    };
+    history.current = add(&history.current, &delta);
+    history.last = *total;
+    delta
+}
+
+fn add(a: &Buckets, b: &Buckets) -> Buckets {
+    std::array::from_fn(|bucket| a[bucket].saturating_add(b[bucket]))
+}
+
+fn percentile_micros(buckets: &Buckets, quantile: f64) -> Option<f64> {
+    let count = buckets
+        .iter()
+        .fold(0u64, |sum, count| sum.saturating_add(*count));
+    if count == 0 {
+        return None;
+    }
+
+    let rank = quantile.clamp(0.0, 1.0) * count as f64;
+    let mut below = 0u64;
+    for (bucket, in_bucket) in buckets.iter().enumerate() {
+        if *in_bucket == 0 {
+            continue;
+        }
+        let through = below.saturating_add(*in_bucket);
+        if through as f64 >= rank {
+            let lower = bucket
+                .checked_sub(1)
+                .and_then(HandshakeLatency::upper_bound_micros)
+                .unwrap_or(0) as f64;
+            // The unbounded last bucket is treated as one more doubling.
+            let upper = HandshakeLatency::upper_bound_micros(bucket)
+                .map_or(lower * 2.0, |upper| upper as f64);
+            let fraction = (rank - below as f64) / *in_bucket as f64;
+            return Some(lower + (upper - lower) * fraction.clamp(0.0, 1.0));
+        }
+        below = through;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buckets(entries: &[(usize, u64)]) -> Buckets {
+        let mut buckets = [0; HANDSHAKE_LATENCY_BUCKETS];
+        for (bucket, count) in entries {
+            buckets[*bucket] = *count;
+        }
+        buckets
+    }
+
+    #[test]
+    fn advance_keeps_two_windows_and_restarts_after_eviction() {
+        let mut history = BackendHistory::default();
+        assert_eq!(
+            advance(&mut history, Some(&buckets(&[(10, 4)])), false),
+            buckets(&[(10, 4)])
+        );
+        assert_eq!(
+            advance(&mut history, Some(&buckets(&[(10, 6), (11, 1)])), true),
+            buckets(&[(10, 2), (11, 1)])
+        );
+        assert_eq!(history.previous, buckets(&[(10, 4)]));
+        assert_eq!(history.current, buckets(&[(10, 2), (11, 1)]));
+
+        // Evicted and recreated with fewer handshakes than last read.
+        assert_eq!(
+            advance(&mut history, Some(&buckets(&[(10, 1)])), true),
+            buckets(&[(10, 1)])
+        );
+        assert_eq!(history.previous, buckets(&[(10, 2), (11, 1)]));
+        assert_eq!(history.current, buckets(&[(10, 1)]));
+
+        assert_eq!(advance(&mut history, None, true), buckets(&[]));
+        assert_eq!(history.previous, buckets(&[(10, 1)]));
+        assert_eq!(history.current, buckets(&[]));
+    }
+
+    #[test]
+    fn percentiles_interpolate_within_power_of_two_buckets() {
+        // 100 handshakes of 512 µs up to 1024 µs, 10 of 1024 µs up to 2048 µs.
+        let sample = HandshakeLatencySample {
+            delta: buckets(&[]),
+            recent: buckets(&[(10, 100), (11, 10)]),
+        };
+
+        assert_eq!(sample.recent_count(), 110);
+        let p50 = sample.recent_percentile_ms(0.5).expect("p50");
+        let p99 = sample.recent_percentile_ms(0.99).expect("p99");
+        assert!((p50 - 0.7936).abs() < 1e-9, "{p50}");
+        assert!((p99 - 1.93536).abs() < 1e-9, "{p99}");
+        assert_eq!(
+            HandshakeLatencySample::default().recent_percentile_ms(0.5),
+            None
+        );
+
+        let slowest = HandshakeLatencySample {
+            delta: buckets(&[]),
+            recent: buckets(&[(HANDSHAKE_LATENCY_BUCKETS - 1, 1)]),
+        };
+        assert_eq!(slowest.recent_percentile_ms(1.0), Some(33_554.432));
+    }
+}
@@ -18,6 +18,12 @@ pub struct Metrics {
     pub closed_rsts_by_server: u32,
     /// Orphaned connections cleaned up (idle timeout)
     pub orphaned_conns: u32,
+    /// Connections the backend refused with an immediate reset or left
+    /// unanswered past [`UNANSWERED_SYN_TIMEOUT`]. Only counted on the
+    /// server's (ToClient) side of a flow.
+    ///
+    /// [`UNANSWERED_SYN_TIMEOUT`]: crate::r#loop::utils::UNANSWERED_SYN_TIMEOUT
+    pub handshake_failures: u32,
     /// Average bandwidth in Mbps between last poll
     pub bandwidth_mbps: f64,
     /// Average packets per second between last poll
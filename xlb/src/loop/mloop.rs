@@ -1,17 +1,27 @@
+use crate::config::Host;
+use crate::health::{HealthChecker, OutlierDetector, OutlierUpdate};
 use crate::r#loop::cleanup::{CleanupSummary, prune_orphaned_or_closed};
+use crate::r#loop::drain::{DrainSettings, DrainTracker, DrainUpdate};
 use crate::r#loop::metrics::Metrics;
+use crate::r#loop::reset::RstSender;
 use crate::r#loop::utils;
 use crate::r#loop::utils::LbFlowStats;
+use crate::r#loop::{
+    DataplaneCounterMaps, HandshakeLatencyMap, PriorityTiers, SlowStart, VipDataplane,
+};
 use crate::metrics;
 use crate::provider::{BackendProvider, hosts_to_backends_with_routes};
-use crate::status::StatusState;
+use crate::status::{EventKind, EventSeverity, StatusState, TierTransition, unix_time_ms};
 use crate::system::ResourceSampler;
 use anyhow::{Context, Result, anyhow};
 use aya::maps::{Array, HashMap, MapData, PerCpuArray};
-use log::{debug, trace, warn};
+use log::{debug, info, trace, warn};
+use std::collections::HashSet;
+use std::net::IpAddr;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, OnceLock};
-use std::time::Duration;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
 use tokio::task::JoinHandle;
 use tokio::time::interval;
 use xlb_common::consts;
@@ -22,11 +32,47 @@ pub struct MaintenanceLoopHandle {
     task: Option<JoinHandle<()>>,
 }
 
+/// Settings a configuration reload changes in a running maintenance loop.
+#[derive(Clone)]
+pub struct LoopSettings {
+    pub provider: Arc<dyn BackendProvider>,
+    pub port_names: Vec<Option<String>>,
+    pub orphan_ttl: Duration,
+    pub drain: DrainSettings,
+}
+
+/// Listen ports with open client connections, as the maintenance loop last
+/// saw them. Configuration reloads consult it so that they do not take away
+/// a port that connections still arrive on.
+#[derive(Default)]
+pub struct LiveListenPorts(RwLock<HashSet<u16>>);
+
+impl LiveListenPorts {
+    pub fn get(&self) -> HashSet<u16> {
+        self.0.read().expect("listen ports lock poisoned").clone()
+    }
+
+    /// Publish the ports of one flow-map iteration. An incomplete iteration
+    /// may have missed connections, so it only adds ports.
+    pub(crate) fn observe(&self, stats: &LbFlowStats) {
+        let mut ports = self.0.write().expect("listen ports lock poisoned");
+        if stats.flow_map_complete {
+            ports.clone_from(&stats.listen_ports);
+        } else {
+            ports.extend(&stats.listen_ports);
+        }
+    }
+}
+
 /// eBPF maps owned and periodically reconciled by the maintenance loop.
 pub struct MaintenanceMaps {
     pub backends: Array<MapData, Backend>,
     pub flows: HashMap<MapData, FlowKeyV4, Flow>,
     pub flow_pair_invariants: PerCpuArray<MapData, u64>,
+    pub dataplane_counters: DataplaneCounterMaps,
+    pub handshake_latency: HandshakeLatencyMap,
+    pub force_close: HashMap<MapData, u128, u8>,
+    pub return_ports: HashMap<MapData, u16, u8>,
 }
 
 impl MaintenanceLoopHandle {
@@ -77,6 +123,16 @@ pub struct MaintenanceLoop {
     ebpf_flows: HashMap<MapData, FlowKeyV4, Flow>,
     /// Per-CPU count of flow-pair invariant repairs performed in eBPF.
     flow_pair_invariants: PerCpuArray<MapData, u64>,
+    /// Per-CPU packet outcome and error counters of the XDP program.
+    dataplane_counters: DataplaneCounterMaps,
+    /// Per-CPU backend handshake latency histograms of the XDP program.
+    handshake_latency: HandshakeLatencyMap,
+    /// Backends whose remaining flows the dataplane resets.
+    ebpf_force_close: HashMap<MapData, u128, u8>,
+    /// Per-backend destination ports the dataplane treats as reply sources.
+    ebpf_return_ports: HashMap<MapData, u16, u8>,
+    /// Service port name of each port mapping, by index.
+    port_names: Vec<Option<String>>,
     /// If a flow is active longer than this TTL it is considered
     /// to be an orpaned connection (closed w/o fin or rst)
     orphan_ttl: Duration,
@@ -100,6 +156,25 @@ pub struct MaintenanceLoop {
     flow_iteration_error_reported: bool,
     /// Latest operational snapshot exposed by the admin API.
     status: Arc<StatusState>,
+    /// Optional active health checks gating new connections.
+    health: Option<Arc<HealthChecker>>,
+    /// Optional passive ejection of backends with elevated failure rates.
+    outliers: Option<OutlierDetector>,
+    /// Optional weight ramp for backends that recently joined the pool.
+    slow_start: Option<SlowStart>,
+    /// Optional restriction of new connections to the preferred priority tier.
+    tiers: Option<PriorityTiers>,
+    /// Drain progress of backends leaving the pool.
+    drains: Option<DrainTracker>,
+    /// Optional VIPs of Kubernetes LoadBalancer Services, each with its
+    /// own backend pool.
+    vips: Option<VipDataplane>,
+    /// Resets every live connection once the loop stops at shutdown.
+    shutdown_reset: Option<RstSender>,
+    /// Settings published by configuration reloads.
+    reloads: Option<watch::Receiver<LoopSettings>>,
+    /// Listen ports of open connections, shared with configuration reloads.
+    live_listen_ports: Option<Arc<LiveListenPorts>>,
 }
 
 impl MaintenanceLoop {
@@ -116,6 +191,10 @@ impl MaintenanceLoop {
             backends,
             flows,
             flow_pair_invariants,
+            dataplane_counters,
+            handshake_latency,
+            force_close,
+            return_ports,
         } = maps;
         Self {
             shutdown: OnceLock::new(),
@@ -123,6 +202,11 @@ impl MaintenanceLoop {
             ebpf_backends: backends,
             ebpf_flows: flows,
             flow_pair_invariants,
+            dataplane_counters,
+            handshake_latency,
+            ebpf_force_close: force_close,
+            ebpf_return_ports: return_ports,
+            port_names: Vec::new(),
             orphan_ttl,
             tcp_time_wait_ttl,
             last_run_ns: 0,
@@ -131,6 +215,99 @@ impl MaintenanceLoop {
             resource_sampler: ResourceSampler::new(attached_interfaces, network_capacity_mbps),
             flow_iteration_error_reported: false,
             status,
+            health: None,
+            outliers: None,
+            slow_start: None,
+            tiers: None,
+            drains: None,
+            vips: None,
+            shutdown_reset: None,
+            reloads: None,
+            live_listen_ports: None,
+        }
+    }
+
+    /// Send each port mapping's traffic to the backend port published under
+    /// its Service port name, when the provider discovers one.
+    pub fn with_port_names(mut self, port_names: Vec<Option<String>>) -> Self {
+        self.port_names = port_names;
+        self
+    }
+
+    /// Only route new connections to discovered backends that pass
+    /// the checker's active health checks.
+    pub fn with_health_checks(mut self, checker: Arc<HealthChecker>) -> Self {
+        self.health = Some(checker);
+        self
+    }
+
+    /// Temporarily eject backends whose observed connection failures
+    /// cross the detector's thresholds.
+    pub fn with_outlier_detection(mut self, detector: OutlierDetector) -> Self {
+        self.outliers = Some(detector);
+        self
+    }
+
+    /// Ramp the selection weight of newly added backends
+    /// up to full over the slow-start window.
+    pub fn with_slow_start(mut self, slow_start: SlowStart) -> Self {
+        self.slow_start = Some(slow_start);
+        self
+    }
+
+    /// Only route new connections to the most preferred priority tier
+    /// with enough eligible backends.
+    pub fn with_priority_tiers(mut self, tiers: PriorityTiers) -> Self {
+        self.tiers = Some(tiers);
+        self
+    }
+
+    /// Track backends leaving the pool until their connections finish,
+    /// resetting stragglers at the deadline when configured.
+    pub fn with_drains(mut self, drains: DrainTracker) -> Self {
+        self.drains = Some(drains);
+        self
+    }
+
+    /// Serve the VIPs allocated by the Kubernetes LoadBalancer controller.
+    pub fn with_load_balancer(mut self, vips: VipDataplane) -> Self {
+        self.vips = Some(vips);
+        self
+    }
+
+    /// Proactively reset both sides of every live connection when
+    /// the loop is stopped for shutdown.
+    pub fn with_shutdown_reset(mut self, sender: RstSender) -> Self {
+        self.shutdown_reset = Some(sender);
+        self
+    }
+
+    /// Apply the settings of each configuration reload at the start of
+    /// the next interval.
+    pub fn with_reloads(mut self, reloads: watch::Receiver<LoopSettings>) -> Self {
+        self.reloads = Some(reloads);
+        self
+    }
+
+    /// Publish the listen ports of open connections after each interval.
+    pub fn with_live_listen_ports(mut self, ports: Arc<LiveListenPorts>) -> Self {
+        self.live_listen_ports = Some(ports);
+        self
+    }
+
+    fn apply_reload(&mut self) {
+        let Some(reloads) = &mut self.reloads else {
+            return;
+        };
+        if !reloads.has_changed().unwrap_or(false) {
+            return;
+        }
+        let settings = reloads.borrow_and_update().clone();
+        self.provider = settings.provider;
+        self.port_names = settings.port_names;
+        self.orphan_ttl = settings.orphan_ttl;
+        if let Some(drains) = &mut self.drains {
+            drains.set_settings(settings.drain);
         }
     }
 
@@ -142,9 +319,15 @@ impl MaintenanceLoop {
     }
 
     async fn run(&mut self) {
+        self.apply_reload();
         let now_ns = utils::monotonic_now_ns();
         let new_hosts = self.provider.get_backends();
-        let mut new_backends = hosts_to_backends_with_routes(&new_hosts).await;
+        let eligible_hosts = match &self.health {
+            Some(checker) => checker.filter(&new_hosts),
+            None => new_hosts.clone(),
+        };
+        let mut new_backends =
+            hosts_to_backends_with_routes(&eligible_hosts, &self.port_names).await;
 
         if new_backends.len() > consts::MAX_BACKENDS as usize {
             warn!(
@@ -192,6 +375,45 @@ impl MaintenanceLoop {
             self.flow_iteration_error_reported = false;
         }
 
+        if let Some(ports) = &self.live_listen_ports {
+            ports.observe(&stats);
+        }
+
+        if let Some(drains) = &mut self.drains {
+            let now = Instant::now();
+            let update = drains.observe(&new_hosts, &stats, now);
+            apply_drain_update(&mut self.ebpf_force_close, &update);
+            drains.retain_available(&mut new_backends);
+            self.status.record_drains(drains.statuses(now));
+            self.status.record_admin_overrides(drains.overrides());
+        }
+
+        if let Some(outliers) = &mut self.outliers {
+            let now = Instant::now();
+            log_outlier_update(
+                &outliers.observe(&stats, &new_backends, now),
+                &new_hosts,
+                &self.status,
+            );
+            outliers.retain_available(&mut new_backends, now);
+            self.status.record_outliers(outliers.statuses(now));
+        }
+
+        if let Some(tiers) = &mut self.tiers {
+            if let Some(transition) =
+                tiers.retain_active(&mut new_backends, &new_hosts, unix_time_ms())
+            {
+                record_tier_transition(&self.status, &transition);
+            }
+            self.status.record_priority_tiers(tiers.status());
+        }
+
+        if let Some(slow_start) = &mut self.slow_start {
+            let in_pool = self.status.time_in_pool(Instant::now());
+            let ramp = slow_start.apply(&mut new_backends, &in_pool);
+            self.status.record_slow_start(ramp);
+        }
+
         stats.available_backends = new_backends.len() as u32;
         stats.resource_utilization = self
             .resource_sampler
@@ -203,6 +425,26 @@ impl MaintenanceLoop {
         // counters.
         self.prev_flow_stats = new_prev_flow_stats;
 
+        let staged_vips = match &self.vips {
+            Some(vips) => vips.stage().await,
+            None => Vec::new(),
+        };
+
+        // Replies from a newly discovered port must be recognised before the
+        // first SYN toward it can be forwarded.
+        sync_return_ports(
+            &mut self.ebpf_return_ports,
+            new_backends
+                .iter()
+                .chain(staged_vips.iter().flat_map(|vip| vip.backends())),
+            &stats,
+        );
+
+        if let Some(vips) = &mut self.vips {
+            let status = vips.program(&staged_vips);
+            self.status.record_load_balancer(status);
+        }
+
         for (i, backend) in new_backends.iter().enumerate() {
             self.ebpf_backends
                 .set(i as u32, backend, 0)
@@ -249,10 +491,26 @@ impl MaintenanceLoop {
                 invariant_violations
             );
             metrics::record_flow_pair_invariant_violations(invariant_violations);
+            self.status.events().record(
+                EventKind::InvariantViolation,
+                EventSeverity::Warning,
+                format!("{invariant_violations} flow-pair invariant violation observation(s)"),
+                None,
+            );
         }
 
         apply_orphan_cleanup_stats(&mut stats, &cleanup);
+        stats.dataplane = self.dataplane_counters.sample();
+        stats.handshake_latency = self.handshake_latency.sample(Instant::now());
 
+        if let Some(checker) = &self.health {
+            self.status.record_health_checks(checker.statuses());
+        }
+        self.status
+            .record_provider_error(self.provider.last_error());
+        self.status
+            .record_provider_topology(self.provider.topology());
+        self.status.record_provider_watches(self.provider.watches());
         // Readiness describes the backend set actually committed to the BPF
         // map, never the candidate set observed before reconciliation.
         self.status.publish(
@@ -293,6 +551,19 @@ impl MaintenanceLoop {
                     break;
                 }
             }
+
+            // Sending is a syscall per flow entry, so a large flow map must
+            // not hold up the runtime while the rest of XLB shuts down.
+            if let Some(sender) = self.shutdown_reset.take() {
+                let flows = self.ebpf_flows;
+                match tokio::task::spawn_blocking(move || sender.reset_live_flows(&flows)).await {
+                    Ok(summary) => info!(
+                        "Reset {} live flow entries at shutdown ({} skipped, {} failed)",
+                        summary.sent, summary.skipped, summary.failed
+                    ),
+                    Err(error) => warn!("Shutdown reset of live flows did not finish: {error}"),
+                }
+            }
         });
 
         MaintenanceLoopHandle {
@@ -302,6 +573,129 @@ impl MaintenanceLoop {
     }
 }
 
+/// Keep the dataplane's reply source ports to the discovered ports of
+/// published backends, plus the destination ports of live flows, so their
+/// replies are still recognised after a reload changes a mapping's
+/// `remote_port`. Ports are only released after a complete flow-map
+/// iteration.
+fn sync_return_ports<'a>(
+    return_ports: &mut HashMap<MapData, u16, u8>,
+    backends: impl Iterator<Item = &'a Backend>,
+    stats: &LbFlowStats,
+) {
+    let wanted: HashSet<u16> = backends
+        .flat_map(|backend| backend.ports)
+        .chain(stats.backend_ports.iter().copied())
+        .filter(|port| *port != 0)
+        .collect();
+    let current: HashSet<u16> = return_ports.keys().filter_map(Result::ok).collect();
+
+    if stats.flow_map_complete {
+        for port in current.iter().filter(|port| !wanted.contains(*port)) {
+            if let Err(err) = return_ports.remove(port) {
+                warn!("Failed to release backend reply port {port}: {err}");
+            }
+        }
+    }
+
+    for port in wanted.difference(&current) {
+        if let Err(err) = return_ports.insert(*port, 1, 0) {
+            warn!(
+                "Failed to register backend reply port {port} (limit {}): {err}",
+                consts::MAX_RETURN_PORTS
+            );
+        }
+    }
+}
+
+fn log_outlier_update(update: &OutlierUpdate, hosts: &[Host], status: &StatusState) {
+    let name = |address: IpAddr| {
+        hosts
+            .iter()
+            .find(|host| host.ip == address)
+            .map_or_else(|| address.to_string(), |host| host.name.clone())
+    };
+
+    for ejection in &update.ejected {
+        warn!(
+            "Ejecting backend {} ({}) for {}s: {:.1}% {} over the last interval",
+            name(ejection.address),
+            ejection.address,
+            ejection.duration.as_secs(),
+            ejection.failure_percent,
+            ejection.reason.as_str()
+        );
+        metrics::record_backend_ejected(ejection.address, ejection.reason);
+        status.events().record(
+            EventKind::OutlierEjected,
+            EventSeverity::Warning,
+            format!(
+                "Ejected backend {} for {}s: {:.1}% {}",
+                name(ejection.address),
+                ejection.duration.as_secs(),
+                ejection.failure_percent,
+                ejection.reason.as_str()
+            ),
+            Some(ejection.address),
+        );
+    }
+    for address in &update.restored {
+        log::info!(
+            "Returning backend {} ({}) to the pool after ejection",
+            name(*address),
+            address
+        );
+        status.events().record(
+            EventKind::OutlierRestored,
+            EventSeverity::Info,
+            format!(
+                "Returned backend {} to the pool after ejection",
+                name(*address)
+            ),
+            Some(*address),
+        );
+    }
+}
+
+fn record_tier_transition(status: &StatusState, transition: &TierTransition) {
+    let (severity, message) = match (transition.from, transition.to) {
+        (Some(from), Some(to)) if to > from => (
+            EventSeverity::Warning,
+            format!("Failed over from priority tier {from} to tier {to}"),
+        ),
+        (Some(from), Some(to)) => (
+            EventSeverity::Info,
+            format!("Failed back from priority tier {from} to tier {to}"),
+        ),
+        (Some(from), None) => (
+            EventSeverity::Warning,
+            format!("No priority tier has eligible backends (was tier {from})"),
+        ),
+        (None, Some(to)) => (
+            EventSeverity::Info,
+            format!("Routing new connections to priority tier {to}"),
+        ),
+        (None, None) => return,
+    };
+    status
+        .events()
+        .record(EventKind::PriorityTierChanged, severity, message, None);
+}
+
+fn apply_drain_update(force_close: &mut HashMap<MapData, u128, u8>, update: &DrainUpdate) {
+    for address in &update.force_close {
+        warn!("Drain deadline passed for backend {address}; resetting remaining connections");
+        if let Err(err) = force_close.insert(utils::pack_ip(*address), 1, 0) {
+            warn!("Failed to force-close backend {address}: {err}");
+        }
+    }
+    for address in &update.release {
+        if let Err(err) = force_close.remove(&utils::pack_ip(*address)) {
+            warn!("Failed to clear force-close for backend {address}: {err}");
+        }
+    }
+}
+
 fn apply_orphan_cleanup_stats(stats: &mut LbFlowStats, cleanup: &CleanupSummary) {
     stats.totals.to_client.orphaned_conns = 0;
     for backend in stats.backends.values_mut() {
@@ -433,6 +827,44 @@ mod tests {
             .expect("join maintenance task");
     }
 
+    #[test]
+    fn return_ports_follow_live_flows_after_their_mapping_changes() {
+        // The dataplane's return ports need a real map, which unprivileged
+        // test runs cannot create.
+        let mut return_ports = match HashMap::<MapData, u16, u8>::create(8, 0) {
+            Ok(map) => map,
+            Err(error) => {
+                eprintln!("skipping: cannot create a return ports map: {error}");
+                return;
+            }
+        };
+        let registered = |ports: &HashMap<MapData, u16, u8>| {
+            ports
+                .keys()
+                .filter_map(Result::ok)
+                .collect::<HashSet<u16>>()
+        };
+
+        // A reload moved the mapping off 8080 while a flow still uses it.
+        let mut stats = LbFlowStats {
+            flow_map_complete: true,
+            backend_ports: HashSet::from([8080]),
+            ..LbFlowStats::default()
+        };
+        sync_return_ports(&mut return_ports, std::iter::empty(), &stats);
+        assert_eq!(registered(&return_ports), HashSet::from([8080]));
+
+        // An incomplete iteration may have missed the flow.
+        stats.backend_ports.clear();
+        stats.flow_map_complete = false;
+        sync_return_ports(&mut return_ports, std::iter::empty(), &stats);
+        assert_eq!(registered(&return_ports), HashSet::from([8080]));
+
+        stats.flow_map_complete = true;
+        sync_return_ports(&mut return_ports, std::iter::empty(), &stats);
+        assert!(registered(&return_ports).is_empty());
+    }
+
     #[test]
     fn cleanup_results_replace_directional_orphan_estimates() {
         let backend_ip = u128::from(0x0a00_0001_u32);
@@ -1,6 +1,22 @@
 mod cleanup;
+mod connections;
+mod dataplane;
+mod drain;
+mod handshake;
 pub(crate) mod metrics;
 mod mloop;
+mod reset;
+mod slow_start;
+mod tiers;
 pub(crate) mod utils;
+mod vips;
 
+pub use connections::*;
+pub use dataplane::{CounterSample, DataplaneCounterMaps, DataplaneCounters};
+pub use drain::{BackendOverrides, DrainTracker};
+pub use handshake::{HandshakeLatencyMap, HandshakeLatencySample};
 pub use mloop::*;
+pub use reset::RstSender;
+pub use slow_start::SlowStart;
+pub use tiers::PriorityTiers;
+pub use vips::VipDataplane;
@@ -0,0 +1,274 @@
+use anyhow::{Context, Result};
+use aya::maps::{HashMap, MapData};
+use log::{debug, warn};
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use xlb_common::types::{Flow, FlowKeyV4};
+
+const ETH_HDR_LEN: usize = 14;
+const IPV4_HDR_LEN: usize = 20;
+const TCP_HDR_LEN: usize = 20;
+const RST_FRAME_LEN: usize = ETH_HDR_LEN + IPV4_HDR_LEN + TCP_HDR_LEN;
+/// Matches the TTL of resets generated by the dataplane.
+const RST_TTL: u8 = 64;
+const TCP_FLAG_RST: u8 = 0x04;
+
+/// Sends complete Ethernet frames through an `AF_PACKET` socket. Frames
+/// leave on the egress path, bypassing both the XDP ingress hook and the
+/// kernel TCP stack.
+pub struct RstSender {
+    socket: OwnedFd,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(super) struct ResetSummary {
+    pub(super) sent: u64,
+    pub(super) skipped: u64,
+    pub(super) failed: u64,
+}
+
+impl RstSender {
+    pub fn new() -> Result<Self> {
+        // Protocol 0 makes the socket send-only; it never receives copies
+        // of interface traffic.
+        let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW | libc::SOCK_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error()).context("Failed to open AF_PACKET socket");
+        }
+
+        Ok(Self {
+            socket: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    fn send(&self, ifindex: u16, frame: &[u8; RST_FRAME_LEN]) -> io::Result<()> {
+        let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as u16;
+        addr.sll_ifindex = i32::from(ifindex);
+        addr.sll_halen = 6;
+        addr.sll_addr[..6].copy_from_slice(&frame[..6]);
+
+        let sent = unsafe {
+            libc::sendto(
+                self.socket.as_raw_fd(),
+                frame.as_ptr().cast(),
+                frame.len(),
+                0,
+                (&addr as *const libc::sockaddr_ll).cast(),
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Reset every live connection in both directions, using each directional
+    /// entry's rewrite recipe and the sequence number its receiver expects.
+    pub(super) fn reset_live_flows(
+        &self,
+        flow_map: &HashMap<MapData, FlowKeyV4, Flow>,
+    ) -> ResetSummary {
+        let mut summary = ResetSummary::default();
+        let mut first_error = None;
+
+        for (_, flow) in flow_map.iter().flatten() {
+            let Some(frame) = rst_frame(&flow) else {
+                summary.skipped += 1;
+                continue;
+            };
+
+            match self.send(flow.src_iface_idx, &frame) {
+                Ok(()) => summary.sent += 1,
+                Err(error) => {
+                    summary.failed += 1;
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+
+        if let Some(error) = first_error {
+            warn!(
+                "Failed to send {} shutdown RST(s); first error: {error}",
+                summary.failed
+            );
+        }
+        debug!("Shutdown reset summary {summary:?}");
+
+        summary
+    }
+}
+
+/// Whether a directional entry still belongs to an open connection whose
+/// receiver would accept a reset built from it.
+fn should_reset(flow: &Flow) -> bool {
+    flow.pair_ready
+        && !flow.pair_invalid
+        && flow.seq_known
+        && flow.rst_ns == 0
+        && flow.fin_both_ns == 0
+        && flow.src_ip <= u128::from(u32::MAX)
+        && flow.dst_ip <= u128::from(u32::MAX)
+}
+
+/// Build the RST the entry's receiver expects next, addressed exactly as the
+/// dataplane would rewrite a packet travelling in this direction.
+fn rst_frame(flow: &Flow) -> Option<[u8; RST_FRAME_LEN]> {
+    if !should_reset(flow) {
+        return None;
+    }
+
+    let src_ip = (flow.src_ip as u32).to_be_bytes();
+    let dst_ip = (flow.dst_ip as u32).to_be_bytes();
+    let mut frame = [0u8; RST_FRAME_LEN];
+
+    let (eth, packet) = frame.split_at_mut(ETH_HDR_LEN);
+    eth[..6].copy_from_slice(&flow.dst_mac);
+    eth[6..12].copy_from_slice(&flow.src_mac);
+    eth[12..].copy_from_slice(&(libc::ETH_P_IP as u16).to_be_bytes());
+
+    let (ip, tcp) = packet.split_at_mut(IPV4_HDR_LEN);
+    ip[0] = 0x45;
+    ip[2..4].copy_from_slice(&((IPV4_HDR_LEN + TCP_HDR_LEN) as u16).to_be_bytes());
+    // Don't fragment; a 40 byte datagram never needs it.
+    ip[6] = 0x40;
+    ip[8] = RST_TTL;
+    ip[9] = libc::IPPROTO_TCP as u8;
+    ip[12..16].copy_from_slice(&src_ip);
+    ip[16..20].copy_from_slice(&dst_ip);
+    let ip_checksum = checksum(0, ip);
+    ip[10..12].copy_from_slice(&ip_checksum.to_be_bytes());
+
+    tcp[0..2].copy_from_slice(&flow.src_port.to_be_bytes());
+    tcp[2..4].copy_from_slice(&flow.dst_port.to_be_bytes());
+    tcp[4..8].copy_from_slice(&flow.next_seq.to_be_bytes());
+    tcp[12] = ((TCP_HDR_LEN / 4) as u8) << 4;
+    tcp[13] = TCP_FLAG_RST;
+
+    let mut pseudo_header = [0u8; 12];
+    pseudo_header[..4].copy_from_slice(&src_ip);
+    pseudo_header[4..8].copy_from_slice(&dst_ip);
+    pseudo_header[9] = libc::IPPROTO_TCP as u8;
+    pseudo_header[10..].copy_from_slice(&(TCP_HDR_LEN as u16).to_be_bytes());
+    let tcp_checksum = checksum(sum_words(&pseudo_header), tcp);
+    tcp[16..18].copy_from_slice(&tcp_checksum.to_be_bytes());
+
+    Some(frame)
+}
+
+fn sum_words(bytes: &[u8]) -> u32 {
+    bytes
+        .chunks(2)
+        .map(|word| u32::from(u16::from_be_bytes([word[0], *word.get(1).unwrap_or(&0)])))
+        .sum()
+}
+
+/// RFC 1071 Internet checksum of `bytes`, seeded with a partial `sum`.
+fn checksum(sum: u32, bytes: &[u8]) -> u16 {
+    let mut sum = sum + sum_words(bytes);
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xlb_common::types::FlowDirection;
+
+    fn live_flow() -> Flow {
+        Flow {
+            client_ip: 0xc000_0201,
+            backend_ip: 0x0a00_0007,
+            src_ip: 0xc633_6401,
+            dst_ip: 0xc000_0201,
+            bytes_transfer: 0,
+            packets_transfer: 4,
+            created_at_ns: 1,
+            last_seen_ns: 1,
+            fin_both_ns: 0,
+            rst_ns: 0,
+            counter_flow_key: FlowKeyV4::tcp(0, 0, 0, 0, FlowDirection::ToServer),
+            direction: FlowDirection::ToClient,
+            src_port: 80,
+            dst_port: 50_000,
+            src_iface_idx: 2,
+            dst_mac: [0x02, 0, 0, 0, 0, 0x01],
+            src_mac: [0x02, 0, 0, 0, 0, 0x02],
+            fin: false,
+            fin_is_src: false,
+            rst_is_src: false,
+            pair_invalid: false,
+            pair_ready: true,
+            force_reset: false,
+            pair_tag: 7,
+            next_seq: 0x1234_5678,
+            seq_known: true,
+            handshake_timed: false,
+            _reserved: [0; 10],
+        }
+    }
+
+    #[test]
+    fn rst_frame_follows_the_entry_recipe() {
+        let frame = rst_frame(&live_flow()).expect("live flow is reset");
+
+        assert_eq!(frame[..6], [0x02, 0, 0, 0, 0, 0x01]);
+        assert_eq!(frame[6..12], [0x02, 0, 0, 0, 0, 0x02]);
+        assert_eq!(frame[12..14], [0x08, 0x00]);
+
+        let ip = &frame[ETH_HDR_LEN..ETH_HDR_LEN + IPV4_HDR_LEN];
+        assert_eq!(ip[12..16], [198, 51, 100, 1]);
+        assert_eq!(ip[16..20], [192, 0, 2, 1]);
+        assert_eq!(checksum(0, ip), 0, "IPv4 header checksum must verify");
+
+        let tcp = &frame[ETH_HDR_LEN + IPV4_HDR_LEN..];
+        assert_eq!(u16::from_be_bytes([tcp[0], tcp[1]]), 80);
+        assert_eq!(u16::from_be_bytes([tcp[2], tcp[3]]), 50_000);
+        assert_eq!(
+            u32::from_be_bytes([tcp[4], tcp[5], tcp[6], tcp[7]]),
+            0x1234_5678
+        );
+        assert_eq!(
+            tcp[8..12],
+            [0; 4],
+            "a RST without ACK carries no ack number"
+        );
+        assert_eq!(tcp[13], TCP_FLAG_RST);
+    }
+
+    #[test]
+    fn rst_frame_tcp_checksum_covers_the_pseudo_header() {
+        let frame = rst_frame(&live_flow()).expect("live flow is reset");
+        let ip = &frame[ETH_HDR_LEN..ETH_HDR_LEN + IPV4_HDR_LEN];
+        let tcp = &frame[ETH_HDR_LEN + IPV4_HDR_LEN..];
+
+        let mut pseudo_header = [0u8; 12];
+        pseudo_header[..8].copy_from_slice(&ip[12..20]);
+        pseudo_header[9] = 6;
+        pseudo_header[11] = TCP_HDR_LEN as u8;
+
+        assert_eq!(checksum(sum_words(&pseudo_header), tcp), 0);
+    }
+
+    #[test]
+    fn closed_incomplete_or_unsequenced_entries_are_skipped() {
+        let mut reset = live_flow();
+        reset.rst_ns = 1;
+        let mut closed = live_flow();
+        closed.fin_both_ns = 1;
+        let mut installing = live_flow();
+        installing.pair_ready = false;
+        let mut invalid = live_flow();
+        invalid.pair_invalid = true;
+        let mut unanswered = live_flow();
+        unanswered.seq_known = false;
+
+        for flow in [reset, closed, installing, invalid, unanswered] {
+            assert!(rst_frame(&flow).is_none());
+        }
+    }
+}
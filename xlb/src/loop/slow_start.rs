@@ -0,0 +1,171 @@
+use crate::r#loop::utils::packed_ip;
+use crate::status::SlowStartStatus;
+use std::collections::{BTreeMap, HashSet};
+use std::net::IpAddr;
+use std::time::Duration;
+use xlb_common::consts::MAX_BACKEND_WEIGHT;
+use xlb_common::types::Backend;
+
+/// Weight a backend starts its ramp with: a tenth of its full share.
+const MIN_WEIGHT: u16 = MAX_BACKEND_WEIGHT / 10;
+
+/// Linear selection-weight ramp for backends that recently joined the pool,
+/// so a cold process is not handed a full round-robin share immediately.
+///
+/// Backends already present when XLB starts are assumed warm and receive
+/// full weight; a backend that leaves and later returns ramps again.
+pub struct SlowStart {
+    window: Duration,
+    /// Backends from the first reconciliation, exempt while they remain.
+    initial: Option<HashSet<u128>>,
+}
+
+impl SlowStart {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            initial: None,
+        }
+    }
+
+    /// Weight for a backend that has been in the pool for `in_pool`.
+    pub fn weight(&self, in_pool: Duration) -> u16 {
+        if self.window.is_zero() || in_pool >= self.window {
+            return MAX_BACKEND_WEIGHT;
+        }
+
+        let progress = in_pool.as_secs_f64() / self.window.as_secs_f64();
+        let ramp = f64::from(MAX_BACKEND_WEIGHT - MIN_WEIGHT) * progress;
+        MIN_WEIGHT
+            .saturating_add(ramp as u16)
+            .min(MAX_BACKEND_WEIGHT)
+    }
+
+    /// Scale each backend's dataplane weight by its ramp progress and
+    /// return the ramp state to report. Expects backends freshly built
+    /// from provider hosts, carrying the weight the provider assigned.
+    /// Backends missing from `in_pool` have not been published yet and
+    /// start at the minimum weight.
+    pub fn apply(
+        &mut self,
+        backends: &mut [Backend],
+        in_pool: &BTreeMap<IpAddr, Duration>,
+    ) -> BTreeMap<IpAddr, SlowStartStatus> {
+        let present: HashSet<u128> = backends.iter().map(|backend| backend.ip).collect();
+        let mut initial = self.initial.take().unwrap_or_else(|| present.clone());
+        initial.retain(|ip| present.contains(ip));
+
+        let mut statuses = BTreeMap::new();
+        for backend in backends.iter_mut() {
+            let address = packed_ip(backend.ip);
+            let elapsed = if initial.contains(&backend.ip) {
+                self.window
+            } else {
+                in_pool.get(&address).copied().unwrap_or_default()
+            };
+
+            let ramp = self.weight(elapsed);
+            let scaled =
+                u32::from(backend.weight) * u32::from(ramp) / u32::from(MAX_BACKEND_WEIGHT);
+            backend.weight = (scaled as u16).max(1);
+            statuses.insert(
+                address,
+                SlowStartStatus {
+                    ramping: ramp < MAX_BACKEND_WEIGHT,
+                    weight_percent: f64::from(ramp) * 100.0 / f64::from(MAX_BACKEND_WEIGHT),
+                    remaining_seconds: self.window.saturating_sub(elapsed).as_secs(),
+                },
+            );
+        }
+
+        self.initial = Some(initial);
+        statuses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend(last_octet: u8) -> Backend {
+        Backend {
+            ip: u128::from(u32::from_be_bytes([10, 0, 0, last_octet])),
+            weight: MAX_BACKEND_WEIGHT,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn weight_ramps_linearly_from_a_tenth_to_full() {
+        let slow_start = SlowStart::new(Duration::from_secs(100));
+
+        assert_eq!(slow_start.weight(Duration::ZERO), 100);
+        assert_eq!(slow_start.weight(Duration::from_secs(50)), 550);
+        assert_eq!(slow_start.weight(Duration::from_secs(100)), 1000);
+        assert_eq!(slow_start.weight(Duration::from_secs(500)), 1000);
+    }
+
+    #[test]
+    fn backends_present_at_startup_are_not_ramped() {
+        let mut slow_start = SlowStart::new(Duration::from_secs(60));
+        let mut backends = vec![backend(1)];
+
+        let statuses = slow_start.apply(&mut backends, &BTreeMap::new());
+
+        assert_eq!(backends[0].weight, MAX_BACKEND_WEIGHT);
+        assert!(!statuses[&packed_ip(backends[0].ip)].ramping);
+    }
+
+    #[test]
+    fn later_backends_ramp_with_time_in_pool() {
+        let mut slow_start = SlowStart::new(Duration::from_secs(60));
+        slow_start.apply(&mut [backend(1)], &BTreeMap::new());
+
+        let mut backends = vec![backend(1), backend(2)];
+        let newcomer = packed_ip(backends[1].ip);
+        let first = slow_start.apply(&mut backends, &BTreeMap::new());
+        assert_eq!(backends[0].weight, MAX_BACKEND_WEIGHT);
+        assert_eq!(backends[1].weight, MIN_WEIGHT);
+        assert_eq!(first[&newcomer].remaining_seconds, 60);
+        assert_eq!(first[&newcomer].weight_percent, 10.0);
+
+        let mut backends = vec![backend(1), backend(2)];
+        let later = slow_start.apply(
+            &mut backends,
+            &BTreeMap::from([(newcomer, Duration::from_secs(30))]),
+        );
+        assert_eq!(backends[1].weight, 550);
+        assert!(later[&newcomer].ramping);
+        assert_eq!(later[&newcomer].remaining_seconds, 30);
+    }
+
+    #[test]
+    fn returning_startup_backend_ramps_again() {
+        let mut slow_start = SlowStart::new(Duration::from_secs(60));
+        slow_start.apply(&mut [backend(1), backend(2)], &BTreeMap::new());
+        slow_start.apply(&mut [backend(2)], &BTreeMap::new());
+
+        let mut backends = vec![backend(1), backend(2)];
+        slow_start.apply(&mut backends, &BTreeMap::new());
+
+        assert_eq!(backends[0].weight, MIN_WEIGHT);
+        assert_eq!(backends[1].weight, MAX_BACKEND_WEIGHT);
+    }
+
+    #[test]
+    fn ramp_scales_a_provider_assigned_weight() {
+        let mut slow_start = SlowStart::new(Duration::from_secs(100));
+        slow_start.apply(&mut [backend(1)], &BTreeMap::new());
+
+        let mut backends = vec![backend(1), backend(2)];
+        backends[1].weight = MAX_BACKEND_WEIGHT / 2;
+        let newcomer = packed_ip(backends[1].ip);
+        let statuses = slow_start.apply(
+            &mut backends,
+            &BTreeMap::from([(newcomer, Duration::from_secs(50))]),
+        );
+
+        assert_eq!(backends[1].weight, 275);
+        assert_eq!(statuses[&newcomer].weight_percent, 55.0);
+    }
+}
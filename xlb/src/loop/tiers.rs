@@ -0,0 +1,242 @@
+use crate::config::{Host, PriorityTierConfig};
+use crate::r#loop::utils::packed_ip;
+use crate::status::{PriorityTierStatus, TierCount, TierTransition};
+use log::{info, warn};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::IpAddr;
+use xlb_common::types::Backend;
+
+/// Tier transitions kept for the status API.
+const RECENT_TRANSITIONS: usize = 16;
+
+/// Publishes only the most preferred priority tier that has enough
+/// eligible backends. When every tier falls short, the most preferred
+/// tier with any eligible backend is used rather than none.
+pub struct PriorityTiers {
+    min_eligible: usize,
+    active: Option<u32>,
+    selected_once: bool,
+    tiers: Vec<TierCount>,
+    transitions_total: u64,
+    recent: VecDeque<TierTransition>,
+}
+
+impl PriorityTiers {
+    pub fn new(config: &PriorityTierConfig) -> Self {
+        Self {
+            min_eligible: config.min_eligible_backends as usize,
+            active: None,
+            selected_once: false,
+            tiers: Vec::new(),
+            transitions_total: 0,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Keep only backends of the selected tier. `backends` must already be
+    /// limited to those eligible for new connections; their tiers come
+    /// from the matching hosts. Returns the transition this selection made,
+    /// if any.
+    pub fn retain_active(
+        &mut self,
+        backends: &mut Vec<Backend>,
+        hosts: &[Host],
+        now_unix_ms: u64,
+    ) -> Option<TierTransition> {
+        let priorities: HashMap<IpAddr, u32> =
+            hosts.iter().map(|host| (host.ip, host.priority)).collect();
+        let priority = |backend: &Backend| {
+            priorities
+                .get(&packed_ip(backend.ip))
+                .copied()
+                .unwrap_or_default()
+        };
+
+        let mut eligible = BTreeMap::<u32, usize>::new();
+        for host in hosts {
+            eligible.entry(host.priority).or_default();
+        }
+        for backend in backends.iter() {
+            *eligible.entry(priority(backend)).or_default() += 1;
+        }
+        let selected = eligible
+            .iter()
+            .find(|(_, count)| **count >= self.min_eligible)
+            .or_else(|| eligible.iter().find(|(_, count)| **count > 0))
+            .map(|(priority, _)| *priority);
+
+        self.tiers = eligible
+            .iter()
+            .map(|(priority, eligible)| TierCount {
+                priority: *priority,
+                eligible: *eligible,
+            })
+            .collect();
+        let transition = if selected != self.active || !self.selected_once {
+            self.record_transition(selected, now_unix_ms)
+        } else {
+            None
+        };
+        if let Some(selected) = selected {
+            backends.retain(|backend| priority(backend) == selected);
+        }
+        transition
+    }
+
+    fn record_transition(&mut self, to: Option<u32>, now_unix_ms: u64) -> Option<TierTransition> {
+        let from = std::mem::replace(&mut self.active, to);
+        if !self.selected_once {
+            // The first selection at startup is not a transition.
+            self.selected_once = true;
+            if let Some(to) = to {
+                info!("Routing new connections to priority tier {to}");
+            }
+            return None;
+        }
+
+        match (from, to) {
+            (Some(from), Some(to)) if to > from => warn!(
+                "Priority tier {from} has fewer than {} eligible backends; failing over to tier {to}",
+                self.min_eligible
+            ),
+            (Some(from), Some(to)) => {
+                info!(
+                    "Priority tier {to} has enough eligible backends again; failing back from tier {from}"
+                )
+            }
+            (Some(from), None) => warn!("No priority tier has eligible backends (was tier {from})"),
+            (None, Some(to)) => info!("Routing new connections to priority tier {to}"),
+            (None, None) => {}
+        }
+
+        let transition = TierTransition {
+            from,
+            to,
+            at_unix_ms: now_unix_ms,
+        };
+        self.transitions_total += 1;
+        self.recent.push_back(transition.clone());
+        if self.recent.len() > RECENT_TRANSITIONS {
+            self.recent.pop_front();
+        }
+        Some(transition)
+    }
+
+    pub fn status(&self) -> PriorityTierStatus {
+        PriorityTierStatus {
+            active: self.active,
+            min_eligible_backends: self.min_eligible,
+            tiers: self.tiers.clone(),
+            transitions_total: self.transitions_total,
+            recent_transitions: self.recent.iter().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(last_octet: u8, priority: u32) -> Host {
+        Host {
+            name: format!("backend-{last_octet}"),
+            ip: IpAddr::from([10, 0, 0, last_octet]),
+            weight: None,
+            priority,
+            ports: BTreeMap::new(),
+        }
+    }
+
+    fn published(tiers: &mut PriorityTiers, hosts: &[Host], eligible: &[u8], at: u64) -> Vec<u8> {
+        let mut backends: Vec<Backend> = hosts
+            .iter()
+            .filter(|host| match host.ip {
+                IpAddr::V4(ip) => eligible.contains(&ip.octets()[3]),
+                IpAddr::V6(_) => false,
+            })
+            .map(Backend::from)
+            .collect();
+        tiers.retain_active(&mut backends, hosts, at);
+        backends
+            .iter()
+            .map(|backend| match packed_ip(backend.ip) {
+                IpAddr::V4(ip) => ip.octets()[3],
+                IpAddr::V6(_) => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fails_over_below_the_threshold_and_back() {
+        let mut tiers = PriorityTiers::new(&PriorityTierConfig {
+            min_eligible_backends: 2,
+        });
+        let hosts = [host(1, 0), host(2, 0), host(3, 1), host(4, 1)];
+
+        assert_eq!(published(&mut tiers, &hosts, &[1, 2, 3, 4], 1), vec![1, 2]);
+        assert_eq!(tiers.status().active, Some(0));
+        assert_eq!(
+            tiers.status().transitions_total,
+            0,
+            "startup is not a transition"
+        );
+
+        assert_eq!(published(&mut tiers, &hosts, &[1, 3, 4], 2), vec![3, 4]);
+        let status = tiers.status();
+        assert_eq!(status.active, Some(1));
+        assert_eq!(
+            status.tiers,
+            vec![
+                TierCount {
+                    priority: 0,
+                    eligible: 1
+                },
+                TierCount {
+                    priority: 1,
+                    eligible: 2
+                },
+            ]
+        );
+
+        assert_eq!(published(&mut tiers, &hosts, &[1, 2, 3, 4], 3), vec![1, 2]);
+        let status = tiers.status();
+        assert_eq!(status.transitions_total, 2);
+        assert_eq!(
+            status.recent_transitions,
+            vec![
+                TierTransition {
+                    from: Some(0),
+                    to: Some(1),
+                    at_unix_ms: 2
+                },
+                TierTransition {
+                    from: Some(1),
+                    to: Some(0),
+                    at_unix_ms: 3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn short_tiers_fall_back_to_the_most_preferred_with_any_backend() {
+        let mut tiers = PriorityTiers::new(&PriorityTierConfig {
+            min_eligible_backends: 3,
+        });
+        let hosts = [host(1, 0), host(2, 0), host(3, 1)];
+
+        assert_eq!(published(&mut tiers, &hosts, &[1, 2, 3], 1), vec![1, 2]);
+        assert_eq!(published(&mut tiers, &hosts, &[3], 2), vec![3]);
+        assert!(published(&mut tiers, &hosts, &[], 3).is_empty());
+        assert_eq!(tiers.status().active, None);
+    }
+
+    #[test]
+    fn a_single_tier_publishes_everything() {
+        let mut tiers = PriorityTiers::new(&PriorityTierConfig::default());
+        let hosts = [host(1, 0), host(2, 0)];
+
+        assert_eq!(published(&mut tiers, &hosts, &[1, 2], 1), vec![1, 2]);
+        assert_eq!(tiers.status().tiers.len(), 1);
+    }
+}
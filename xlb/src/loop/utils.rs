@@ -1,10 +1,17 @@
 use crate::r#loop::metrics::Metrics;
+use crate::r#loop::{DataplaneCounters, HandshakeLatencySample};
 use crate::system::ResourceUtilization;
 use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::time::Duration;
 use xlb_common::types::FlowDirection::ToClient;
 use xlb_common::types::{Flow, FlowDirection, FlowKeyV4};
 
+/// Age after which a connection whose backend has sent nothing at all is
+/// counted as a failed handshake. Clients keep retransmitting their SYN, so
+/// the flow itself stays open until it is orphaned.
+pub const UNANSWERED_SYN_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Clone, Default)]
 pub struct AggregateFlowStats {
     pub(crate) client_set: HashSet<u128>,
@@ -26,6 +33,14 @@ pub struct LbFlowStats {
     pub resource_utilization: ResourceUtilization,
     /// Elapsed time represented by interval counters and byte deltas.
     pub sample_duration_seconds: f64,
+    /// Destination ports of flows toward backends.
+    pub backend_ports: HashSet<u16>,
+    /// Listen ports of open connections from clients.
+    pub listen_ports: HashSet<u16>,
+    /// Packet outcome and error counters of the XDP program.
+    pub dataplane: DataplaneCounters,
+    /// Handshake latency histograms, keyed like `backends`.
+    pub handshake_latency: HashMap<u128, HandshakeLatencySample>,
 }
 
 impl AggregateFlowStats {
@@ -46,6 +61,7 @@ struct FlowObservation {
     is_fin: bool,
     is_orphaned: bool,
     is_active: bool,
+    is_handshake_failure: bool,
 }
 
 fn add_flow_stats(flow: &Flow, metrics: &mut Metrics, observation: FlowObservation) {
@@ -73,6 +89,30 @@ fn add_flow_stats(flow: &Flow, metrics: &mut Metrics, observation: FlowObservati
     if observation.is_active {
         metrics.active_conns += 1;
     }
+
+    if observation.is_handshake_failure {
+        metrics.handshake_failures += 1;
+    }
+}
+
+/// Whether this interval observed the backend failing a new connection's
+/// handshake. The server-side entry is seeded with the client's SYN, so a
+/// packet count of one means the backend never replied and two means its
+/// only reply was the reset.
+fn is_handshake_failure(flow: &Flow, is_rst: bool, event_ns: u64, now_ns: u64) -> bool {
+    if flow.direction != ToClient {
+        return false;
+    }
+
+    let refused = is_rst && flow.rst_is_src && flow.packets_transfer <= 2;
+    let timeout_ns = UNANSWERED_SYN_TIMEOUT.as_nanos() as u64;
+    let unanswered_at_ns = flow.created_at_ns.saturating_add(timeout_ns);
+    let unanswered = is_active(flow)
+        && flow.packets_transfer <= 1
+        && unanswered_at_ns > event_ns
+        && unanswered_at_ns <= now_ns;
+
+    refused || unanswered
 }
 
 pub fn aggregate_flow_stats(
@@ -86,6 +126,8 @@ pub fn aggregate_flow_stats(
     let mut totals = AggregateFlowStats::default();
     let mut new_prev_flow_stats = HashMap::new();
     let mut flow_map_entries = 0u64;
+    let mut backend_ports = HashSet::new();
+    let mut listen_ports = HashSet::new();
 
     let delta_ns = now_ns.saturating_sub(event_ns);
     let delta_secs = if delta_ns > 0 {
@@ -107,12 +149,14 @@ pub fn aggregate_flow_stats(
         // Count RST and FIN transitions in the interval where they occur.
         // Cleanup may retain terminal mappings through TCP TIME_WAIT.
         let is_orphaned = is_orphan(flow.last_seen_ns, now_ns, orphan_ttl);
+        let is_rst = flow.rst_ns >= event_ns && flow.rst_ns <= now_ns;
         let observation = FlowObservation {
             is_new: flow.created_at_ns > event_ns,
-            is_rst: flow.rst_ns >= event_ns && flow.rst_ns <= now_ns,
+            is_rst,
             is_fin: flow.fin_both_ns >= event_ns && flow.fin_both_ns <= now_ns,
             is_orphaned,
             is_active: is_active(&flow) && !is_orphaned,
+            is_handshake_failure: is_handshake_failure(&flow, is_rst, event_ns, now_ns),
         };
 
         if flow.direction == ToClient {
@@ -131,6 +175,10 @@ pub fn aggregate_flow_stats(
             continue;
         }
 
+        backend_ports.insert(flow.dst_port);
+        if observation.is_active {
+            listen_ports.insert(key.dst_port());
+        }
         add_flow_stats(&flow, &mut totals.to_server, observation);
         add_flow_stats(&flow, &mut backend.to_server, observation);
 
@@ -166,6 +214,10 @@ pub fn aggregate_flow_stats(
             flow_map_complete: true,
             resource_utilization: ResourceUtilization::default(),
             sample_duration_seconds: delta_secs,
+            backend_ports,
+            listen_ports,
+            dataplane: DataplaneCounters::default(),
+            handshake_latency: HashMap::new(),
         },
         new_prev_flow_stats,
     )
@@ -205,12 +257,24 @@ pub fn monotonic_now_ns() -> u64 {
 
 /// Formats a u128 IP address (IPv4 or IPv6) as a string
 pub fn format_ip(ip: u128) -> String {
-    use std::net::{Ipv4Addr, Ipv6Addr};
+    packed_ip(ip).to_string()
+}
 
-    if ip <= u32::MAX as u128 {
-        Ipv4Addr::from(ip as u32).to_string()
+/// Unpack an address stored in the dataplane's u128 representation,
+/// where IPv4 occupies the low 32 bits.
+pub fn packed_ip(ip: u128) -> IpAddr {
+    if ip <= u128::from(u32::MAX) {
+        IpAddr::V4(Ipv4Addr::from(ip as u32))
     } else {
-        Ipv6Addr::from(ip).to_string()
+        IpAddr::V6(Ipv6Addr::from(ip))
+    }
+}
+
+/// Pack an address into the dataplane's u128 representation.
+pub fn pack_ip(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u128::from(ip.to_bits()),
+        IpAddr::V6(ip) => ip.to_bits(),
     }
 }
 
@@ -242,7 +306,11 @@ mod tests {
             rst_is_src: false,
             pair_invalid: false,
             pair_ready: true,
-            _reserved: [0; 1],
+            force_reset: false,
+            next_seq: 0,
+            seq_known: false,
+            handshake_timed: false,
+            _reserved: [0; 10],
             pair_tag: 1,
         }
     }
@@ -276,4 +344,109 @@ mod tests {
         assert_eq!(stats.flow_map_entries, 2);
         assert!(stats.flow_map_complete);
     }
+
+    #[test]
+    fn aggregation_collects_listen_ports_of_open_connections() {
+        let key = |listen_port| {
+            FlowKeyV4::tcp(
+                0xc000_0201,
+                0xcb00_710a,
+                50_000,
+                listen_port,
+                FlowDirection::ToServer,
+            )
+        };
+        let counter = FlowKeyV4::tcp(
+            0xc633_6402,
+            0x0a00_0001,
+            8080,
+            30_000,
+            FlowDirection::ToClient,
+        );
+        let mut open = flow(counter);
+        open.dst_port = 8080;
+        let mut closed = open;
+        closed.dst_port = 8443;
+        closed.fin_both_ns = 1;
+        let flows = [(key(80), open), (key(443), closed)];
+
+        let (stats, _) = aggregate_flow_stats(
+            0,
+            flows.into_iter(),
+            &HashMap::new(),
+            &Duration::from_secs(300),
+            1,
+        );
+
+        assert_eq!(stats.listen_ports, HashSet::from([80]));
+        assert_eq!(stats.backend_ports, HashSet::from([8080, 8443]));
+    }
+
+    fn server_side(packets_transfer: u64) -> Flow {
+        let mut flow = flow(FlowKeyV4::tcp(
+            0xc000_0201,
+            0xcb00_710a,
+            50_000,
+            80,
+            FlowDirection::ToServer,
+        ));
+        flow.direction = FlowDirection::ToClient;
+        flow.packets_transfer = packets_transfer;
+        flow.created_at_ns = 1_000;
+        flow
+    }
+
+    #[test]
+    fn immediate_backend_reset_is_a_handshake_failure() {
+        let mut refused = server_side(2);
+        refused.rst_ns = 2_000;
+        refused.rst_is_src = true;
+        assert!(is_handshake_failure(&refused, true, 1_500, 2_500));
+
+        let mut established = refused;
+        established.packets_transfer = 20;
+        assert!(!is_handshake_failure(&established, true, 1_500, 2_500));
+
+        let mut client_reset = refused;
+        client_reset.rst_is_src = false;
+        assert!(!is_handshake_failure(&client_reset, true, 1_500, 2_500));
+    }
+
+    #[test]
+    fn unanswered_syn_is_counted_once_when_it_times_out() {
+        let flow = server_side(1);
+        let timeout_at = 1_000 + UNANSWERED_SYN_TIMEOUT.as_nanos() as u64;
+
+        assert!(!is_handshake_failure(&flow, false, 0, timeout_at - 1));
+        assert!(is_handshake_failure(
+            &flow,
+            false,
+            timeout_at - 1,
+            timeout_at
+        ));
+        assert!(!is_handshake_failure(
+            &flow,
+            false,
+            timeout_at,
+            timeout_at + 1
+        ));
+
+        let mut answered = flow;
+        answered.packets_transfer = 2;
+        assert!(!is_handshake_failure(
+            &answered,
+            false,
+            timeout_at - 1,
+            timeout_at
+        ));
+    }
+
+    #[test]
+    fn packed_addresses_round_trip() {
+        for address in ["10.0.0.7", "2001:db8::7"] {
+            let address: IpAddr = address.parse().expect("valid IP");
+            assert_eq!(packed_ip(pack_ip(address)), address);
+        }
+        assert_eq!(pack_ip("10.0.0.7".parse().expect("valid IP")), 0x0a00_0007);
+    }
 }
@@ -0,0 +1,168 @@
+use crate::provider::{LoadBalancerController, LoadBalancerStatus, hosts_to_backends_with_routes};
+use aya::maps::{Array, HashMap, MapData};
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use xlb_common::consts::{MAX_PORT_MAPPINGS, MAX_VIP_BACKENDS};
+use xlb_common::types::{Backend, PortMapping, Vip};
+
+/// Backends and port mappings resolved for one VIP, ready to program.
+pub struct StagedVip {
+    namespace: String,
+    name: String,
+    vip: Ipv4Addr,
+    entry: Vip,
+    backends: Vec<Backend>,
+}
+
+impl StagedVip {
+    pub fn backends(&self) -> &[Backend] {
+        &self.backends
+    }
+}
+
+/// Programs the VIPs of the Kubernetes LoadBalancer controller into the
+/// dataplane, each with its own backend pool.
+pub struct VipDataplane {
+    controller: Arc<LoadBalancerController>,
+    vips: HashMap<MapData, u32, Vip>,
+    backends: Array<MapData, Backend>,
+    /// Programmed VIPs and their slots.
+    programmed: BTreeMap<Ipv4Addr, u32>,
+}
+
+impl VipDataplane {
+    pub fn new(
+        controller: Arc<LoadBalancerController>,
+        vips: HashMap<MapData, u32, Vip>,
+        backends: Array<MapData, Backend>,
+    ) -> Self {
+        Self {
+            controller,
+            vips,
+            backends,
+            programmed: BTreeMap::new(),
+        }
+    }
+
+    /// Resolve routes for the backends of every allocated VIP. Backends get
+    /// an explicit destination port for each mapping so their replies are
+    /// recognised whatever the port.
+    pub async fn stage(&self) -> Vec<StagedVip> {
+        let mut staged = Vec::new();
+        for service in self.controller.vip_backends() {
+            let port_names: Vec<Option<String>> = service
+                .ports
+                .iter()
+                .map(|port| Some(port.port_name.clone()))
+                .collect();
+            let mut backends = hosts_to_backends_with_routes(&service.hosts, &port_names).await;
+            for backend in &mut backends {
+                for (port, mapping) in backend.ports.iter_mut().zip(&service.ports) {
+                    if *port == 0 {
+                        *port = mapping.remote_port;
+                    }
+                }
+            }
+            if backends.len() > MAX_VIP_BACKENDS as usize {
+                warn!(
+                    "More backends than allowed for VIP {} of {}/{} ({}); ignoring {} excess backend(s)",
+                    service.vip,
+                    service.namespace,
+                    service.name,
+                    MAX_VIP_BACKENDS,
+                    backends.len() - MAX_VIP_BACKENDS as usize
+                );
+                backends.truncate(MAX_VIP_BACKENDS as usize);
+            }
+
+            let mut port_mappings = [PortMapping {
+                local_port: 0,
+                remote_port: 0,
+            }; MAX_PORT_MAPPINGS];
+            for (slot, port) in port_mappings.iter_mut().zip(&service.ports) {
+                *slot = PortMapping {
+                    local_port: port.local_port,
+                    remote_port: port.remote_port,
+                };
+            }
+
+            staged.push(StagedVip {
+                namespace: service.namespace,
+                name: service.name,
+                vip: service.vip,
+                entry: Vip {
+                    slot: service.slot,
+                    port_mappings,
+                },
+                backends,
+            });
+        }
+        staged
+    }
+
+    /// Withdraw released VIPs, then write each staged VIP's backend pool
+    /// before publishing the VIP itself.
+    pub fn program(&mut self, staged: &[StagedVip]) -> LoadBalancerStatus {
+        let released: Vec<(Ipv4Addr, u32)> = self
+            .programmed
+            .iter()
+            .filter(|(vip, slot)| {
+                !staged
+                    .iter()
+                    .any(|staged| staged.vip == **vip && staged.entry.slot == **slot)
+            })
+            .map(|(vip, slot)| (*vip, *slot))
+            .collect();
+        for (vip, slot) in released {
+            if let Err(err) = self.vips.remove(&vip.to_bits()) {
+                warn!("Failed to withdraw VIP {vip}: {err}");
+            }
+            self.write_pool(slot, &[]);
+            self.programmed.remove(&vip);
+            info!("Withdrew VIP {vip}");
+        }
+
+        for staged in staged {
+            self.write_pool(staged.entry.slot, &staged.backends);
+            match self.vips.insert(staged.vip.to_bits(), staged.entry, 0) {
+                Ok(()) => {
+                    if self
+                        .programmed
+                        .insert(staged.vip, staged.entry.slot)
+                        .is_none()
+                    {
+                        info!(
+                            "Serving VIP {} for {}/{}",
+                            staged.vip, staged.namespace, staged.name
+                        );
+                    }
+                }
+                Err(err) => warn!("Failed to program VIP {}: {err}", staged.vip),
+            }
+        }
+
+        let mut status = self.controller.status();
+        for service in &mut status.services {
+            service.backends = staged
+                .iter()
+                .find(|staged| staged.namespace == service.namespace && staged.name == service.name)
+                .map_or(0, |staged| staged.backends.len());
+        }
+        status
+    }
+
+    fn write_pool(&mut self, slot: u32, backends: &[Backend]) {
+        let empty_backend = Backend::default();
+        for index in 0..MAX_VIP_BACKENDS {
+            let backend = backends.get(index as usize).unwrap_or(&empty_backend);
+            if let Err(err) = self
+                .backends
+                .set(slot * MAX_VIP_BACKENDS + index, backend, 0)
+            {
+                warn!("Failed to write backend {index} of VIP slot {slot}: {err}");
+            }
+        }
+    }
+}
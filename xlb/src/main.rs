@@ -1,30 +1,66 @@
+mod cli;
 mod config;
 mod ebpf;
+mod health;
 mod r#loop;
 mod metrics;
 mod provider;
+mod reload;
 mod status;
 mod system;
 
+use crate::cli::Command;
 use crate::config::{BackendSource, XlbConfig};
-use crate::r#loop::{MaintenanceLoop, MaintenanceMaps};
-use crate::provider::{BackendProvider, FixedProvider, KubernetesProvider};
+use crate::health::{HealthCheckSettings, HealthChecker, OutlierDetector};
+use crate::r#loop::{
+    BackendOverrides, ConnectionBrowser, DataplaneCounterMaps, DrainTracker, HandshakeLatencyMap,
+    LiveListenPorts, MaintenanceLoop, MaintenanceMaps, PriorityTiers, RstSender, SlowStart,
+    VipDataplane,
+};
+use crate::provider::{
+    BackendProvider, BackendStore, CompositeChild, CompositeProvider, ConsulProvider,
+    ConsulSettings, DnsProvider, FileProvider, FixedProvider, HttpProvider, KubernetesProvider,
+    LoadBalancerController,
+};
+use crate::reload::{ConfigReloader, loop_settings, port_statuses};
 use crate::status::{
-    AdminAuth, PortStatus, ProviderKind, StatusMetadata, StatusState, start_admin_server,
+    AdminAuth, AdminCredentials, AdminTls, EventKind, EventSeverity, MetricsEndpoint, ProviderKind,
+    StatusMetadata, StatusState, XdpAttachmentMode, start_admin_server,
 };
 use anyhow::{Context, anyhow};
-use aya::maps::{Array, HashMap, PerCpuArray};
+use aya::maps::{Array, HashMap, IterableMap, Map, MapData, PerCpuArray, PerCpuHashMap};
 use log::{info, warn};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::signal::unix::{SignalKind, signal};
-use xlb_common::types::{Backend, Flow, FlowKeyV4};
+use tokio::sync::{mpsc, watch};
+use xlb_common::config::ebpf::EbpfConfig;
+use xlb_common::types::{Backend, Flow, FlowKeyV4, HandshakeLatency, Vip};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     env_logger::init();
 
-    let config = XlbConfig::load("xlb.yaml".into())?;
+    match Command::parse(std::env::args().skip(1))? {
+        Command::Run { config } => run(config).await,
+        Command::Validate { config } => cli::validate(config).await,
+        Command::Status {
+            url,
+            user,
+            json,
+            tls,
+        } => cli::status(&url, &user, json, &tls).await,
+        Command::Schema => cli::schema(),
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+    }
+}
+
+async fn run(config_path: PathBuf) -> anyhow::Result<()> {
+    let config = XlbConfig::load(config_path.clone())?;
     let iface = system::get_listen_iface(&config.listen)?;
     config.validate_listen_ip(iface.ip)?;
 
@@ -36,13 +72,23 @@ async fn main() -> anyhow::Result<()> {
         .as_ref()
         .map(|auth| AdminAuth::from_env(auth.username.clone()))
         .transpose()?;
+    let admin_tls = config
+        .admin
+        .tls
+        .as_ref()
+        .map(AdminTls::load)
+        .transpose()
+        .context("Failed to load admin TLS certificates")?;
+    let client_certificates = admin_tls
+        .as_ref()
+        .is_some_and(AdminTls::client_certificates);
     if !config.admin.address.is_loopback() {
-        if admin_auth.is_some() {
+        if admin_auth.is_some() && admin_tls.is_none() {
             warn!(
                 "Admin API uses HTTP Basic auth on non-loopback address {}; protect it with TLS or another secure transport",
                 config.admin.address
             );
-        } else {
+        } else if admin_auth.is_none() && !client_certificates {
             warn!(
                 "Admin API is unauthenticated and configured on non-loopback address {}",
                 config.admin.address
@@ -50,30 +96,78 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
+    let admin_credentials = AdminCredentials::new(admin_auth);
     let service_name = config.name.clone().unwrap_or_else(|| "xlb".to_string());
 
-    if let Some(otel_config) = &config.otel
-        && otel_config.enabled
-    {
-        metrics::init(otel_config, service_name.clone())?;
-    }
+    let prometheus = metrics::init(
+        config.otel.as_ref().filter(|otel| otel.enabled),
+        config
+            .admin
+            .metrics
+            .enabled
+            .then_some(&config.admin.metrics),
+        service_name.clone(),
+    )?;
+    let metrics_endpoint = prometheus
+        .map(|metrics| -> anyhow::Result<MetricsEndpoint> {
+            let auth = config
+                .admin
+                .metrics
+                .auth
+                .as_ref()
+                .map(|auth| AdminAuth::metrics_from_env(auth.username.clone()))
+                .transpose()?;
+            if auth.is_none() && !config.admin.address.is_loopback() {
+                warn!(
+                    "Prometheus metrics are unauthenticated on non-loopback address {}",
+                    config.admin.address
+                );
+            }
+            Ok(MetricsEndpoint { metrics, auth })
+        })
+        .transpose()?;
 
-    let (provider, provider_kind): (Arc<dyn BackendProvider>, ProviderKind) = match &config.provider
-    {
-        BackendSource::Static { backends } => (
-            Arc::new(FixedProvider::new(backends.clone())),
-            ProviderKind::Static,
-        ),
-        BackendSource::Kubernetes { namespace, service } => (
-            Arc::new(KubernetesProvider::new(namespace.clone(), service.clone())),
-            ProviderKind::Kubernetes,
-        ),
-    };
+    let mut backend_store = None;
+    let (provider, provider_kind) = build_provider(&config.provider, &mut backend_store);
+
+    let health_checker = config
+        .health_check
+        .as_ref()
+        .map(|health_check| -> anyhow::Result<Arc<HealthChecker>> {
+            let port = config
+                .health_check_port()
+                .context("Health checks require a backend port")?;
+            let probe = health::probe_from_config(&health_check.probe)
+                .context("Failed to configure health checks")?;
+            Ok(Arc::new(HealthChecker::new(
+                probe,
+                HealthCheckSettings::new(health_check, port),
+            )))
+        })
+        .transpose()?;
 
     provider
         .start()
         .await
         .context("Failed to start backend provider")?;
+    if let Some(checker) = &health_checker {
+        checker.start(provider.clone());
+    }
+
+    let load_balancer = match &config.load_balancer {
+        Some(load_balancer) => {
+            let controller = Arc::new(LoadBalancerController::new(
+                load_balancer.class.clone(),
+                load_balancer.address_ranges()?,
+            ));
+            controller
+                .start()
+                .await
+                .context("Failed to start load balancer controller")?;
+            Some(controller)
+        }
+        None => None,
+    };
 
     let ebpf::LoadedEbpf {
         mut ebpf,
@@ -84,6 +178,10 @@ async fn main() -> anyhow::Result<()> {
         .map(|attachment| attachment.interface.clone())
         .collect::<Vec<_>>();
 
+    let config_map: Array<_, EbpfConfig> = ebpf
+        .take_map("CONFIG")
+        .ok_or_else(|| anyhow!("Failed to load CONFIG map"))?
+        .try_into()?;
     let ebpf_backends: Array<_, Backend> = ebpf
         .take_map("BACKENDS")
         .ok_or_else(|| anyhow!("Failed to load BACKENDS map"))?
@@ -92,37 +190,91 @@ async fn main() -> anyhow::Result<()> {
         .take_map("FLOW_MAP")
         .ok_or_else(|| anyhow!("Failed to load FLOW_MAP map"))?
         .try_into()?;
+    // A second handle on the same map lets the admin API list connections
+    // without sharing the maintenance loop's handle.
+    let flows_id = IterableMap::map(&ebpf_flows)
+        .info()
+        .context("Failed to read FLOW_MAP info")?
+        .id();
+    let connections = Arc::new(ConnectionBrowser::new(
+        HashMap::try_from(Map::HashMap(
+            MapData::from_id(flows_id).context("Failed to reopen FLOW_MAP")?,
+        ))?,
+        config.admin.redact_client_ips,
+    ));
     let flow_pair_invariants: PerCpuArray<_, u64> = ebpf
         .take_map("FLOW_PAIR_INVARIANTS")
         .ok_or_else(|| anyhow!("Failed to load FLOW_PAIR_INVARIANTS map"))?
         .try_into()?;
+    let packet_outcomes: PerCpuArray<_, u64> = ebpf
+        .take_map("PACKET_OUTCOMES")
+        .ok_or_else(|| anyhow!("Failed to load PACKET_OUTCOMES map"))?
+        .try_into()?;
+    let dataplane_errors: PerCpuArray<_, u64> = ebpf
+        .take_map("DATAPLANE_ERRORS")
+        .ok_or_else(|| anyhow!("Failed to load DATAPLANE_ERRORS map"))?
+        .try_into()?;
+    let handshake_latency: PerCpuHashMap<_, u32, HandshakeLatency> = ebpf
+        .take_map("HANDSHAKE_LATENCY")
+        .ok_or_else(|| anyhow!("Failed to load HANDSHAKE_LATENCY map"))?
+        .try_into()?;
+    let force_close: HashMap<_, u128, u8> = ebpf
+        .take_map("FORCE_CLOSE")
+        .ok_or_else(|| anyhow!("Failed to load FORCE_CLOSE map"))?
+        .try_into()?;
+
+    let return_ports: HashMap<_, u16, u8> = ebpf
+        .take_map("RETURN_PORTS")
+        .ok_or_else(|| anyhow!("Failed to load RETURN_PORTS map"))?
+        .try_into()?;
 
     let status = Arc::new(StatusState::new(StatusMetadata {
         service: service_name.clone(),
         provider: provider_kind,
         listen_address: iface.ip,
         listen_interface: iface.name.clone(),
-        xdp_attachments: attachments,
+        xdp_attachments: attachments.clone(),
         protocol: config.proto,
         routing_mode: config.mode,
-        ports: config
-            .ports
-            .iter()
-            .map(|port| PortStatus {
-                listen: port.local_port,
-                backend: port.remote_port,
-            })
-            .collect(),
+        ports: port_statuses(&config),
     }));
-    let mut admin_server =
-        start_admin_server(config.admin.socket_addr(), status.clone(), admin_auth).await?;
+    for attachment in &attachments {
+        let (severity, mode) = match attachment.mode {
+            XdpAttachmentMode::Native => (EventSeverity::Info, "native"),
+            XdpAttachmentMode::Generic => (EventSeverity::Warning, "generic"),
+        };
+        status.events().record(
+            EventKind::XdpAttached,
+            severity,
+            format!("Attached XDP to {} in {mode} mode", attachment.interface),
+            None,
+        );
+    }
+    let backend_overrides = Arc::new(BackendOverrides::default());
+    let (reload_requests, mut reload_rx) = mpsc::channel(1);
+    let mut admin_server = start_admin_server(
+        config.admin.socket_addr(),
+        status.clone(),
+        backend_overrides.clone(),
+        backend_store,
+        Some(connections),
+        Some(reload_requests),
+        metrics_endpoint,
+        admin_credentials.clone(),
+        admin_tls,
+    )
+    .await?;
 
-    let maint_loop = MaintenanceLoop::new(
+    let mut maint_loop = MaintenanceLoop::new(
         provider.clone(),
         MaintenanceMaps {
             backends: ebpf_backends,
             flows: ebpf_flows,
             flow_pair_invariants,
+            dataplane_counters: DataplaneCounterMaps::new(packet_outcomes, dataplane_errors),
+            handshake_latency: HandshakeLatencyMap::new(handshake_latency),
+            force_close,
+            return_ports,
         },
         Duration::from_secs(config.orphan_ttl_secs as u64),
         Duration::from_mins(1),
@@ -130,6 +282,51 @@ async fn main() -> anyhow::Result<()> {
         config.resources.network_capacity_mbps,
         status.clone(),
     );
+    maint_loop = maint_loop.with_port_names(
+        config
+            .ports
+            .iter()
+            .map(|port| port.port_name.clone())
+            .collect(),
+    );
+    let (loop_settings_tx, loop_settings_rx) = watch::channel(loop_settings(&config, &provider));
+    maint_loop = maint_loop.with_reloads(loop_settings_rx);
+    let live_listen_ports = Arc::new(LiveListenPorts::default());
+    maint_loop = maint_loop.with_live_listen_ports(live_listen_ports.clone());
+    maint_loop =
+        maint_loop.with_drains(DrainTracker::new((&config.drain).into(), backend_overrides));
+    if config.shutdown_reset_connections {
+        maint_loop = maint_loop.with_shutdown_reset(
+            RstSender::new().context("Failed to prepare shutdown connection resets")?,
+        );
+    }
+    if let Some(checker) = &health_checker {
+        maint_loop = maint_loop.with_health_checks(checker.clone());
+    }
+    if let Some(outliers) = &config.outlier_detection {
+        maint_loop = maint_loop
+            .with_outlier_detection(OutlierDetector::new(outliers.into(), Instant::now()));
+    }
+    maint_loop = maint_loop.with_priority_tiers(PriorityTiers::new(&config.priority_tiers));
+    if config.slow_start_secs > 0 {
+        maint_loop =
+            maint_loop.with_slow_start(SlowStart::new(Duration::from_secs(config.slow_start_secs)));
+    }
+    if let Some(controller) = &load_balancer {
+        let vips: HashMap<_, u32, Vip> = ebpf
+            .take_map("VIPS")
+            .ok_or_else(|| anyhow!("Failed to load VIPS map"))?
+            .try_into()?;
+        let vip_backends: Array<_, Backend> = ebpf
+            .take_map("VIP_BACKENDS")
+            .ok_or_else(|| anyhow!("Failed to load VIP_BACKENDS map"))?
+            .try_into()?;
+        maint_loop = maint_loop.with_load_balancer(VipDataplane::new(
+            controller.clone(),
+            vips,
+            vip_backends,
+        ));
+    }
 
     let mut loop_handle = maint_loop.start(Duration::from_secs(1));
     status.mark_running();
@@ -138,22 +335,48 @@ async fn main() -> anyhow::Result<()> {
         service_name, iface.name, iface.ip
     );
 
+    let mut reloader = ConfigReloader::new(
+        config_path,
+        iface,
+        config,
+        provider,
+        config_map,
+        loop_settings_tx,
+        status.clone(),
+    );
+    if let Some(checker) = &health_checker {
+        reloader = reloader.with_health_checks(checker.clone());
+    }
+    reloader = reloader
+        .with_admin_credentials(admin_credentials)
+        .with_live_listen_ports(live_listen_ports);
     let mut sigterm = signal(SignalKind::terminate())?;
     let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sighup = signal(SignalKind::hangup())?;
 
     info!("Waiting for shutdown signal...");
 
-    let shutdown_result = tokio::select! {
-        _ = sigterm.recv() => {
-            info!("Received SIGTERM");
-            Ok(())
-        }
-        _ = sigint.recv() => {
-            info!("Received SIGINT");
-            Ok(())
+    let shutdown_result = loop {
+        tokio::select! {
+            _ = sigterm.recv() => {
+                info!("Received SIGTERM");
+                break Ok(());
+            }
+            _ = sigint.recv() => {
+                info!("Received SIGINT");
+                break Ok(());
+            }
+            _ = sighup.recv() => {
+                info!("Received SIGHUP, reloading configuration");
+                reloader.reload().await;
+            }
+            Some(reply) = reload_rx.recv() => {
+                info!("Configuration reload requested through the admin API");
+                let _ = reply.send(reloader.reload().await);
+            }
+            result = admin_server.wait_for_unexpected_exit() => break result,
+            result = loop_handle.wait_for_unexpected_exit() => break result,
         }
-        result = admin_server.wait_for_unexpected_exit() => result,
-        result = loop_handle.wait_for_unexpected_exit() => result,
     };
 
     if let Err(error) = &shutdown_result {
@@ -171,14 +394,24 @@ async fn main() -> anyhow::Result<()> {
     shutdown_flag.set(0, 1, 0)?;
     loop_handle.request_stop();
 
-    provider
+    reloader
+        .provider()
         .shutdown()
         .await
         .context("Failed to shutdown backend provider")?;
     info!("Backend provider shutdown");
+    if let Some(controller) = &load_balancer {
+        controller
+            .shutdown()
+            .await
+            .context("Failed to shutdown load balancer controller")?;
+    }
+    if let Some(checker) = &health_checker {
+        checker.shutdown();
+    }
 
     info!("Waiting for graceful shutdown timeout, will reset any active conns...");
-    let shutdown_timeout = Duration::from_secs(config.shutdown_timeout as u64);
+    let shutdown_timeout = Duration::from_secs(reloader.config().shutdown_timeout as u64);
     tokio::time::sleep(shutdown_timeout.saturating_sub(shutdown_started.elapsed())).await;
 
     info!("Graceful shutdown complete");
@@ -200,7 +433,59 @@ async fn main() -> anyhow::Result<()> {
         warn!("{error:#}");
     }
 
+    metrics::shutdown();
+
     shutdown_result?;
     maintenance_result?;
     admin_result
 }
+
+/// Create the provider for a backend source. The HTTP provider's backend
+/// set is handed out through `backend_store` for the admin API.
+fn build_provider(
+    source: &BackendSource,
+    backend_store: &mut Option<Arc<BackendStore>>,
+) -> (Arc<dyn BackendProvider>, ProviderKind) {
+    match source {
+        BackendSource::Static { backends } => (
+            Arc::new(FixedProvider::new(backends.clone())),
+            ProviderKind::Static,
+        ),
+        BackendSource::Kubernetes(kubernetes) => (
+            Arc::new(KubernetesProvider::new(
+                kubernetes.watches(),
+                kubernetes.topology.clone(),
+            )),
+            ProviderKind::Kubernetes,
+        ),
+        BackendSource::Dns(dns) => (Arc::new(DnsProvider::new(dns.into())), ProviderKind::Dns),
+        BackendSource::File(file) => (
+            Arc::new(FileProvider::new(file.path.clone())),
+            ProviderKind::File,
+        ),
+        BackendSource::Consul(consul) => (
+            Arc::new(ConsulProvider::new(ConsulSettings::from_env(consul))),
+            ProviderKind::Consul,
+        ),
+        BackendSource::Http(http) => {
+            let provider = HttpProvider::new(http.into());
+            *backend_store = Some(provider.store());
+            (Arc::new(provider), ProviderKind::Http)
+        }
+        BackendSource::Composite(composite) => {
+            let children = composite
+                .sources
+                .iter()
+                .map(|source| CompositeChild {
+                    provider: build_provider(&source.provider, backend_store).0,
+                    weight_percent: source.weight,
+                    priority: source.priority,
+                })
+                .collect();
+            (
+                Arc::new(CompositeProvider::new(children, composite.health)),
+                ProviderKind::Composite,
+            )
+        }
+    }
+}
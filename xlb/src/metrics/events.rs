@@ -0,0 +1,75 @@
+use super::otel::{http_exporter, tonic_exporter};
+use crate::config::{OtelConfig, OtelProtocol};
+use crate::status::{Event, EventSeverity};
+use anyhow::Result;
+use opentelemetry::logs::{AnyValue, LogRecord, Logger, LoggerProvider, Severity};
+use opentelemetry_otlp::LogExporter;
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::logs::{SdkLogger, SdkLoggerProvider};
+use std::sync::OnceLock;
+use std::time::{Duration, UNIX_EPOCH};
+
+static LOGGER_PROVIDER: OnceLock<SdkLoggerProvider> = OnceLock::new();
+static LOGGER: OnceLock<SdkLogger> = OnceLock::new();
+
+/// Initialize the export of lifecycle events as log records to the
+/// configured OTLP endpoint.
+pub fn init(config: &OtelConfig, resource: Resource) -> Result<()> {
+    let builder = LogExporter::builder();
+    let exporter = match config.protocol {
+        OtelProtocol::Grpc => tonic_exporter(builder.with_tonic(), config)?.build()?,
+        OtelProtocol::Http => http_exporter(builder.with_http(), config).build()?,
+    };
+
+    let provider = SdkLoggerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build();
+
+    LOGGER
+        .set(provider.logger("xlb"))
+        .map_err(|_| anyhow::anyhow!("Event logger already initialized"))?;
+    LOGGER_PROVIDER
+        .set(provider)
+        .map_err(|_| anyhow::anyhow!("Event logger provider already initialized"))?;
+
+    Ok(())
+}
+
+/// Emit one lifecycle event as a log record.
+pub fn export_event(event: &Event) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+
+    let mut record = logger.create_log_record();
+    record.set_event_name(event.kind.event_name());
+    record.set_timestamp(UNIX_EPOCH + Duration::from_millis(event.at_unix_ms));
+    match event.severity {
+        EventSeverity::Info => {
+            record.set_severity_number(Severity::Info);
+            record.set_severity_text("INFO");
+        }
+        EventSeverity::Warning => {
+            record.set_severity_number(Severity::Warn);
+            record.set_severity_text("WARN");
+        }
+    }
+    record.set_body(AnyValue::from(event.message.clone()));
+    record.add_attribute("xlb.event.id", event.id as i64);
+    if let Some(backend) = event.backend {
+        record.add_attribute("backend", backend.to_string());
+    }
+    logger.emit(record);
+}
+
+/// Flush event log records that are still batched.
+pub fn shutdown() {
+    let Some(provider) = LOGGER_PROVIDER.get() else {
+        return;
+    };
+
+    if let Err(err) = provider.shutdown() {
+        log::warn!("Failed to flush OpenTelemetry event logs: {err}");
+    }
+}
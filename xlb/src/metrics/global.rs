@@ -1,8 +1,14 @@
 use crate::config::Host;
-use crate::r#loop::utils::LbFlowStats;
+use crate::r#loop::utils::{LbFlowStats, format_ip};
+use crate::status::OutlierReason;
 use anyhow::Result;
-use opentelemetry::metrics::{Counter, Gauge, Meter};
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::{Counter, Gauge, Histogram, Meter};
+use std::net::IpAddr;
 use std::sync::OnceLock;
+use xlb_common::XlbErr;
+use xlb_common::consts::HANDSHAKE_LATENCY_BUCKETS;
+use xlb_common::types::{HandshakeLatency, PacketOutcome};
 
 struct GlobalMetrics {
     backends_available: Gauge<u64>,
@@ -11,6 +17,10 @@ struct GlobalMetrics {
     connections_closed: Counter<u64>,
     connections_orphaned: Counter<u64>,
     flow_pair_invariant_violations: Counter<u64>,
+    backends_ejected: Counter<u64>,
+    packets: Counter<u64>,
+    dataplane_errors: Counter<u64>,
+    handshake_duration: Histogram<f64>,
 }
 
 static METRICS: OnceLock<GlobalMetrics> = OnceLock::new();
@@ -49,6 +59,33 @@ pub fn init(meter: &Meter) -> Result<()> {
                 "Observations of missing, mismatched, or concurrently removed flow-pair entries",
             )
             .build(),
+
+        backends_ejected: meter
+            .u64_counter("xlb.global.backends.ejected")
+            .with_description("Backends ejected by passive outlier detection")
+            .build(),
+
+        packets: meter
+            .u64_counter("xlb.global.packets")
+            .with_description("Packets handled by the XDP program, by outcome")
+            .build(),
+
+        dataplane_errors: meter
+            .u64_counter("xlb.global.dataplane.errors")
+            .with_description("Packets the XDP program dropped because processing failed, by error")
+            .build(),
+
+        handshake_duration: meter
+            .f64_histogram("xlb.global.backends.handshake.duration")
+            .with_description("Time from forwarding a client SYN to the backend's first SYN-ACK")
+            .with_unit("s")
+            .with_boundaries(
+                (0..HANDSHAKE_LATENCY_BUCKETS)
+                    .filter_map(HandshakeLatency::upper_bound_micros)
+                    .map(|micros| micros as f64 / 1_000_000.0)
+                    .collect(),
+            )
+            .build(),
     };
 
     METRICS
@@ -66,6 +103,20 @@ pub fn record_flow_pair_invariant_violations(count: u64) {
     metrics.flow_pair_invariant_violations.add(count, &[]);
 }
 
+pub fn record_backend_ejected(backend: IpAddr, reason: OutlierReason) {
+    let Some(metrics) = METRICS.get() else {
+        return;
+    };
+
+    metrics.backends_ejected.add(
+        1,
+        &[
+            KeyValue::new("backend", backend.to_string()),
+            KeyValue::new("reason", reason.as_str()),
+        ],
+    );
+}
+
 pub fn record_connections_orphaned(count: u64) {
     let Some(metrics) = METRICS.get() else {
         return;
@@ -92,4 +143,35 @@ pub fn log_global(stats: &LbFlowStats, backends: &[Host]) {
     let total_closed =
         stats.totals.to_server.closed_total_conns + stats.totals.to_client.closed_total_conns;
     m.connections_closed.add(total_closed as u64, &[]);
+
+    for (outcome, sample) in PacketOutcome::ALL.iter().zip(&stats.dataplane.outcomes) {
+        m.packets
+            .add(sample.delta, &[KeyValue::new("outcome", outcome.label())]);
+    }
+    for (err, sample) in XlbErr::ALL.iter().zip(&stats.dataplane.errors) {
+        m.dataplane_errors
+            .add(sample.delta, &[KeyValue::new("error", err.label())]);
+    }
+
+    // The dataplane only counts handshakes per bucket, so each is recorded
+    // at its bucket's midpoint. Bucket counts are exact; the sum is not.
+    for (backend_ip, sample) in &stats.handshake_latency {
+        let attributes = [KeyValue::new("backend", format_ip(*backend_ip))];
+        for (bucket, count) in sample.delta.iter().enumerate() {
+            let seconds = handshake_bucket_midpoint_secs(bucket);
+            for _ in 0..*count {
+                m.handshake_duration.record(seconds, &attributes);
+            }
+        }
+    }
+}
+
+fn handshake_bucket_midpoint_secs(bucket: usize) -> f64 {
+    let lower = bucket
+        .checked_sub(1)
+        .and_then(HandshakeLatency::upper_bound_micros)
+        .unwrap_or(0) as f64;
+    let upper =
+        HandshakeLatency::upper_bound_micros(bucket).map_or(lower * 2.0, |upper| upper as f64);
+    (lower + upper) / 2.0 / 1_000_000.0
 }
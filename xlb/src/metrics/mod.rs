@@ -1,7 +1,10 @@
 mod egress;
+mod events;
 mod global;
 mod ingress;
 mod otel;
+mod prometheus;
 mod resource;
 
 pub use otel::*;
+pub use prometheus::PrometheusMetrics;
@@ -1,68 +1,131 @@
-use super::{egress, global, ingress, resource};
-use crate::config::{Host, OtelConfig, OtelProtocol};
+use super::prometheus::{PROMETHEUS_REFRESH, PrometheusExporter, PrometheusMetrics};
+use super::{egress, events, global, ingress, resource};
+use crate::config::{AdminMetricsConfig, Host, OtelConfig, OtelProtocol};
 use crate::r#loop::utils::LbFlowStats;
+use crate::status::{Event, OutlierReason};
 use anyhow::Result;
 use opentelemetry::KeyValue;
 use opentelemetry::metrics::MeterProvider;
 use opentelemetry_otlp::{MetricExporter, WithExportConfig, WithHttpConfig, WithTonicConfig};
 use opentelemetry_sdk::Resource;
 use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider, Temporality};
-use std::sync::OnceLock;
+use std::net::IpAddr;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 static OTEL_PROVIDER: OnceLock<SdkMeterProvider> = OnceLock::new();
 
-/// Initialize the OTEL metrics system
-/// Sets up provider, meter, and initializes all metric modules
-pub fn init(config: &OtelConfig, service_name: String) -> Result<()> {
+/// Initialize the metrics system
+/// Sets up provider, meter, and initializes all metric modules. Instruments
+/// are exported over OTLP when `otel` is given and rendered for `/metrics`
+/// when `prometheus` is given; the returned exposition exists in that case.
+pub fn init(
+    otel: Option<&OtelConfig>,
+    prometheus: Option<&AdminMetricsConfig>,
+    service_name: String,
+) -> Result<Option<Arc<PrometheusMetrics>>> {
+    if otel.is_none() && prometheus.is_none() {
+        return Ok(None);
+    }
+
+    let resource = service_resource(service_name);
+    let mut provider = SdkMeterProvider::builder().with_resource(resource.clone());
+
+    if let Some(config) = otel {
+        provider = provider.with_reader(otlp_reader(config)?);
+        events::init(config, resource)?;
+    }
+
+    let exposition = prometheus.map(|config| Arc::new(PrometheusMetrics::new(config.max_backends)));
+    if let Some(exposition) = &exposition {
+        provider = provider.with_reader(
+            PeriodicReader::builder(PrometheusExporter::new(exposition.clone()))
+                .with_interval(PROMETHEUS_REFRESH)
+                .build(),
+        );
+    }
+
+    let provider = provider.build();
+    let meter = provider.meter("xlb");
+
+    global::init(&meter)?;
+    ingress::init(&meter)?;
+    egress::init(&meter)?;
+    resource::init(&meter)?;
+
+    OTEL_PROVIDER
+        .set(provider)
+        .map_err(|_| anyhow::anyhow!("OTEL provider already initialized"))?;
+
+    if let Some(config) = otel {
+        log::info!(
+            "OTEL metrics and event log export initialized: endpoint={} protocol={:?} temporality=Delta",
+            config.endpoint,
+            config.protocol
+        );
+    }
+    if let Some(config) = prometheus {
+        log::info!(
+            "Prometheus metrics initialized: max_backends={}",
+            config.max_backends
+        );
+    }
+
+    Ok(exposition)
+}
+
+fn otlp_reader(config: &OtelConfig) -> Result<PeriodicReader<MetricExporter>> {
+    let builder = MetricExporter::builder();
     let exporter = match config.protocol {
-        OtelProtocol::Grpc => {
-            let mut builder = MetricExporter::builder()
-                .with_tonic()
-                .with_temporality(Temporality::Delta)
-                .with_endpoint(&config.endpoint);
-
-            if !config.headers.is_empty() {
-                let mut metadata = tonic::metadata::MetadataMap::new();
-                for (key, value) in &config.headers {
-                    let key_name = tonic::metadata::MetadataKey::from_bytes(key.as_bytes())
-                        .map_err(|e| {
-                            anyhow::anyhow!("Invalid gRPC metadata key '{}': {}", key, e)
-                        })?;
-                    let value_str = tonic::metadata::MetadataValue::try_from(value.as_str())
-                        .map_err(|e| {
-                            anyhow::anyhow!("Invalid gRPC metadata value for '{}': {}", key, e)
-                        })?;
-                    metadata.insert(key_name, value_str);
-                }
-                builder = builder.with_metadata(metadata);
-            }
-
-            builder.build()?
-        }
-        OtelProtocol::Http => {
-            let mut builder = MetricExporter::builder()
-                .with_http()
-                .with_temporality(Temporality::Delta)
-                .with_endpoint(&config.endpoint);
-
-            if !config.headers.is_empty() {
-                let headers = config
-                    .headers
-                    .iter()
-                    .map(|(k, v)| (k.clone(), v.clone()))
-                    .collect();
-                builder = builder.with_headers(headers);
-            }
-
-            builder.build()?
-        }
+        OtelProtocol::Grpc => tonic_exporter(builder.with_tonic(), config)?
+            .with_temporality(Temporality::Delta)
+            .build()?,
+        OtelProtocol::Http => http_exporter(builder.with_http(), config)
+            .with_temporality(Temporality::Delta)
+            .build()?,
     };
 
-    let reader = PeriodicReader::builder(exporter)
+    Ok(PeriodicReader::builder(exporter)
         .with_interval(Duration::from_secs(config.export_interval_secs))
-        .build();
+        .build())
+}
+
+/// Point a gRPC exporter builder, for metrics or event logs, at the
+/// configured endpoint and send the configured headers as metadata.
+pub(super) fn tonic_exporter<B: WithExportConfig + WithTonicConfig>(
+    builder: B,
+    config: &OtelConfig,
+) -> Result<B> {
+    let builder = builder.with_endpoint(&config.endpoint);
+    if config.headers.is_empty() {
+        return Ok(builder);
+    }
 
+    let mut metadata = tonic::metadata::MetadataMap::new();
+    for (key, value) in &config.headers {
+        let key_name = tonic::metadata::MetadataKey::from_bytes(key.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Invalid gRPC metadata key '{}': {}", key, e))?;
+        let value_str = tonic::metadata::MetadataValue::try_from(value.as_str())
+            .map_err(|e| anyhow::anyhow!("Invalid gRPC metadata value for '{}': {}", key, e))?;
+        metadata.insert(key_name, value_str);
+    }
+    Ok(builder.with_metadata(metadata))
+}
+
+/// Point an HTTP exporter builder, for metrics or event logs, at the
+/// configured endpoint and send the configured headers with each export.
+pub(super) fn http_exporter<B: WithExportConfig + WithHttpConfig>(
+    builder: B,
+    config: &OtelConfig,
+) -> B {
+    let builder = builder.with_endpoint(&config.endpoint);
+    if config.headers.is_empty() {
+        return builder;
+    }
+    builder.with_headers(config.headers.clone())
+}
+
+fn service_resource(service_name: String) -> Resource {
     let mut resource_attributes = vec![
         KeyValue::new("service.name", service_name.clone()),
         KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
@@ -92,33 +155,9 @@ pub fn init(config: &OtelConfig, service_name: String) -> Result<()> {
         resource_attributes.push(KeyValue::new("service.instance.id", hostname));
     }
 
-    let resource = Resource::builder()
+    Resource::builder()
         .with_attributes(resource_attributes)
-        .build();
-
-    let provider = SdkMeterProvider::builder()
-        .with_reader(reader)
-        .with_resource(resource)
-        .build();
-
-    let meter = provider.meter("xlb");
-
-    global::init(&meter)?;
-    ingress::init(&meter)?;
-    egress::init(&meter)?;
-    resource::init(&meter)?;
-
-    OTEL_PROVIDER
-        .set(provider)
-        .map_err(|_| anyhow::anyhow!("OTEL provider already initialized"))?;
-
-    log::info!(
-        "OTEL metrics provider initialized: endpoint={} protocol={:?} temporality=Delta",
-        config.endpoint,
-        config.protocol
-    );
-
-    Ok(())
+        .build()
 }
 
 /// Export otel for global, ingress, and egress metrics
@@ -134,7 +173,22 @@ pub fn record_flow_pair_invariant_violations(count: u64) {
     global::record_flow_pair_invariant_violations(count);
 }
 
+/// Record one passive outlier ejection of a backend.
+pub fn record_backend_ejected(backend: IpAddr, reason: OutlierReason) {
+    global::record_backend_ejected(backend, reason);
+}
+
 /// Record orphan cleanup once per connection rather than per directional entry.
 pub fn record_connections_orphaned(count: u64) {
     global::record_connections_orphaned(count);
 }
+
+/// Export a lifecycle event as an OpenTelemetry log record.
+pub fn export_event(event: &Event) {
+    events::export_event(event);
+}
+
+/// Flush telemetry that is still batched for export.
+pub fn shutdown() {
+    events::shutdown();
+}
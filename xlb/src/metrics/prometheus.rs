@@ -0,0 +1,566 @@
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::metrics::Temporality;
+use opentelemetry_sdk::metrics::data::{AggregatedMetrics, Metric, MetricData, ResourceMetrics};
+use opentelemetry_sdk::metrics::exporter::PushMetricExporter;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Write};
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Interval at which the exposition served at `/metrics` is re-rendered.
+pub const PROMETHEUS_REFRESH: Duration = Duration::from_secs(1);
+
+const BACKEND_LABEL: &str = "backend";
+const OTHER_BACKENDS: &str = "other";
+
+/// Latest Prometheus text exposition of XLB's metric instruments.
+#[derive(Debug)]
+pub struct PrometheusMetrics {
+    max_backends: usize,
+    text: RwLock<String>,
+}
+
+impl PrometheusMetrics {
+    pub fn new(max_backends: usize) -> Self {
+        Self {
+            max_backends,
+            text: RwLock::new(String::new()),
+        }
+    }
+
+    /// Exposition rendered by the most recent export.
+    pub fn text(&self) -> String {
+        self.text.read().expect("metrics lock poisoned").clone()
+    }
+
+    fn update(&self, metrics: &ResourceMetrics) {
+        let text = render(metrics, self.max_backends);
+        *self.text.write().expect("metrics lock poisoned") = text;
+    }
+}
+
+/// Exporter that renders every collection as Prometheus text instead of
+/// sending it anywhere. Counters are cumulative, as Prometheus expects.
+pub struct PrometheusExporter {
+    metrics: Arc<PrometheusMetrics>,
+}
+
+impl PrometheusExporter {
+    pub fn new(metrics: Arc<PrometheusMetrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl PushMetricExporter for PrometheusExporter {
+    async fn export(&self, metrics: &ResourceMetrics) -> OTelSdkResult {
+        self.metrics.update(metrics);
+        Ok(())
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        Ok(())
+    }
+
+    fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+        Ok(())
+    }
+
+    fn temporality(&self) -> Temporality {
+        Temporality::Cumulative
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Value {
+    Int(i128),
+    Float(f64),
+}
+
+impl Value {
+    fn add(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => Self::Int(a.saturating_add(b)),
+            (a, b) => Self::Float(a.as_f64() + b.as_f64()),
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Self::Int(value) => value as f64,
+            Self::Float(value) => value,
+        }
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Self::Int(value.into())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Int(value.into())
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Float(value) if value.is_nan() => f.write_str("NaN"),
+            Self::Float(value) if value == f64::INFINITY => f.write_str("+Inf"),
+            Self::Float(value) if value == f64::NEG_INFINITY => f.write_str("-Inf"),
+            Self::Float(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Point {
+    Value(Value),
+    Histogram {
+        bounds: Vec<f64>,
+        counts: Vec<u64>,
+        sum: Value,
+        count: u64,
+    },
+}
+
+impl Point {
+    /// Combine two points of the same metric, or `None` when histogram
+    /// bucket bounds differ.
+    fn add(&self, other: &Self) -> Option<Self> {
+        match (self, other) {
+            (Self::Value(a), Self::Value(b)) => Some(Self::Value(a.add(*b))),
+            (
+                Self::Histogram {
+                    bounds,
+                    counts,
+                    sum,
+                    count,
+                },
+                Self::Histogram {
+                    bounds: other_bounds,
+                    counts: other_counts,
+                    sum: other_sum,
+                    count: other_count,
+                },
+            ) if bounds == other_bounds => Some(Self::Histogram {
+                bounds: bounds.clone(),
+                counts: counts
+                    .iter()
+                    .zip(other_counts)
+                    .map(|(a, b)| a.saturating_add(*b))
+                    .collect(),
+                sum: sum.add(*other_sum),
+                count: count.saturating_add(*other_count),
+            }),
+            _ => None,
+        }
+    }
+}
+
+type Labels = BTreeMap<String, String>;
+
+struct Family {
+    name: String,
+    help: String,
+    kind: &'static str,
+    series: Vec<(Labels, Point)>,
+}
+
+fn render(metrics: &ResourceMetrics, max_backends: usize) -> String {
+    let mut out = String::new();
+    let target: Labels = metrics
+        .resource()
+        .iter()
+        .map(|(key, value)| (label_name(key.as_str()), value.as_str().into_owned()))
+        .collect();
+    if !target.is_empty() {
+        out.push_str("# HELP target_info Target metadata\n# TYPE target_info gauge\n");
+        write_sample(&mut out, "target_info", &target, None, Value::Int(1));
+    }
+
+    for scope in metrics.scope_metrics() {
+        for metric in scope.metrics() {
+            let Some(mut family) = family(metric) else {
+                continue;
+            };
+            bound_backends(&mut family.series, max_backends);
+            write_family(&mut out, &family);
+        }
+    }
+    out
+}
+
+fn family(metric: &Metric) -> Option<Family> {
+    let (kind, monotonic, series) = match metric.data() {
+        AggregatedMetrics::F64(data) => series(data),
+        AggregatedMetrics::U64(data) => series(data),
+        AggregatedMetrics::I64(data) => series(data),
+    }?;
+
+    let mut name = metric_name(metric.name());
+    if let Some(unit) = unit_suffix(metric.unit())
+        && !name.ends_with(unit)
+    {
+        name = format!("{name}_{unit}");
+    }
+    if monotonic {
+        name.push_str("_total");
+    }
+    Some(Family {
+        name,
+        help: metric
+            .description()
+            .replace('\\', "\\\\")
+            .replace('\n', "\\n"),
+        kind,
+        series,
+    })
+}
+
+type Series = (&'static str, bool, Vec<(Labels, Point)>);
+
+fn series<T: Copy + Into<Value>>(data: &MetricData<T>) -> Option<Series> {
+    match data {
+        MetricData::Gauge(gauge) => Some((
+            "gauge",
+            false,
+            gauge
+                .data_points()
+                .map(|point| {
+                    (
+                        labels(point.attributes()),
+                        Point::Value(point.value().into()),
+                    )
+                })
+                .collect(),
+        )),
+        MetricData::Sum(sum) => Some((
+            if sum.is_monotonic() {
+                "counter"
+            } else {
+                "gauge"
+            },
+            sum.is_monotonic(),
+            sum.data_points()
+                .map(|point| {
+                    (
+                        labels(point.attributes()),
+                        Point::Value(point.value().into()),
+                    )
+                })
+                .collect(),
+        )),
+        MetricData::Histogram(histogram) => Some((
+            "histogram",
+            false,
+            histogram
+                .data_points()
+                .map(|point| {
+                    (
+                        labels(point.attributes()),
+                        Point::Histogram {
+                            bounds: point.bounds().collect(),
+                            counts: point.bucket_counts().collect(),
+                            sum: point.sum().into(),
+                            count: point.count(),
+                        },
+                    )
+                })
+                .collect(),
+        )),
+        MetricData::ExponentialHistogram(_) => None,
+    }
+}
+
+fn labels<'a>(attributes: impl Iterator<Item = &'a KeyValue>) -> Labels {
+    attributes
+        .map(|attribute| {
+            (
+                label_name(attribute.key.as_str()),
+                attribute.value.as_str().into_owned(),
+            )
+        })
+        .collect()
+}
+
+/// Keep a `backend` label for the first `max_backends` backends in address
+/// order and sum the series of every other backend under `backend="other"`.
+fn bound_backends(series: &mut Vec<(Labels, Point)>, max_backends: usize) {
+    let backends: BTreeSet<BackendOrder> = series
+        .iter()
+        .filter_map(|(labels, _)| labels.get(BACKEND_LABEL))
+        .map(|backend| BackendOrder::new(backend))
+        .collect();
+    if backends.len() <= max_backends {
+        return;
+    }
+    let kept: BTreeSet<&str> = backends
+        .iter()
+        .take(max_backends)
+        .map(|backend| backend.label.as_str())
+        .collect();
+    let kept: BTreeSet<String> = kept.into_iter().map(str::to_owned).collect();
+
+    let mut folded: Vec<(Labels, Point)> = Vec::with_capacity(series.len());
+    for (mut labels, point) in series.drain(..) {
+        if let Some(backend) = labels.get_mut(BACKEND_LABEL)
+            && !kept.contains(backend.as_str())
+        {
+            *backend = OTHER_BACKENDS.to_owned();
+            if let Some((_, existing)) = folded.iter_mut().find(|(other, _)| *other == labels)
+                && let Some(sum) = existing.add(&point)
+            {
+                *existing = sum;
+                continue;
+            }
+        }
+        folded.push((labels, point));
+    }
+    *series = folded;
+}
+
+/// Orders backend labels by address, then by text for labels that are not
+/// addresses.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct BackendOrder {
+    address: Option<IpAddr>,
+    label: String,
+}
+
+impl BackendOrder {
+    fn new(label: &str) -> Self {
+        Self {
+            address: label.parse().ok(),
+            label: label.to_owned(),
+        }
+    }
+}
+
+fn write_family(out: &mut String, family: &Family) {
+    if family.series.is_empty() {
+        return;
+    }
+    let _ = writeln!(out, "# HELP {} {}", family.name, family.help);
+    let _ = writeln!(out, "# TYPE {} {}", family.name, family.kind);
+    for (labels, point) in &family.series {
+        match point {
+            Point::Value(value) => write_sample(out, &family.name, labels, None, *value),
+            Point::Histogram {
+                bounds,
+                counts,
+                sum,
+                count,
+            } => {
+                let bucket = format!("{}_bucket", family.name);
+                let mut cumulative = 0u64;
+                for (index, bucket_count) in counts.iter().enumerate() {
+                    cumulative = cumulative.saturating_add(*bucket_count);
+                    let le = bounds
+                        .get(index)
+                        .map_or(Value::Float(f64::INFINITY), |bound| Value::Float(*bound));
+                    write_sample(
+                        out,
+                        &bucket,
+                        labels,
+                        Some(le.to_string()),
+                        Value::Int(cumulative.into()),
+                    );
+                }
+                write_sample(out, &format!("{}_sum", family.name), labels, None, *sum);
+                write_sample(
+                    out,
+                    &format!("{}_count", family.name),
+                    labels,
+                    None,
+                    Value::Int((*count).into()),
+                );
+            }
+        }
+    }
+}
+
+fn write_sample(out: &mut String, name: &str, labels: &Labels, le: Option<String>, value: Value) {
+    out.push_str(name);
+    let mut pairs = labels
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .chain(le.as_deref().map(|le| ("le", le)))
+        .peekable();
+    if pairs.peek().is_some() {
+        out.push('{');
+        for (index, (key, value)) in pairs.enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "{key}=\"{}\"", escape_label_value(value));
+        }
+        out.push('}');
+    }
+    let _ = writeln!(out, " {value}");
+}
+
+fn metric_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == ':' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+fn label_name(name: &str) -> String {
+    metric_name(name).replace(':', "_")
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Prometheus suffix of the OpenTelemetry units XLB uses.
+fn unit_suffix(unit: &str) -> Option<&'static str> {
+    match unit {
+        "By" => Some("bytes"),
+        "s" => Some("seconds"),
+        "ms" => Some("milliseconds"),
+        "%" => Some("percent"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+
+    fn exposition(
+        max_backends: usize,
+        record: impl FnOnce(&opentelemetry::metrics::Meter),
+    ) -> String {
+        let metrics = Arc::new(PrometheusMetrics::new(max_backends));
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(PrometheusExporter::new(metrics.clone())).build())
+            .with_resource(
+                Resource::builder_empty()
+                    .with_attribute(KeyValue::new("service.name", "edge"))
+                    .build(),
+            )
+            .build();
+        record(&provider.meter("xlb"));
+        provider.force_flush().expect("metrics flush");
+        metrics.text()
+    }
+
+    #[test]
+    fn renders_gauges_counters_and_histograms_in_text_format() {
+        let text = exposition(10, |meter| {
+            meter
+                .f64_gauge("xlb.ingress.mbps")
+                .with_description("Bandwidth")
+                .build()
+                .record(1.5, &[KeyValue::new("backend", "10.0.0.1")]);
+            let bytes = meter
+                .u64_counter("xlb.ingress.bytes")
+                .with_unit("By")
+                .build();
+            bytes.add(100, &[KeyValue::new("backend", "10.0.0.1")]);
+            bytes.add(50, &[KeyValue::new("backend", "10.0.0.1")]);
+            meter
+                .f64_histogram("xlb.latency")
+                .with_unit("s")
+                .with_boundaries(vec![0.1, 1.0])
+                .build()
+                .record(0.5, &[]);
+        });
+
+        assert!(
+            text.contains("target_info{service_name=\"edge\"} 1\n"),
+            "{text}"
+        );
+        assert!(
+            text.contains("# HELP xlb_ingress_mbps Bandwidth\n# TYPE xlb_ingress_mbps gauge\n")
+        );
+        assert!(text.contains("xlb_ingress_mbps{backend=\"10.0.0.1\"} 1.5\n"));
+        assert!(text.contains("# TYPE xlb_ingress_bytes_total counter\n"));
+        assert!(text.contains("xlb_ingress_bytes_total{backend=\"10.0.0.1\"} 150\n"));
+        assert!(text.contains("# TYPE xlb_latency_seconds histogram\n"));
+        assert!(text.contains("xlb_latency_seconds_bucket{le=\"0.1\"} 0\n"));
+        assert!(text.contains("xlb_latency_seconds_bucket{le=\"1\"} 1\n"));
+        assert!(text.contains("xlb_latency_seconds_bucket{le=\"+Inf\"} 1\n"));
+        assert!(text.contains("xlb_latency_seconds_sum 0.5\n"));
+        assert!(text.contains("xlb_latency_seconds_count 1\n"));
+    }
+
+    #[test]
+    fn folds_backends_beyond_the_limit_into_other() {
+        let text = exposition(2, |meter| {
+            let active = meter.u64_gauge("xlb.ingress.flows.active").build();
+            for (backend, flows) in [
+                ("10.0.0.10", 4),
+                ("10.0.0.2", 1),
+                ("10.0.0.9", 2),
+                ("10.0.0.3", 3),
+            ] {
+                active.record(flows, &[KeyValue::new("backend", backend)]);
+            }
+            let closed = meter.u64_counter("xlb.ingress.flows.closed").build();
+            for backend in ["10.0.0.10", "10.0.0.9", "10.0.0.3", "10.0.0.2"] {
+                for kind in ["fin", "rst"] {
+                    closed.add(
+                        1,
+                        &[
+                            KeyValue::new("backend", backend),
+                            KeyValue::new("type", kind),
+                        ],
+                    );
+                }
+            }
+        });
+
+        assert!(
+            text.contains("xlb_ingress_flows_active{backend=\"10.0.0.2\"} 1\n"),
+            "{text}"
+        );
+        assert!(text.contains("xlb_ingress_flows_active{backend=\"10.0.0.3\"} 3\n"));
+        assert!(text.contains("xlb_ingress_flows_active{backend=\"other\"} 6\n"));
+        assert!(!text.contains("10.0.0.10"));
+        assert!(
+            text.contains("xlb_ingress_flows_closed_total{backend=\"other\",type=\"fin\"} 2\n")
+        );
+    }
+
+    #[test]
+    fn escapes_label_values_and_sanitizes_names() {
+        assert_eq!(escape_label_value("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+        assert_eq!(
+            metric_name("xlb.resource.cpu-host"),
+            "xlb_resource_cpu_host"
+        );
+        assert_eq!(label_name("k8s.pod:name"), "k8s_pod_name");
+        assert_eq!(Value::Float(f64::NAN).to_string(), "NaN");
+    }
+}
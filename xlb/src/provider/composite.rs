@@ -0,0 +1,264 @@
+use crate::config::{CompositeHealth, Host};
+use crate::provider::{BackendProvider, TopologyStatus, WatchStatus};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::{info, warn};
+use std::collections::HashSet;
+use std::sync::Arc;
+use xlb_common::consts;
+
+/// One provider feeding a composite pool.
+pub struct CompositeChild {
+    pub provider: Arc<dyn BackendProvider>,
+    /// Percentage of their selection weight this child's backends keep.
+    pub weight_percent: u8,
+    /// Lower values supply an address published by several children. Also
+    /// the lowest priority tier this child's backends are placed in.
+    pub priority: u32,
+}
+
+/// Merges the backends of several providers into one pool. An address
+/// published by more than one child is taken from the child with the
+/// lowest priority, or the first listed on a tie.
+pub struct CompositeProvider {
+    /// Children ordered by priority, then by configuration order.
+    children: Vec<CompositeChild>,
+    health: CompositeHealth,
+}
+
+impl CompositeProvider {
+    pub fn new(mut children: Vec<CompositeChild>, health: CompositeHealth) -> Self {
+        children.sort_by_key(|child| child.priority);
+        Self { children, health }
+    }
+}
+
+#[async_trait]
+impl BackendProvider for CompositeProvider {
+    async fn start(&self) -> Result<()> {
+        for (index, child) in self.children.iter().enumerate() {
+            if let Err(error) = child.provider.start().await {
+                for started in &self.children[..index] {
+                    if let Err(error) = started.provider.shutdown().await {
+                        warn!("Failed to stop composite source after startup failure: {error:#}");
+                    }
+                }
+                return Err(error).context("Failed to start composite provider source");
+            }
+        }
+
+        info!(
+            "Started composite provider with {} sources",
+            self.children.len()
+        );
+        Ok(())
+    }
+
+    fn get_backends(&self) -> Vec<Host> {
+        let mut seen = HashSet::new();
+        let mut merged = Vec::new();
+        for child in &self.children {
+            for mut host in child.provider.get_backends() {
+                if !seen.insert(host.ip) {
+                    continue;
+                }
+                host.weight = scale_weight(host.weight, child.weight_percent);
+                host.priority = host.priority.max(child.priority);
+                merged.push(host);
+            }
+        }
+        merged
+    }
+
+    fn is_healthy(&self) -> bool {
+        let mut children = self
+            .children
+            .iter()
+            .map(|child| child.provider.is_healthy());
+        match self.health {
+            CompositeHealth::All => children.all(|healthy| healthy),
+            CompositeHealth::Any => children.any(|healthy| healthy),
+        }
+    }
+
+    fn last_error(&self) -> Option<String> {
+        let errors: Vec<String> = self
+            .children
+            .iter()
+            .filter_map(|child| child.provider.last_error())
+            .collect();
+        (!errors.is_empty()).then(|| errors.join("; "))
+    }
+
+    /// The locality of the most preferred source that reports one.
+    fn topology(&self) -> Option<TopologyStatus> {
+        self.children
+            .iter()
+            .find_map(|child| child.provider.topology())
+    }
+
+    fn watches(&self) -> Vec<WatchStatus> {
+        self.children
+            .iter()
+            .flat_map(|child| child.provider.watches())
+            .collect()
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        let mut result = Ok(());
+        for child in &self.children {
+            if let Err(error) = child.provider.shutdown().await
+                && result.is_ok()
+            {
+                result = Err(error);
+            }
+        }
+        result.context("Failed to shutdown composite provider source")
+    }
+}
+
+fn scale_weight(weight: Option<u16>, percent: u8) -> Option<u16> {
+    if percent >= 100 {
+        return weight;
+    }
+    let weight = u32::from(weight.unwrap_or(consts::MAX_BACKEND_WEIGHT));
+    Some(((weight * u32::from(percent) / 100) as u16).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::net::IpAddr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct StubProvider {
+        hosts: Vec<Host>,
+        healthy: AtomicBool,
+        fail_start: bool,
+        stopped: AtomicBool,
+    }
+
+    impl StubProvider {
+        fn new(hosts: &[(&str, [u8; 4])]) -> Arc<Self> {
+            Arc::new(Self {
+                hosts: hosts
+                    .iter()
+                    .map(|(name, ip)| Host {
+                        name: (*name).into(),
+                        ip: IpAddr::from(*ip),
+                        weight: None,
+                        priority: 0,
+                        ports: BTreeMap::new(),
+                    })
+                    .collect(),
+                healthy: AtomicBool::new(true),
+                fail_start: false,
+                stopped: AtomicBool::new(false),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl BackendProvider for StubProvider {
+        async fn start(&self) -> Result<()> {
+            if self.fail_start {
+                anyhow::bail!("stub start failure");
+            }
+            Ok(())
+        }
+
+        fn get_backends(&self) -> Vec<Host> {
+            self.hosts.clone()
+        }
+
+        fn is_healthy(&self) -> bool {
+            self.healthy.load(Ordering::Relaxed)
+        }
+
+        fn last_error(&self) -> Option<String> {
+            (!self.is_healthy()).then(|| "stub unhealthy".to_string())
+        }
+
+        async fn shutdown(&self) -> Result<()> {
+            self.stopped.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    fn child(provider: Arc<StubProvider>, weight_percent: u8, priority: u32) -> CompositeChild {
+        CompositeChild {
+            provider,
+            weight_percent,
+            priority,
+        }
+    }
+
+    #[test]
+    fn merges_children_by_priority_and_scales_weights() {
+        let legacy = StubProvider::new(&[("legacy-1", [10, 0, 0, 1]), ("legacy-2", [10, 0, 0, 2])]);
+        let kubernetes = StubProvider::new(&[("pod-2", [10, 0, 0, 2]), ("pod-3", [10, 0, 0, 3])]);
+        let composite = CompositeProvider::new(
+            vec![child(legacy, 25, 1), child(kubernetes, 100, 0)],
+            CompositeHealth::All,
+        );
+
+        let merged: Vec<(String, Option<u16>, u32)> = composite
+            .get_backends()
+            .into_iter()
+            .map(|host| (host.name, host.weight, host.priority))
+            .collect();
+        assert_eq!(
+            merged,
+            vec![
+                ("pod-2".into(), None, 0),
+                ("pod-3".into(), None, 0),
+                ("legacy-1".into(), Some(250), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn health_follows_the_policy() {
+        let first = StubProvider::new(&[]);
+        let second = StubProvider::new(&[]);
+        let all = CompositeProvider::new(
+            vec![child(first.clone(), 100, 0), child(second.clone(), 100, 0)],
+            CompositeHealth::All,
+        );
+        let any = CompositeProvider::new(
+            vec![child(first.clone(), 100, 0), child(second.clone(), 100, 0)],
+            CompositeHealth::Any,
+        );
+
+        first.healthy.store(false, Ordering::Relaxed);
+        assert!(!all.is_healthy());
+        assert!(any.is_healthy());
+        assert_eq!(any.last_error().as_deref(), Some("stub unhealthy"));
+
+        second.healthy.store(false, Ordering::Relaxed);
+        assert!(!any.is_healthy());
+        assert_eq!(
+            all.last_error().as_deref(),
+            Some("stub unhealthy; stub unhealthy")
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_start_stops_already_started_children() {
+        let started = StubProvider::new(&[]);
+        let failing = Arc::new(StubProvider {
+            hosts: Vec::new(),
+            healthy: AtomicBool::new(true),
+            fail_start: true,
+            stopped: AtomicBool::new(false),
+        });
+        let composite = CompositeProvider::new(
+            vec![child(started.clone(), 100, 0), child(failing, 100, 1)],
+            CompositeHealth::Any,
+        );
+
+        assert!(composite.start().await.is_err());
+        assert!(started.stopped.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,357 @@
+use crate::config::{ConsulProviderConfig, Host};
+use crate::provider::{BackendProvider, relative_weights, replace_backends};
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+const CONSUL_TOKEN_ENV: &str = "CONSUL_HTTP_TOKEN";
+const CONSUL_TOKEN_HEADER: &str = "X-Consul-Token";
+const CONSUL_INDEX_HEADER: &str = "X-Consul-Index";
+/// Extra time allowed beyond the requested wait before a blocking query
+/// counts as failed. Consul adds up to wait/16 of jitter on its side.
+const REQUEST_TIMEOUT_MARGIN: Duration = Duration::from_secs(5);
+
+/// Connection settings for the Consul provider. Deliberately does not
+/// implement `Debug` so the ACL token cannot be logged.
+#[derive(Clone)]
+pub struct ConsulSettings {
+    pub address: String,
+    pub service: String,
+    pub tags: Vec<String>,
+    pub datacenter: Option<String>,
+    pub weight_meta_key: Option<String>,
+    pub token: Option<String>,
+    pub wait: Duration,
+    /// First delay after a failed query, doubled per consecutive failure.
+    pub retry_interval: Duration,
+    pub max_retry_interval: Duration,
+}
+
+impl ConsulSettings {
+    pub fn from_env(config: &ConsulProviderConfig) -> Self {
+        Self {
+            address: config.address.trim_end_matches('/').to_string(),
+            service: config.service.clone(),
+            tags: config.tags.clone(),
+            datacenter: config.datacenter.clone(),
+            weight_meta_key: config.weight_meta_key.clone(),
+            token: std::env::var(CONSUL_TOKEN_ENV)
+                .ok()
+                .filter(|token| !token.is_empty()),
+            wait: Duration::from_secs(config.wait_secs),
+            retry_interval: Duration::from_secs(1),
+            max_retry_interval: Duration::from_secs(30),
+        }
+    }
+
+    fn url(&self) -> String {
+        format!("{}/v1/health/service/{}", self.address, self.service)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ServiceEntry {
+    node: CatalogNode,
+    service: CatalogService,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CatalogNode {
+    node: String,
+    address: String,
+    #[serde(default)]
+    meta: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CatalogService {
+    #[serde(rename = "ID")]
+    id: String,
+    #[serde(default)]
+    address: String,
+}
+
+/// Passing instances from one query and the index to block on next.
+#[derive(Debug)]
+struct Answer {
+    hosts: Vec<Host>,
+    index: u64,
+}
+
+/// Follows the passing instances of a Consul service with blocking
+/// queries. When the agent cannot be reached the last known instances are
+/// retained and the provider reports unhealthy until a query succeeds.
+pub struct ConsulProvider {
+    settings: ConsulSettings,
+    /// Current backend list, updated by the watch task.
+    backends: Arc<RwLock<Vec<Host>>>,
+    /// Why the most recent query failed, if it did.
+    error: Arc<RwLock<Option<String>>>,
+    /// Handle to the watch task.
+    watch_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
+}
+
+impl ConsulProvider {
+    pub fn new(settings: ConsulSettings) -> Self {
+        Self {
+            settings,
+            backends: Arc::new(RwLock::new(Vec::new())),
+            error: Arc::new(RwLock::new(None)),
+            watch_handle: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    fn publish_backends(next: Vec<Host>, backends: &RwLock<Vec<Host>>, service: &str) {
+        let mut current = backends.write().expect("backend lock poisoned");
+        let changes = replace_backends(&mut current, next);
+        let total = current.len();
+
+        for host in changes.removed {
+            info!(
+                "Removed backend: {} ({}) for Consul service {} (total: {})",
+                host.name, host.ip, service, total
+            );
+        }
+        for host in changes.added {
+            info!(
+                "Added backend: {} ({}) for Consul service {} (total: {})",
+                host.name, host.ip, service, total
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl BackendProvider for ConsulProvider {
+    async fn start(&self) -> Result<()> {
+        let client = Client::builder()
+            .build()
+            .context("Failed to build Consul HTTP client")?;
+        let answer = query(&client, &self.settings, 0).await.with_context(|| {
+            format!(
+                "Failed to query Consul service {} at {}",
+                self.settings.service, self.settings.address
+            )
+        })?;
+
+        let settings = self.settings.clone();
+        let mut index = next_index(0, answer.index);
+        Self::publish_backends(answer.hosts, &self.backends, &settings.service);
+
+        let backends = self.backends.clone();
+        let last_error = self.error.clone();
+        let handle = tokio::spawn(async move {
+            let mut failures = 0u32;
+            loop {
+                match query(&client, &settings, index).await {
+                    Ok(answer) => {
+                        failures = 0;
+                        if last_error
+                            .write()
+                            .expect("error lock poisoned")
+                            .take()
+                            .is_some()
+                        {
+                            info!("Consul queries for {} recovered", settings.service);
+                        }
+                        // A wait that times out without changes returns the
+                        // same index and instances; publishing is a no-op.
+                        Self::publish_backends(answer.hosts, &backends, &settings.service);
+                        index = next_index(index, answer.index);
+                    }
+                    Err(error) => {
+                        failures = failures.saturating_add(1);
+                        let message = format!("{error:#}");
+                        let previous = last_error
+                            .write()
+                            .expect("error lock poisoned")
+                            .replace(message);
+                        if previous.is_none() {
+                            warn!(
+                                "Consul query for {} failed, keeping the last known backends: {:#}",
+                                settings.service, error
+                            );
+                        } else {
+                            debug!("Consul query for {} failed: {:#}", settings.service, error);
+                        }
+                        tokio::time::sleep(retry_delay(failures, &settings)).await;
+                    }
+                }
+            }
+        });
+
+        *self
+            .watch_handle
+            .write()
+            .expect("watch handle lock poisoned") = Some(handle);
+        info!(
+            "Started Consul provider watching service {} at {}",
+            self.settings.service, self.settings.address
+        );
+        Ok(())
+    }
+
+    fn get_backends(&self) -> Vec<Host> {
+        self.backends.read().expect("backend lock poisoned").clone()
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.error.read().expect("error lock poisoned").is_none()
+            && self
+                .watch_handle
+                .read()
+                .expect("watch handle lock poisoned")
+                .as_ref()
+                .is_some_and(|handle| !handle.is_finished())
+    }
+
+    fn last_error(&self) -> Option<String> {
+        self.error.read().expect("error lock poisoned").clone()
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        if let Some(handle) = self
+            .watch_handle
+            .write()
+            .expect("watch handle lock poisoned")
+            .take()
+        {
+            handle.abort();
+            info!("Stopped watching Consul service {}", self.settings.service);
+        }
+        Ok(())
+    }
+}
+
+/// Run one blocking query for the passing instances of the service. An
+/// index of zero returns immediately with the current state.
+async fn query(client: &Client, settings: &ConsulSettings, index: u64) -> Result<Answer> {
+    let mut params = vec![
+        ("passing", String::new()),
+        ("index", index.to_string()),
+        ("wait", format!("{}ms", settings.wait.as_millis())),
+    ];
+    if let Some(datacenter) = &settings.datacenter {
+        params.push(("dc", datacenter.clone()));
+    }
+    params.extend(settings.tags.iter().map(|tag| ("tag", tag.clone())));
+
+    let mut request = client
+        .get(settings.url())
+        .query(&params)
+        .timeout(settings.wait + settings.wait / 16 + REQUEST_TIMEOUT_MARGIN);
+    if let Some(token) = &settings.token {
+        request = request.header(CONSUL_TOKEN_HEADER, token);
+    }
+
+    let response = request.send().await.context("Consul request failed")?;
+    let status = response.status();
+    if status != StatusCode::OK {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Consul responded with {}: {}", status, body.trim());
+    }
+    let index = response
+        .headers()
+        .get(CONSUL_INDEX_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .with_context(|| format!("Consul response has no valid {CONSUL_INDEX_HEADER} header"))?;
+    let entries: Vec<ServiceEntry> = response
+        .json()
+        .await
+        .context("Failed to parse Consul health response")?;
+
+    Ok(Answer {
+        hosts: hosts_from_entries(entries, settings.weight_meta_key.as_deref()),
+        index,
+    })
+}
+
+/// Map service instances to hosts, one per IPv4 address. The service
+/// address takes precedence over the node address, as in Consul DNS.
+fn hosts_from_entries(entries: Vec<ServiceEntry>, weight_meta_key: Option<&str>) -> Vec<Host> {
+    let mut instances = BTreeMap::new();
+    for entry in entries {
+        let address = if entry.service.address.is_empty() {
+            &entry.node.address
+        } else {
+            &entry.service.address
+        };
+        let name = format!("{}/{}", entry.node.node, entry.service.id);
+        let ip = match address.parse::<IpAddr>() {
+            Ok(ip) if ip.is_ipv4() => ip,
+            Ok(_) => {
+                warn!("Skipping Consul instance {name} with IPv6 address {address}");
+                continue;
+            }
+            Err(_) => {
+                warn!("Skipping Consul instance {name} without an IP address ({address:?})");
+                continue;
+            }
+        };
+        let weight = weight_meta_key.and_then(|key| {
+            let value = entry.node.meta.as_ref()?.get(key)?;
+            let weight = value.parse::<u32>().ok();
+            if weight.is_none() {
+                warn!("Ignoring invalid weight {value:?} in node meta {key} of {name}");
+            }
+            weight
+        });
+        instances.entry(ip).or_insert((name, weight));
+    }
+
+    // Instances without a weight get the same share as the heaviest.
+    let heaviest = instances
+        .values()
+        .filter_map(|(_, weight)| *weight)
+        .max()
+        .unwrap_or_default();
+    let raw: Vec<u32> = instances
+        .values()
+        .map(|(_, weight)| weight.unwrap_or(heaviest))
+        .collect();
+
+    instances
+        .into_iter()
+        .zip(relative_weights(&raw))
+        .map(|((ip, (name, _)), weight)| Host {
+            name,
+            ip,
+            weight,
+            priority: 0,
+            ports: BTreeMap::new(),
+        })
+        .collect()
+}
+
+/// Index for the next blocking query. Consul indexes only grow, except
+/// after a snapshot restore or leader change: going backwards resets to a
+/// non-blocking query, and an index is otherwise never zero.
+fn next_index(previous: u64, returned: u64) -> u64 {
+    if returned < previous {
+        0
+    } else {
+        returned.max(1)
+    }
+}
+
+/// Exponential backoff between failed queries, capped.
+fn retry_delay(failures: u32, settings: &ConsulSettings) -> Duration {
+    settings
+        .retry_interval
+        .saturating_mul(1 << failures.saturating_sub(1).min(16))
+        .min(settings.max_retry_interval)
+}
+
+#[cfg(test)]
+mod tests;
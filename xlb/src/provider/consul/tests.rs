@@ -0,0 +1,279 @@
+use super::*;
+use axum::extract::{RawQuery, State};
+use axum::http::HeaderMap;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+#[derive(Default)]
+struct Catalog {
+    index: u64,
+    entries: Value,
+    /// Query string and token of every request received.
+    requests: Vec<(String, Option<String>)>,
+}
+
+/// Consul agent stand-in serving `/v1/health/service/<name>`. A query for
+/// the current index waits briefly and returns it unchanged, like a
+/// blocking query that times out.
+struct MockConsul {
+    address: SocketAddr,
+    catalog: Arc<Mutex<Catalog>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockConsul {
+    async fn start(index: u64, entries: Value) -> Self {
+        let catalog = Arc::new(Mutex::new(Catalog {
+            index,
+            entries,
+            requests: Vec::new(),
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock Consul");
+        let address = listener.local_addr().expect("mock Consul address");
+        let app = Router::new()
+            .route("/v1/health/service/web", get(health_service))
+            .with_state(catalog.clone());
+        let (shutdown, signal) = oneshot::channel();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = signal.await;
+                })
+                .await;
+        });
+
+        Self {
+            address,
+            catalog,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    fn settings(&self) -> ConsulSettings {
+        ConsulSettings {
+            address: format!("http://{}", self.address),
+            service: "web".into(),
+            tags: vec!["primary".into(), "v2".into()],
+            datacenter: Some("dc2".into()),
+            weight_meta_key: Some("xlb-weight".into()),
+            token: Some("secret-token".into()),
+            wait: Duration::from_millis(50),
+            retry_interval: Duration::from_millis(10),
+            max_retry_interval: Duration::from_millis(50),
+        }
+    }
+
+    fn update(&self, index: u64, entries: Value) {
+        let mut catalog = self.catalog.lock().unwrap();
+        catalog.index = index;
+        catalog.entries = entries;
+    }
+
+    fn requests(&self) -> Vec<(String, Option<String>)> {
+        self.catalog.lock().unwrap().requests.clone()
+    }
+
+    fn stop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+impl Drop for MockConsul {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+async fn health_service(
+    State(catalog): State<Arc<Mutex<Catalog>>>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let query = query.unwrap_or_default();
+    let requested: u64 = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("index="))
+        .and_then(|index| index.parse().ok())
+        .unwrap_or_default();
+    let token = headers
+        .get(CONSUL_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let current = {
+        let mut catalog = catalog.lock().unwrap();
+        catalog.requests.push((query, token));
+        catalog.index
+    };
+    if requested == current {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let catalog = catalog.lock().unwrap();
+    (
+        [(CONSUL_INDEX_HEADER, catalog.index.to_string())],
+        Json(catalog.entries.clone()),
+    )
+}
+
+fn entry(node: &str, node_address: &str, service_address: &str, weight: Option<&str>) -> Value {
+    json!({
+        "Node": {
+            "Node": node,
+            "Address": node_address,
+            "Meta": weight.map(|weight| json!({ "xlb-weight": weight })),
+        },
+        "Service": {
+            "ID": format!("web-{node}"),
+            "Service": "web",
+            "Address": service_address,
+            "Port": 8080,
+            "Tags": ["primary", "v2"],
+        },
+        "Checks": [],
+    })
+}
+
+fn entries(value: Value) -> Vec<ServiceEntry> {
+    serde_json::from_value(value).expect("valid health response")
+}
+
+fn addresses(hosts: &[Host]) -> Vec<IpAddr> {
+    hosts.iter().map(|host| host.ip).collect()
+}
+
+async fn wait_until(mut condition: impl FnMut() -> bool) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("condition reached within timeout");
+}
+
+#[test]
+fn instances_map_to_weighted_ipv4_hosts() {
+    let hosts = hosts_from_entries(
+        entries(json!([
+            entry("node-1", "10.0.0.1", "", Some("200")),
+            entry("node-2", "10.0.0.9", "10.0.0.2", Some("50")),
+            entry("node-3", "10.0.0.3", "", None),
+            entry("node-4", "10.0.0.4", "", Some("heavy")),
+            entry("node-5", "2001:db8::5", "", None),
+            entry("node-6", "node-6.consul", "", None),
+            entry("node-7", "10.0.0.1", "", None),
+        ])),
+        Some("xlb-weight"),
+    );
+
+    assert_eq!(
+        addresses(&hosts),
+        vec![
+            IpAddr::from([10, 0, 0, 1]),
+            IpAddr::from([10, 0, 0, 2]),
+            IpAddr::from([10, 0, 0, 3]),
+            IpAddr::from([10, 0, 0, 4]),
+        ]
+    );
+    assert_eq!(hosts[0].name, "node-1/web-node-1", "first instance wins");
+    let weights: Vec<_> = hosts.iter().map(|host| host.weight).collect();
+    assert_eq!(weights, vec![Some(1000), Some(250), Some(1000), Some(1000)]);
+
+    let unweighted = hosts_from_entries(
+        entries(json!([entry("node-1", "10.0.0.1", "", Some("200"))])),
+        None,
+    );
+    assert_eq!(unweighted[0].weight, None);
+}
+
+#[test]
+fn index_resets_when_it_goes_backwards() {
+    assert_eq!(next_index(0, 0), 1);
+    assert_eq!(next_index(10, 10), 10);
+    assert_eq!(next_index(10, 12), 12);
+    assert_eq!(next_index(10, 3), 0);
+}
+
+#[tokio::test]
+async fn blocking_queries_follow_catalog_changes() {
+    let consul = MockConsul::start(
+        10,
+        json!([
+            entry("node-1", "10.0.0.1", "", Some("100")),
+            entry("node-2", "10.0.0.2", "", Some("25")),
+        ]),
+    )
+    .await;
+    let provider = ConsulProvider::new(consul.settings());
+    provider.start().await.expect("provider starts");
+
+    let hosts = provider.get_backends();
+    assert_eq!(
+        addresses(&hosts),
+        vec![IpAddr::from([10, 0, 0, 1]), IpAddr::from([10, 0, 0, 2])]
+    );
+    assert_eq!(hosts[1].weight, Some(250));
+
+    let (query, token) = &consul.requests()[0];
+    assert!(query.contains("passing="), "{query}");
+    assert!(query.contains("index=0"), "{query}");
+    assert!(query.contains("dc=dc2"), "{query}");
+    assert!(query.contains("tag=primary&tag=v2"), "{query}");
+    assert_eq!(token.as_deref(), Some("secret-token"));
+
+    // Waits that time out without changes keep the provider healthy.
+    wait_until(|| consul.requests().len() >= 4).await;
+    assert!(provider.is_healthy());
+    assert!(
+        consul.requests()[1..]
+            .iter()
+            .all(|(query, _)| query.contains("index=10"))
+    );
+
+    consul.update(11, json!([entry("node-2", "10.0.0.2", "", None)]));
+    wait_until(|| addresses(&provider.get_backends()) == vec![IpAddr::from([10, 0, 0, 2])]).await;
+    assert!(provider.is_healthy());
+
+    provider.shutdown().await.unwrap();
+    assert!(!provider.is_healthy());
+}
+
+#[tokio::test]
+async fn unreachable_agent_keeps_backends_and_turns_unhealthy() {
+    let mut consul = MockConsul::start(7, json!([entry("node-1", "10.0.0.1", "", None)])).await;
+    let provider = ConsulProvider::new(consul.settings());
+    provider.start().await.expect("provider starts");
+    assert!(provider.is_healthy());
+
+    consul.stop();
+    wait_until(|| !provider.is_healthy()).await;
+    assert!(provider.last_error().is_some());
+    assert_eq!(
+        addresses(&provider.get_backends()),
+        vec![IpAddr::from([10, 0, 0, 1])]
+    );
+
+    provider.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn start_fails_when_the_agent_is_unreachable() {
+    let mut consul = MockConsul::start(1, json!([])).await;
+    let settings = consul.settings();
+    consul.stop();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let provider = ConsulProvider::new(settings);
+
+    assert!(provider.start().await.is_err());
+    assert!(!provider.is_healthy());
+}
@@ -0,0 +1,342 @@
+use crate::config::{DnsProviderConfig, DnsRecordType, Host};
+use crate::provider::{BackendProvider, relative_weights, replace_backends};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use hickory_resolver::config::{NameServerConfig, ResolverConfig};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::proto::op::ResponseCode;
+use hickory_resolver::proto::xfer::Protocol;
+use hickory_resolver::proto::{ProtoError, ProtoErrorKind};
+use hickory_resolver::{Name, ResolveError, TokioResolver};
+use log::{debug, info, warn};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone)]
+pub struct DnsSettings {
+    pub name: String,
+    pub record_type: DnsRecordType,
+    pub nameservers: Vec<SocketAddr>,
+    pub min_refresh: Duration,
+    pub max_refresh: Duration,
+}
+
+impl From<&DnsProviderConfig> for DnsSettings {
+    fn from(config: &DnsProviderConfig) -> Self {
+        Self {
+            name: config.name.clone(),
+            record_type: config.record_type,
+            nameservers: config.nameservers.clone(),
+            min_refresh: Duration::from_secs(config.min_refresh_secs),
+            max_refresh: Duration::from_secs(config.max_refresh_secs),
+        }
+    }
+}
+
+/// Hosts from one successful resolution and when its records expire.
+#[derive(Debug)]
+struct Resolution {
+    hosts: Vec<Host>,
+    valid_until: Instant,
+}
+
+/// Follows backends registered in DNS by resolving a name whenever its
+/// records expire. On resolver failure the last good set is retained and
+/// the provider reports unhealthy until a resolution succeeds again.
+pub struct DnsProvider {
+    settings: DnsSettings,
+    /// Current backend list, updated by the resolution task.
+    backends: Arc<RwLock<Vec<Host>>>,
+    /// Why the most recent resolution failed, if it did.
+    error: Arc<RwLock<Option<String>>>,
+    /// Handle to the resolution task.
+    resolve_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
+}
+
+impl DnsProvider {
+    pub fn new(settings: DnsSettings) -> Self {
+        Self {
+            settings,
+            backends: Arc::new(RwLock::new(Vec::new())),
+            error: Arc::new(RwLock::new(None)),
+            resolve_handle: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    fn resolver(&self) -> Result<TokioResolver> {
+        if self.settings.nameservers.is_empty() {
+            return Ok(TokioResolver::builder_tokio()
+                .context("Failed to read the system resolver configuration")?
+                .build());
+        }
+
+        let nameservers: Vec<NameServerConfig> = self
+            .settings
+            .nameservers
+            .iter()
+            .flat_map(|address| {
+                [
+                    NameServerConfig::new(*address, Protocol::Udp),
+                    NameServerConfig::new(*address, Protocol::Tcp),
+                ]
+            })
+            .collect();
+        let config = ResolverConfig::from_parts(None, Vec::new(), nameservers);
+
+        Ok(TokioResolver::builder_with_config(config, TokioConnectionProvider::default()).build())
+    }
+
+    fn publish_backends(next: Vec<Host>, backends: &RwLock<Vec<Host>>, name: &str) {
+        let mut current = backends.write().expect("backend lock poisoned");
+        let changes = replace_backends(&mut current, next);
+        let total = current.len();
+
+        for host in changes.removed {
+            info!(
+                "Removed backend: {} ({}) for DNS name {} (total: {})",
+                host.name, host.ip, name, total
+            );
+        }
+        for host in changes.added {
+            info!(
+                "Added backend: {} ({}) for DNS name {} (total: {})",
+                host.name, host.ip, name, total
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl BackendProvider for DnsProvider {
+    async fn start(&self) -> Result<()> {
+        let resolver = self.resolver()?;
+        let resolution = resolve(&resolver, &self.settings)
+            .await
+            .with_context(|| format!("Failed to resolve DNS name {}", self.settings.name))?;
+
+        let settings = self.settings.clone();
+        let mut delay = refresh_delay(resolution.valid_until, Instant::now(), &settings);
+        Self::publish_backends(resolution.hosts, &self.backends, &settings.name);
+
+        let backends = self.backends.clone();
+        let last_error = self.error.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(delay).await;
+
+                delay = match resolve(&resolver, &settings).await {
+                    Ok(resolution) => {
+                        if last_error
+                            .write()
+                            .expect("error lock poisoned")
+                            .take()
+                            .is_some()
+                        {
+                            info!("DNS resolution of {} recovered", settings.name);
+                        }
+                        Self::publish_backends(resolution.hosts, &backends, &settings.name);
+                        refresh_delay(resolution.valid_until, Instant::now(), &settings)
+                    }
+                    Err(error) => {
+                        let message = format!("{error:#}");
+                        let previous = last_error
+                            .write()
+                            .expect("error lock poisoned")
+                            .replace(message);
+                        if previous.is_none() {
+                            warn!(
+                                "DNS resolution of {} failed, keeping the last known backends: {:#}",
+                                settings.name, error
+                            );
+                        } else {
+                            debug!("DNS resolution of {} failed: {:#}", settings.name, error);
+                        }
+                        settings.min_refresh
+                    }
+                };
+            }
+        });
+
+        *self
+            .resolve_handle
+            .write()
+            .expect("resolve handle lock poisoned") = Some(handle);
+        info!(
+            "Started DNS provider resolving {:?} records for {}",
+            self.settings.record_type, self.settings.name
+        );
+        Ok(())
+    }
+
+    fn get_backends(&self) -> Vec<Host> {
+        self.backends.read().expect("backend lock poisoned").clone()
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.error.read().expect("error lock poisoned").is_none()
+            && self
+                .resolve_handle
+                .read()
+                .expect("resolve handle lock poisoned")
+                .as_ref()
+                .is_some_and(|handle| !handle.is_finished())
+    }
+
+    fn last_error(&self) -> Option<String> {
+        self.error.read().expect("error lock poisoned").clone()
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        if let Some(handle) = self
+            .resolve_handle
+            .write()
+            .expect("resolve handle lock poisoned")
+            .take()
+        {
+            handle.abort();
+            info!("Stopped DNS resolution of {}", self.settings.name);
+        }
+        Ok(())
+    }
+}
+
+/// Time until the next resolution: when the records expire, kept within
+/// the configured refresh bounds.
+fn refresh_delay(valid_until: Instant, now: Instant, settings: &DnsSettings) -> Duration {
+    valid_until
+        .saturating_duration_since(now)
+        .clamp(settings.min_refresh, settings.max_refresh)
+}
+
+async fn resolve(resolver: &TokioResolver, settings: &DnsSettings) -> Result<Resolution> {
+    let name = Name::from_utf8(&settings.name)
+        .with_context(|| format!("Invalid DNS name {}", settings.name))?;
+
+    match settings.record_type {
+        DnsRecordType::A => resolve_a(resolver, name, &settings.name).await,
+        DnsRecordType::Srv => resolve_srv(resolver, name).await,
+    }
+}
+
+async fn resolve_a(resolver: &TokioResolver, name: Name, host_name: &str) -> Result<Resolution> {
+    let lookup = match resolver.ipv4_lookup(name).await {
+        Ok(lookup) => lookup,
+        Err(error) if is_empty_answer(&error) => return Ok(Resolution::empty()),
+        Err(error) => return Err(error.into()),
+    };
+
+    Ok(Resolution {
+        hosts: lookup
+            .iter()
+            .map(|record| Host {
+                name: host_name.to_string(),
+                ip: IpAddr::V4(record.0),
+                weight: None,
+                priority: 0,
+                ports: BTreeMap::new(),
+            })
+            .collect(),
+        valid_until: lookup.valid_until(),
+    })
+}
+
+/// Resolve the targets of the lowest-priority SRV records. Higher
+/// priorities are only meant for clients that cannot reach those targets.
+/// Each target's port is published under the service name of the SRV
+/// owner, so a port mapping with that `port_name` reaches it.
+async fn resolve_srv(resolver: &TokioResolver, name: Name) -> Result<Resolution> {
+    let service = srv_service(&name);
+    let lookup = match resolver.srv_lookup(name).await {
+        Ok(lookup) => lookup,
+        Err(error) if is_empty_answer(&error) => return Ok(Resolution::empty()),
+        Err(error) => return Err(error.into()),
+    };
+    let mut valid_until = lookup.as_lookup().valid_until();
+
+    let Some(priority) = lookup.iter().map(|srv| srv.priority()).min() else {
+        return Ok(Resolution::empty());
+    };
+    // RFC 2782: a target of "." means the service is not available.
+    let records: Vec<_> = lookup
+        .iter()
+        .filter(|srv| srv.priority() == priority && !srv.target().is_root())
+        .collect();
+    let weights = relative_weights(
+        &records
+            .iter()
+            .map(|srv| u32::from(srv.weight()))
+            .collect::<Vec<_>>(),
+    );
+
+    let mut hosts = BTreeMap::new();
+    for (srv, weight) in records.into_iter().zip(weights) {
+        let target = srv.target();
+        let addresses = match resolver.ipv4_lookup(target.clone()).await {
+            Ok(addresses) => addresses,
+            Err(error) if is_empty_answer(&error) => {
+                warn!("SRV target {} has no IPv4 address, skipping", target);
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+        valid_until = valid_until.min(addresses.valid_until());
+
+        let host_name = target.to_utf8();
+        let ports: BTreeMap<String, u16> = service
+            .iter()
+            .map(|service| (service.clone(), srv.port()))
+            .collect();
+        for address in addresses.iter() {
+            let ip = IpAddr::V4(address.0);
+            hosts.entry(ip).or_insert_with(|| Host {
+                name: host_name.trim_end_matches('.').to_string(),
+                ip,
+                weight,
+                priority: 0,
+                ports: ports.clone(),
+            });
+        }
+    }
+
+    Ok(Resolution {
+        hosts: hosts.into_values().collect(),
+        valid_until,
+    })
+}
+
+/// Service of an RFC 2782 name such as `_http._tcp.example.com`: its first
+/// label without the underscore.
+fn srv_service(name: &Name) -> Option<String> {
+    let label = name.iter().next()?.strip_prefix(b"_")?;
+    let service = std::str::from_utf8(label).ok()?;
+    (!service.is_empty()).then(|| service.to_ascii_lowercase())
+}
+
+/// Whether the nameserver answered that the name has no such records.
+/// Failed queries such as SERVFAIL surface as the same error kind and
+/// must not empty the pool.
+fn is_empty_answer(error: &ResolveError) -> bool {
+    matches!(
+        error.proto().map(ProtoError::kind),
+        Some(ProtoErrorKind::NoRecordsFound {
+            response_code: ResponseCode::NoError | ResponseCode::NXDomain,
+            ..
+        })
+    )
+}
+
+impl Resolution {
+    /// An authoritative answer without records: the name has no backends.
+    fn empty() -> Self {
+        Self {
+            hosts: Vec::new(),
+            valid_until: Instant::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,345 @@
+use super::*;
+use hickory_resolver::proto::op::{Message, MessageType, ResponseCode};
+use hickory_resolver::proto::rr::rdata::{A, SRV};
+use hickory_resolver::proto::rr::{RData, Record, RecordType};
+use hickory_resolver::proto::serialize::binary::{BinDecodable, BinEncodable};
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::net::UdpSocket;
+use xlb_common::consts;
+
+type Zone = HashMap<(Name, RecordType), Vec<Record>>;
+
+/// Minimal UDP nameserver answering from an in-memory zone, or with
+/// SERVFAIL while failing.
+struct StubDns {
+    address: SocketAddr,
+    zone: Arc<Mutex<Zone>>,
+    failing: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl StubDns {
+    async fn start() -> Self {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind stub DNS");
+        let address = socket.local_addr().expect("stub DNS address");
+        let zone = Arc::new(Mutex::new(Zone::new()));
+        let failing = Arc::new(AtomicBool::new(false));
+
+        let answers = zone.clone();
+        let fail = failing.clone();
+        let handle = tokio::spawn(async move {
+            let mut buffer = [0u8; 512];
+            loop {
+                let Ok((len, peer)) = socket.recv_from(&mut buffer).await else {
+                    return;
+                };
+                let Ok(request) = Message::from_bytes(&buffer[..len]) else {
+                    continue;
+                };
+                let response = respond(&request, &answers.lock().unwrap(), &fail);
+                let _ = socket
+                    .send_to(&response.to_bytes().expect("encode response"), peer)
+                    .await;
+            }
+        });
+
+        Self {
+            address,
+            zone,
+            failing,
+            handle,
+        }
+    }
+
+    fn set(&self, name: &str, record_type: RecordType, records: Vec<Record>) {
+        self.zone
+            .lock()
+            .unwrap()
+            .insert((fqdn(name), record_type), records);
+    }
+
+    fn settings(&self, name: &str, record_type: DnsRecordType) -> DnsSettings {
+        DnsSettings {
+            name: name.to_string(),
+            record_type,
+            nameservers: vec![self.address],
+            min_refresh: Duration::from_millis(20),
+            max_refresh: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Drop for StubDns {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+fn respond(request: &Message, zone: &Zone, failing: &AtomicBool) -> Message {
+    let mut response = Message::new();
+    response
+        .set_id(request.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(request.op_code())
+        .set_recursion_desired(request.recursion_desired())
+        .set_recursion_available(true);
+
+    let Some(query) = request.query() else {
+        response.set_response_code(ResponseCode::FormErr);
+        return response;
+    };
+    response.add_query(query.clone());
+
+    if failing.load(Ordering::Relaxed) {
+        response.set_response_code(ResponseCode::ServFail);
+        return response;
+    }
+    match zone.get(&(query.name().to_lowercase(), query.query_type())) {
+        Some(records) => {
+            response.insert_answers(records.clone());
+        }
+        None => {
+            response.set_response_code(ResponseCode::NXDomain);
+        }
+    }
+    response
+}
+
+fn fqdn(name: &str) -> Name {
+    Name::from_utf8(name)
+        .expect("valid test name")
+        .to_lowercase()
+}
+
+fn a(name: &str, ttl: u32, ip: [u8; 4]) -> Record {
+    Record::from_rdata(fqdn(name), ttl, RData::A(A(Ipv4Addr::from(ip))))
+}
+
+fn srv(name: &str, priority: u16, weight: u16, target: &str) -> Record {
+    srv_on_port(name, priority, weight, 8080, target)
+}
+
+fn srv_on_port(name: &str, priority: u16, weight: u16, port: u16, target: &str) -> Record {
+    Record::from_rdata(
+        fqdn(name),
+        300,
+        RData::SRV(SRV::new(priority, weight, port, fqdn(target))),
+    )
+}
+
+fn addresses(hosts: &[Host]) -> Vec<IpAddr> {
+    let mut addresses: Vec<IpAddr> = hosts.iter().map(|host| host.ip).collect();
+    addresses.sort();
+    addresses
+}
+
+async fn wait_until(mut condition: impl FnMut() -> bool) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("condition reached within timeout");
+}
+
+#[tokio::test]
+async fn a_records_resolve_with_their_ttl() {
+    let dns = StubDns::start().await;
+    dns.set(
+        "backends.xlb.test.",
+        RecordType::A,
+        vec![
+            a("backends.xlb.test.", 30, [10, 0, 0, 2]),
+            a("backends.xlb.test.", 30, [10, 0, 0, 1]),
+        ],
+    );
+    let provider = DnsProvider::new(dns.settings("backends.xlb.test.", DnsRecordType::A));
+
+    let resolution = resolve(&provider.resolver().unwrap(), &provider.settings)
+        .await
+        .expect("A lookup succeeds");
+
+    assert_eq!(
+        addresses(&resolution.hosts),
+        vec![IpAddr::from([10, 0, 0, 1]), IpAddr::from([10, 0, 0, 2])]
+    );
+    assert!(resolution.hosts.iter().all(|host| host.weight.is_none()));
+    let ttl = resolution
+        .valid_until
+        .saturating_duration_since(Instant::now());
+    assert!(
+        ttl > Duration::from_secs(25) && ttl <= Duration::from_secs(30),
+        "{ttl:?}"
+    );
+}
+
+#[tokio::test]
+async fn srv_records_use_lowest_priority_targets_and_weights() {
+    let dns = StubDns::start().await;
+    let service = "_http._tcp.xlb.test.";
+    dns.set(
+        service,
+        RecordType::SRV,
+        vec![
+            srv(service, 10, 100, "heavy.xlb.test."),
+            srv(service, 10, 25, "light.xlb.test."),
+            srv(service, 20, 100, "standby.xlb.test."),
+        ],
+    );
+    dns.set(
+        "heavy.xlb.test.",
+        RecordType::A,
+        vec![a("heavy.xlb.test.", 60, [10, 0, 0, 1])],
+    );
+    dns.set(
+        "light.xlb.test.",
+        RecordType::A,
+        vec![a("light.xlb.test.", 5, [10, 0, 0, 2])],
+    );
+    dns.set(
+        "standby.xlb.test.",
+        RecordType::A,
+        vec![a("standby.xlb.test.", 60, [10, 0, 0, 3])],
+    );
+    let provider = DnsProvider::new(dns.settings(service, DnsRecordType::Srv));
+
+    let resolution = resolve(&provider.resolver().unwrap(), &provider.settings)
+        .await
+        .expect("SRV lookup succeeds");
+
+    let hosts: BTreeMap<IpAddr, &Host> = resolution
+        .hosts
+        .iter()
+        .map(|host| (host.ip, host))
+        .collect();
+    assert_eq!(hosts.len(), 2, "standby priority is not published");
+    let heavy = hosts[&IpAddr::from([10, 0, 0, 1])];
+    assert_eq!(heavy.name, "heavy.xlb.test");
+    assert_eq!(heavy.weight, Some(consts::MAX_BACKEND_WEIGHT));
+    assert_eq!(hosts[&IpAddr::from([10, 0, 0, 2])].weight, Some(250));
+    assert!(
+        resolution.valid_until <= Instant::now() + Duration::from_secs(5),
+        "the shortest TTL of the answer governs the refresh"
+    );
+}
+
+#[tokio::test]
+async fn srv_ports_become_each_backends_named_port() {
+    let dns = StubDns::start().await;
+    let service = "_HTTP._tcp.xlb.test.";
+    dns.set(
+        service,
+        RecordType::SRV,
+        vec![
+            srv_on_port(service, 10, 100, 8080, "one.xlb.test."),
+            srv_on_port(service, 10, 100, 9090, "two.xlb.test."),
+        ],
+    );
+    dns.set(
+        "one.xlb.test.",
+        RecordType::A,
+        vec![a("one.xlb.test.", 60, [10, 0, 0, 1])],
+    );
+    dns.set(
+        "two.xlb.test.",
+        RecordType::A,
+        vec![a("two.xlb.test.", 60, [10, 0, 0, 2])],
+    );
+    let provider = DnsProvider::new(dns.settings(service, DnsRecordType::Srv));
+
+    let resolution = resolve(&provider.resolver().unwrap(), &provider.settings)
+        .await
+        .expect("SRV lookup succeeds");
+
+    let ports: BTreeMap<IpAddr, BTreeMap<String, u16>> = resolution
+        .hosts
+        .into_iter()
+        .map(|host| (host.ip, host.ports))
+        .collect();
+    assert_eq!(
+        ports,
+        BTreeMap::from([
+            (
+                IpAddr::from([10, 0, 0, 1]),
+                BTreeMap::from([("http".to_string(), 8080)])
+            ),
+            (
+                IpAddr::from([10, 0, 0, 2]),
+                BTreeMap::from([("http".to_string(), 9090)])
+            ),
+        ])
+    );
+}
+
+#[test]
+fn srv_service_is_the_first_underscore_label() {
+    let service = |name: &str| srv_service(&Name::from_utf8(name).expect("valid test name"));
+    assert_eq!(service("_http._tcp.xlb.test."), Some("http".to_string()));
+    assert_eq!(service("backends.xlb.test."), None);
+    assert_eq!(service("_._tcp.xlb.test."), None);
+}
+
+#[tokio::test]
+async fn resolution_failure_keeps_last_good_backends() {
+    let dns = StubDns::start().await;
+    let name = "backends.xlb.test.";
+    dns.set(name, RecordType::A, vec![a(name, 0, [10, 0, 0, 1])]);
+    let provider = DnsProvider::new(dns.settings(name, DnsRecordType::A));
+    provider.start().await.expect("provider starts");
+    assert!(provider.is_healthy());
+
+    dns.failing.store(true, Ordering::Relaxed);
+    wait_until(|| !provider.is_healthy()).await;
+    assert!(provider.last_error().is_some());
+    assert_eq!(
+        addresses(&provider.get_backends()),
+        vec![IpAddr::from([10, 0, 0, 1])]
+    );
+
+    dns.set(name, RecordType::A, vec![a(name, 0, [10, 0, 0, 2])]);
+    dns.failing.store(false, Ordering::Relaxed);
+    wait_until(|| provider.is_healthy()).await;
+    assert_eq!(provider.last_error(), None);
+    wait_until(|| addresses(&provider.get_backends()) == vec![IpAddr::from([10, 0, 0, 2])]).await;
+
+    provider.shutdown().await.unwrap();
+    assert!(!provider.is_healthy());
+}
+
+#[tokio::test]
+async fn missing_name_is_an_empty_healthy_pool() {
+    let dns = StubDns::start().await;
+    let provider = DnsProvider::new(dns.settings("absent.xlb.test.", DnsRecordType::A));
+
+    provider.start().await.expect("NXDOMAIN is an answer");
+
+    assert!(provider.is_healthy());
+    assert!(provider.get_backends().is_empty());
+    provider.shutdown().await.unwrap();
+}
+
+#[test]
+fn refresh_follows_ttl_within_bounds() {
+    let settings = DnsSettings {
+        name: "backends.xlb.test.".into(),
+        record_type: DnsRecordType::A,
+        nameservers: Vec::new(),
+        min_refresh: Duration::from_secs(5),
+        max_refresh: Duration::from_secs(300),
+    };
+    let now = Instant::now();
+
+    assert_eq!(refresh_delay(now, now, &settings), Duration::from_secs(5));
+    assert_eq!(
+        refresh_delay(now + Duration::from_secs(60), now, &settings),
+        Duration::from_secs(60)
+    );
+    assert_eq!(
+        refresh_delay(now + Duration::from_secs(3600), now, &settings),
+        Duration::from_secs(300)
+    );
+}
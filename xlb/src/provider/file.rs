@@ -0,0 +1,353 @@
+use crate::config::Host;
+use crate::provider::{BackendProvider, replace_backends};
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use config::Config;
+use futures::StreamExt;
+use inotify::{Inotify, WatchMask};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tokio::task::JoinHandle;
+
+/// Directory events after which the backend file is read again. Watching
+/// the directory rather than the file follows editors and tools that
+/// replace the file by renaming a new one over it.
+const RELOAD_EVENTS: WatchMask = WatchMask::CLOSE_WRITE
+    .union(WatchMask::MOVED_TO)
+    .union(WatchMask::MOVED_FROM)
+    .union(WatchMask::DELETE);
+/// Symlink that mounted ConfigMaps and Secrets swap on every update, rather
+/// than replacing the file itself.
+const ATOMIC_WRITER_DATA: &str = "..data";
+
+#[derive(Debug, Deserialize)]
+struct BackendFile {
+    backends: Vec<Host>,
+}
+
+/// Reads backends from a YAML or JSON file and reloads it whenever it
+/// changes. A file that cannot be read or parsed leaves the previous
+/// backends in place and marks the provider unhealthy until it is fixed.
+pub struct FileProvider {
+    path: PathBuf,
+    /// Current backend list, replaced as a whole on each successful reload.
+    backends: Arc<RwLock<Vec<Host>>>,
+    /// Why the most recent reload failed, if it did.
+    error: Arc<RwLock<Option<String>>>,
+    /// Handle to the watch task.
+    watch_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
+}
+
+impl FileProvider {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            backends: Arc::new(RwLock::new(Vec::new())),
+            error: Arc::new(RwLock::new(None)),
+            watch_handle: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    fn publish_backends(next: Vec<Host>, backends: &RwLock<Vec<Host>>, path: &Path) {
+        let mut current = backends.write().expect("backend lock poisoned");
+        let changes = replace_backends(&mut current, next);
+        let total = current.len();
+
+        for host in changes.removed {
+            info!(
+                "Removed backend: {} ({}) from {} (total: {})",
+                host.name,
+                host.ip,
+                path.display(),
+                total
+            );
+        }
+        for host in changes.added {
+            info!(
+                "Added backend: {} ({}) from {} (total: {})",
+                host.name,
+                host.ip,
+                path.display(),
+                total
+            );
+        }
+    }
+
+    async fn reload(path: &Path, backends: &RwLock<Vec<Host>>, error: &RwLock<Option<String>>) {
+        let read_path = path.to_path_buf();
+        let read = tokio::task::spawn_blocking(move || read_backends(&read_path))
+            .await
+            .unwrap_or_else(|join_error| Err(anyhow!("Backend file read failed: {join_error}")));
+        match read {
+            Ok(hosts) => {
+                Self::publish_backends(hosts, backends, path);
+                if error.write().expect("error lock poisoned").take().is_some() {
+                    info!("Backend file {} is valid again", path.display());
+                }
+            }
+            Err(reload_error) => {
+                warn!(
+                    "Failed to reload backend file {}, keeping the previous backends: {:#}",
+                    path.display(),
+                    reload_error
+                );
+                *error.write().expect("error lock poisoned") = Some(format!("{reload_error:#}"));
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl BackendProvider for FileProvider {
+    async fn start(&self) -> Result<()> {
+        let hosts = read_backends(&self.path)?;
+        Self::publish_backends(hosts, &self.backends, &self.path);
+
+        let directory = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let inotify = Inotify::init().context("Failed to initialize inotify")?;
+        inotify
+            .watches()
+            .add(directory, RELOAD_EVENTS)
+            .with_context(|| format!("Failed to watch {}", directory.display()))?;
+        let mut events = inotify
+            .into_event_stream([0u8; 4096])
+            .context("Failed to read inotify events")?;
+
+        let path = self.path.clone();
+        let backends = self.backends.clone();
+        let error = self.error.clone();
+        let handle = tokio::spawn(async move {
+            while let Some(event) = events.next().await {
+                match event {
+                    Ok(event) if affects_backend_file(&path, event.name.as_deref()) => {
+                        debug!(
+                            "Backend directory event {:?} on {:?}",
+                            event.mask, event.name
+                        );
+                        Self::reload(&path, &backends, &error).await;
+                    }
+                    Ok(_) => {}
+                    Err(watch_error) => {
+                        warn!(
+                            "Backend file watch error for {}: {}",
+                            path.display(),
+                            watch_error
+                        );
+                    }
+                }
+            }
+
+            warn!(
+                "Backend file watch ended unexpectedly for {}",
+                path.display()
+            );
+        });
+
+        *self
+            .watch_handle
+            .write()
+            .expect("watch handle lock poisoned") = Some(handle);
+        info!("Started file provider watching {}", self.path.display());
+        Ok(())
+    }
+
+    fn get_backends(&self) -> Vec<Host> {
+        self.backends.read().expect("backend lock poisoned").clone()
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.error.read().expect("error lock poisoned").is_none()
+            && self
+                .watch_handle
+                .read()
+                .expect("watch handle lock poisoned")
+                .as_ref()
+                .is_some_and(|handle| !handle.is_finished())
+    }
+
+    fn last_error(&self) -> Option<String> {
+        self.error.read().expect("error lock poisoned").clone()
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        if let Some(handle) = self
+            .watch_handle
+            .write()
+            .expect("watch handle lock poisoned")
+            .take()
+        {
+            handle.abort();
+            info!("Stopped watching backend file {}", self.path.display());
+        }
+        Ok(())
+    }
+}
+
+/// Whether a directory entry event can change the backend file: an event on
+/// the file itself, or on the symlink a mounted ConfigMap swaps.
+fn affects_backend_file(path: &Path, name: Option<&OsStr>) -> bool {
+    name.is_some_and(|name| path.file_name() == Some(name) || name == ATOMIC_WRITER_DATA)
+}
+
+/// Parse the complete backend list; nothing is published unless the
+/// whole file is valid.
+fn read_backends(path: &Path) -> Result<Vec<Host>> {
+    let file: BackendFile = Config::builder()
+        .add_source(config::File::from(path))
+        .build()
+        .and_then(Config::try_deserialize)
+        .with_context(|| format!("Failed to parse backend file {}", path.display()))?;
+
+    if let Some(backend) = file.backends.iter().find(|backend| backend.ip.is_ipv6()) {
+        bail!(
+            "Unsupported IPv6 backend '{}' ({}) in {}: XLB currently supports only IPv4 backends",
+            backend.name,
+            backend.ip,
+            path.display()
+        );
+    }
+
+    Ok(file.backends)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::net::IpAddr;
+    use std::time::Duration;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("xlb-file-provider-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).expect("create test directory");
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn addresses(provider: &FileProvider) -> Vec<IpAddr> {
+        provider.get_backends().iter().map(|host| host.ip).collect()
+    }
+
+    async fn wait_until(mut condition: impl FnMut() -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !condition() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("condition reached within timeout");
+    }
+
+    #[test]
+    fn reads_yaml_and_json_backend_lists() {
+        let dir = TempDir::new("formats");
+        let yaml = dir.0.join("backends.yaml");
+        fs::write(&yaml, "backends:\n  - name: backend-1\n    ip: 10.0.0.1\n").unwrap();
+        let json = dir.0.join("backends.json");
+        fs::write(
+            &json,
+            r#"{"backends": [{"name": "backend-2", "ip": "10.0.0.2"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(read_backends(&yaml).unwrap()[0].name, "backend-1");
+        assert_eq!(
+            read_backends(&json).unwrap()[0].ip,
+            IpAddr::from([10, 0, 0, 2])
+        );
+    }
+
+    #[test]
+    fn rejects_ipv6_backends() {
+        let dir = TempDir::new("ipv6");
+        let path = dir.0.join("backends.yaml");
+        fs::write(&path, "backends:\n  - name: v6\n    ip: \"2001:db8::1\"\n").unwrap();
+
+        let error = read_backends(&path).expect_err("IPv6 must be rejected");
+        assert!(error.to_string().contains("Unsupported IPv6 backend 'v6'"));
+    }
+
+    #[tokio::test]
+    async fn reloads_on_change_and_keeps_backends_on_parse_error() {
+        let dir = TempDir::new("reload");
+        let path = dir.0.join("backends.yaml");
+        fs::write(&path, "backends:\n  - name: backend-1\n    ip: 10.0.0.1\n").unwrap();
+        let provider = FileProvider::new(path.clone());
+        provider.start().await.expect("provider starts");
+        assert!(provider.is_healthy());
+        assert_eq!(addresses(&provider), vec![IpAddr::from([10, 0, 0, 1])]);
+
+        fs::write(&path, "backends:\n  - name: broken\n    ip: not-an-ip\n").unwrap();
+        wait_until(|| !provider.is_healthy()).await;
+        assert_eq!(addresses(&provider), vec![IpAddr::from([10, 0, 0, 1])]);
+        assert!(
+            provider
+                .last_error()
+                .is_some_and(|error| error.contains("backends.yaml"))
+        );
+
+        // Replace the file atomically, as generators usually do.
+        let staged = dir.0.join("backends.yaml.tmp");
+        fs::write(
+            &staged,
+            "backends:\n  - name: backend-2\n    ip: 10.0.0.2\n",
+        )
+        .unwrap();
+        fs::rename(&staged, &path).unwrap();
+        wait_until(|| provider.is_healthy()).await;
+        assert_eq!(addresses(&provider), vec![IpAddr::from([10, 0, 0, 2])]);
+        assert_eq!(provider.last_error(), None);
+
+        provider.shutdown().await.unwrap();
+        assert!(!provider.is_healthy());
+    }
+
+    #[tokio::test]
+    async fn ignores_changes_to_other_files_in_the_directory() {
+        let dir = TempDir::new("unrelated");
+        let path = dir.0.join("backends.yaml");
+        fs::write(&path, "backends:\n  - name: backend-1\n    ip: 10.0.0.1\n").unwrap();
+        // Writes through a link elsewhere change the file without an event
+        // in the watched directory, so only a reload would notice them.
+        let elsewhere = TempDir::new("unrelated-link");
+        let link = elsewhere.0.join("backends.yaml");
+        fs::hard_link(&path, &link).unwrap();
+        let provider = FileProvider::new(path.clone());
+        provider.start().await.expect("provider starts");
+
+        fs::write(&link, "backends:\n  - name: backend-2\n    ip: 10.0.0.2\n").unwrap();
+        fs::write(dir.0.join("notes.txt"), "unrelated\n").unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(addresses(&provider), vec![IpAddr::from([10, 0, 0, 1])]);
+
+        fs::write(dir.0.join(ATOMIC_WRITER_DATA), "").unwrap();
+        wait_until(|| addresses(&provider) == vec![IpAddr::from([10, 0, 0, 2])]).await;
+        provider.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn start_fails_without_a_valid_file() {
+        let dir = TempDir::new("missing");
+        let provider = FileProvider::new(dir.0.join("backends.yaml"));
+
+        assert!(provider.start().await.is_err());
+        assert!(!provider.is_healthy());
+    }
+}
@@ -0,0 +1,635 @@
+use crate::config::{Host, HttpProviderConfig};
+use crate::provider::{BackendProvider, replace_backends};
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Longest time one poll of the remote URL may take.
+const POLL_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct HttpSettings {
+    pub state_path: PathBuf,
+    pub poll: Option<PollSettings>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PollSettings {
+    pub url: String,
+    pub interval: Duration,
+}
+
+impl From<&HttpProviderConfig> for HttpSettings {
+    fn from(config: &HttpProviderConfig) -> Self {
+        Self {
+            state_path: config.state_path.clone(),
+            poll: config.poll.as_ref().map(|poll| PollSettings {
+                url: poll.url.clone(),
+                interval: Duration::from_secs(poll.interval_secs),
+            }),
+        }
+    }
+}
+
+/// An accepted backend set and the generation that guards updates to it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackendSet {
+    pub generation: u64,
+    pub backends: Vec<Host>,
+}
+
+/// Document returned by a polled URL.
+#[derive(Debug, Deserialize)]
+struct BackendList {
+    backends: Vec<Host>,
+}
+
+#[derive(Debug)]
+pub enum BackendUpdateError {
+    /// The update was based on another generation than the current one.
+    Conflict { current: u64 },
+    /// The resulting backend set is not valid.
+    Invalid(String),
+    /// Backends are polled from a URL and cannot be pushed.
+    Polled,
+    /// The new set could not be persisted and was not applied.
+    Persist(anyhow::Error),
+}
+
+impl fmt::Display for BackendUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Conflict { current } => write!(f, "current generation is {current}"),
+            Self::Invalid(reason) => f.write_str(reason),
+            Self::Polled => f.write_str("backends are polled from a URL and cannot be pushed"),
+            Self::Persist(error) => write!(f, "failed to persist backends: {error:#}"),
+        }
+    }
+}
+
+/// Backend set shared between the HTTP provider and the admin API. Every
+/// accepted change is written to disk before it becomes visible.
+pub struct BackendStore {
+    path: PathBuf,
+    accepts_pushes: bool,
+    current: RwLock<BackendSet>,
+}
+
+impl BackendStore {
+    fn new(path: PathBuf, accepts_pushes: bool) -> Self {
+        Self {
+            path,
+            accepts_pushes,
+            current: RwLock::new(BackendSet::default()),
+        }
+    }
+
+    pub fn snapshot(&self) -> BackendSet {
+        self.current
+            .read()
+            .expect("backend set lock poisoned")
+            .clone()
+    }
+
+    /// Replace the whole set if `generation` is still current. Returns the
+    /// new generation.
+    pub fn replace(&self, generation: u64, backends: Vec<Host>) -> Result<u64, BackendUpdateError> {
+        self.update(generation, |_| backends)
+    }
+
+    /// Remove backends by address and add or replace others if
+    /// `generation` is still current. Returns the new generation.
+    pub fn patch(
+        &self,
+        generation: u64,
+        add: Vec<Host>,
+        remove: &[IpAddr],
+    ) -> Result<u64, BackendUpdateError> {
+        self.update(generation, |current| {
+            let replaced: HashSet<IpAddr> = add.iter().map(|host| host.ip).collect();
+            let mut next: Vec<Host> = current
+                .iter()
+                .filter(|host| !remove.contains(&host.ip) && !replaced.contains(&host.ip))
+                .cloned()
+                .collect();
+            next.extend(add);
+            next
+        })
+    }
+
+    fn update(
+        &self,
+        generation: u64,
+        next: impl FnOnce(&[Host]) -> Vec<Host>,
+    ) -> Result<u64, BackendUpdateError> {
+        if !self.accepts_pushes {
+            return Err(BackendUpdateError::Polled);
+        }
+        let mut current = self.current.write().expect("backend set lock poisoned");
+        if current.generation != generation {
+            return Err(BackendUpdateError::Conflict {
+                current: current.generation,
+            });
+        }
+        let backends = next(&current.backends);
+        validate_backends(&backends).map_err(BackendUpdateError::Invalid)?;
+
+        self.commit(&mut current, backends)
+            .map_err(BackendUpdateError::Persist)?;
+        Ok(current.generation)
+    }
+
+    /// Replace the set from a poll, keeping the generation when nothing
+    /// changed.
+    fn replace_polled(&self, backends: Vec<Host>) -> Result<()> {
+        validate_backends(&backends).map_err(|reason| anyhow!(reason))?;
+        let mut current = self.current.write().expect("backend set lock poisoned");
+        if current.backends == backends {
+            return Ok(());
+        }
+        self.commit(&mut current, backends)
+    }
+
+    fn commit(&self, current: &mut BackendSet, backends: Vec<Host>) -> Result<()> {
+        let next = BackendSet {
+            generation: current.generation + 1,
+            backends,
+        };
+        write_state(&self.path, &next)?;
+
+        let mut hosts = std::mem::take(&mut current.backends);
+        let changes = replace_backends(&mut hosts, next.backends);
+        *current = BackendSet {
+            generation: next.generation,
+            backends: hosts,
+        };
+        for host in changes.removed {
+            info!(
+                "Removed backend: {} ({}) at generation {} (total: {})",
+                host.name,
+                host.ip,
+                current.generation,
+                current.backends.len()
+            );
+        }
+        for host in changes.added {
+            info!(
+                "Added backend: {} ({}) at generation {} (total: {})",
+                host.name,
+                host.ip,
+                current.generation,
+                current.backends.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Restore the persisted set. Returns false if there is none yet.
+    fn restore(&self) -> Result<bool> {
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("Failed to read {}", self.path.display()));
+            }
+        };
+        let set: BackendSet = serde_json::from_slice(&contents)
+            .with_context(|| format!("Failed to parse {}", self.path.display()))?;
+        validate_backends(&set.backends)
+            .map_err(|reason| anyhow!("Invalid backends in {}: {reason}", self.path.display()))?;
+
+        info!(
+            "Restored {} backends at generation {} from {}",
+            set.backends.len(),
+            set.generation,
+            self.path.display()
+        );
+        *self.current.write().expect("backend set lock poisoned") = set;
+        Ok(true)
+    }
+}
+
+/// Accepts a backend set from an external control plane, either pushed
+/// through the admin API or polled from a URL. A failed poll keeps the
+/// current set and marks the provider unhealthy until a poll succeeds.
+pub struct HttpProvider {
+    settings: HttpSettings,
+    store: Arc<BackendStore>,
+    /// Why the most recent poll failed, if it did.
+    error: Arc<RwLock<Option<String>>>,
+    /// Handle to the poll task, when polling.
+    poll_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
+}
+
+impl HttpProvider {
+    pub fn new(settings: HttpSettings) -> Self {
+        let store = BackendStore::new(settings.state_path.clone(), settings.poll.is_none());
+        Self {
+            settings,
+            store: Arc::new(store),
+            error: Arc::new(RwLock::new(None)),
+            poll_handle: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// The backend set updated through the admin API.
+    pub fn store(&self) -> Arc<BackendStore> {
+        self.store.clone()
+    }
+
+    fn record_poll(result: Result<()>, url: &str, error: &RwLock<Option<String>>) {
+        match result {
+            Ok(()) => {
+                if error.write().expect("error lock poisoned").take().is_some() {
+                    info!("Polling backends from {url} recovered");
+                }
+            }
+            Err(poll_error) => {
+                let message = format!("{poll_error:#}");
+                let previous = error.write().expect("error lock poisoned").replace(message);
+                if previous.is_none() {
+                    warn!(
+                        "Polling backends from {url} failed, keeping the current backends: {poll_error:#}"
+                    );
+                } else {
+                    debug!("Polling backends from {url} failed: {poll_error:#}");
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl BackendProvider for HttpProvider {
+    async fn start(&self) -> Result<()> {
+        let restored = self.store.restore()?;
+        let Some(poll) = self.settings.poll.clone() else {
+            info!(
+                "Started HTTP provider accepting backends through the admin API, persisted to {}",
+                self.settings.state_path.display()
+            );
+            return Ok(());
+        };
+
+        let client = Client::builder()
+            .timeout(POLL_TIMEOUT)
+            .build()
+            .context("Failed to build HTTP provider client")?;
+        match fetch(&client, &poll.url).await {
+            Ok(backends) => self.store.replace_polled(backends)?,
+            // The persisted set stands in until the URL can be reached.
+            Err(error) if restored => Self::record_poll(Err(error), &poll.url, &self.error),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("Failed to poll backends from {}", poll.url));
+            }
+        }
+
+        info!(
+            "Started HTTP provider polling {} every {}s",
+            poll.url,
+            poll.interval.as_secs()
+        );
+        let store = self.store.clone();
+        let error = self.error.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(poll.interval).await;
+                let result = match fetch(&client, &poll.url).await {
+                    Ok(backends) => store.replace_polled(backends),
+                    Err(error) => Err(error),
+                };
+                Self::record_poll(result, &poll.url, &error);
+            }
+        });
+
+        *self.poll_handle.write().expect("poll handle lock poisoned") = Some(handle);
+        Ok(())
+    }
+
+    fn get_backends(&self) -> Vec<Host> {
+        self.store.snapshot().backends
+    }
+
+    fn is_healthy(&self) -> bool {
+        if self.settings.poll.is_none() {
+            return true;
+        }
+        self.error.read().expect("error lock poisoned").is_none()
+            && self
+                .poll_handle
+                .read()
+                .expect("poll handle lock poisoned")
+                .as_ref()
+                .is_some_and(|handle| !handle.is_finished())
+    }
+
+    fn last_error(&self) -> Option<String> {
+        self.error.read().expect("error lock poisoned").clone()
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        if let Some(handle) = self
+            .poll_handle
+            .write()
+            .expect("poll handle lock poisoned")
+            .take()
+        {
+            handle.abort();
+            info!("Stopped polling backends");
+        }
+        Ok(())
+    }
+}
+
+async fn fetch(client: &Client, url: &str) -> Result<Vec<Host>> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .context("Backend poll request failed")?
+        .error_for_status()
+        .context("Backend poll was rejected")?;
+    let list: BackendList = response
+        .json()
+        .await
+        .context("Failed to parse polled backends")?;
+    Ok(list.backends)
+}
+
+fn validate_backends(backends: &[Host]) -> Result<(), String> {
+    let mut addresses = HashSet::new();
+    for backend in backends {
+        if backend.ip.is_ipv6() {
+            return Err(format!(
+                "Unsupported IPv6 backend '{}' ({}): XLB currently supports only IPv4 backends",
+                backend.name, backend.ip
+            ));
+        }
+        if !addresses.insert(backend.ip) {
+            return Err(format!("Duplicate backend address {}", backend.ip));
+        }
+    }
+    Ok(())
+}
+
+/// Write the set next to its final path and rename it into place, so a
+/// crash never leaves a partially written file behind.
+fn write_state(path: &Path, set: &BackendSet) -> Result<()> {
+    let Some(file_name) = path.file_name() else {
+        bail!(
+            "HTTP provider state path {} has no file name",
+            path.display()
+        );
+    };
+    let mut staged = file_name.to_owned();
+    staged.push(".tmp");
+    let staged = path.with_file_name(staged);
+
+    let contents = serde_json::to_vec_pretty(set).context("Failed to encode backends")?;
+    fs::write(&staged, contents)
+        .with_context(|| format!("Failed to write {}", staged.display()))?;
+    fs::rename(&staged, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::State;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use serde_json::{Value, json};
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("xlb-http-provider-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).expect("create test directory");
+            Self(path)
+        }
+
+        fn state_path(&self) -> PathBuf {
+            self.0.join("backends.json")
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn host(name: &str, ip: [u8; 4]) -> Host {
+        Host {
+            name: name.into(),
+            ip: IpAddr::from(ip),
+            weight: None,
+            priority: 0,
+            ports: BTreeMap::new(),
+        }
+    }
+
+    fn addresses(provider: &HttpProvider) -> Vec<IpAddr> {
+        provider.get_backends().iter().map(|host| host.ip).collect()
+    }
+
+    fn push_provider(dir: &TempDir) -> HttpProvider {
+        HttpProvider::new(HttpSettings {
+            state_path: dir.state_path(),
+            poll: None,
+        })
+    }
+
+    async fn serve(backends: Arc<Mutex<Value>>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind backend source");
+        let address = listener.local_addr().expect("backend source address");
+        let app = Router::new()
+            .route(
+                "/backends",
+                get(|State(backends): State<Arc<Mutex<Value>>>| async move {
+                    Json(backends.lock().unwrap().clone())
+                }),
+            )
+            .with_state(backends);
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        format!("http://{address}/backends")
+    }
+
+    async fn wait_until(mut condition: impl FnMut() -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !condition() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("condition reached within timeout");
+    }
+
+    #[tokio::test]
+    async fn pushes_require_the_current_generation() {
+        let dir = TempDir::new("generation");
+        let provider = push_provider(&dir);
+        provider
+            .start()
+            .await
+            .expect("provider starts without state");
+        let store = provider.store();
+        assert_eq!(store.snapshot(), BackendSet::default());
+
+        let generation = store
+            .replace(0, vec![host("a", [10, 0, 0, 1]), host("b", [10, 0, 0, 2])])
+            .expect("replace at the current generation");
+        assert_eq!(generation, 1);
+        assert!(matches!(
+            store.replace(0, Vec::new()),
+            Err(BackendUpdateError::Conflict { current: 1 })
+        ));
+
+        let generation = store
+            .patch(
+                1,
+                vec![host("b2", [10, 0, 0, 2]), host("c", [10, 0, 0, 3])],
+                &[IpAddr::from([10, 0, 0, 1])],
+            )
+            .expect("patch at the current generation");
+        assert_eq!(generation, 2);
+        let backends = provider.get_backends();
+        assert_eq!(
+            addresses(&provider),
+            vec![IpAddr::from([10, 0, 0, 2]), IpAddr::from([10, 0, 0, 3])]
+        );
+        assert_eq!(backends[0].name, "b2");
+        assert!(provider.is_healthy());
+    }
+
+    #[tokio::test]
+    async fn invalid_pushes_leave_the_set_unchanged() {
+        let dir = TempDir::new("invalid");
+        let provider = push_provider(&dir);
+        provider.start().await.expect("provider starts");
+        let store = provider.store();
+
+        let duplicate = store.replace(0, vec![host("a", [10, 0, 0, 1]), host("b", [10, 0, 0, 1])]);
+        assert!(
+            matches!(duplicate, Err(BackendUpdateError::Invalid(reason)) if reason.contains("Duplicate"))
+        );
+        let ipv6 = store.patch(
+            0,
+            vec![Host {
+                name: "v6".into(),
+                ip: "2001:db8::1".parse().unwrap(),
+                weight: None,
+                priority: 0,
+                ports: BTreeMap::new(),
+            }],
+            &[],
+        );
+        assert!(
+            matches!(ipv6, Err(BackendUpdateError::Invalid(reason)) if reason.contains("IPv6"))
+        );
+
+        assert_eq!(store.snapshot(), BackendSet::default());
+        assert!(!dir.state_path().exists());
+    }
+
+    #[tokio::test]
+    async fn restart_restores_the_persisted_set() {
+        let dir = TempDir::new("restore");
+        let provider = push_provider(&dir);
+        provider.start().await.expect("provider starts");
+        provider
+            .store()
+            .replace(0, vec![host("a", [10, 0, 0, 1])])
+            .expect("replace");
+
+        let restarted = push_provider(&dir);
+        restarted.start().await.expect("provider restarts");
+
+        assert_eq!(restarted.store().snapshot(), provider.store().snapshot());
+        assert_eq!(addresses(&restarted), vec![IpAddr::from([10, 0, 0, 1])]);
+    }
+
+    #[tokio::test]
+    async fn polling_replaces_the_set_and_rejects_pushes() {
+        let dir = TempDir::new("poll");
+        let source = Arc::new(Mutex::new(
+            json!({ "backends": [{ "name": "a", "ip": "10.0.0.1" }] }),
+        ));
+        let url = serve(source.clone()).await;
+        let provider = HttpProvider::new(HttpSettings {
+            state_path: dir.state_path(),
+            poll: Some(PollSettings {
+                url,
+                interval: Duration::from_millis(20),
+            }),
+        });
+        provider.start().await.expect("provider starts");
+        assert_eq!(addresses(&provider), vec![IpAddr::from([10, 0, 0, 1])]);
+        assert!(matches!(
+            provider.store().replace(1, Vec::new()),
+            Err(BackendUpdateError::Polled)
+        ));
+
+        *source.lock().unwrap() = json!({ "backends": [{ "name": "b", "ip": "10.0.0.2" }] });
+        wait_until(|| addresses(&provider) == vec![IpAddr::from([10, 0, 0, 2])]).await;
+        assert_eq!(provider.store().snapshot().generation, 2);
+
+        *source.lock().unwrap() = json!({ "backends": "not a list" });
+        wait_until(|| !provider.is_healthy()).await;
+        assert!(provider.last_error().is_some());
+        assert_eq!(addresses(&provider), vec![IpAddr::from([10, 0, 0, 2])]);
+
+        provider.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn unreachable_poll_url_starts_from_the_persisted_set() {
+        let dir = TempDir::new("poll-restore");
+        write_state(
+            &dir.state_path(),
+            &BackendSet {
+                generation: 4,
+                backends: vec![host("a", [10, 0, 0, 1])],
+            },
+        )
+        .unwrap();
+        let settings = HttpSettings {
+            state_path: dir.state_path(),
+            poll: Some(PollSettings {
+                url: "http://127.0.0.1:1/backends".into(),
+                interval: Duration::from_secs(60),
+            }),
+        };
+
+        let provider = HttpProvider::new(settings.clone());
+        provider.start().await.expect("persisted set stands in");
+        assert!(!provider.is_healthy());
+        assert_eq!(addresses(&provider), vec![IpAddr::from([10, 0, 0, 1])]);
+        provider.shutdown().await.unwrap();
+
+        fs::remove_file(dir.state_path()).unwrap();
+        let provider = HttpProvider::new(settings);
+        assert!(provider.start().await.is_err());
+    }
+}
@@ -1,63 +1,86 @@
 mod endpoints;
+mod load_balancer;
 
-use self::endpoints::{EndpointSliceState, SliceApply, replace_backends};
-use crate::config::Host;
-use crate::provider::BackendProvider;
-use anyhow::{Context, Result, bail};
+use self::endpoints::{EndpointSliceState, SliceApply, TopologyPolicy};
+pub use self::load_balancer::*;
+use crate::config::{Host, KubernetesTopologyConfig, KubernetesWatch, TopologyPreference};
+use crate::provider::{
+    BackendProvider, TopologyStatus, WatchStatus, ZoneBackends, replace_backends,
+};
+use anyhow::{Context, Result, anyhow, bail};
 use async_trait::async_trait;
 use futures::StreamExt;
-use k8s_openapi::api::core::v1::Service;
+use k8s_openapi::api::core::v1::{Node, Service};
 use k8s_openapi::api::discovery::v1::EndpointSlice;
 use kube::runtime::WatchStreamExt;
 use kube::runtime::watcher::{self, Event};
 use kube::{Api, Client};
 use log::{debug, info, warn};
+use std::collections::{BTreeMap, HashSet};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
+use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
 
 const INITIAL_SYNC_TIMEOUT: Duration = Duration::from_secs(30);
 const SERVICE_NAME_LABEL: &str = "kubernetes.io/service-name";
+const ZONE_LABEL: &str = "topology.kubernetes.io/zone";
+/// Set from `spec.nodeName` through the downward API.
+const NODE_NAME_ENV: &str = "NODE_NAME";
 
+/// What one watch task last published.
+#[derive(Debug, Default)]
+struct WatchState {
+    backends: Vec<Host>,
+    /// Locality of `backends`.
+    topology: Option<TopologyStatus>,
+    /// The most recent watch error, cleared by the next event.
+    error: Option<String>,
+}
+
+/// Follows the EndpointSlices of one or more Services, each with its own
+/// watch, and merges their eligible endpoints into one pool.
 pub struct KubernetesProvider {
-    namespace: String,
-    service: String,
-    /// Current backend list, updated by the EndpointSlice watcher.
-    backends: Arc<RwLock<Vec<Host>>>,
-    /// Handle to the watch task.
-    watch_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
+    watches: Vec<KubernetesWatch>,
+    topology_config: KubernetesTopologyConfig,
+    /// Published state of each watch, in `watches` order.
+    states: Arc<RwLock<Vec<WatchState>>>,
+    /// Handles to the watch tasks, in `watches` order.
+    watch_handles: RwLock<Vec<JoinHandle<()>>>,
 }
 
 impl KubernetesProvider {
-    pub fn new(namespace: String, service: String) -> Self {
+    pub fn new(watches: Vec<KubernetesWatch>, topology: KubernetesTopologyConfig) -> Self {
+        let states = watches.iter().map(|_| WatchState::default()).collect();
         Self {
-            namespace,
-            service,
-            backends: Arc::new(RwLock::new(Vec::new())),
-            watch_handle: Arc::new(RwLock::new(None)),
+            watches,
+            topology_config: topology,
+            states: Arc::new(RwLock::new(states)),
+            watch_handles: RwLock::new(Vec::new()),
         }
     }
 
     fn publish_backends(
         next: Vec<Host>,
-        backends: &RwLock<Vec<Host>>,
-        namespace: &str,
-        service: &str,
+        states: &RwLock<Vec<WatchState>>,
+        index: usize,
+        watch: &KubernetesWatch,
     ) {
-        let mut current = backends.write().expect("backend lock poisoned");
-        let changes = replace_backends(&mut current, next);
+        let mut states = states.write().expect("watch state lock poisoned");
+        let current = &mut states[index].backends;
+        let changes = replace_backends(current, next);
         let total = current.len();
 
         for host in changes.removed {
             info!(
-                "Removed backend: {} ({}) for service {}/{} (no longer eligible, total: {})",
-                host.name, host.ip, namespace, service, total
+                "Removed backend: {} ({}) for {} (no longer eligible, total: {})",
+                host.name, host.ip, watch, total
             );
         }
         for host in changes.added {
             info!(
-                "Added backend: {} ({}) for service {}/{} (total: {})",
-                host.name, host.ip, namespace, service, total
+                "Added backend: {} ({}) for {} (total: {})",
+                host.name, host.ip, watch, total
             );
         }
     }
@@ -65,188 +88,448 @@ impl KubernetesProvider {
     fn apply_slice(
         state: &mut EndpointSliceState,
         slice: &EndpointSlice,
-        backends: &RwLock<Vec<Host>>,
-        namespace: &str,
-        service: &str,
+        states: &RwLock<Vec<WatchState>>,
+        index: usize,
+        watch: &KubernetesWatch,
     ) {
         match state.apply(slice) {
-            SliceApply::Unnamed => warn!(
-                "Ignoring unnamed EndpointSlice for service {}/{}",
-                namespace, service
-            ),
+            SliceApply::Unnamed => warn!("Ignoring unnamed EndpointSlice for {watch}"),
             SliceApply::Deferred => {}
             SliceApply::Publish(next) => {
-                Self::publish_backends(next, backends, namespace, service);
+                Self::publish_backends(next, states, index, watch);
             }
         }
     }
-}
 
-#[async_trait]
-impl BackendProvider for KubernetesProvider {
-    async fn start(&self) -> Result<()> {
-        let client = Client::try_default().await.context(
-            "Failed to create Kubernetes client - ensure running in cluster or kubeconfig is set",
-        )?;
+    /// Resolve the node and zone this instance runs in, as far as the
+    /// configured preference needs them.
+    async fn topology_policy(&self, client: &Client) -> Result<TopologyPolicy> {
+        let config = &self.topology_config;
+        let node = std::env::var(NODE_NAME_ENV)
+            .ok()
+            .filter(|node| !node.is_empty());
+        let mut zone = config.zone.clone();
 
-        // Validate the configured Service and make the stricter XLB policy
-        // explicit when Kubernetes is configured to publish not-ready peers.
-        let service_api: Api<Service> = Api::namespaced(client.clone(), &self.namespace);
-        let service = service_api.get(&self.service).await.context(format!(
-            "Failed to get service {}/{}",
-            self.namespace, self.service
-        ))?;
-        let publishes_not_ready = service
-            .spec
-            .as_ref()
-            .and_then(|spec| spec.publish_not_ready_addresses)
-            .unwrap_or(false);
-        if publishes_not_ready {
+        match config.prefer {
+            TopologyPreference::None => {}
+            TopologyPreference::Node if node.is_none() => bail!(
+                "Preferring endpoints on the local node requires the {NODE_NAME_ENV} environment variable"
+            ),
+            TopologyPreference::Node => {}
+            TopologyPreference::Zone if zone.is_some() => {}
+            TopologyPreference::Zone => {
+                let Some(node) = node.as_deref() else {
+                    bail!(
+                        "Preferring endpoints in the local zone requires topology.zone or the {NODE_NAME_ENV} environment variable"
+                    );
+                };
+                let nodes: Api<Node> = Api::all(client.clone());
+                let labels = nodes
+                    .get(node)
+                    .await
+                    .with_context(|| format!("Failed to get node {node} to find its zone"))?
+                    .metadata
+                    .labels
+                    .unwrap_or_default();
+                let Some(label) = labels.get(ZONE_LABEL) else {
+                    bail!("Node {node} has no {ZONE_LABEL} label; set topology.zone instead");
+                };
+                zone = Some(label.clone());
+            }
+        }
+
+        if config.prefer != TopologyPreference::None {
             info!(
-                "Service {}/{} publishes not-ready addresses; XLB will still require ready, serving, non-terminating endpoints for new flows",
-                self.namespace, self.service
+                "Preferring endpoints by {:?} (zone: {}, node: {}, min local: {})",
+                config.prefer,
+                zone.as_deref().unwrap_or("unknown"),
+                node.as_deref().unwrap_or("unknown"),
+                config.min_local_backends
             );
         }
+        Ok(TopologyPolicy {
+            prefer: config.prefer,
+            min_local: config.min_local_backends as usize,
+            hints: config.hints,
+            zone,
+            node,
+        })
+    }
 
-        let label_selector = format!("{SERVICE_NAME_LABEL}={}", self.service);
-        info!(
-            "Watching EndpointSlices with selector '{}' for service {}/{}",
-            label_selector, self.namespace, self.service
-        );
+    /// Label selector for the EndpointSlices of a watch. A Service watch
+    /// also validates the Service, and makes the stricter XLB policy
+    /// explicit when it publishes not-ready peers.
+    async fn slice_selector(client: &Client, watch: &KubernetesWatch) -> Result<String> {
+        match watch {
+            KubernetesWatch::Service { namespace, service } => {
+                let service_api: Api<Service> = Api::namespaced(client.clone(), namespace);
+                let resource = service_api
+                    .get(service)
+                    .await
+                    .with_context(|| format!("Failed to get service {namespace}/{service}"))?;
+                let publishes_not_ready = resource
+                    .spec
+                    .as_ref()
+                    .and_then(|spec| spec.publish_not_ready_addresses)
+                    .unwrap_or(false);
+                if publishes_not_ready {
+                    info!(
+                        "Service {namespace}/{service} publishes not-ready addresses; XLB will still require ready, serving, non-terminating endpoints for new flows"
+                    );
+                }
+                Ok(format!("{SERVICE_NAME_LABEL}={service}"))
+            }
+            KubernetesWatch::Selector { labels, .. } => Ok(labels.clone()),
+        }
+    }
 
-        let slices_api: Api<EndpointSlice> = Api::namespaced(client, &self.namespace);
-        let backends = self.backends.clone();
-        let namespace = self.namespace.clone();
-        let service = self.service.clone();
-        let (initial_sync_tx, initial_sync_rx) = tokio::sync::oneshot::channel();
+    fn spawn_watch(
+        client: Client,
+        watch: KubernetesWatch,
+        index: usize,
+        label_selector: String,
+        policy: TopologyPolicy,
+        states: Arc<RwLock<Vec<WatchState>>>,
+        initial_sync_tx: oneshot::Sender<()>,
+    ) -> JoinHandle<()> {
+        let slices_api: Api<EndpointSlice> = Api::namespaced(client, watch.namespace());
 
-        let handle = tokio::spawn(async move {
-            let watch = watcher::watcher(
+        tokio::spawn(async move {
+            let stream = watcher::watcher(
                 slices_api,
                 watcher::Config::default().labels(&label_selector),
             )
             .default_backoff();
-            futures::pin_mut!(watch);
+            futures::pin_mut!(stream);
 
-            let mut state = EndpointSliceState::default();
+            let mut state = EndpointSliceState::new(policy);
             let mut initial_sync_tx = Some(initial_sync_tx);
 
-            while let Some(event) = watch.next().await {
-                match event {
-                    Ok(Event::Apply(slice)) => {
-                        Self::apply_slice(&mut state, &slice, &backends, &namespace, &service);
+            while let Some(event) = stream.next().await {
+                let error = match event {
+                    Ok(Event::Apply(slice)) | Ok(Event::InitApply(slice)) => {
+                        Self::apply_slice(&mut state, &slice, &states, index, &watch);
+                        None
                     }
                     Ok(Event::Init) => {
-                        debug!(
-                            "EndpointSlice watch sync starting for service {}/{}",
-                            namespace, service
-                        );
+                        debug!("EndpointSlice watch sync starting for {watch}");
                         state.begin_sync();
-                    }
-                    Ok(Event::InitApply(slice)) => {
-                        Self::apply_slice(&mut state, &slice, &backends, &namespace, &service);
+                        None
                     }
                     Ok(Event::Delete(slice)) => {
                         if let Some(name) = slice.metadata.name.as_deref()
                             && let Some(next) = state.remove(name)
                         {
-                            Self::publish_backends(next, &backends, &namespace, &service);
+                            Self::publish_backends(next, &states, index, &watch);
                         }
+                        None
                     }
                     Ok(Event::InitDone) => {
                         if let Some(completion) = state.finish_sync() {
                             if completion.stale_slices > 0 {
                                 debug!(
-                                    "Removed {} stale EndpointSlice(s) after sync for service {}/{}",
-                                    completion.stale_slices, namespace, service
+                                    "Removed {} stale EndpointSlice(s) after sync for {}",
+                                    completion.stale_slices, watch
                                 );
                             }
-                            Self::publish_backends(
-                                completion.backends,
-                                &backends,
-                                &namespace,
-                                &service,
-                            );
+                            Self::publish_backends(completion.backends, &states, index, &watch);
                         }
-                        debug!(
-                            "EndpointSlice watch sync complete for service {}/{}",
-                            namespace, service
-                        );
+                        debug!("EndpointSlice watch sync complete for {watch}");
                         if let Some(tx) = initial_sync_tx.take() {
                             let _ = tx.send(());
                         }
+                        None
                     }
                     Err(error) => {
-                        warn!(
-                            "EndpointSlice watch error for service {}/{}: {}",
-                            namespace, service, error
-                        );
+                        warn!("EndpointSlice watch error for {watch}: {error}");
+                        Some(error.to_string())
                     }
+                };
+
+                let mut states = states.write().expect("watch state lock poisoned");
+                let published = &mut states[index];
+                if published.error.is_some() && error.is_none() {
+                    info!("EndpointSlice watch for {watch} recovered");
                 }
+                published.error = error;
+                published.topology = state.topology().cloned();
             }
 
-            warn!(
-                "EndpointSlice watch ended unexpectedly for service {}/{}",
-                namespace, service
-            );
-        });
-
-        match tokio::time::timeout(INITIAL_SYNC_TIMEOUT, initial_sync_rx).await {
-            Ok(Ok(())) => {}
-            Ok(Err(_)) => {
-                handle.abort();
-                bail!(
-                    "EndpointSlice watch ended before initial sync for service {}/{}",
-                    self.namespace,
-                    self.service
-                );
-            }
-            Err(_) => {
-                handle.abort();
-                bail!(
-                    "Timed out waiting for initial EndpointSlice sync for service {}/{}",
-                    self.namespace,
-                    self.service
-                );
+            warn!("EndpointSlice watch ended unexpectedly for {watch}");
+        })
+    }
+
+    /// Start the watches with an existing client.
+    pub(crate) async fn start_with_client(&self, client: Client) -> Result<()> {
+        let policy = self.topology_policy(&client).await?;
+
+        let mut handles = Vec::new();
+        let mut initial_syncs = Vec::new();
+        for (index, watch) in self.watches.iter().enumerate() {
+            let label_selector = match Self::slice_selector(&client, watch).await {
+                Ok(label_selector) => label_selector,
+                Err(error) => {
+                    Self::abort_watches(&handles);
+                    return Err(error);
+                }
+            };
+            info!("Watching EndpointSlices with selector '{label_selector}' for {watch}");
+
+            let (initial_sync_tx, initial_sync_rx) = oneshot::channel();
+            handles.push(Self::spawn_watch(
+                client.clone(),
+                watch.clone(),
+                index,
+                label_selector,
+                policy.clone(),
+                self.states.clone(),
+                initial_sync_tx,
+            ));
+            initial_syncs.push((watch, initial_sync_rx));
+        }
+
+        let deadline = tokio::time::Instant::now() + INITIAL_SYNC_TIMEOUT;
+        for (watch, initial_sync_rx) in initial_syncs {
+            let synced = match tokio::time::timeout_at(deadline, initial_sync_rx).await {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(_)) => Err(anyhow!(
+                    "EndpointSlice watch ended before initial sync for {watch}"
+                )),
+                Err(_) => Err(anyhow!(
+                    "Timed out waiting for initial EndpointSlice sync for {watch}"
+                )),
+            };
+            if let Err(error) = synced {
+                Self::abort_watches(&handles);
+                return Err(error);
             }
         }
 
         *self
-            .watch_handle
+            .watch_handles
             .write()
-            .expect("watch handle lock poisoned") = Some(handle);
+            .expect("watch handle lock poisoned") = handles;
         info!(
-            "Started Kubernetes provider watching EndpointSlices for {}/{}",
-            self.namespace, self.service
+            "Started Kubernetes provider watching EndpointSlices for {} watch(es)",
+            self.watches.len()
         );
         Ok(())
     }
 
+    fn abort_watches(handles: &[JoinHandle<()>]) {
+        for handle in handles {
+            handle.abort();
+        }
+    }
+}
+
+#[async_trait]
+impl BackendProvider for KubernetesProvider {
+    async fn start(&self) -> Result<()> {
+        let client = Client::try_default().await.context(
+            "Failed to create Kubernetes client - ensure running in cluster or kubeconfig is set",
+        )?;
+        self.start_with_client(client).await
+    }
+
     fn get_backends(&self) -> Vec<Host> {
-        self.backends.read().expect("backend lock poisoned").clone()
+        // A Pod selected by several watches is published once, by the
+        // first watch in configuration order.
+        let states = self.states.read().expect("watch state lock poisoned");
+        let mut seen = HashSet::new();
+        states
+            .iter()
+            .flat_map(|state| state.backends.iter())
+            .filter(|host| seen.insert(host.ip))
+            .cloned()
+            .collect()
+    }
+
+    fn topology(&self) -> Option<TopologyStatus> {
+        let states = self.states.read().expect("watch state lock poisoned");
+        merge_topology(states.iter().filter_map(|state| state.topology.as_ref()))
+    }
+
+    fn watches(&self) -> Vec<WatchStatus> {
+        let handles = self
+            .watch_handles
+            .read()
+            .expect("watch handle lock poisoned");
+        let states = self.states.read().expect("watch state lock poisoned");
+        self.watches
+            .iter()
+            .zip(states.iter())
+            .enumerate()
+            .map(|(index, (watch, state))| WatchStatus {
+                name: watch.to_string(),
+                healthy: handles
+                    .get(index)
+                    .is_some_and(|handle| !handle.is_finished()),
+                backends: state.backends.len(),
+                last_error: state.error.clone(),
+            })
+            .collect()
     }
 
     fn is_healthy(&self) -> bool {
-        self.watch_handle
+        let handles = self
+            .watch_handles
             .read()
-            .expect("watch handle lock poisoned")
-            .as_ref()
-            .is_some_and(|handle| !handle.is_finished())
+            .expect("watch handle lock poisoned");
+        !handles.is_empty() && handles.iter().all(|handle| !handle.is_finished())
+    }
+
+    fn last_error(&self) -> Option<String> {
+        let errors: Vec<String> = self
+            .watches
+            .iter()
+            .zip(
+                self.states
+                    .read()
+                    .expect("watch state lock poisoned")
+                    .iter(),
+            )
+            .filter_map(|(watch, state)| {
+                state
+                    .error
+                    .as_ref()
+                    .map(|error| format!("{watch}: {error}"))
+            })
+            .collect();
+        (!errors.is_empty()).then(|| errors.join("; "))
     }
 
     async fn shutdown(&self) -> Result<()> {
-        if let Some(handle) = self
-            .watch_handle
-            .write()
-            .expect("watch handle lock poisoned")
-            .take()
-        {
-            handle.abort();
+        let handles = std::mem::take(
+            &mut *self
+                .watch_handles
+                .write()
+                .expect("watch handle lock poisoned"),
+        );
+        if !handles.is_empty() {
+            Self::abort_watches(&handles);
             info!(
-                "Stopped Kubernetes EndpointSlice watch for {}/{}",
-                self.namespace, self.service
+                "Stopped {} Kubernetes EndpointSlice watch(es)",
+                handles.len()
             );
         }
         Ok(())
     }
 }
+
+/// Combine the locality of several watches: zone counts add up, and hints
+/// or spill-over count when any watch used them.
+fn merge_topology<'a>(
+    statuses: impl Iterator<Item = &'a TopologyStatus>,
+) -> Option<TopologyStatus> {
+    let mut merged: Option<TopologyStatus> = None;
+    let mut zones: BTreeMap<Option<String>, ZoneBackends> = BTreeMap::new();
+    for status in statuses {
+        for zone in &status.zones {
+            let total = zones
+                .entry(zone.zone.clone())
+                .or_insert_with(|| ZoneBackends {
+                    zone: zone.zone.clone(),
+                    eligible: 0,
+                    selected: 0,
+                });
+            total.eligible += zone.eligible;
+            total.selected += zone.selected;
+        }
+        match &mut merged {
+            Some(merged) => {
+                merged.hints_applied |= status.hints_applied;
+                merged.spilled_over |= status.spilled_over;
+            }
+            None => merged = Some(status.clone()),
+        }
+    }
+
+    merged.map(|mut merged| {
+        merged.zones = zones.into_values().collect();
+        merged
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(zones: &[(&str, usize, usize)], spilled_over: bool) -> TopologyStatus {
+        TopologyStatus {
+            prefer: TopologyPreference::Zone,
+            zone: Some("zone-a".into()),
+            node: None,
+            hints_applied: false,
+            spilled_over,
+            zones: zones
+                .iter()
+                .map(|(zone, eligible, selected)| ZoneBackends {
+                    zone: Some((*zone).into()),
+                    eligible: *eligible,
+                    selected: *selected,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn topology_of_several_watches_adds_zone_counts() {
+        let first = status(&[("zone-a", 2, 2), ("zone-b", 1, 0)], false);
+        let second = status(&[("zone-b", 3, 3)], true);
+
+        let merged = merge_topology([&first, &second].into_iter()).expect("merged topology");
+
+        assert!(merged.spilled_over);
+        assert_eq!(
+            merged.zones,
+            status(&[("zone-a", 2, 2), ("zone-b", 4, 3)], true).zones
+        );
+        assert_eq!(merge_topology(std::iter::empty()), None);
+    }
+
+    #[test]
+    fn backends_are_merged_across_watches_by_address() {
+        let provider = KubernetesProvider::new(
+            vec![
+                KubernetesWatch::Service {
+                    namespace: "default".into(),
+                    service: "web".into(),
+                },
+                KubernetesWatch::Selector {
+                    namespace: "shop".into(),
+                    labels: "app=web".into(),
+                },
+            ],
+            KubernetesTopologyConfig::default(),
+        );
+        let host = |name: &str, ip: [u8; 4]| Host {
+            name: name.into(),
+            ip: ip.into(),
+            weight: None,
+            priority: 0,
+            ports: BTreeMap::new(),
+        };
+        {
+            let mut states = provider.states.write().unwrap();
+            states[0].backends = vec![host("web-1", [10, 0, 0, 1])];
+            states[1].backends = vec![host("shared", [10, 0, 0, 1]), host("cart-1", [10, 0, 0, 2])];
+            states[1].error = Some("connection refused".into());
+        }
+
+        let names: Vec<String> = provider
+            .get_backends()
+            .into_iter()
+            .map(|host| host.name)
+            .collect();
+        assert_eq!(names, vec!["web-1", "cart-1"]);
+        assert_eq!(
+            provider.last_error().as_deref(),
+            Some("selector 'app=web' in namespace shop: connection refused")
+        );
+        let watches = provider.watches();
+        assert_eq!(watches[1].backends, 2);
+        assert!(!watches[0].healthy, "watches are unhealthy before start");
+        assert!(!provider.is_healthy());
+    }
+}
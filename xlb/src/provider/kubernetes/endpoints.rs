@@ -1,4 +1,5 @@
-use crate::config::Host;
+use crate::config::{Host, TopologyPreference};
+use crate::provider::{TopologyStatus, ZoneBackends};
 use k8s_openapi::api::discovery::v1::{Endpoint, EndpointSlice};
 use std::collections::{BTreeMap, HashSet};
 use std::net::{IpAddr, Ipv4Addr};
@@ -10,13 +11,17 @@ pub(super) struct ServiceEndpoint {
     pub ip: Ipv4Addr,
     pub node: Option<String>,
     pub zone: Option<String>,
+    /// Zones the EndpointSlice controller hinted this endpoint for.
+    pub for_zones: Option<Vec<String>>,
     pub ready: Option<bool>,
     pub serving: Option<bool>,
     pub terminating: Option<bool>,
+    /// TCP ports of the slice by name; an unnamed port is keyed by "".
+    pub ports: BTreeMap<String, u16>,
 }
 
 impl ServiceEndpoint {
-    fn from_endpoint(endpoint: &Endpoint) -> Option<Self> {
+    fn from_endpoint(endpoint: &Endpoint, ports: &BTreeMap<String, u16>) -> Option<Self> {
         let ip = endpoint
             .addresses
             .iter()
@@ -36,9 +41,15 @@ impl ServiceEndpoint {
             ip,
             node: endpoint.node_name.clone(),
             zone: endpoint.zone.clone(),
+            for_zones: endpoint
+                .hints
+                .as_ref()
+                .and_then(|hints| hints.for_zones.as_ref())
+                .map(|zones| zones.iter().map(|zone| zone.name.clone()).collect()),
             ready: conditions.and_then(|conditions| conditions.ready),
             serving: conditions.and_then(|conditions| conditions.serving),
             terminating: conditions.and_then(|conditions| conditions.terminating),
+            ports: ports.clone(),
         })
     }
 
@@ -55,10 +66,33 @@ impl ServiceEndpoint {
         Host {
             name: self.name.clone().unwrap_or_else(|| self.ip.to_string()),
             ip: IpAddr::V4(self.ip),
+            weight: None,
+            priority: 0,
+            ports: self.ports.clone(),
         }
     }
 }
 
+/// TCP ports published by a slice. A port without a protocol is TCP.
+fn slice_ports(slice: &EndpointSlice) -> BTreeMap<String, u16> {
+    slice
+        .ports
+        .iter()
+        .flatten()
+        .filter(|port| {
+            port.protocol
+                .as_deref()
+                .is_none_or(|protocol| protocol == "TCP")
+        })
+        .filter_map(|port| {
+            let number = u16::try_from(port.port?)
+                .ok()
+                .filter(|number| *number != 0)?;
+            Some((port.name.clone().unwrap_or_default(), number))
+        })
+        .collect()
+}
+
 #[derive(Debug, Default)]
 pub(super) struct EndpointSliceCache {
     slices: BTreeMap<String, Vec<ServiceEndpoint>>,
@@ -75,10 +109,11 @@ impl EndpointSliceCache {
             return true;
         }
 
+        let ports = slice_ports(slice);
         let endpoints = slice
             .endpoints
             .iter()
-            .filter_map(ServiceEndpoint::from_endpoint)
+            .filter_map(|endpoint| ServiceEndpoint::from_endpoint(endpoint, &ports))
             .collect();
         self.slices.insert(name, endpoints);
         true
@@ -94,22 +129,30 @@ impl EndpointSliceCache {
         previous - self.slices.len()
     }
 
-    pub fn eligible_hosts(&self) -> Vec<Host> {
+    fn eligible_endpoints(&self) -> Vec<&ServiceEndpoint> {
         // EndpointSlice updates may temporarily duplicate one endpoint across
         // slices. Deduplicate by routable address, and require every observed
         // copy to remain eligible so a stale slice cannot revive a draining
         // endpoint.
-        let mut hosts: BTreeMap<Ipv4Addr, (Host, bool)> = BTreeMap::new();
+        let mut endpoints: BTreeMap<Ipv4Addr, (&ServiceEndpoint, bool)> = BTreeMap::new();
         for endpoint in self.slices.values().flatten() {
-            hosts
+            endpoints
                 .entry(endpoint.ip)
                 .and_modify(|(_, eligible)| *eligible &= endpoint.is_eligible())
-                .or_insert_with(|| (endpoint.host(), endpoint.is_eligible()));
+                .or_insert((endpoint, endpoint.is_eligible()));
         }
 
-        hosts
+        endpoints
             .into_values()
-            .filter_map(|(host, eligible)| eligible.then_some(host))
+            .filter_map(|(endpoint, eligible)| eligible.then_some(endpoint))
+            .collect()
+    }
+
+    #[cfg(test)]
+    fn eligible_hosts(&self) -> Vec<Host> {
+        self.eligible_endpoints()
+            .into_iter()
+            .map(ServiceEndpoint::host)
             .collect()
     }
 
@@ -119,6 +162,75 @@ impl EndpointSliceCache {
     }
 }
 
+/// Where this XLB instance runs, and which nearby endpoints it prefers.
+#[derive(Debug, Clone, Default)]
+pub(super) struct TopologyPolicy {
+    pub prefer: TopologyPreference,
+    pub min_local: usize,
+    pub hints: bool,
+    pub zone: Option<String>,
+    pub node: Option<String>,
+}
+
+impl TopologyPolicy {
+    /// Select the eligible endpoints to publish. Local endpoints are used
+    /// while at least `min_local` are eligible, and every endpoint otherwise.
+    fn select(&self, endpoints: &[&ServiceEndpoint]) -> (Vec<Host>, TopologyStatus) {
+        // Like kube-proxy, hints only count when every endpoint has them.
+        let hints_applied = self.prefer == TopologyPreference::Zone
+            && self.hints
+            && !endpoints.is_empty()
+            && endpoints
+                .iter()
+                .all(|endpoint| endpoint.for_zones.is_some());
+        let is_local = |endpoint: &ServiceEndpoint| match self.prefer {
+            TopologyPreference::None => true,
+            TopologyPreference::Zone if hints_applied => endpoint
+                .for_zones
+                .iter()
+                .flatten()
+                .any(|zone| self.zone.as_ref() == Some(zone)),
+            TopologyPreference::Zone => endpoint.zone.is_some() && endpoint.zone == self.zone,
+            TopologyPreference::Node => endpoint.node.is_some() && endpoint.node == self.node,
+        };
+        let local = endpoints
+            .iter()
+            .filter(|endpoint| is_local(endpoint))
+            .count();
+        let spilled_over = self.prefer != TopologyPreference::None
+            && !endpoints.is_empty()
+            && local < self.min_local;
+
+        let mut zones: BTreeMap<Option<String>, ZoneBackends> = BTreeMap::new();
+        let mut hosts = Vec::new();
+        for endpoint in endpoints {
+            let selected = spilled_over || is_local(endpoint);
+            let zone = zones
+                .entry(endpoint.zone.clone())
+                .or_insert_with(|| ZoneBackends {
+                    zone: endpoint.zone.clone(),
+                    eligible: 0,
+                    selected: 0,
+                });
+            zone.eligible += 1;
+            if selected {
+                zone.selected += 1;
+                hosts.push(endpoint.host());
+            }
+        }
+
+        let status = TopologyStatus {
+            prefer: self.prefer,
+            zone: self.zone.clone(),
+            node: self.node.clone(),
+            hints_applied,
+            spilled_over,
+            zones: zones.into_values().collect(),
+        };
+        (hosts, status)
+    }
+}
+
 /// Owns the EndpointSlice cache and its relist lifecycle.
 ///
 /// While a relist is in progress, updates are cached but not published. This
@@ -127,6 +239,9 @@ impl EndpointSliceCache {
 pub(super) struct EndpointSliceState {
     cache: EndpointSliceCache,
     resync_seen: Option<HashSet<String>>,
+    policy: TopologyPolicy,
+    /// Locality of the most recently selected endpoints.
+    topology: Option<TopologyStatus>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -143,6 +258,23 @@ pub(super) struct SyncCompletion {
 }
 
 impl EndpointSliceState {
+    pub fn new(policy: TopologyPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    pub fn topology(&self) -> Option<&TopologyStatus> {
+        self.topology.as_ref()
+    }
+
+    fn select(&mut self) -> Vec<Host> {
+        let (hosts, topology) = self.policy.select(&self.cache.eligible_endpoints());
+        self.topology = Some(topology);
+        hosts
+    }
+
     pub fn begin_sync(&mut self) {
         self.resync_seen = Some(HashSet::new());
     }
@@ -158,15 +290,13 @@ impl EndpointSliceState {
             }
             SliceApply::Deferred
         } else {
-            SliceApply::Publish(self.cache.eligible_hosts())
+            SliceApply::Publish(self.select())
         }
     }
 
     pub fn remove(&mut self, name: &str) -> Option<Vec<Host>> {
         self.cache.remove(name);
-        self.resync_seen
-            .is_none()
-            .then(|| self.cache.eligible_hosts())
+        self.resync_seen.is_none().then(|| self.select())
     }
 
     pub fn finish_sync(&mut self) -> Option<SyncCompletion> {
@@ -174,40 +304,16 @@ impl EndpointSliceState {
         let stale_slices = self.cache.retain_seen(&seen);
         Some(SyncCompletion {
             stale_slices,
-            backends: self.cache.eligible_hosts(),
+            backends: self.select(),
         })
     }
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
-pub(super) struct BackendChanges {
-    pub added: Vec<Host>,
-    pub removed: Vec<Host>,
-}
-
-pub(super) fn replace_backends(current: &mut Vec<Host>, next: Vec<Host>) -> BackendChanges {
-    let current_set: HashSet<Host> = current.iter().cloned().collect();
-    let next_set: HashSet<Host> = next.iter().cloned().collect();
-    let removed = current
-        .iter()
-        .filter(|host| !next_set.contains(host))
-        .cloned()
-        .collect();
-    let added = next
-        .iter()
-        .filter(|host| !current_set.contains(host))
-        .cloned()
-        .collect();
-    *current = next;
-
-    BackendChanges { added, removed }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use k8s_openapi::api::core::v1::ObjectReference;
-    use k8s_openapi::api::discovery::v1::EndpointConditions;
+    use k8s_openapi::api::discovery::v1::{EndpointConditions, EndpointPort};
     use kube::api::ObjectMeta;
 
     fn endpoint(
@@ -250,6 +356,9 @@ mod tests {
         Host {
             name: name.into(),
             ip: ip.parse().expect("valid IP"),
+            weight: None,
+            priority: 0,
+            ports: BTreeMap::new(),
         }
     }
 
@@ -260,8 +369,11 @@ mod tests {
         serving: Option<bool>,
         terminating: Option<bool>,
     ) -> ServiceEndpoint {
-        ServiceEndpoint::from_endpoint(&endpoint(name, ip, ready, serving, terminating))
-            .expect("valid endpoint")
+        ServiceEndpoint::from_endpoint(
+            &endpoint(name, ip, ready, serving, terminating),
+            &BTreeMap::new(),
+        )
+        .expect("valid endpoint")
     }
 
     #[test]
@@ -356,6 +468,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hosts_carry_the_tcp_ports_of_their_slice() {
+        let port = |name: Option<&str>, port: i32, protocol: Option<&str>| EndpointPort {
+            name: name.map(Into::into),
+            port: Some(port),
+            protocol: protocol.map(Into::into),
+            ..Default::default()
+        };
+        let mut published = slice(
+            "backend-a",
+            "IPv4",
+            vec![endpoint("pod-a", "10.0.0.1", Some(true), Some(true), None)],
+        );
+        published.ports = Some(vec![
+            port(Some("http"), 8080, None),
+            port(Some("metrics"), 9090, Some("TCP")),
+            port(Some("dns"), 53, Some("UDP")),
+            port(None, 7000, Some("TCP")),
+        ]);
+        let mut cache = EndpointSliceCache::default();
+        assert!(cache.apply(&published));
+
+        let hosts = cache.eligible_hosts();
+        assert_eq!(
+            hosts[0].ports,
+            BTreeMap::from([
+                (String::new(), 7000),
+                ("http".into(), 8080),
+                ("metrics".into(), 9090),
+            ])
+        );
+    }
+
     #[test]
     fn duplicate_endpoint_uses_conservative_eligibility() {
         let mut cache = EndpointSliceCache::default();
@@ -407,4 +552,134 @@ mod tests {
         assert_eq!(removed, 1);
         assert_eq!(cache.slice_count(), 1);
     }
+
+    fn located(
+        name: &str,
+        ip: &str,
+        node: &str,
+        zone: &str,
+        for_zones: Option<&[&str]>,
+    ) -> ServiceEndpoint {
+        ServiceEndpoint {
+            name: Some(name.into()),
+            ip: ip.parse().expect("valid IPv4"),
+            node: Some(node.into()),
+            zone: Some(zone.into()),
+            for_zones: for_zones.map(|zones| zones.iter().map(|zone| (*zone).into()).collect()),
+            ready: Some(true),
+            serving: Some(true),
+            terminating: None,
+            ports: BTreeMap::new(),
+        }
+    }
+
+    fn policy(prefer: TopologyPreference, min_local: usize) -> TopologyPolicy {
+        TopologyPolicy {
+            prefer,
+            min_local,
+            hints: true,
+            zone: Some("zone-a".into()),
+            node: Some("node-1".into()),
+        }
+    }
+
+    fn names(hosts: &[Host]) -> Vec<&str> {
+        hosts.iter().map(|host| host.name.as_str()).collect()
+    }
+
+    #[test]
+    fn zone_preference_spills_over_below_the_local_threshold() {
+        let endpoints = [
+            located("pod-a1", "10.0.0.1", "node-1", "zone-a", None),
+            located("pod-a2", "10.0.0.2", "node-2", "zone-a", None),
+            located("pod-b1", "10.0.0.3", "node-3", "zone-b", None),
+        ];
+        let endpoints: Vec<&ServiceEndpoint> = endpoints.iter().collect();
+
+        let (hosts, status) = policy(TopologyPreference::Zone, 2).select(&endpoints);
+        assert_eq!(names(&hosts), vec!["pod-a1", "pod-a2"]);
+        assert!(!status.spilled_over);
+        assert_eq!(
+            status.zones,
+            vec![
+                ZoneBackends {
+                    zone: Some("zone-a".into()),
+                    eligible: 2,
+                    selected: 2,
+                },
+                ZoneBackends {
+                    zone: Some("zone-b".into()),
+                    eligible: 1,
+                    selected: 0,
+                },
+            ]
+        );
+
+        let (hosts, status) = policy(TopologyPreference::Zone, 3).select(&endpoints);
+        assert_eq!(hosts.len(), 3);
+        assert!(status.spilled_over);
+
+        let (hosts, _) = policy(TopologyPreference::Node, 1).select(&endpoints);
+        assert_eq!(names(&hosts), vec!["pod-a1"]);
+
+        let (hosts, status) = policy(TopologyPreference::None, 1).select(&endpoints);
+        assert_eq!(hosts.len(), 3);
+        assert!(!status.spilled_over);
+    }
+
+    #[test]
+    fn zone_hints_apply_only_when_every_endpoint_has_them() {
+        let hinted = [
+            located("pod-a1", "10.0.0.1", "node-1", "zone-a", Some(&["zone-a"])),
+            located(
+                "pod-b1",
+                "10.0.0.2",
+                "node-2",
+                "zone-b",
+                Some(&["zone-a", "zone-b"]),
+            ),
+            located("pod-c1", "10.0.0.3", "node-3", "zone-c", Some(&["zone-c"])),
+        ];
+        let endpoints: Vec<&ServiceEndpoint> = hinted.iter().collect();
+
+        let (hosts, status) = policy(TopologyPreference::Zone, 1).select(&endpoints);
+        assert_eq!(names(&hosts), vec!["pod-a1", "pod-b1"]);
+        assert!(status.hints_applied);
+
+        let mut ignored = policy(TopologyPreference::Zone, 1);
+        ignored.hints = false;
+        let (hosts, status) = ignored.select(&endpoints);
+        assert_eq!(names(&hosts), vec!["pod-a1"]);
+        assert!(!status.hints_applied);
+
+        let partial = [
+            hinted[0].clone(),
+            hinted[1].clone(),
+            located("pod-a2", "10.0.0.4", "node-4", "zone-a", None),
+        ];
+        let endpoints: Vec<&ServiceEndpoint> = partial.iter().collect();
+        let (hosts, status) = policy(TopologyPreference::Zone, 1).select(&endpoints);
+        assert_eq!(names(&hosts), vec!["pod-a1", "pod-a2"]);
+        assert!(!status.hints_applied);
+    }
+
+    #[test]
+    fn state_reports_the_topology_of_each_publication() {
+        let mut state = EndpointSliceState::new(policy(TopologyPreference::Zone, 1));
+        assert_eq!(state.topology(), None);
+
+        let published = match state.apply(&slice(
+            "backend",
+            "IPv4",
+            vec![endpoint("pod-a", "10.0.0.1", Some(true), Some(true), None)],
+        )) {
+            SliceApply::Publish(backends) => backends,
+            update => panic!("expected a publication, got {update:?}"),
+        };
+
+        assert_eq!(published, vec![host("pod-a", "10.0.0.1")]);
+        let topology = state.topology().expect("topology after publication");
+        assert_eq!(topology.zone.as_deref(), Some("zone-a"));
+        assert_eq!(topology.zones[0].selected, 1);
+    }
 }
@@ -0,0 +1,908 @@
+use super::KubernetesProvider;
+use crate::config::{Host, KubernetesTopologyConfig, KubernetesWatch};
+use crate::provider::BackendProvider;
+use anyhow::{Context, Result, anyhow, bail};
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Service;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::{Patch, PatchParams};
+use kube::runtime::WatchStreamExt;
+use kube::runtime::watcher::{self, Event};
+use kube::{Api, Client};
+use log::{debug, info, warn};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+use std::net::Ipv4Addr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use xlb_common::consts::{MAX_PORT_MAPPINGS, MAX_VIPS};
+
+const INITIAL_SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+const PROVIDER_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+const LOAD_BALANCER_TYPE: &str = "LoadBalancer";
+
+/// One port of a load-balanced Service.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ServicePortMapping {
+    /// Service port, served on the VIP.
+    pub local_port: u16,
+    /// Backend port used when an endpoint publishes no port named
+    /// `port_name`.
+    pub remote_port: u16,
+    /// Service port name, under which EndpointSlices publish each
+    /// endpoint's target port. Empty for an unnamed port.
+    pub port_name: String,
+}
+
+/// A Service of the controller's class and the VIP serving it.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct LoadBalancerService {
+    pub namespace: String,
+    pub name: String,
+    pub vip: Option<Ipv4Addr>,
+    pub ports: Vec<ServicePortMapping>,
+    /// Backends programmed into the dataplane for the VIP.
+    pub backends: usize,
+    /// Why the Service is not fully served, if it is not.
+    pub error: Option<String>,
+}
+
+/// State of the Kubernetes LoadBalancer controller.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct LoadBalancerStatus {
+    /// `spec.loadBalancerClass` implemented by this instance.
+    pub class: String,
+    pub healthy: bool,
+    /// Addresses in the VIP pool.
+    pub addresses: u64,
+    pub services: Vec<LoadBalancerService>,
+}
+
+/// Backends discovered for one allocated VIP.
+#[derive(Debug, Clone)]
+pub struct VipBackends {
+    pub namespace: String,
+    pub name: String,
+    pub vip: Ipv4Addr,
+    /// Slot of the VIP's backend pool in the dataplane.
+    pub slot: u32,
+    pub ports: Vec<ServicePortMapping>,
+    pub hosts: Vec<Host>,
+}
+
+/// What a Service of the controller's class asks for.
+#[derive(Debug, PartialEq, Eq)]
+struct DesiredService {
+    ports: Vec<ServicePortMapping>,
+    /// `spec.loadBalancerIP`, which must be honoured when set.
+    requested_ip: Option<Ipv4Addr>,
+    /// First ingress IP already in the Service status, kept when possible
+    /// so a restart does not move the VIP.
+    ingress_ip: Option<Ipv4Addr>,
+}
+
+/// VIP addresses and dataplane slots handed out to Services.
+#[derive(Debug, Default)]
+struct AddressPool {
+    ranges: Vec<(Ipv4Addr, Ipv4Addr)>,
+    /// Allocated VIPs and their slots.
+    allocated: BTreeMap<Ipv4Addr, u32>,
+}
+
+impl AddressPool {
+    fn new(ranges: Vec<(Ipv4Addr, Ipv4Addr)>) -> Self {
+        Self {
+            ranges,
+            allocated: BTreeMap::new(),
+        }
+    }
+
+    fn size(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|(first, last)| u64::from(last.to_bits() - first.to_bits()) + 1)
+            .sum()
+    }
+
+    fn contains(&self, address: Ipv4Addr) -> bool {
+        self.ranges
+            .iter()
+            .any(|(first, last)| (*first..=*last).contains(&address))
+    }
+
+    fn is_free(&self, address: Ipv4Addr) -> bool {
+        self.contains(address) && !self.allocated.contains_key(&address)
+    }
+
+    /// Allocate `requested` when set, otherwise `preferred` when free,
+    /// otherwise the first free address.
+    fn allocate(
+        &mut self,
+        requested: Option<Ipv4Addr>,
+        preferred: Option<Ipv4Addr>,
+    ) -> Result<(Ipv4Addr, u32)> {
+        let slots: HashSet<u32> = self.allocated.values().copied().collect();
+        let Some(slot) = (0..MAX_VIPS).find(|slot| !slots.contains(slot)) else {
+            bail!("XLB serves at most {MAX_VIPS} load balancer VIPs");
+        };
+
+        let address = match requested {
+            Some(address) if !self.contains(address) => {
+                bail!("Requested load balancer IP {address} is not in the address pool")
+            }
+            Some(address) if !self.is_free(address) => {
+                bail!("Requested load balancer IP {address} is already allocated")
+            }
+            Some(address) => address,
+            None => match preferred.filter(|address| self.is_free(*address)) {
+                Some(address) => address,
+                None => self
+                    .first_free()
+                    .ok_or_else(|| anyhow!("No free address in the load balancer pool"))?,
+            },
+        };
+
+        self.allocated.insert(address, slot);
+        Ok((address, slot))
+    }
+
+    fn first_free(&self) -> Option<Ipv4Addr> {
+        self.ranges.iter().find_map(|(first, last)| {
+            (first.to_bits()..=last.to_bits())
+                .map(Ipv4Addr::from_bits)
+                .find(|address| !self.allocated.contains_key(address))
+        })
+    }
+
+    fn release(&mut self, address: Ipv4Addr) {
+        self.allocated.remove(&address);
+    }
+}
+
+/// A Service being served, with the watch of its EndpointSlices.
+struct TrackedService {
+    desired: DesiredService,
+    vip: Option<(Ipv4Addr, u32)>,
+    provider: Arc<KubernetesProvider>,
+    /// Starts `provider`, retrying until its initial sync succeeds.
+    start: JoinHandle<()>,
+    error: Arc<RwLock<Option<String>>>,
+}
+
+#[derive(Default)]
+struct ControllerState {
+    pool: AddressPool,
+    services: BTreeMap<(String, String), TrackedService>,
+}
+
+/// Implements `Service` `type: LoadBalancer` for one load balancer class:
+/// allocates each matching Service a VIP from the pool, follows its
+/// EndpointSlices, and publishes the VIP in `status.loadBalancer.ingress`.
+pub struct LoadBalancerController {
+    class: String,
+    state: Arc<tokio::sync::Mutex<ControllerState>>,
+    /// Cheap copy of the served Services for readers outside the
+    /// controller task.
+    published: Arc<RwLock<Vec<VipBackendsSource>>>,
+    handle: RwLock<Option<JoinHandle<()>>>,
+    addresses: u64,
+}
+
+/// Published view of one tracked Service.
+#[derive(Clone)]
+struct VipBackendsSource {
+    namespace: String,
+    name: String,
+    vip: Option<(Ipv4Addr, u32)>,
+    ports: Vec<ServicePortMapping>,
+    provider: Arc<KubernetesProvider>,
+    error: Arc<RwLock<Option<String>>>,
+}
+
+impl LoadBalancerController {
+    pub fn new(class: String, ranges: Vec<(Ipv4Addr, Ipv4Addr)>) -> Self {
+        let pool = AddressPool::new(ranges);
+        let addresses = pool.size();
+        Self {
+            class,
+            state: Arc::new(tokio::sync::Mutex::new(ControllerState {
+                pool,
+                services: BTreeMap::new(),
+            })),
+            published: Arc::new(RwLock::new(Vec::new())),
+            handle: RwLock::new(None),
+            addresses,
+        }
+    }
+
+    /// Watch Services of the configured class until the initial list has
+    /// been reconciled.
+    pub async fn start(&self) -> Result<()> {
+        let client = Client::try_default().await.context(
+            "Failed to create Kubernetes client - ensure running in cluster or kubeconfig is set",
+        )?;
+        self.start_with_client(client).await
+    }
+
+    pub(crate) async fn start_with_client(&self, client: Client) -> Result<()> {
+        let (initial_sync_tx, initial_sync_rx) = oneshot::channel();
+        let handle = tokio::spawn(Self::run(
+            client,
+            self.class.clone(),
+            self.state.clone(),
+            self.published.clone(),
+            initial_sync_tx,
+        ));
+
+        match tokio::time::timeout(INITIAL_SYNC_TIMEOUT, initial_sync_rx).await {
+            Ok(Ok(())) => {}
+            Ok(Err(_)) => {
+                handle.abort();
+                bail!("Service watch ended before initial sync");
+            }
+            Err(_) => {
+                handle.abort();
+                bail!("Timed out waiting for initial Service sync");
+            }
+        }
+
+        *self
+            .handle
+            .write()
+            .expect("controller handle lock poisoned") = Some(handle);
+        info!(
+            "Started load balancer controller for class '{}' with {} address(es)",
+            self.class, self.addresses
+        );
+        Ok(())
+    }
+
+    /// Backends of every Service that holds a VIP.
+    pub fn vip_backends(&self) -> Vec<VipBackends> {
+        let published = self.published.read().expect("published lock poisoned");
+        published
+            .iter()
+            .filter_map(|service| {
+                let (vip, slot) = service.vip?;
+                Some(VipBackends {
+                    namespace: service.namespace.clone(),
+                    name: service.name.clone(),
+                    vip,
+                    slot,
+                    ports: service.ports.clone(),
+                    hosts: service.provider.get_backends(),
+                })
+            })
+            .collect()
+    }
+
+    /// Controller state, with no backends counted yet.
+    pub fn status(&self) -> LoadBalancerStatus {
+        let published = self.published.read().expect("published lock poisoned");
+        LoadBalancerStatus {
+            class: self.class.clone(),
+            healthy: self.is_healthy(),
+            addresses: self.addresses,
+            services: published
+                .iter()
+                .map(|service| LoadBalancerService {
+                    namespace: service.namespace.clone(),
+                    name: service.name.clone(),
+                    vip: service.vip.map(|(vip, _)| vip),
+                    ports: service.ports.clone(),
+                    backends: 0,
+                    error: service
+                        .error
+                        .read()
+                        .expect("service error lock poisoned")
+                        .clone()
+                        .or_else(|| service.provider.last_error()),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.handle
+            .read()
+            .expect("controller handle lock poisoned")
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    pub async fn shutdown(&self) -> Result<()> {
+        if let Some(handle) = self
+            .handle
+            .write()
+            .expect("controller handle lock poisoned")
+            .take()
+        {
+            handle.abort();
+        }
+
+        let mut state = self.state.lock().await;
+        let services = std::mem::take(&mut state.services);
+        for service in services.values() {
+            service.start.abort();
+            service.provider.shutdown().await?;
+        }
+        self.published
+            .write()
+            .expect("published lock poisoned")
+            .clear();
+        info!(
+            "Stopped load balancer controller and {} Service watch(es)",
+            services.len()
+        );
+        Ok(())
+    }
+
+    async fn run(
+        client: Client,
+        class: String,
+        state: Arc<tokio::sync::Mutex<ControllerState>>,
+        published: Arc<RwLock<Vec<VipBackendsSource>>>,
+        initial_sync_tx: oneshot::Sender<()>,
+    ) {
+        let services: Api<Service> = Api::all(client.clone());
+        let stream = watcher::watcher(services, watcher::Config::default()).default_backoff();
+        futures::pin_mut!(stream);
+
+        let mut initial_sync_tx = Some(initial_sync_tx);
+        let mut seen: Option<HashSet<(String, String)>> = None;
+
+        while let Some(event) = stream.next().await {
+            let mut state = state.lock().await;
+            match event {
+                Ok(Event::Init) => seen = Some(HashSet::new()),
+                Ok(Event::InitApply(service)) | Ok(Event::Apply(service)) => {
+                    let Some(key) = service_key(&service) else {
+                        continue;
+                    };
+                    if let Some(seen) = &mut seen {
+                        seen.insert(key.clone());
+                    }
+                    Self::reconcile(&client, &class, &mut state, key, &service).await;
+                }
+                Ok(Event::Delete(service)) => {
+                    if let Some(key) = service_key(&service) {
+                        Self::release(&client, &mut state, &key, false).await;
+                    }
+                }
+                Ok(Event::InitDone) => {
+                    if let Some(seen) = seen.take() {
+                        let stale: Vec<_> = state
+                            .services
+                            .keys()
+                            .filter(|key| !seen.contains(*key))
+                            .cloned()
+                            .collect();
+                        for key in stale {
+                            Self::release(&client, &mut state, &key, false).await;
+                        }
+                    }
+                    if let Some(tx) = initial_sync_tx.take() {
+                        let _ = tx.send(());
+                    }
+                }
+                Err(error) => warn!("Service watch error for load balancer controller: {error}"),
+            }
+
+            Self::allocate_pending(&client, &mut state).await;
+            *published.write().expect("published lock poisoned") = state
+                .services
+                .iter()
+                .map(|((namespace, name), service)| VipBackendsSource {
+                    namespace: namespace.clone(),
+                    name: name.clone(),
+                    vip: service.vip,
+                    ports: service.desired.ports.clone(),
+                    provider: service.provider.clone(),
+                    error: service.error.clone(),
+                })
+                .collect();
+        }
+
+        warn!("Service watch ended unexpectedly for load balancer controller");
+    }
+
+    async fn reconcile(
+        client: &Client,
+        class: &str,
+        state: &mut ControllerState,
+        key: (String, String),
+        service: &Service,
+    ) {
+        let desired = match desired_service(service, class) {
+            None => {
+                Self::release(client, state, &key, true).await;
+                return;
+            }
+            Some(Err(error)) => {
+                if let Some(tracked) = state.services.get(&key) {
+                    *tracked.error.write().expect("service error lock poisoned") =
+                        Some(error.to_string());
+                } else {
+                    warn!("Ignoring Service {}/{}: {error}", key.0, key.1);
+                }
+                return;
+            }
+            Some(Ok(desired)) => desired,
+        };
+
+        if let Some(tracked) = state.services.get_mut(&key) {
+            if tracked.desired.ports != desired.ports {
+                info!("Updated ports of load balancer Service {}/{}", key.0, key.1);
+            }
+            tracked.desired = desired;
+            return;
+        }
+
+        let provider = Arc::new(KubernetesProvider::new(
+            vec![KubernetesWatch::Service {
+                namespace: key.0.clone(),
+                service: key.1.clone(),
+            }],
+            KubernetesTopologyConfig::default(),
+        ));
+        let error = Arc::new(RwLock::new(None));
+        let start = tokio::spawn(Self::start_provider(
+            client.clone(),
+            provider.clone(),
+            error.clone(),
+        ));
+        info!("Serving load balancer Service {}/{}", key.0, key.1);
+        state.services.insert(
+            key,
+            TrackedService {
+                desired,
+                vip: None,
+                provider,
+                start,
+                error,
+            },
+        );
+    }
+
+    async fn start_provider(
+        client: Client,
+        provider: Arc<KubernetesProvider>,
+        error: Arc<RwLock<Option<String>>>,
+    ) {
+        loop {
+            match provider.start_with_client(client.clone()).await {
+                Ok(()) => {
+                    *error.write().expect("service error lock poisoned") = None;
+                    return;
+                }
+                Err(start_error) => {
+                    warn!("{start_error:#}; retrying");
+                    *error.write().expect("service error lock poisoned") =
+                        Some(format!("{start_error:#}"));
+                }
+            }
+            tokio::time::sleep(PROVIDER_RETRY_INTERVAL).await;
+        }
+    }
+
+    /// Give a VIP to every tracked Service without one, publishing it in
+    /// the Service status.
+    async fn allocate_pending(client: &Client, state: &mut ControllerState) {
+        let ControllerState { pool, services } = state;
+        for ((namespace, name), service) in services.iter_mut() {
+            if service.vip.is_some() {
+                continue;
+            }
+
+            let allocated = pool.allocate(service.desired.requested_ip, service.desired.ingress_ip);
+            let (vip, slot) = match allocated {
+                Ok(allocated) => allocated,
+                Err(error) => {
+                    *service.error.write().expect("service error lock poisoned") =
+                        Some(error.to_string());
+                    continue;
+                }
+            };
+            service.vip = Some((vip, slot));
+            info!("Allocated VIP {vip} to load balancer Service {namespace}/{name}");
+
+            let published = if service.desired.ingress_ip == Some(vip) {
+                Ok(())
+            } else {
+                publish_ingress(client, namespace, name, Some(vip)).await
+            };
+            *service.error.write().expect("service error lock poisoned") =
+                published.err().map(|error| format!("{error:#}"));
+        }
+    }
+
+    /// Stop serving a Service and free its VIP, clearing the Service
+    /// status when the Service still exists.
+    async fn release(
+        client: &Client,
+        state: &mut ControllerState,
+        key: &(String, String),
+        exists: bool,
+    ) {
+        let Some(service) = state.services.remove(key) else {
+            return;
+        };
+        service.start.abort();
+        if let Err(error) = service.provider.shutdown().await {
+            warn!(
+                "Failed to stop EndpointSlice watch of {}/{}: {error}",
+                key.0, key.1
+            );
+        }
+
+        if let Some((vip, _)) = service.vip {
+            state.pool.release(vip);
+            info!("Released VIP {vip} of Service {}/{}", key.0, key.1);
+            if exists && let Err(error) = publish_ingress(client, &key.0, &key.1, None).await {
+                warn!("{error:#}");
+            }
+        } else {
+            debug!("Stopped serving Service {}/{} without a VIP", key.0, key.1);
+        }
+    }
+}
+
+fn service_key(service: &Service) -> Option<(String, String)> {
+    Some((
+        service.metadata.namespace.clone()?,
+        service.metadata.name.clone()?,
+    ))
+}
+
+/// What `service` asks of the controller, or `None` when it is not a
+/// LoadBalancer Service of `class`.
+fn desired_service(service: &Service, class: &str) -> Option<Result<DesiredService>> {
+    let spec = service.spec.as_ref()?;
+    if spec.type_.as_deref() != Some(LOAD_BALANCER_TYPE)
+        || spec.load_balancer_class.as_deref() != Some(class)
+    {
+        return None;
+    }
+
+    Some(parse_desired_service(service))
+}
+
+fn parse_desired_service(service: &Service) -> Result<DesiredService> {
+    let spec = service.spec.as_ref().context("Service has no spec")?;
+    let ports: Vec<ServicePortMapping> = spec
+        .ports
+        .iter()
+        .flatten()
+        .filter(|port| {
+            port.protocol
+                .as_deref()
+                .is_none_or(|protocol| protocol == "TCP")
+        })
+        .map(|port| {
+            let local_port = u16::try_from(port.port)
+                .ok()
+                .filter(|port| *port != 0)
+                .with_context(|| format!("Invalid Service port {}", port.port))?;
+            let remote_port = match &port.target_port {
+                Some(IntOrString::Int(target)) => u16::try_from(*target)
+                    .ok()
+                    .filter(|port| *port != 0)
+                    .with_context(|| format!("Invalid Service targetPort {target}"))?,
+                // Named target ports resolve through the EndpointSlice
+                // ports; fall back to the Service port.
+                Some(IntOrString::String(_)) | None => local_port,
+            };
+            Ok(ServicePortMapping {
+                local_port,
+                remote_port,
+                port_name: port.name.clone().unwrap_or_default(),
+            })
+        })
+        .collect::<Result<_>>()?;
+    if ports.is_empty() {
+        bail!("Service has no TCP ports");
+    }
+    if ports.len() > MAX_PORT_MAPPINGS {
+        bail!("Service has more than {MAX_PORT_MAPPINGS} TCP ports");
+    }
+
+    let requested_ip = spec
+        .load_balancer_ip
+        .as_deref()
+        .filter(|ip| !ip.is_empty())
+        .map(|ip| {
+            ip.parse::<Ipv4Addr>()
+                .with_context(|| format!("Requested load balancer IP '{ip}' is not IPv4"))
+        })
+        .transpose()?;
+    let ingress_ip = service
+        .status
+        .as_ref()
+        .and_then(|status| status.load_balancer.as_ref())
+        .and_then(|load_balancer| load_balancer.ingress.as_ref())
+        .and_then(|ingress| ingress.first())
+        .and_then(|ingress| ingress.ip.as_deref())
+        .and_then(|ip| ip.parse().ok());
+
+    Ok(DesiredService {
+        ports,
+        requested_ip,
+        ingress_ip,
+    })
+}
+
+/// Write `vip` as the only ingress of a Service, or clear the ingress.
+async fn publish_ingress(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    vip: Option<Ipv4Addr>,
+) -> Result<()> {
+    let services: Api<Service> = Api::namespaced(client.clone(), namespace);
+    let ingress = vip.map(|vip| json!([{ "ip": vip.to_string() }]));
+    let patch = json!({ "status": { "loadBalancer": { "ingress": ingress } } });
+    services
+        .patch_status(name, &PatchParams::default(), &Patch::Merge(&patch))
+        .await
+        .with_context(|| format!("Failed to update load balancer status of {namespace}/{name}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::{Body, Bytes};
+    use axum::extract::{Path, RawQuery, State};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::{get, patch};
+    use serde_json::Value;
+    use std::sync::Mutex;
+
+    fn ip(address: &str) -> Ipv4Addr {
+        address.parse().expect("valid IPv4")
+    }
+
+    fn service(name: &str, class: Option<&str>, load_balancer_ip: Option<&str>) -> Value {
+        json!({
+            "apiVersion": "v1",
+            "kind": "Service",
+            "metadata": { "namespace": "shop", "name": name, "resourceVersion": "1" },
+            "spec": {
+                "type": "LoadBalancer",
+                "loadBalancerClass": class,
+                "loadBalancerIP": load_balancer_ip,
+                "ports": [
+                    { "name": "http", "port": 80, "targetPort": "web", "protocol": "TCP" },
+                    { "name": "admin", "port": 9000, "targetPort": 9001 },
+                    { "name": "dns", "port": 53, "targetPort": 53, "protocol": "UDP" }
+                ]
+            }
+        })
+    }
+
+    fn parse(value: Value) -> Service {
+        serde_json::from_value(value).expect("valid Service")
+    }
+
+    #[test]
+    fn pool_prefers_requested_then_previous_then_first_free_addresses() {
+        let mut pool = AddressPool::new(vec![
+            (ip("192.0.2.10"), ip("192.0.2.11")),
+            (ip("198.51.100.5"), ip("198.51.100.5")),
+        ]);
+        assert_eq!(pool.size(), 3);
+
+        assert_eq!(
+            pool.allocate(None, Some(ip("198.51.100.5")))
+                .expect("previous VIP"),
+            (ip("198.51.100.5"), 0)
+        );
+        assert_eq!(
+            pool.allocate(None, Some(ip("198.51.100.5")))
+                .expect("first free"),
+            (ip("192.0.2.10"), 1)
+        );
+        let error = pool
+            .allocate(Some(ip("192.0.2.10")), None)
+            .expect_err("requested VIP in use");
+        assert!(error.to_string().contains("already allocated"), "{error}");
+        let error = pool
+            .allocate(Some(ip("203.0.113.1")), None)
+            .expect_err("requested VIP outside the pool");
+        assert!(
+            error.to_string().contains("not in the address pool"),
+            "{error}"
+        );
+        assert_eq!(
+            pool.allocate(Some(ip("192.0.2.11")), None)
+                .expect("requested VIP"),
+            (ip("192.0.2.11"), 2)
+        );
+        assert!(pool.allocate(None, None).is_err());
+
+        pool.release(ip("192.0.2.10"));
+        assert_eq!(
+            pool.allocate(None, None).expect("released VIP"),
+            (ip("192.0.2.10"), 1)
+        );
+    }
+
+    #[test]
+    fn only_load_balancer_services_of_the_class_are_served() {
+        assert!(desired_service(&parse(service("web", Some("other"), None)), "xlb").is_none());
+        let mut cluster_ip = service("web", Some("xlb"), None);
+        cluster_ip["spec"]["type"] = json!("ClusterIP");
+        assert!(desired_service(&parse(cluster_ip), "xlb").is_none());
+
+        let mut served = service("web", Some("xlb"), Some("192.0.2.10"));
+        served["status"] = json!({ "loadBalancer": { "ingress": [{ "ip": "192.0.2.11" }] } });
+        let desired = desired_service(&parse(served), "xlb")
+            .expect("served Service")
+            .expect("valid Service");
+        assert_eq!(
+            desired,
+            DesiredService {
+                ports: vec![
+                    ServicePortMapping {
+                        local_port: 80,
+                        remote_port: 80,
+                        port_name: "http".into(),
+                    },
+                    ServicePortMapping {
+                        local_port: 9000,
+                        remote_port: 9001,
+                        port_name: "admin".into(),
+                    },
+                ],
+                requested_ip: Some(ip("192.0.2.10")),
+                ingress_ip: Some(ip("192.0.2.11")),
+            }
+        );
+
+        let mut udp_only = service("dns", Some("xlb"), None);
+        udp_only["spec"]["ports"] = json!([{ "port": 53, "protocol": "UDP" }]);
+        let error = desired_service(&parse(udp_only), "xlb")
+            .expect("served Service")
+            .expect_err("no TCP ports");
+        assert!(error.to_string().contains("no TCP ports"), "{error}");
+    }
+
+    #[derive(Clone, Default)]
+    struct FakeApi {
+        patches: Arc<Mutex<Vec<(String, Value)>>>,
+    }
+
+    /// Watch requests stay open without events, like an idle API server.
+    fn list_or_watch(query: Option<String>, list: Value) -> Response {
+        if query.is_some_and(|query| query.contains("watch=true")) {
+            let idle = futures::stream::pending::<Result<Bytes, std::io::Error>>();
+            return Response::new(Body::from_stream(idle));
+        }
+        axum::Json(list).into_response()
+    }
+
+    fn fake_api_server(api: FakeApi) -> Router {
+        Router::new()
+            .route(
+                "/api/v1/services",
+                get(|RawQuery(query): RawQuery| async move {
+                    list_or_watch(
+                        query,
+                        json!({
+                            "apiVersion": "v1",
+                            "kind": "ServiceList",
+                            "metadata": { "resourceVersion": "1" },
+                            "items": [
+                                service("web", Some("xlb"), None),
+                                service("other", Some("other"), None)
+                            ]
+                        }),
+                    )
+                }),
+            )
+            .route(
+                "/api/v1/namespaces/shop/services/{name}",
+                get(|Path(name): Path<String>| async move {
+                    axum::Json(service(&name, Some("xlb"), None))
+                }),
+            )
+            .route(
+                "/api/v1/namespaces/shop/services/{name}/status",
+                patch(
+                    |State(api): State<FakeApi>,
+                     Path(name): Path<String>,
+                     axum::Json(patch): axum::Json<Value>| async move {
+                        api.patches
+                            .lock()
+                            .expect("patch lock poisoned")
+                            .push((name.clone(), patch));
+                        axum::Json(service(&name, Some("xlb"), None))
+                    },
+                ),
+            )
+            .route(
+                "/apis/discovery.k8s.io/v1/namespaces/shop/endpointslices",
+                get(|RawQuery(query): RawQuery| async move {
+                    list_or_watch(
+                        query,
+                        json!({
+                            "apiVersion": "discovery.k8s.io/v1",
+                            "kind": "EndpointSliceList",
+                            "metadata": { "resourceVersion": "1" },
+                            "items": [{
+                                "metadata": { "name": "web-abcde", "namespace": "shop" },
+                                "addressType": "IPv4",
+                                "endpoints": [{
+                                    "addresses": ["10.0.0.1"],
+                                    "targetRef": { "name": "web-1" }
+                                }],
+                                "ports": [
+                                    { "name": "http", "port": 8080, "protocol": "TCP" },
+                                    { "name": "admin", "port": 9001, "protocol": "TCP" }
+                                ]
+                            }]
+                        }),
+                    )
+                }),
+            )
+            .with_state(api)
+    }
+
+    #[tokio::test]
+    async fn allocates_a_vip_and_publishes_it_against_a_fake_api_server() {
+        let api = FakeApi::default();
+        let client = Client::new(fake_api_server(api.clone()), "default");
+        let controller =
+            LoadBalancerController::new("xlb".into(), vec![(ip("192.0.2.10"), ip("192.0.2.19"))]);
+
+        controller
+            .start_with_client(client)
+            .await
+            .expect("controller starts");
+
+        assert_eq!(
+            *api.patches.lock().expect("patch lock poisoned"),
+            vec![(
+                "web".to_string(),
+                json!({ "status": { "loadBalancer": { "ingress": [{ "ip": "192.0.2.10" }] } } })
+            )]
+        );
+
+        let vip_backends = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let vip_backends = controller.vip_backends();
+                if vip_backends.iter().any(|service| !service.hosts.is_empty()) {
+                    return vip_backends;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("EndpointSlices sync");
+        assert_eq!(vip_backends.len(), 1);
+        let web = &vip_backends[0];
+        assert_eq!(
+            (web.name.as_str(), web.vip, web.slot),
+            ("web", ip("192.0.2.10"), 0)
+        );
+        assert_eq!(web.hosts.len(), 1);
+        assert_eq!(web.hosts[0].name, "web-1");
+        assert_eq!(
+            web.hosts[0].ports,
+            BTreeMap::from([("admin".into(), 9001), ("http".into(), 8080)])
+        );
+
+        let status = controller.status();
+        assert!(status.healthy);
+        assert_eq!(status.addresses, 10);
+        assert_eq!(status.services.len(), 1);
+        assert_eq!(status.services[0].error, None);
+
+        controller.shutdown().await.expect("controller stops");
+        assert!(controller.vip_backends().is_empty());
+    }
+}
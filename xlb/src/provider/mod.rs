@@ -1,9 +1,19 @@
+mod composite;
+mod consul;
+mod dns;
+mod file;
 mod fixed;
+mod http;
 mod kubernetes;
 // `provider.rs` holds the shared API; its sibling modules are implementations.
 #[allow(clippy::module_inception)]
 mod provider;
 
+pub use composite::*;
+pub use consul::*;
+pub use dns::*;
+pub use file::*;
 pub use fixed::*;
+pub use http::*;
 pub use kubernetes::*;
 pub use provider::*;
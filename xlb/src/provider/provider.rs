@@ -1,11 +1,50 @@
+use crate::config::TopologyPreference;
 use crate::{config::Host, system};
 use anyhow::Result;
 use async_trait::async_trait;
 use log::trace;
+use serde::Serialize;
+use std::collections::HashSet;
 use std::net::IpAddr;
+use xlb_common::consts;
 use xlb_common::net::IpVersion;
 use xlb_common::types::Backend;
 
+/// Endpoint counts of one zone.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ZoneBackends {
+    /// Zone reported for the endpoints, if any.
+    pub zone: Option<String>,
+    pub eligible: usize,
+    /// Eligible endpoints published as backends.
+    pub selected: usize,
+}
+
+/// Locality preference applied to the published backends.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TopologyStatus {
+    pub prefer: TopologyPreference,
+    /// Zone of this XLB instance, when known.
+    pub zone: Option<String>,
+    /// Node running this XLB instance, when known.
+    pub node: Option<String>,
+    /// Whether EndpointSlice hints chose the local endpoints.
+    pub hints_applied: bool,
+    /// Whether too few local endpoints were eligible, so every zone is used.
+    pub spilled_over: bool,
+    pub zones: Vec<ZoneBackends>,
+}
+
+/// State of one watch of a provider that follows several sources.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct WatchStatus {
+    pub name: String,
+    pub healthy: bool,
+    /// Backends currently published by this watch.
+    pub backends: usize,
+    pub last_error: Option<String>,
+}
+
 /// Responsible for maintaining a live list of
 /// backend nodes traffic should be routed to.
 #[async_trait]
@@ -20,6 +59,23 @@ pub trait BackendProvider: Send + Sync {
     /// state. A provider may remain healthy with zero discovered backends.
     fn is_healthy(&self) -> bool;
 
+    /// Why the provider last failed to refresh its backends, while it
+    /// keeps serving the previous set.
+    fn last_error(&self) -> Option<String> {
+        None
+    }
+
+    /// Locality of the published backends, for providers that prefer
+    /// endpoints near this XLB instance.
+    fn topology(&self) -> Option<TopologyStatus> {
+        None
+    }
+
+    /// Per-watch state, for providers that follow several sources.
+    fn watches(&self) -> Vec<WatchStatus> {
+        Vec::new()
+    }
+
     /// Shutdown the provider
     async fn shutdown(&self) -> Result<()>;
 }
@@ -39,7 +95,11 @@ impl From<&Host> for Backend {
             next_hop_mac: [0; 6],
             src_iface_ifindex: 0,
             conns: 0,
+            weight: value.weight.map_or(consts::MAX_BACKEND_WEIGHT, |weight| {
+                weight.clamp(1, consts::MAX_BACKEND_WEIGHT)
+            }),
             bytes_transfer: 0,
+            ports: [0; consts::MAX_PORT_MAPPINGS],
         }
     }
 }
@@ -47,7 +107,14 @@ impl From<&Host> for Backend {
 /// Converts hosts to backends with routing information populated by
 /// performing kernel route and neighbor lookups for each backend.
 /// Skips backends that cannot be reached and logs warnings.
-pub async fn hosts_to_backends_with_routes(hosts: &[Host]) -> Vec<Backend> {
+///
+/// `port_names` holds the optional Service port name of each port mapping;
+/// a host publishing a port of that name receives the mapping's traffic on
+/// that port instead of the configured `remote_port`.
+pub async fn hosts_to_backends_with_routes(
+    hosts: &[Host],
+    port_names: &[Option<String>],
+) -> Vec<Backend> {
     let mut backends = Vec::new();
 
     for host in hosts {
@@ -59,6 +126,11 @@ pub async fn hosts_to_backends_with_routes(hosts: &[Host]) -> Vec<Backend> {
         }
 
         let mut backend = Backend::from(host);
+        for (slot, name) in backend.ports.iter_mut().zip(port_names) {
+            if let Some(port) = name.as_ref().and_then(|name| host.ports.get(name)) {
+                *slot = *port;
+            }
+        }
 
         match system::populate_backend_route(&mut backend).await {
             Ok(()) => {
@@ -82,18 +154,75 @@ pub async fn hosts_to_backends_with_routes(hosts: &[Host]) -> Vec<Backend> {
     backends
 }
 
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct BackendChanges {
+    pub added: Vec<Host>,
+    pub removed: Vec<Host>,
+}
+
+pub(crate) fn replace_backends(current: &mut Vec<Host>, next: Vec<Host>) -> BackendChanges {
+    let current_set: HashSet<Host> = current.iter().cloned().collect();
+    let next_set: HashSet<Host> = next.iter().cloned().collect();
+    let removed = current
+        .iter()
+        .filter(|host| !next_set.contains(host))
+        .cloned()
+        .collect();
+    let added = next
+        .iter()
+        .filter(|host| !current_set.contains(host))
+        .cloned()
+        .collect();
+    *current = next;
+
+    BackendChanges { added, removed }
+}
+
+/// Scale discovery weights such as SRV or node-meta weights to backend
+/// selection weights relative to the heaviest host. Equal weights,
+/// including all zero, leave every host a full share.
+pub(crate) fn relative_weights(weights: &[u32]) -> Vec<Option<u16>> {
+    let max = weights.iter().copied().max().unwrap_or_default();
+    if weights.iter().all(|weight| *weight == max) {
+        return vec![None; weights.len()];
+    }
+
+    weights
+        .iter()
+        .map(|weight| {
+            let scaled =
+                u64::from(*weight) * u64::from(consts::MAX_BACKEND_WEIGHT) / u64::from(max);
+            Some((scaled as u16).max(1))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::hosts_to_backends_with_routes;
+    use super::{hosts_to_backends_with_routes, relative_weights};
     use crate::config::Host;
+    use std::collections::BTreeMap;
 
     #[tokio::test]
     async fn route_population_never_emits_ipv6_backends() {
         let hosts = vec![Host {
             name: "backend-v6".into(),
             ip: "2001:db8::20".parse().expect("valid IPv6 test address"),
+            weight: None,
+            priority: 0,
+            ports: BTreeMap::new(),
         }];
 
-        assert!(hosts_to_backends_with_routes(&hosts).await.is_empty());
+        assert!(hosts_to_backends_with_routes(&hosts, &[]).await.is_empty());
+    }
+
+    #[test]
+    fn weights_scale_relative_to_the_heaviest_host() {
+        assert_eq!(relative_weights(&[10, 10]), vec![None, None]);
+        assert_eq!(relative_weights(&[0, 0]), vec![None, None]);
+        assert_eq!(
+            relative_weights(&[40, 10, 0]),
+            vec![Some(1000), Some(250), Some(1)]
+        );
     }
 }
@@ -0,0 +1,387 @@
+use crate::build_provider;
+use crate::config::XlbConfig;
+use crate::ebpf;
+use crate::health::HealthChecker;
+use crate::r#loop::{LiveListenPorts, LoopSettings};
+use crate::provider::BackendProvider;
+use crate::status::{
+    AdminAuth, AdminCredentials, ConfigReloadOutcome, ConfigReloadStatus, PortStatus, StatusState,
+    unix_time_ms,
+};
+use crate::system::ListenIface;
+use anyhow::{Context, Result, anyhow};
+use aya::maps::{Array, MapData};
+use log::{info, warn};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use xlb_common::config::ebpf::EbpfConfig;
+
+/// Re-reads the configuration file and applies the settings a running
+/// instance can change: port mappings, the backend provider, the orphan
+/// TTL, drain handling, the shutdown timeout, and the admin credentials.
+/// A file that changes anything else is refused as a whole.
+pub struct ConfigReloader {
+    path: PathBuf,
+    iface: ListenIface,
+    config: XlbConfig,
+    provider: Arc<dyn BackendProvider>,
+    config_map: Array<MapData, EbpfConfig>,
+    loop_settings: watch::Sender<LoopSettings>,
+    health_checker: Option<Arc<HealthChecker>>,
+    admin_credentials: Option<AdminCredentials>,
+    live_listen_ports: Option<Arc<LiveListenPorts>>,
+    status: Arc<StatusState>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        path: PathBuf,
+        iface: ListenIface,
+        config: XlbConfig,
+        provider: Arc<dyn BackendProvider>,
+        config_map: Array<MapData, EbpfConfig>,
+        loop_settings: watch::Sender<LoopSettings>,
+        status: Arc<StatusState>,
+    ) -> Self {
+        Self {
+            path,
+            iface,
+            config,
+            provider,
+            config_map,
+            loop_settings,
+            health_checker: None,
+            admin_credentials: None,
+            live_listen_ports: None,
+            status,
+        }
+    }
+
+    /// Point the health checker at each provider a reload swaps in.
+    pub fn with_health_checks(mut self, checker: Arc<HealthChecker>) -> Self {
+        self.health_checker = Some(checker);
+        self
+    }
+
+    /// Replace the admin API's credentials when `admin.auth` changes.
+    pub fn with_admin_credentials(mut self, credentials: AdminCredentials) -> Self {
+        self.admin_credentials = Some(credentials);
+        self
+    }
+
+    /// Refuse port changes that remove a listen port with open connections.
+    pub fn with_live_listen_ports(mut self, ports: Arc<LiveListenPorts>) -> Self {
+        self.live_listen_ports = Some(ports);
+        self
+    }
+
+    /// Configuration currently in effect.
+    pub fn config(&self) -> &XlbConfig {
+        &self.config
+    }
+
+    /// Provider currently feeding the maintenance loop.
+    pub fn provider(&self) -> &Arc<dyn BackendProvider> {
+        &self.provider
+    }
+
+    /// Reload the configuration file, then record the result in the
+    /// status API and event log.
+    pub async fn reload(&mut self) -> ConfigReloadStatus {
+        let (outcome, changed, error) =
+            match XlbConfig::load(self.path.clone()).context("Failed to load configuration") {
+                Err(err) => (ConfigReloadOutcome::Rejected, Vec::new(), Some(err)),
+                Ok(next) => {
+                    let live_listen_ports = self
+                        .live_listen_ports
+                        .as_ref()
+                        .map(|ports| ports.get())
+                        .unwrap_or_default();
+                    let diff = self.config.diff(&next, &live_listen_ports);
+                    if !diff.restart.is_empty() {
+                        let reasons: Vec<&str> = diff
+                            .restart
+                            .iter()
+                            .filter_map(|setting| restart_reason(setting))
+                            .collect();
+                        let error = if reasons.is_empty() {
+                            anyhow!(
+                                "Changes to {} require a restart; nothing was applied",
+                                diff.restart.join(", ")
+                            )
+                        } else {
+                            anyhow!(
+                                "Changes to {} require a restart ({}); nothing was applied",
+                                diff.restart.join(", "),
+                                reasons.join("; ")
+                            )
+                        };
+                        (ConfigReloadOutcome::Rejected, diff.restart, Some(error))
+                    } else if diff.live.is_empty() {
+                        (ConfigReloadOutcome::Unchanged, Vec::new(), None)
+                    } else {
+                        match self.apply(next, &diff.live).await {
+                            Ok(()) => (ConfigReloadOutcome::Applied, diff.live, None),
+                            Err(err) => (ConfigReloadOutcome::Failed, diff.live, Some(err)),
+                        }
+                    }
+                }
+            };
+
+        match &error {
+            Some(err) => warn!("Configuration reload failed: {err:#}"),
+            None => info!("Configuration reloaded ({outcome:?}): {changed:?}"),
+        }
+        let reload = ConfigReloadStatus {
+            at_unix_ms: unix_time_ms(),
+            outcome,
+            changed: changed.into_iter().map(str::to_string).collect(),
+            error: error.map(|err| format!("{err:#}")),
+        };
+        self.status.record_config_reload(reload.clone());
+        reload
+    }
+
+    /// Start the new provider and update the dataplane before committing
+    /// anything, so a failure leaves the running configuration untouched.
+    async fn apply(&mut self, next: XlbConfig, changed: &[&str]) -> Result<()> {
+        let admin_auth = if changed.contains(&"admin.auth") {
+            let auth = next
+                .admin
+                .auth
+                .as_ref()
+                .map(|auth| AdminAuth::from_env(auth.username.clone()))
+                .transpose()?;
+            Some(auth)
+        } else {
+            None
+        };
+
+        let replacement = if changed.contains(&"provider") {
+            // `diff` keeps HTTP providers restart-only, so there is no
+            // backend store to hand to the admin API.
+            let (provider, kind) = build_provider(&next.provider, &mut None);
+            provider
+                .start()
+                .await
+                .context("Failed to start backend provider")?;
+            Some((provider, kind))
+        } else {
+            None
+        };
+
+        if changed.contains(&"ports") {
+            let written = self
+                .config_map
+                .set(0, ebpf::to_ebpf_config(&next, &self.iface), 0)
+                .context("Failed to update CONFIG map");
+            if let Err(err) = written {
+                if let Some((provider, _)) = &replacement
+                    && let Err(shutdown_err) = provider.shutdown().await
+                {
+                    warn!("Failed to shutdown unused backend provider: {shutdown_err:#}");
+                }
+                return Err(err);
+            }
+        }
+
+        let previous = replacement.as_ref().map(|(provider, kind)| {
+            if let Some(checker) = &self.health_checker {
+                checker.start(provider.clone());
+            }
+            self.status.record_provider_kind(*kind);
+            std::mem::replace(&mut self.provider, provider.clone())
+        });
+        self.loop_settings
+            .send_replace(loop_settings(&next, &self.provider));
+        self.status.record_ports(port_statuses(&next));
+        if let Some(auth) = admin_auth
+            && let Some(credentials) = &self.admin_credentials
+        {
+            if !next.admin.authenticates() && !next.admin.address.is_loopback() {
+                warn!(
+                    "Admin API is unauthenticated and configured on non-loopback address {}",
+                    next.admin.address
+                );
+            }
+            credentials.replace(auth);
+        }
+        self.config = next;
+
+        if let Some(previous) = previous
+            && let Err(err) = previous.shutdown().await
+        {
+            warn!("Failed to shutdown replaced backend provider: {err:#}");
+        }
+        Ok(())
+    }
+}
+
+/// Why a restart-only setting cannot change in a running instance, where
+/// the setting's name alone does not say.
+fn restart_reason(setting: &str) -> Option<&'static str> {
+    match setting {
+        "ports" => Some("a listen port it removes still has open connections"),
+        "otel" => Some(
+            "OpenTelemetry exporters and instruments are created once at startup and cannot be replaced",
+        ),
+        _ => None,
+    }
+}
+
+/// Settings the maintenance loop takes from a configuration.
+pub fn loop_settings(config: &XlbConfig, provider: &Arc<dyn BackendProvider>) -> LoopSettings {
+    LoopSettings {
+        provider: provider.clone(),
+        port_names: config
+            .ports
+            .iter()
+            .map(|port| port.port_name.clone())
+            .collect(),
+        orphan_ttl: Duration::from_secs(config.orphan_ttl_secs as u64),
+        drain: (&config.drain).into(),
+    }
+}
+
+pub fn port_statuses(config: &XlbConfig) -> Vec<PortStatus> {
+    config
+        .ports
+        .iter()
+        .map(|port| PortStatus {
+            listen: port.local_port,
+            backend: port.remote_port,
+            backend_name: port.port_name.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r#loop::utils::LbFlowStats;
+    use crate::provider::FixedProvider;
+    use crate::status::{ProviderKind, StatusMetadata};
+    use xlb_common::config::routing::RoutingMode;
+    use xlb_common::net::{IpVersion, Proto};
+
+    const CONFIG: &str = r#"
+name: reload-test
+listen: auto
+proto: tcp
+ports:
+  - local_port: 80
+    remote_port: 8080
+provider:
+  static:
+    backends:
+      - name: backend-1
+        ip: 127.0.0.1
+mode: nat
+"#;
+
+    /// A flow-map iteration that found open connections on `listen_ports`.
+    fn iteration(listen_ports: &[u16]) -> LbFlowStats {
+        LbFlowStats {
+            flow_map_complete: true,
+            listen_ports: listen_ports.iter().copied().collect(),
+            ..LbFlowStats::default()
+        }
+    }
+
+    fn mappings(config_map: &Array<MapData, EbpfConfig>) -> (u16, u16) {
+        let mapping = config_map.get(&0, 0).expect("read CONFIG").port_mappings[0];
+        (mapping.local_port, mapping.remote_port)
+    }
+
+    #[tokio::test]
+    async fn open_connections_keep_their_listen_port_across_reloads() {
+        // Writing the dataplane configuration needs a real map, which
+        // unprivileged test runs cannot create.
+        let config_map = match Array::<MapData, EbpfConfig>::create(1, 0) {
+            Ok(map) => map,
+            Err(error) => {
+                eprintln!("skipping: cannot create a CONFIG map: {error}");
+                return;
+            }
+        };
+        let path = std::env::temp_dir().join(format!("xlb-reload-{}.yaml", std::process::id()));
+        std::fs::write(&path, CONFIG).expect("write test config");
+        let config = XlbConfig::load(path.clone()).expect("config must load");
+        let iface = ListenIface {
+            name: "lo".into(),
+            ip: "127.0.0.1".parse().expect("valid IP"),
+            ver: IpVersion::Ipv4,
+        };
+        let provider: Arc<dyn BackendProvider> = Arc::new(FixedProvider::new(Vec::new()));
+        let (loop_settings, _settings) = watch::channel(loop_settings(&config, &provider));
+        let status = Arc::new(StatusState::new(StatusMetadata {
+            service: "reload-test".into(),
+            provider: ProviderKind::Static,
+            listen_address: iface.ip,
+            listen_interface: iface.name.clone(),
+            xdp_attachments: Vec::new(),
+            protocol: Proto::Tcp,
+            routing_mode: RoutingMode::Nat,
+            ports: port_statuses(&config),
+        }));
+        let mut config_map = config_map;
+        config_map
+            .set(0, ebpf::to_ebpf_config(&config, &iface), 0)
+            .expect("write CONFIG");
+        let open = Arc::new(LiveListenPorts::default());
+        let mut reloader = ConfigReloader::new(
+            path.clone(),
+            iface,
+            config,
+            provider,
+            config_map,
+            loop_settings,
+            status,
+        )
+        .with_live_listen_ports(open.clone());
+
+        // A connection is open on port 80 while its mapping moves to 81.
+        open.observe(&iteration(&[80]));
+        std::fs::write(&path, CONFIG.replace("local_port: 80", "local_port: 81"))
+            .expect("write test config");
+        let moved = reloader.reload().await;
+        assert_eq!(moved.outcome, ConfigReloadOutcome::Rejected);
+        assert!(
+            moved
+                .error
+                .as_deref()
+                .is_some_and(|error| error.contains("still has open connections")),
+            "{moved:?}"
+        );
+        assert_eq!(mappings(&reloader.config_map), (80, 8080));
+
+        // Its backend port can change; replies from 8080 are still
+        // recognised through the return ports of live flows.
+        std::fs::write(
+            &path,
+            CONFIG.replace("remote_port: 8080", "remote_port: 9090"),
+        )
+        .expect("write test config");
+        let rerouted = reloader.reload().await;
+        assert_eq!(rerouted.outcome, ConfigReloadOutcome::Applied);
+        assert_eq!(mappings(&reloader.config_map), (80, 9090));
+
+        // Once it closes, the listen port can move.
+        open.observe(&iteration(&[]));
+        std::fs::write(&path, CONFIG.replace("local_port: 80", "local_port: 81"))
+            .expect("write test config");
+        let moved = reloader.reload().await;
+        assert_eq!(moved.outcome, ConfigReloadOutcome::Applied);
+        assert_eq!(mappings(&reloader.config_map), (81, 8080));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn otel_rejections_say_why_a_restart_is_needed() {
+        assert!(restart_reason("otel").is_some_and(|reason| reason.contains("startup")));
+        assert_eq!(restart_reason("listen"), None);
+    }
+}
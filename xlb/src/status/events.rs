@@ -0,0 +1,267 @@
+use super::model::STATUS_SCHEMA_VERSION;
+use super::snapshot::unix_time_ms;
+use crate::metrics;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+/// Lifecycle events retained in memory for the events API.
+pub const EVENT_CAPACITY: usize = 1024;
+/// Events returned by a page that does not request a limit.
+pub const DEFAULT_EVENT_PAGE: usize = 100;
+/// Largest page the events API returns.
+pub const MAX_EVENT_PAGE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    BackendAdded,
+    BackendRemoved,
+    BackendDrained,
+    BackendDisabled,
+    BackendEnabled,
+    ReadinessChanged,
+    XdpAttached,
+    OutlierEjected,
+    OutlierRestored,
+    PriorityTierChanged,
+    InvariantViolation,
+    ConfigReloaded,
+    ConfigReloadFailed,
+    ShutdownStarted,
+}
+
+impl EventKind {
+    /// Event name of the exported OpenTelemetry log record.
+    pub const fn event_name(self) -> &'static str {
+        match self {
+            Self::BackendAdded => "xlb.backend.added",
+            Self::BackendRemoved => "xlb.backend.removed",
+            Self::BackendDrained => "xlb.backend.drained",
+            Self::BackendDisabled => "xlb.backend.disabled",
+            Self::BackendEnabled => "xlb.backend.enabled",
+            Self::ReadinessChanged => "xlb.readiness.changed",
+            Self::XdpAttached => "xlb.xdp.attached",
+            Self::OutlierEjected => "xlb.outlier.ejected",
+            Self::OutlierRestored => "xlb.outlier.restored",
+            Self::PriorityTierChanged => "xlb.priority_tier.changed",
+            Self::InvariantViolation => "xlb.flow_pair.invariant_violation",
+            Self::ConfigReloaded => "xlb.config.reloaded",
+            Self::ConfigReloadFailed => "xlb.config.reload_failed",
+            Self::ShutdownStarted => "xlb.shutdown.started",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSeverity {
+    Info,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Event {
+    /// Monotonic identifier, starting at 1, never reused by this process.
+    pub id: u64,
+    pub at_unix_ms: u64,
+    pub kind: EventKind,
+    pub severity: EventSeverity,
+    pub message: String,
+    /// Backend the event concerns, if any.
+    pub backend: Option<IpAddr>,
+}
+
+/// Bounds of one page of the events API. Events are returned oldest first.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+pub struct EventQuery {
+    /// Only events with a greater ID; the page starts at the oldest of them.
+    pub after: Option<u64>,
+    /// Only events with a smaller ID; without `after`, the page ends at the
+    /// newest of them.
+    pub before: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct EventPage {
+    pub schema_version: u16,
+    pub events: Vec<Event>,
+    /// Whether more events match beyond this page: newer ones when paging
+    /// with `after`, older ones otherwise.
+    pub has_more: bool,
+    /// Oldest retained ID. Events between a client's cursor and this ID
+    /// were dropped from the ring.
+    pub oldest_id: Option<u64>,
+    pub newest_id: Option<u64>,
+}
+
+#[derive(Debug)]
+struct EventRing {
+    next_id: u64,
+    events: VecDeque<Event>,
+}
+
+/// Bounded ring of lifecycle events. The oldest events are dropped once
+/// the ring holds `capacity` events.
+#[derive(Debug)]
+pub struct EventLog {
+    capacity: usize,
+    ring: Mutex<EventRing>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(EVENT_CAPACITY)
+    }
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            ring: Mutex::new(EventRing {
+                next_id: 1,
+                events: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Append an event and export it as an OpenTelemetry log record.
+    pub fn record(
+        &self,
+        kind: EventKind,
+        severity: EventSeverity,
+        message: impl Into<String>,
+        backend: Option<IpAddr>,
+    ) -> Event {
+        let event = self.record_at(kind, severity, message.into(), backend, unix_time_ms());
+        metrics::export_event(&event);
+        event
+    }
+
+    fn record_at(
+        &self,
+        kind: EventKind,
+        severity: EventSeverity,
+        message: String,
+        backend: Option<IpAddr>,
+        at_unix_ms: u64,
+    ) -> Event {
+        let mut ring = self.ring.lock().expect("event lock poisoned");
+        let event = Event {
+            id: ring.next_id,
+            at_unix_ms,
+            kind,
+            severity,
+            message,
+            backend,
+        };
+        ring.next_id += 1;
+        if ring.events.len() == self.capacity {
+            ring.events.pop_front();
+        }
+        ring.events.push_back(event.clone());
+        event
+    }
+
+    pub fn page(&self, query: EventQuery) -> EventPage {
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_EVENT_PAGE)
+            .clamp(1, MAX_EVENT_PAGE);
+        let ring = self.ring.lock().expect("event lock poisoned");
+        let matching: Vec<&Event> = ring
+            .events
+            .iter()
+            .filter(|event| query.after.is_none_or(|after| event.id > after))
+            .filter(|event| query.before.is_none_or(|before| event.id < before))
+            .collect();
+        let has_more = matching.len() > limit;
+        let page = if query.after.is_some() {
+            &matching[..matching.len().min(limit)]
+        } else {
+            &matching[matching.len().saturating_sub(limit)..]
+        };
+
+        EventPage {
+            schema_version: STATUS_SCHEMA_VERSION,
+            events: page.iter().map(|event| (*event).clone()).collect(),
+            has_more,
+            oldest_id: ring.events.front().map(|event| event.id),
+            newest_id: ring.events.back().map(|event| event.id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_with(capacity: usize, count: u64) -> EventLog {
+        let log = EventLog::new(capacity);
+        for at in 0..count {
+            log.record_at(
+                EventKind::BackendAdded,
+                EventSeverity::Info,
+                format!("event {at}"),
+                None,
+                at,
+            );
+        }
+        log
+    }
+
+    fn ids(page: &EventPage) -> Vec<u64> {
+        page.events.iter().map(|event| event.id).collect()
+    }
+
+    #[test]
+    fn ring_drops_the_oldest_events_but_never_reuses_ids() {
+        let log = log_with(3, 5);
+        let page = log.page(EventQuery::default());
+
+        assert_eq!(ids(&page), vec![3, 4, 5]);
+        assert_eq!(page.oldest_id, Some(3));
+        assert_eq!(page.newest_id, Some(5));
+        assert!(!page.has_more);
+    }
+
+    #[test]
+    fn pages_forward_from_a_cursor_and_backward_from_the_newest() {
+        let log = log_with(10, 7);
+
+        let forward = log.page(EventQuery {
+            after: Some(2),
+            limit: Some(3),
+            ..Default::default()
+        });
+        assert_eq!(ids(&forward), vec![3, 4, 5]);
+        assert!(forward.has_more);
+        let rest = log.page(EventQuery {
+            after: Some(5),
+            limit: Some(3),
+            ..Default::default()
+        });
+        assert_eq!(ids(&rest), vec![6, 7]);
+        assert!(!rest.has_more);
+
+        let latest = log.page(EventQuery {
+            limit: Some(2),
+            ..Default::default()
+        });
+        assert_eq!(ids(&latest), vec![6, 7]);
+        assert!(latest.has_more);
+        let older = log.page(EventQuery {
+            before: Some(6),
+            limit: Some(2),
+            ..Default::default()
+        });
+        assert_eq!(ids(&older), vec![4, 5]);
+
+        let empty = EventLog::new(10).page(EventQuery::default());
+        assert!(empty.events.is_empty());
+        assert_eq!(empty.oldest_id, None);
+    }
+}
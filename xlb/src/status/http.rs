@@ -1,22 +1,35 @@
-use super::StatusState;
+use super::tls::{AdminTls, TlsClient, TlsListener};
+use super::{
+    AdminOverrideMode, ConfigReloadOutcome, ConfigReloadStatus, EventKind, EventQuery,
+    EventSeverity, Lifecycle, StatusSnapshot, StatusState, status_delta,
+};
+use crate::config::Host;
+use crate::r#loop::{BackendOverrides, ConnectionBrowser, ConnectionQuery};
+use crate::metrics::PrometheusMetrics;
+use crate::provider::{BackendStore, BackendUpdateError};
 use anyhow::{Context, Result, anyhow};
 use axum::body::Body;
-use axum::extract::{Path, Request, State};
+use axum::extract::{ConnectInfo, Extension, Path, Query, Request, State};
 use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
 use axum::middleware::{self, Next};
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
 use axum::response::{IntoResponse, Redirect, Response};
-use axum::routing::get;
+use axum::routing::{get, post, put};
 use axum::{Json, Router};
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
-use log::info;
+use futures::future::BoxFuture;
+use futures::{StreamExt, stream};
+use log::{info, warn};
 use rust_embed::RustEmbed;
+use serde::Deserialize;
 use std::borrow::Cow;
-use std::net::SocketAddr;
-use std::sync::Arc;
+use std::future::IntoFuture;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use subtle::ConstantTimeEq;
-use tokio::sync::oneshot;
+use tokio::sync::{Semaphore, mpsc, oneshot};
 use tokio::task::JoinHandle;
 
 #[derive(RustEmbed)]
@@ -25,7 +38,12 @@ struct AdminUi;
 
 const UI_CONTENT_SECURITY_POLICY: &str = "default-src 'self'; script-src 'self'; style-src 'self' 'unsafe-inline'; img-src 'self' data:; connect-src 'self'; object-src 'none'; base-uri 'self'; frame-ancestors 'none'";
 const ADMIN_PASSWORD_ENV: &str = "XLB_ADMIN_PASSWORD";
+const METRICS_PASSWORD_ENV: &str = "XLB_METRICS_PASSWORD";
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
 const BASIC_AUTH_CHALLENGE: &str = "Basic realm=\"XLB admin\", charset=\"UTF-8\"";
+/// Status streams served at once; further requests are refused until one
+/// closes.
+pub const MAX_STATUS_STREAMS: usize = 64;
 
 /// Credentials protecting the administrative UI and versioned status API.
 /// Deliberately does not implement `Debug` so the password cannot be logged
@@ -38,16 +56,25 @@ pub struct AdminAuth {
 
 impl AdminAuth {
     pub fn from_env(username: String) -> Result<Self> {
-        let password = std::env::var(ADMIN_PASSWORD_ENV).with_context(|| {
-            format!("Admin auth requires environment variable {ADMIN_PASSWORD_ENV}")
-        })?;
-        Self::new(username, password)
+        Self::from_password_env(username, "Admin", ADMIN_PASSWORD_ENV)
     }
 
-    fn new(username: String, password: String) -> Result<Self> {
+    /// Credentials of the Prometheus `/metrics` endpoint, separate from the
+    /// administrative ones so a scraper cannot drain backends.
+    pub fn metrics_from_env(username: String) -> Result<Self> {
+        Self::from_password_env(username, "Metrics", METRICS_PASSWORD_ENV)
+    }
+
+    fn from_password_env(username: String, scope: &str, env: &str) -> Result<Self> {
+        let password = std::env::var(env)
+            .with_context(|| format!("{scope} auth requires environment variable {env}"))?;
+        Self::new(username, password, scope, env)
+    }
+
+    fn new(username: String, password: String, scope: &str, env: &str) -> Result<Self> {
         if password.is_empty() {
             return Err(anyhow!(
-                "Admin auth environment variable {ADMIN_PASSWORD_ENV} cannot be empty"
+                "{scope} auth environment variable {env} cannot be empty"
             ));
         }
         Ok(Self {
@@ -57,6 +84,81 @@ impl AdminAuth {
     }
 }
 
+/// Basic credentials of the admin API, shared with the configuration
+/// reload that replaces them while the server keeps running.
+#[derive(Clone, Default)]
+pub struct AdminCredentials(Arc<RwLock<Option<AdminAuth>>>);
+
+impl AdminCredentials {
+    pub fn new(auth: Option<AdminAuth>) -> Self {
+        Self(Arc::new(RwLock::new(auth)))
+    }
+
+    /// Credentials checked from the next request on.
+    pub fn replace(&self, auth: Option<AdminAuth>) {
+        *self.0.write().expect("admin credentials lock poisoned") = auth;
+    }
+
+    fn current(&self) -> Option<AdminAuth> {
+        self.0
+            .read()
+            .expect("admin credentials lock poisoned")
+            .clone()
+    }
+}
+
+/// How requests to protected routes authenticate: Basic credentials, a
+/// client certificate verified during the TLS handshake, or either.
+#[derive(Clone)]
+struct AdminAccess {
+    auth: AdminCredentials,
+    client_certificates: bool,
+}
+
+impl AdminAccess {
+    fn authenticates(&self) -> bool {
+        self.client_certificates || self.auth.current().is_some()
+    }
+}
+
+/// Username an admin request authenticated as, recorded with the backend
+/// overrides it makes.
+#[derive(Clone)]
+struct AdminUser(Arc<str>);
+
+/// Backend overrides set through the admin API, with the status whose
+/// event log records every change.
+#[derive(Clone)]
+struct OverrideApi {
+    overrides: Arc<BackendOverrides>,
+    status: Arc<StatusState>,
+}
+
+/// Status followed by `/api/v1/status/stream`, with a slot per open stream.
+#[derive(Clone)]
+struct StatusStreams {
+    status: Arc<StatusState>,
+    slots: Arc<Semaphore>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StatusStreamQuery {
+    /// Follow the first snapshot with deltas of the changed backends.
+    #[serde(default)]
+    delta: bool,
+}
+
+/// Configuration reload requested through the admin API, answered by the
+/// task that owns the running configuration.
+pub type ReloadRequest = oneshot::Sender<ConfigReloadStatus>;
+
+/// Prometheus exposition served at `/metrics`, outside the administrative
+/// routes and their credentials.
+pub struct MetricsEndpoint {
+    pub metrics: Arc<PrometheusMetrics>,
+    pub auth: Option<AdminAuth>,
+}
+
 pub struct AdminServerHandle {
     shutdown: Option<oneshot::Sender<()>>,
     exited: Option<oneshot::Receiver<Result<()>>>,
@@ -118,10 +220,17 @@ impl AdminServerHandle {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn start_admin_server(
     listen: SocketAddr,
     status: Arc<StatusState>,
-    auth: Option<AdminAuth>,
+    overrides: Arc<BackendOverrides>,
+    backends: Option<Arc<BackendStore>>,
+    connections: Option<Arc<ConnectionBrowser>>,
+    reloads: Option<mpsc::Sender<ReloadRequest>>,
+    metrics: Option<MetricsEndpoint>,
+    auth: AdminCredentials,
+    tls: Option<AdminTls>,
 ) -> Result<AdminServerHandle> {
     let listener = tokio::net::TcpListener::bind(listen)
         .await
@@ -129,21 +238,47 @@ pub async fn start_admin_server(
     let local_addr = listener
         .local_addr()
         .context("Failed to read admin HTTP server address")?;
-    let app = router(status, auth);
+    let client_certificates = tls.as_ref().is_some_and(AdminTls::client_certificates);
+    let app = router(
+        status,
+        overrides,
+        backends,
+        connections,
+        reloads,
+        metrics,
+        auth,
+        client_certificates,
+    );
     let (shutdown_tx, shutdown_rx) = oneshot::channel();
     let (exit_tx, exit_rx) = oneshot::channel();
+    let shutdown = async {
+        let _ = shutdown_rx.await;
+    };
 
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    let server: BoxFuture<'static, std::io::Result<()>> = match tls {
+        Some(tls) => Box::pin(
+            axum::serve(
+                TlsListener::new(listener, tls)?,
+                app.into_make_service_with_connect_info::<TlsClient>(),
+            )
+            .with_graceful_shutdown(shutdown)
+            .into_future(),
+        ),
+        None => Box::pin(
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown)
+                .into_future(),
+        ),
+    };
     let task = tokio::spawn(async move {
-        let result = axum::serve(listener, app)
-            .with_graceful_shutdown(async {
-                let _ = shutdown_rx.await;
-            })
+        let result = server
             .await
             .context("Admin HTTP server stopped while serving requests");
         let _ = exit_tx.send(result);
     });
 
-    info!("Admin HTTP server listening on http://{local_addr} (UI: /admin/)");
+    info!("Admin HTTP server listening on {scheme}://{local_addr} (UI: /admin/)");
     Ok(AdminServerHandle {
         shutdown: Some(shutdown_tx),
         exited: Some(exit_rx),
@@ -151,39 +286,162 @@ pub async fn start_admin_server(
     })
 }
 
-fn router(status: Arc<StatusState>, auth: Option<AdminAuth>) -> Router {
+#[allow(clippy::too_many_arguments)]
+fn router(
+    status: Arc<StatusState>,
+    overrides: Arc<BackendOverrides>,
+    backends: Option<Arc<BackendStore>>,
+    connections: Option<Arc<ConnectionBrowser>>,
+    reloads: Option<mpsc::Sender<ReloadRequest>>,
+    metrics: Option<MetricsEndpoint>,
+    auth: AdminCredentials,
+    client_certificates: bool,
+) -> Router {
+    // Credentials can be added or removed by a reload, so whether the API
+    // authenticates is decided per request.
+    let access = AdminAccess {
+        auth,
+        client_certificates,
+    };
     let mut administrative = Router::new()
         .route("/", get(admin_redirect))
         .route("/admin", get(admin_redirect))
         .route("/admin/", get(admin_index))
         .route("/admin/{*path}", get(admin_asset))
-        .route("/api/v1/status", get(api_status));
-    if let Some(auth) = auth {
-        administrative =
-            administrative.route_layer(middleware::from_fn_with_state(auth, require_admin_auth));
+        .route("/api/v1/status", get(api_status))
+        .route("/api/v1/events", get(api_events))
+        .with_state(status.clone())
+        .merge(require_authentication(
+            Router::new()
+                .route(
+                    "/api/v1/backends/{address}/drain",
+                    post(drain_backend).delete(undrain_backend),
+                )
+                .route("/api/v1/backends/{address}/disable", post(disable_backend))
+                .route("/api/v1/backends/{address}/enable", post(enable_backend))
+                .with_state(OverrideApi {
+                    overrides,
+                    status: status.clone(),
+                }),
+            access.clone(),
+        ))
+        .merge(
+            Router::new()
+                .route("/api/v1/status/stream", get(api_status_stream))
+                .with_state(StatusStreams {
+                    status: status.clone(),
+                    slots: Arc::new(Semaphore::new(MAX_STATUS_STREAMS)),
+                }),
+        );
+    // Only the HTTP provider takes its backend set from the admin API.
+    if let Some(backends) = backends {
+        administrative = administrative
+            .merge(
+                Router::new()
+                    .route("/api/v1/backends", get(get_backends))
+                    .with_state(backends.clone()),
+            )
+            .merge(require_authentication(
+                Router::new()
+                    .route("/api/v1/backends", put(put_backends).patch(patch_backends))
+                    .with_state(backends),
+                access.clone(),
+            ));
+    }
+    if let Some(connections) = connections {
+        administrative = administrative.merge(require_authentication(
+            Router::new()
+                .route("/api/v1/connections", get(api_connections))
+                .with_state(connections),
+            access.clone(),
+        ));
+    }
+    if let Some(reloads) = reloads {
+        administrative = administrative.merge(require_authentication(
+            Router::new()
+                .route("/api/v1/config/reload", post(reload_config))
+                .with_state(reloads),
+            access.clone(),
+        ));
     }
+    administrative =
+        administrative.route_layer(middleware::from_fn_with_state(access, require_admin_auth));
 
-    Router::new()
-        .merge(administrative)
+    let mut app = Router::new()
         .route("/healthz", get(healthz))
         .route("/readyz", get(readyz))
         .with_state(status)
+        .merge(administrative);
+    if let Some(MetricsEndpoint { metrics, auth }) = metrics {
+        let mut scrape = Router::new()
+            .route("/metrics", get(prometheus_metrics))
+            .with_state(metrics);
+        if let Some(auth) = auth {
+            scrape = scrape.route_layer(middleware::from_fn_with_state(
+                AdminAccess {
+                    auth: AdminCredentials::new(Some(auth)),
+                    client_certificates,
+                },
+                require_admin_auth,
+            ));
+        }
+        app = app.merge(scrape);
+    }
+    app
+}
+
+/// Refuses every request to `routes` unless the admin API authenticates
+/// its clients, so that anyone who can reach it cannot change what it
+/// balances.
+fn require_authentication(routes: Router, access: AdminAccess) -> Router {
+    routes.route_layer(middleware::from_fn_with_state(
+        access,
+        |State(access): State<AdminAccess>, request: Request, next: Next| async move {
+            if !access.authenticates() {
+                return text_response(
+                    StatusCode::FORBIDDEN,
+                    "configure admin.auth or admin.tls.client_ca to use this endpoint",
+                );
+            }
+            next.run(request).await
+        },
+    ))
 }
 
 async fn require_admin_auth(
-    State(auth): State<AdminAuth>,
-    request: Request,
+    State(access): State<AdminAccess>,
+    mut request: Request,
     next: Next,
 ) -> Response {
-    if authorization_matches(request.headers(), &auth) {
+    let auth = access.auth.current();
+    if !access.client_certificates && auth.is_none() {
+        return next.run(request).await;
+    }
+    if access.client_certificates
+        && let Some(certificate) = request
+            .extensions()
+            .get::<ConnectInfo<TlsClient>>()
+            .and_then(|ConnectInfo(client)| client.certificate.clone())
+    {
+        request.extensions_mut().insert(AdminUser(certificate));
+        return next.run(request).await;
+    }
+    if let Some(auth) = &auth
+        && authorization_matches(request.headers(), auth)
+    {
+        request
+            .extensions_mut()
+            .insert(AdminUser(auth.username.clone()));
         return next.run(request).await;
     }
 
     let mut response = text_response(StatusCode::UNAUTHORIZED, "authentication required");
-    response.headers_mut().insert(
-        header::WWW_AUTHENTICATE,
-        HeaderValue::from_static(BASIC_AUTH_CHALLENGE),
-    );
+    if auth.is_some() {
+        response.headers_mut().insert(
+            header::WWW_AUTHENTICATE,
+            HeaderValue::from_static(BASIC_AUTH_CHALLENGE),
+        );
+    }
     response
 }
 
@@ -213,6 +471,18 @@ fn authorization_matches(headers: &HeaderMap, auth: &AdminAuth) -> bool {
     bool::from(username.ct_eq(auth.username.as_bytes()) & password.ct_eq(auth.password.as_bytes()))
 }
 
+async fn prometheus_metrics(State(metrics): State<Arc<PrometheusMetrics>>) -> Response {
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE),
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        metrics.text(),
+    )
+        .into_response()
+}
+
 async fn admin_redirect() -> Redirect {
     Redirect::permanent("/admin/")
 }
@@ -334,6 +604,278 @@ async fn api_status(State(status): State<Arc<StatusState>>) -> Response {
     response
 }
 
+/// Server-sent events of each snapshot the maintenance loop publishes. The
+/// first event is always a full `snapshot`; with `?delta=true` the rest are
+/// `delta` events. A client that reads slowly receives the latest snapshot
+/// once it catches up, not every one it missed.
+async fn api_status_stream(
+    State(streams): State<StatusStreams>,
+    Query(query): Query<StatusStreamQuery>,
+) -> Response {
+    let Ok(slot) = streams.slots.clone().try_acquire_owned() else {
+        return text_response(StatusCode::SERVICE_UNAVAILABLE, "too many status streams");
+    };
+    let mut updates = streams.status.subscribe();
+    updates.mark_unchanged();
+    let first = Arc::new(streams.status.snapshot());
+    let delta = query.delta;
+
+    let initial = snapshot_event(&first);
+    // A snapshot of a shutting-down instance ends the stream, so open
+    // streams do not hold up the admin server's graceful shutdown.
+    let state = (first.lifecycle != Lifecycle::ShuttingDown).then_some((updates, first, slot));
+    let updates = stream::unfold(state, move |state| async move {
+        let (mut updates, previous, slot) = state?;
+        loop {
+            updates.changed().await.ok()?;
+            let next = updates.borrow_and_update().clone();
+            let Some(next) = next else {
+                continue;
+            };
+            let event = if delta {
+                SseEvent::default()
+                    .event("delta")
+                    .json_data(status_delta(&previous, &next))
+            } else {
+                snapshot_event(&next)
+            };
+            let state =
+                (next.lifecycle != Lifecycle::ShuttingDown).then_some((updates, next, slot));
+            return Some((event, state));
+        }
+    });
+
+    let mut response = Sse::new(stream::once(async move { initial }).chain(updates))
+        .keep_alive(KeepAlive::default())
+        .into_response();
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+fn snapshot_event(snapshot: &StatusSnapshot) -> Result<SseEvent, axum::Error> {
+    SseEvent::default().event("snapshot").json_data(snapshot)
+}
+
+async fn api_events(
+    State(status): State<Arc<StatusState>>,
+    Query(query): Query<EventQuery>,
+) -> Response {
+    let mut response = Json(status.events().page(query)).into_response();
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+async fn api_connections(
+    State(connections): State<Arc<ConnectionBrowser>>,
+    Query(query): Query<ConnectionQuery>,
+) -> Response {
+    let listed = tokio::task::spawn_blocking(move || connections.list(&query)).await;
+    let mut response = match listed {
+        Ok(Ok(page)) => Json(page).into_response(),
+        Ok(Err(err)) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": err.to_string() })),
+        )
+            .into_response(),
+        Err(err) => {
+            warn!("Failed to list connections: {err}");
+            text_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to list connections",
+            )
+        }
+    };
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+async fn reload_config(State(reloads): State<mpsc::Sender<ReloadRequest>>) -> Response {
+    let (reply, reloaded) = oneshot::channel();
+    if reloads.send(reply).await.is_err() {
+        return text_response(StatusCode::SERVICE_UNAVAILABLE, "reloads unavailable");
+    }
+    let Ok(reload) = reloaded.await else {
+        return text_response(StatusCode::SERVICE_UNAVAILABLE, "reloads unavailable");
+    };
+    let status = match reload.outcome {
+        ConfigReloadOutcome::Applied | ConfigReloadOutcome::Unchanged => StatusCode::OK,
+        ConfigReloadOutcome::Rejected => StatusCode::UNPROCESSABLE_ENTITY,
+        ConfigReloadOutcome::Failed => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(reload)).into_response()
+}
+
+async fn drain_backend(
+    State(api): State<OverrideApi>,
+    Extension(AdminUser(user)): Extension<AdminUser>,
+    Path(address): Path<IpAddr>,
+) -> Response {
+    if api.set(address, AdminOverrideMode::Drain, user) {
+        text_response(StatusCode::ACCEPTED, "draining")
+    } else {
+        text_response(StatusCode::OK, "already draining")
+    }
+}
+
+async fn disable_backend(
+    State(api): State<OverrideApi>,
+    Extension(AdminUser(user)): Extension<AdminUser>,
+    Path(address): Path<IpAddr>,
+) -> Response {
+    if api.set(address, AdminOverrideMode::Disable, user) {
+        text_response(StatusCode::ACCEPTED, "disabled")
+    } else {
+        text_response(StatusCode::OK, "already disabled")
+    }
+}
+
+async fn undrain_backend(
+    State(api): State<OverrideApi>,
+    Extension(AdminUser(user)): Extension<AdminUser>,
+    Path(address): Path<IpAddr>,
+) -> Response {
+    if api.clear(address, Some(AdminOverrideMode::Drain), user) {
+        text_response(StatusCode::OK, "undrained")
+    } else {
+        text_response(StatusCode::NOT_FOUND, "not drained")
+    }
+}
+
+async fn enable_backend(
+    State(api): State<OverrideApi>,
+    Extension(AdminUser(user)): Extension<AdminUser>,
+    Path(address): Path<IpAddr>,
+) -> Response {
+    if api.clear(address, None, user) {
+        text_response(StatusCode::OK, "enabled")
+    } else {
+        text_response(StatusCode::NOT_FOUND, "not drained or disabled")
+    }
+}
+
+impl OverrideApi {
+    fn set(&self, address: IpAddr, mode: AdminOverrideMode, user: Arc<str>) -> bool {
+        if !self.overrides.set(address, mode, user.to_string()) {
+            return false;
+        }
+
+        let (kind, severity, message) = match mode {
+            AdminOverrideMode::Drain => (
+                EventKind::BackendDrained,
+                EventSeverity::Info,
+                format!("Draining backend {address} by admin request from '{user}'"),
+            ),
+            AdminOverrideMode::Disable => (
+                EventKind::BackendDisabled,
+                EventSeverity::Warning,
+                format!("Disabled backend {address} by admin request from '{user}'"),
+            ),
+        };
+        info!("{message}");
+        self.status
+            .events()
+            .record(kind, severity, message, Some(address));
+        true
+    }
+
+    fn clear(&self, address: IpAddr, mode: Option<AdminOverrideMode>, user: Arc<str>) -> bool {
+        if self.overrides.clear(address, mode).is_none() {
+            return false;
+        }
+
+        let message =
+            format!("Returned backend {address} to the pool by admin request from '{user}'");
+        info!("{message}");
+        self.status.events().record(
+            EventKind::BackendEnabled,
+            EventSeverity::Info,
+            message,
+            Some(address),
+        );
+        true
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplaceBackends {
+    generation: u64,
+    backends: Vec<Host>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PatchBackends {
+    generation: u64,
+    #[serde(default)]
+    add: Vec<Host>,
+    #[serde(default)]
+    remove: Vec<IpAddr>,
+}
+
+async fn get_backends(State(backends): State<Arc<BackendStore>>) -> Response {
+    let mut response = Json(backends.snapshot()).into_response();
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+async fn put_backends(
+    State(backends): State<Arc<BackendStore>>,
+    Json(request): Json<ReplaceBackends>,
+) -> Response {
+    backend_update_response(backends.replace(request.generation, request.backends))
+}
+
+async fn patch_backends(
+    State(backends): State<Arc<BackendStore>>,
+    Json(request): Json<PatchBackends>,
+) -> Response {
+    backend_update_response(backends.patch(request.generation, request.add, &request.remove))
+}
+
+fn backend_update_response(result: Result<u64, BackendUpdateError>) -> Response {
+    let (status, body) = match result {
+        Ok(generation) => {
+            info!("Accepted backend set generation {generation} by admin request");
+            (
+                StatusCode::OK,
+                serde_json::json!({ "generation": generation }),
+            )
+        }
+        Err(BackendUpdateError::Conflict { current }) => (
+            StatusCode::CONFLICT,
+            serde_json::json!({ "error": "generation mismatch", "generation": current }),
+        ),
+        Err(error @ BackendUpdateError::Invalid(_)) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            serde_json::json!({ "error": error.to_string() }),
+        ),
+        Err(error @ BackendUpdateError::Polled) => (
+            StatusCode::CONFLICT,
+            serde_json::json!({ "error": error.to_string() }),
+        ),
+        Err(error @ BackendUpdateError::Persist(_)) => {
+            warn!("Rejected backend update: {error}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({ "error": error.to_string() }),
+            )
+        }
+    };
+
+    let mut response = (status, Json(body)).into_response();
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
 fn text_response(status: StatusCode, body: &'static str) -> Response {
     (
         status,
@@ -349,9 +891,10 @@ fn text_response(status: StatusCode, body: &'static str) -> Response {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::provider::{HttpProvider, HttpSettings};
     use crate::status::{
-        PortStatus, ProviderKind, ReadinessReason, StatusMetadata, StatusState, XdpAttachment,
-        XdpAttachmentMode,
+        EventKind, EventSeverity, PortStatus, ProviderKind, ReadinessReason, StatusMetadata,
+        StatusState, XdpAttachment, XdpAttachmentMode,
     };
     use axum::body::{Body, to_bytes};
     use axum::http::Request;
@@ -372,6 +915,7 @@ mod tests {
             ports: vec![PortStatus {
                 listen: 80,
                 backend: 8080,
+                backend_name: None,
             }],
         }))
     }
@@ -442,9 +986,165 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn events_api_pages_the_event_ring() {
+        let status = state();
+        for interface in ["eth0", "eth1", "eth2"] {
+            status.events().record(
+                EventKind::XdpAttached,
+                EventSeverity::Info,
+                format!("Attached XDP to {interface} in native mode"),
+                None,
+            );
+        }
+        let app = router(
+            status,
+            Arc::default(),
+            None,
+            None,
+            None,
+            None,
+            AdminCredentials::default(),
+            false,
+        );
+
+        let response = request(app.clone(), "/api/v1/events?after=1&limit=1").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CACHE_CONTROL),
+            Some(&HeaderValue::from_static("no-store"))
+        );
+        let value: serde_json::Value =
+            serde_json::from_str(&body(response).await).expect("valid events JSON");
+        assert_eq!(value["schema_version"], 1);
+        assert_eq!(value["events"][0]["id"], 2);
+        assert_eq!(value["events"][0]["kind"], "xdp_attached");
+        assert_eq!(
+            value["events"][0]["message"],
+            "Attached XDP to eth1 in native mode"
+        );
+        assert_eq!(value["has_more"], true);
+        assert_eq!(value["oldest_id"], 1);
+        assert_eq!(value["newest_id"], 3);
+
+        let invalid = request(app, "/api/v1/events?after=latest").await;
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    }
+
+    async fn next_sse_event(
+        events: &mut (impl futures::Stream<Item = Result<axum::body::Bytes, axum::Error>> + Unpin),
+    ) -> (String, serde_json::Value) {
+        let frame = tokio::time::timeout(Duration::from_secs(1), events.next())
+            .await
+            .expect("event within a second")
+            .expect("stream still open")
+            .expect("readable event");
+        let frame = String::from_utf8(frame.to_vec()).expect("UTF-8 event");
+        let (kind, data) = frame
+            .trim_end()
+            .split_once('\n')
+            .expect("event and data lines");
+        (
+            kind.strip_prefix("event: ")
+                .expect("event line")
+                .to_string(),
+            serde_json::from_str(data.strip_prefix("data: ").expect("data line"))
+                .expect("valid event JSON"),
+        )
+    }
+
+    #[tokio::test]
+    async fn status_stream_sends_a_snapshot_then_deltas_until_shutdown() {
+        let status = state();
+        let app = router(
+            status.clone(),
+            Arc::default(),
+            None,
+            None,
+            None,
+            None,
+            AdminCredentials::default(),
+            false,
+        );
+        let response = request(app, "/api/v1/status/stream?delta=true").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("text/event-stream"))
+        );
+        let mut events = response.into_body().into_data_stream();
+
+        let (kind, snapshot) = next_sse_event(&mut events).await;
+        assert_eq!(kind, "snapshot");
+        assert_eq!(snapshot["lifecycle"], "starting");
+        assert_eq!(snapshot["backends"], serde_json::json!([]));
+
+        let stats = crate::r#loop::utils::LbFlowStats {
+            flow_map_complete: true,
+            ..Default::default()
+        };
+        let web = Host {
+            name: "web-1".into(),
+            ip: "10.0.0.1".parse().expect("valid IP"),
+            weight: None,
+            priority: 0,
+            ports: Default::default(),
+        };
+        status.publish(&stats, &[web], &[], true);
+        let (kind, delta) = next_sse_event(&mut events).await;
+        assert_eq!(kind, "delta");
+        assert_eq!(delta["backends"][0]["name"], "web-1");
+        assert_eq!(delta["removed_backends"], serde_json::json!([]));
+
+        status.publish(&stats, &[], &[], true);
+        let (_, delta) = next_sse_event(&mut events).await;
+        assert_eq!(delta["backends"], serde_json::json!([]));
+        assert_eq!(delta["removed_backends"][0], "10.0.0.1");
+
+        status.begin_shutdown();
+        let (_, last) = next_sse_event(&mut events).await;
+        assert_eq!(last["lifecycle"], "shutting_down");
+        assert!(events.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn status_streams_are_limited() {
+        let app = router(
+            state(),
+            Arc::default(),
+            None,
+            None,
+            None,
+            None,
+            AdminCredentials::default(),
+            false,
+        );
+        let mut open = Vec::new();
+        for _ in 0..MAX_STATUS_STREAMS {
+            let response = request(app.clone(), "/api/v1/status/stream").await;
+            assert_eq!(response.status(), StatusCode::OK);
+            open.push(response);
+        }
+        let refused = request(app.clone(), "/api/v1/status/stream").await;
+        assert_eq!(refused.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        open.pop();
+        let reopened = request(app, "/api/v1/status/stream").await;
+        assert_eq!(reopened.status(), StatusCode::OK);
+    }
+
     #[tokio::test]
     async fn router_exposes_only_the_versioned_status_path() {
-        let app = router(state(), None);
+        let app = router(
+            state(),
+            Arc::default(),
+            None,
+            None,
+            None,
+            None,
+            AdminCredentials::default(),
+            false,
+        );
         let status_response = app
             .clone()
             .oneshot(
@@ -469,9 +1169,539 @@ mod tests {
         assert_eq!(old_path_response.status(), StatusCode::NOT_FOUND);
     }
 
+    #[tokio::test]
+    async fn drain_endpoint_requests_and_cancels_backend_drains() {
+        let auth = AdminAuth::new(
+            "operator".into(),
+            "secret".into(),
+            "Admin",
+            ADMIN_PASSWORD_ENV,
+        )
+        .expect("valid test credentials");
+        let overrides = Arc::new(BackendOverrides::default());
+        let app = router(
+            state(),
+            overrides.clone(),
+            None,
+            None,
+            None,
+            None,
+            AdminCredentials::new(Some(auth)),
+            false,
+        );
+        let authorization = format!("Basic {}", BASE64_STANDARD.encode("operator:secret"));
+        let call = |method: &str, uri: &str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header(header::AUTHORIZATION, &authorization)
+                    .body(Body::empty())
+                    .expect("valid request"),
+            )
+        };
+        let address: IpAddr = "10.0.0.7".parse().expect("valid IP");
+        let uri = "/api/v1/backends/10.0.0.7/drain";
+
+        let started = call("POST", uri).await.expect("drain response");
+        assert_eq!(started.status(), StatusCode::ACCEPTED);
+        let repeated = call("POST", uri).await.expect("drain response");
+        assert_eq!(repeated.status(), StatusCode::OK);
+        assert!(!overrides.set(address, AdminOverrideMode::Drain, "operator".to_string()));
+
+        let undrained = call("DELETE", uri).await.expect("undrain response");
+        assert_eq!(undrained.status(), StatusCode::OK);
+        let missing = call("DELETE", uri).await.expect("undrain response");
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+        // Undraining leaves a disabled backend disabled.
+        assert!(overrides.set(address, AdminOverrideMode::Disable, "operator".to_string()));
+        let disabled = call("DELETE", uri).await.expect("undrain response");
+        assert_eq!(disabled.status(), StatusCode::NOT_FOUND);
+
+        let invalid = call("POST", "/api/v1/backends/backend-1/drain")
+            .await
+            .expect("invalid address response");
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn connections_are_refused_without_admin_authentication() {
+        // The browser needs a real flow map, which unprivileged test runs
+        // cannot create.
+        let flows = match aya::maps::HashMap::create(1, 0) {
+            Ok(flows) => flows,
+            Err(error) => {
+                eprintln!("skipping: cannot create a flow map: {error}");
+                return;
+            }
+        };
+        let connections = Arc::new(ConnectionBrowser::new(flows, false));
+        let open = router(
+            state(),
+            Arc::default(),
+            None,
+            Some(connections.clone()),
+            None,
+            None,
+            AdminCredentials::default(),
+            false,
+        );
+        let refused = request(open, "/api/v1/connections").await;
+        assert_eq!(refused.status(), StatusCode::FORBIDDEN);
+
+        let certificate_only = router(
+            state(),
+            Arc::default(),
+            None,
+            Some(connections),
+            None,
+            None,
+            AdminCredentials::default(),
+            true,
+        );
+        let mut listed = Request::builder()
+            .uri("/api/v1/connections")
+            .body(Body::empty())
+            .expect("valid request");
+        listed.extensions_mut().insert(ConnectInfo(TlsClient {
+            address: "192.0.2.50:40000".parse().expect("valid address"),
+            certificate: Some("operator".into()),
+        }));
+        let listed = certificate_only
+            .oneshot(listed)
+            .await
+            .expect("connections response");
+        assert_eq!(listed.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn overrides_are_refused_without_admin_authentication() {
+        let overrides = Arc::new(BackendOverrides::default());
+        let app = router(
+            state(),
+            overrides.clone(),
+            None,
+            None,
+            None,
+            None,
+            AdminCredentials::default(),
+            false,
+        );
+
+        for (method, action) in [
+            ("POST", "drain"),
+            ("DELETE", "drain"),
+            ("POST", "disable"),
+            ("POST", "enable"),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method(method)
+                        .uri(format!("/api/v1/backends/10.0.0.7/{action}"))
+                        .body(Body::empty())
+                        .expect("valid request"),
+                )
+                .await
+                .expect("override response");
+            assert_eq!(
+                response.status(),
+                StatusCode::FORBIDDEN,
+                "{method} {action}"
+            );
+        }
+        assert!(
+            overrides
+                .clear("10.0.0.7".parse().expect("valid IP"), None)
+                .is_none()
+        );
+        assert_eq!(
+            request(app, "/api/v1/status").await.status(),
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn reload_endpoint_reports_the_outcome_of_each_reload() {
+        let auth = AdminAuth::new(
+            "operator".into(),
+            "secret".into(),
+            "Admin",
+            ADMIN_PASSWORD_ENV,
+        )
+        .expect("valid test credentials");
+        let (reloads, mut requests) = mpsc::channel::<ReloadRequest>(1);
+        let app = router(
+            state(),
+            Arc::default(),
+            None,
+            None,
+            Some(reloads.clone()),
+            None,
+            AdminCredentials::new(Some(auth)),
+            false,
+        );
+        let authorization = format!("Basic {}", BASE64_STANDARD.encode("operator:secret"));
+        let call = || {
+            app.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/config/reload")
+                    .header(header::AUTHORIZATION, &authorization)
+                    .body(Body::empty())
+                    .expect("valid request"),
+            )
+        };
+        let reload = |outcome, error: Option<&str>| ConfigReloadStatus {
+            at_unix_ms: 1,
+            outcome,
+            changed: vec!["ports".into()],
+            error: error.map(str::to_string),
+        };
+
+        let responder = tokio::spawn(async move {
+            for outcome in [
+                reload(ConfigReloadOutcome::Applied, None),
+                reload(ConfigReloadOutcome::Rejected, Some("restart required")),
+                reload(ConfigReloadOutcome::Failed, Some("map update failed")),
+            ] {
+                let reply = requests.recv().await.expect("reload request");
+                reply.send(outcome).expect("reload reply");
+            }
+        });
+
+        let applied = call().await.expect("reload response");
+        assert_eq!(applied.status(), StatusCode::OK);
+        let applied: serde_json::Value =
+            serde_json::from_str(&body(applied).await).expect("reload JSON");
+        assert_eq!(applied["outcome"], "applied");
+        assert_eq!(applied["changed"], serde_json::json!(["ports"]));
+
+        let rejected = call().await.expect("reload response");
+        assert_eq!(rejected.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body(rejected).await.contains("restart required"));
+        let failed = call().await.expect("reload response");
+        assert_eq!(failed.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        responder.await.expect("responder task");
+
+        let unavailable = call().await.expect("reload response");
+        assert_eq!(unavailable.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let without_reloads = router(
+            state(),
+            Arc::default(),
+            None,
+            None,
+            None,
+            None,
+            AdminCredentials::default(),
+            false,
+        )
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/config/reload")
+                .body(Body::empty())
+                .expect("valid request"),
+        )
+        .await
+        .expect("reload response");
+        assert_eq!(without_reloads.status(), StatusCode::NOT_FOUND);
+
+        let unauthenticated = router(
+            state(),
+            Arc::default(),
+            None,
+            None,
+            Some(reloads),
+            None,
+            AdminCredentials::default(),
+            false,
+        )
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/config/reload")
+                .body(Body::empty())
+                .expect("valid request"),
+        )
+        .await
+        .expect("reload response");
+        assert_eq!(unauthenticated.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn replaced_credentials_apply_to_the_next_request() {
+        let credentials = AdminCredentials::default();
+        let app = router(
+            state(),
+            Arc::default(),
+            None,
+            None,
+            None,
+            None,
+            credentials.clone(),
+            false,
+        );
+        let enable = || {
+            app.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/backends/10.0.0.7/enable")
+                    .header(
+                        header::AUTHORIZATION,
+                        format!("Basic {}", BASE64_STANDARD.encode("operator:secret")),
+                    )
+                    .body(Body::empty())
+                    .expect("valid request"),
+            )
+        };
+
+        let open = request(app.clone(), "/api/v1/status").await;
+        assert_eq!(open.status(), StatusCode::OK);
+        let refused = enable().await.expect("enable response");
+        assert_eq!(refused.status(), StatusCode::FORBIDDEN);
+
+        credentials.replace(Some(
+            AdminAuth::new(
+                "operator".into(),
+                "secret".into(),
+                "Admin",
+                ADMIN_PASSWORD_ENV,
+            )
+            .expect("valid test credentials"),
+        ));
+        let challenged = request(app.clone(), "/api/v1/status").await;
+        assert_eq!(challenged.status(), StatusCode::UNAUTHORIZED);
+        let enabled = enable().await.expect("enable response");
+        assert_eq!(enabled.status(), StatusCode::NOT_FOUND);
+
+        credentials.replace(None);
+        let reopened = request(app.clone(), "/api/v1/status").await;
+        assert_eq!(reopened.status(), StatusCode::OK);
+        let refused = enable().await.expect("enable response");
+        assert_eq!(refused.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn overrides_record_the_admin_user_and_an_event_per_change() {
+        let auth = AdminAuth::new(
+            "operator".into(),
+            "secret".into(),
+            "Admin",
+            ADMIN_PASSWORD_ENV,
+        )
+        .expect("valid test credentials");
+        let status = state();
+        let overrides = Arc::new(BackendOverrides::default());
+        let app = router(
+            status.clone(),
+            overrides.clone(),
+            None,
+            None,
+            None,
+            None,
+            AdminCredentials::new(Some(auth)),
+            false,
+        );
+        let authorization = format!("Basic {}", BASE64_STANDARD.encode("operator:secret"));
+        let post = |action: &str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/v1/backends/10.0.0.7/{action}"))
+                    .header(header::AUTHORIZATION, &authorization)
+                    .body(Body::empty())
+                    .expect("valid request"),
+            )
+        };
+        let address: IpAddr = "10.0.0.7".parse().expect("valid IP");
+
+        assert_eq!(
+            post("disable").await.expect("disable").status(),
+            StatusCode::ACCEPTED
+        );
+        assert_eq!(
+            post("disable").await.expect("disable").status(),
+            StatusCode::OK
+        );
+        let disabled = overrides
+            .clear(address, Some(AdminOverrideMode::Disable))
+            .expect("disabled by request");
+        assert_eq!(disabled.by, "operator");
+        assert!(disabled.at_unix_ms > 0);
+
+        assert_eq!(
+            post("drain").await.expect("drain").status(),
+            StatusCode::ACCEPTED
+        );
+        assert_eq!(
+            post("enable").await.expect("enable").status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            post("enable").await.expect("enable").status(),
+            StatusCode::NOT_FOUND
+        );
+
+        let events = status.events().page(EventQuery::default()).events;
+        let kinds: Vec<_> = events.iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::BackendDisabled,
+                EventKind::BackendDrained,
+                EventKind::BackendEnabled,
+            ]
+        );
+        assert!(events.iter().all(|event| event.backend == Some(address)));
+        assert_eq!(
+            events[0].message,
+            "Disabled backend 10.0.0.7 by admin request from 'operator'"
+        );
+    }
+
+    #[tokio::test]
+    async fn backend_endpoints_update_the_pushed_set_by_generation() {
+        let dir = std::env::temp_dir().join(format!("xlb-admin-backends-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create test directory");
+        let provider = HttpProvider::new(HttpSettings {
+            state_path: dir.join("backends.json"),
+            poll: None,
+        });
+        let store = provider.store();
+        let unauthenticated = router(
+            state(),
+            Arc::default(),
+            Some(store.clone()),
+            None,
+            None,
+            None,
+            AdminCredentials::default(),
+            false,
+        );
+        let refused = unauthenticated
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/v1/backends")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"generation": 0, "backends": []}"#))
+                    .expect("valid request"),
+            )
+            .await
+            .expect("replace response");
+        assert_eq!(refused.status(), StatusCode::FORBIDDEN);
+        let readable = request(unauthenticated, "/api/v1/backends").await;
+        assert_eq!(readable.status(), StatusCode::OK);
+
+        let auth = AdminAuth::new(
+            "operator".into(),
+            "secret".into(),
+            "Admin",
+            ADMIN_PASSWORD_ENV,
+        )
+        .expect("valid test credentials");
+        let app = router(
+            state(),
+            Arc::default(),
+            Some(store.clone()),
+            None,
+            None,
+            None,
+            AdminCredentials::new(Some(auth)),
+            false,
+        );
+        let authorization = format!("Basic {}", BASE64_STANDARD.encode("operator:secret"));
+        let call = |method: &str, body: &str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri("/api/v1/backends")
+                    .header(header::AUTHORIZATION, &authorization)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .expect("valid request"),
+            )
+        };
+
+        let replaced = call(
+            "PUT",
+            r#"{"generation": 0, "backends": [{"name": "a", "ip": "10.0.0.1"}]}"#,
+        )
+        .await
+        .expect("replace response");
+        assert_eq!(replaced.status(), StatusCode::OK);
+        assert_eq!(body(replaced).await, r#"{"generation":1}"#);
+
+        let stale = call("PATCH", r#"{"generation": 0, "remove": ["10.0.0.1"]}"#)
+            .await
+            .expect("patch response");
+        assert_eq!(stale.status(), StatusCode::CONFLICT);
+        let value: serde_json::Value =
+            serde_json::from_str(&body(stale).await).expect("valid conflict JSON");
+        assert_eq!(value["generation"], 1);
+
+        let invalid = call(
+            "PATCH",
+            r#"{"generation": 1, "add": [{"name": "b", "ip": "10.0.0.1"}, {"name": "c", "ip": "10.0.0.1"}]}"#,
+        )
+        .await
+        .expect("patch response");
+        assert_eq!(invalid.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let patched = call(
+            "PATCH",
+            r#"{"generation": 1, "add": [{"name": "b", "ip": "10.0.0.2"}], "remove": ["10.0.0.1"]}"#,
+        )
+        .await
+        .expect("patch response");
+        assert_eq!(patched.status(), StatusCode::OK);
+
+        let current = call("GET", "").await.expect("backends response");
+        let value: serde_json::Value =
+            serde_json::from_str(&body(current).await).expect("valid backends JSON");
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "generation": 2,
+                "backends": [{ "name": "b", "ip": "10.0.0.2", "priority": 0 }],
+            })
+        );
+
+        let without_provider = request(
+            router(
+                state(),
+                Arc::default(),
+                None,
+                None,
+                None,
+                None,
+                AdminCredentials::default(),
+                false,
+            ),
+            "/api/v1/backends",
+        )
+        .await;
+        assert_eq!(without_provider.status(), StatusCode::NOT_FOUND);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[tokio::test]
     async fn admin_ui_redirects_and_serves_spa_routes_safely() {
-        let app = router(state(), None);
+        let app = router(
+            state(),
+            Arc::default(),
+            None,
+            None,
+            None,
+            None,
+            AdminCredentials::default(),
+            false,
+        );
         let redirect = request(app.clone(), "/").await;
         assert_eq!(redirect.status(), StatusCode::PERMANENT_REDIRECT);
         assert_eq!(
@@ -525,11 +1755,25 @@ mod tests {
 
     #[tokio::test]
     async fn optional_basic_auth_protects_admin_routes_but_not_health_probes() {
-        let auth = AdminAuth::new("operator".into(), "secret:with-colons".into())
-            .expect("valid test credentials");
-        let app = router(state(), Some(auth));
+        let auth = AdminAuth::new(
+            "operator".into(),
+            "secret:with-colons".into(),
+            "Admin",
+            ADMIN_PASSWORD_ENV,
+        )
+        .expect("valid test credentials");
+        let app = router(
+            state(),
+            Arc::default(),
+            None,
+            None,
+            None,
+            None,
+            AdminCredentials::new(Some(auth)),
+            false,
+        );
 
-        for path in ["/", "/admin/", "/api/v1/status"] {
+        for path in ["/", "/admin/", "/api/v1/status", "/api/v1/events"] {
             let response = request(app.clone(), path).await;
             assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{path}");
             assert_eq!(
@@ -544,6 +1788,19 @@ mod tests {
             );
         }
 
+        let drain = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/backends/10.0.0.7/drain")
+                    .body(Body::empty())
+                    .expect("valid request"),
+            )
+            .await
+            .expect("drain response");
+        assert_eq!(drain.status(), StatusCode::UNAUTHORIZED);
+
         let wrong = format!("Basic {}", BASE64_STANDARD.encode("operator:wrong"));
         assert_eq!(
             request_with_authorization(app.clone(), "/api/v1/status", Some(&wrong))
@@ -572,11 +1829,192 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn client_certificates_authenticate_as_an_alternative_to_basic_auth() {
+        let auth = AdminAuth::new(
+            "operator".into(),
+            "secret".into(),
+            "Admin",
+            ADMIN_PASSWORD_ENV,
+        )
+        .expect("valid test credentials");
+        let overrides = Arc::new(BackendOverrides::default());
+        let app = router(
+            state(),
+            overrides.clone(),
+            None,
+            None,
+            None,
+            None,
+            AdminCredentials::new(Some(auth)),
+            true,
+        );
+        let from_client = |certificate: Option<&str>, authorization: Option<&str>| {
+            let mut request = Request::builder().uri("/api/v1/status");
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            let mut request = request.body(Body::empty()).expect("valid request");
+            request.extensions_mut().insert(ConnectInfo(TlsClient {
+                address: "192.0.2.50:40000".parse().expect("valid address"),
+                certificate: certificate.map(Into::into),
+            }));
+            app.clone().oneshot(request)
+        };
+
+        let certificate = from_client(Some("deploy-bot"), None)
+            .await
+            .expect("status response");
+        assert_eq!(certificate.status(), StatusCode::OK);
+        let anonymous = from_client(None, None).await.expect("status response");
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+        let valid = format!("Basic {}", BASE64_STANDARD.encode("operator:secret"));
+        let basic = from_client(None, Some(&valid))
+            .await
+            .expect("status response");
+        assert_eq!(basic.status(), StatusCode::OK);
+
+        // The certificate's name is recorded as the acting admin user.
+        let mut disable = Request::builder()
+            .method("POST")
+            .uri("/api/v1/backends/10.0.0.7/disable")
+            .body(Body::empty())
+            .expect("valid request");
+        disable.extensions_mut().insert(ConnectInfo(TlsClient {
+            address: "192.0.2.50:40000".parse().expect("valid address"),
+            certificate: Some("deploy-bot".into()),
+        }));
+        let disabled = app
+            .clone()
+            .oneshot(disable)
+            .await
+            .expect("disable response");
+        assert_eq!(disabled.status(), StatusCode::ACCEPTED);
+        let disabled = overrides
+            .clear(
+                "10.0.0.7".parse().expect("valid address"),
+                Some(AdminOverrideMode::Disable),
+            )
+            .expect("disabled by request");
+        assert_eq!(disabled.by, "deploy-bot");
+
+        let certificate_only = router(
+            state(),
+            Arc::default(),
+            None,
+            None,
+            None,
+            None,
+            AdminCredentials::default(),
+            true,
+        );
+        let refused = request(certificate_only.clone(), "/api/v1/status").await;
+        assert_eq!(refused.status(), StatusCode::UNAUTHORIZED);
+        assert!(refused.headers().get(header::WWW_AUTHENTICATE).is_none());
+        assert_eq!(
+            request(certificate_only, "/healthz").await.status(),
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn metrics_endpoint_uses_its_own_credentials() {
+        let admin = AdminAuth::new(
+            "operator".into(),
+            "admin".into(),
+            "Admin",
+            ADMIN_PASSWORD_ENV,
+        )
+        .expect("valid test credentials");
+        let scraper = AdminAuth::new(
+            "prometheus".into(),
+            "scrape".into(),
+            "Metrics",
+            METRICS_PASSWORD_ENV,
+        )
+        .expect("valid test credentials");
+        let metrics = |auth| MetricsEndpoint {
+            metrics: Arc::new(PrometheusMetrics::new(10)),
+            auth,
+        };
+
+        let open = router(
+            state(),
+            Arc::default(),
+            None,
+            None,
+            None,
+            Some(metrics(None)),
+            AdminCredentials::new(Some(admin.clone())),
+            false,
+        );
+        let response = request(open, "/metrics").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static(PROMETHEUS_CONTENT_TYPE))
+        );
+
+        let protected = router(
+            state(),
+            Arc::default(),
+            None,
+            None,
+            None,
+            Some(metrics(Some(scraper))),
+            AdminCredentials::new(Some(admin)),
+            false,
+        );
+        let admin_credentials = format!("Basic {}", BASE64_STANDARD.encode("operator:admin"));
+        let scrape_credentials = format!("Basic {}", BASE64_STANDARD.encode("prometheus:scrape"));
+        assert_eq!(
+            request_with_authorization(protected.clone(), "/metrics", Some(&admin_credentials))
+                .await
+                .status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            request_with_authorization(protected.clone(), "/metrics", Some(&scrape_credentials))
+                .await
+                .status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            request_with_authorization(protected, "/api/v1/status", Some(&scrape_credentials))
+                .await
+                .status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            request(
+                router(
+                    state(),
+                    Arc::default(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    AdminCredentials::default(),
+                    false
+                ),
+                "/metrics"
+            )
+            .await
+            .status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
     #[test]
     fn admin_auth_rejects_an_empty_password() {
-        let error = AdminAuth::new("operator".into(), String::new())
-            .err()
-            .expect("empty password must fail");
+        let error = AdminAuth::new(
+            "operator".into(),
+            String::new(),
+            "Admin",
+            ADMIN_PASSWORD_ENV,
+        )
+        .err()
+        .expect("empty password must fail");
         assert!(error.to_string().contains(ADMIN_PASSWORD_ENV));
     }
 
@@ -1,7 +1,11 @@
+mod events;
 mod http;
 mod model;
 mod snapshot;
+mod tls;
 
+pub use events::*;
 pub use http::*;
 pub use model::*;
 pub use snapshot::*;
+pub use tls::*;
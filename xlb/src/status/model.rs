@@ -1,3 +1,4 @@
+use crate::provider::{LoadBalancerStatus, TopologyStatus, WatchStatus};
 use crate::system::ResourceUtilization;
 use serde::Serialize;
 use std::net::IpAddr;
@@ -36,6 +37,11 @@ pub struct XdpAttachment {
 pub enum ProviderKind {
     Static,
     Kubernetes,
+    Dns,
+    File,
+    Consul,
+    Http,
+    Composite,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
@@ -110,6 +116,7 @@ pub struct ReadinessStatus {
 pub struct PortStatus {
     pub listen: u16,
     pub backend: u16,
+    pub backend_name: Option<String>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, PartialEq)]
@@ -165,17 +172,168 @@ pub struct BackendStatus {
     pub connections: ConnectionStatus,
     pub ingress: TrafficStatus,
     pub egress: TrafficStatus,
+    pub health_check: Option<HealthCheckStatus>,
+    pub outlier: Option<OutlierStatus>,
+    pub slow_start: Option<SlowStartStatus>,
+    pub drain: Option<DrainStatus>,
+    /// Operator intent set through the admin API, kept until re-enabled.
+    pub admin_override: Option<AdminOverrideStatus>,
+    pub priority: u32,
+    /// Absent until a handshake with this backend completed recently.
+    pub handshake_latency: Option<HandshakeLatencyStatus>,
+}
+
+/// Time from forwarding a client's SYN to receiving the backend's first
+/// SYN-ACK, over the last one to two minutes.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct HandshakeLatencyStatus {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    /// Handshakes the percentiles are estimated from.
+    pub samples: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCheckState {
+    Pending,
+    Healthy,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct HealthCheckStatus {
+    pub state: HealthCheckState,
+    pub consecutive_successes: u32,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlierReason {
+    ServerResets,
+    HandshakeFailures,
+}
+
+impl OutlierReason {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::ServerResets => "server_resets",
+            Self::HandshakeFailures => "handshake_failures",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct OutlierStatus {
+    pub ejected: bool,
+    pub ejection_remaining_seconds: u64,
+    pub ejections_total: u64,
+    pub last_reason: Option<OutlierReason>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SlowStartStatus {
+    pub ramping: bool,
+    pub weight_percent: f64,
+    pub remaining_seconds: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DrainTrigger {
+    ProviderRemoved,
+    Admin,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DrainState {
+    /// Existing connections may finish before the deadline.
+    Draining,
+    /// The deadline passed and remaining connections are being reset.
+    ForceClosing,
+    /// No connections remain, or the deadline passed without force-close.
+    Drained,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminOverrideMode {
+    /// No new connections; existing ones may finish.
+    Drain,
+    /// No new connections, and existing ones are reset.
+    Disable,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct AdminOverrideStatus {
+    pub mode: AdminOverrideMode,
+    /// Admin user who set the override.
+    pub by: String,
+    pub at_unix_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct DrainStatus {
+    pub state: DrainState,
+    pub trigger: DrainTrigger,
+    pub remaining_connections: u32,
+    pub deadline_remaining_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TierCount {
+    pub priority: u32,
+    pub eligible: usize,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TierTransition {
+    /// Tier receiving new connections before, if any.
+    pub from: Option<u32>,
+    /// Tier receiving new connections after, if any.
+    pub to: Option<u32>,
+    pub at_unix_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct PriorityTierStatus {
+    /// Tier receiving new connections.
+    pub active: Option<u32>,
+    pub min_eligible_backends: usize,
+    /// Backends eligible for new connections in each discovered tier.
+    pub tiers: Vec<TierCount>,
+    pub transitions_total: u64,
+    /// Most recent transitions, oldest first.
+    pub recent_transitions: Vec<TierTransition>,
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
 pub struct ProviderStatus {
     pub kind: ProviderKind,
     pub healthy: bool,
+    pub last_error: Option<String>,
     pub discovered_backends: usize,
     pub routable_backends: usize,
+    /// Locality of the discovered backends, for providers that prefer
+    /// nearby endpoints.
+    pub topology: Option<TopologyStatus>,
+    /// Each watch of a provider that follows several sources.
+    pub watches: Vec<WatchStatus>,
 }
 
-#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+/// One packet counter of the XDP program.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DataplaneCounterStatus {
+    pub name: &'static str,
+    pub per_second: f64,
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
 pub struct DataplaneStatus {
     pub listen_address: IpAddr,
     pub listen_interface: String,
@@ -186,6 +344,12 @@ pub struct DataplaneStatus {
     pub ports: Vec<PortStatus>,
     pub directional_flow_entries: u64,
     pub flow_map_complete: bool,
+    /// Packets by XDP verdict: `pass`, `drop`, `reply`, `forward`, and
+    /// `aborted`. Empty until the first dataplane sample.
+    pub packet_outcomes: Vec<DataplaneCounterStatus>,
+    /// Packets that failed processing, by cause. Every failed packet is
+    /// also counted as a `drop` outcome.
+    pub errors: Vec<DataplaneCounterStatus>,
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq)]
@@ -205,9 +369,47 @@ pub struct StatusSnapshot {
     pub ingress: TrafficStatus,
     pub egress: TrafficStatus,
     pub resources: ResourceStatus,
+    pub priority_tiers: Option<PriorityTierStatus>,
+    /// Kubernetes LoadBalancer Services served on their own VIPs.
+    pub load_balancer: Option<LoadBalancerStatus>,
+    /// Result of the last configuration reload, if there was one.
+    pub config_reload: Option<ConfigReloadStatus>,
     pub backends: Vec<BackendStatus>,
 }
 
+/// A snapshot streamed after the first one: every field of the snapshot,
+/// but only the backends that changed since the previous event.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StatusDelta {
+    #[serde(flatten)]
+    pub status: StatusSnapshot,
+    /// Backends listed by the previous event but no longer present.
+    pub removed_backends: Vec<IpAddr>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigReloadOutcome {
+    /// Every changed setting was applied.
+    Applied,
+    /// The file matched the running configuration.
+    Unchanged,
+    /// The file was invalid or changed settings that need a restart, so
+    /// nothing was applied.
+    Rejected,
+    /// Applying the changes failed and the running configuration was kept.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ConfigReloadStatus {
+    pub at_unix_ms: u64,
+    pub outcome: ConfigReloadOutcome,
+    /// Settings applied, or for a rejected reload those needing a restart.
+    pub changed: Vec<String>,
+    pub error: Option<String>,
+}
+
 fn finite(value: Option<f64>) -> Option<f64> {
     value.filter(|value| value.is_finite())
 }
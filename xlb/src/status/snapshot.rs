@@ -1,12 +1,17 @@
+use super::events::{EventKind, EventLog, EventSeverity};
 use super::model::*;
 use crate::config::Host;
 use crate::r#loop::metrics::Metrics;
-use crate::r#loop::utils::{AggregateFlowStats, LbFlowStats};
+use crate::r#loop::utils::{AggregateFlowStats, LbFlowStats, pack_ip, packed_ip};
+use crate::r#loop::{CounterSample, HandshakeLatencySample};
+use crate::provider::{LoadBalancerStatus, TopologyStatus, WatchStatus};
 use std::collections::{BTreeMap, HashSet};
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
-use std::sync::RwLock;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use xlb_common::types::Backend;
+use tokio::sync::watch;
+use xlb_common::XlbErr;
+use xlb_common::types::{Backend, PacketOutcome};
 
 pub const DEFAULT_MAX_SAMPLE_AGE: Duration = Duration::from_secs(30);
 
@@ -25,10 +30,14 @@ struct Sample {
     provider: ProviderStatus,
     directional_flow_entries: u64,
     flow_map_complete: bool,
+    packet_outcomes: Vec<DataplaneCounterStatus>,
+    dataplane_errors: Vec<DataplaneCounterStatus>,
     connections: ConnectionStatus,
     ingress: TrafficStatus,
     egress: TrafficStatus,
     resources: ResourceStatus,
+    priority_tiers: Option<PriorityTierStatus>,
+    load_balancer: Option<LoadBalancerStatus>,
     backends: Vec<BackendStatus>,
 }
 
@@ -40,6 +49,25 @@ struct StatusInner {
     totals: CumulativeTotals,
     backend_totals: BTreeMap<IpAddr, CumulativeTotals>,
     backend_observed_at: BTreeMap<IpAddr, Instant>,
+    health_checks: BTreeMap<IpAddr, HealthCheckStatus>,
+    outliers: BTreeMap<IpAddr, OutlierStatus>,
+    slow_start: BTreeMap<IpAddr, SlowStartStatus>,
+    drains: BTreeMap<IpAddr, DrainStatus>,
+    admin_overrides: BTreeMap<IpAddr, AdminOverrideStatus>,
+    priority_tiers: Option<PriorityTierStatus>,
+    load_balancer: Option<LoadBalancerStatus>,
+    /// Provider kind and port mappings, replaced by configuration reloads.
+    provider_kind: ProviderKind,
+    ports: Vec<PortStatus>,
+    config_reload: Option<ConfigReloadStatus>,
+    provider_error: Option<String>,
+    provider_topology: Option<TopologyStatus>,
+    provider_watches: Vec<WatchStatus>,
+    /// Readiness last reported as an event.
+    readiness: ReadinessReason,
+    /// Names of the backends discovered by the last published sample; the
+    /// first sample only establishes this baseline.
+    discovered: Option<BTreeMap<IpAddr, String>>,
 }
 
 /// Shared, read-mostly operational state consumed by health checks and the
@@ -50,6 +78,9 @@ pub struct StatusState {
     started_at: Instant,
     max_sample_age: Duration,
     inner: RwLock<StatusInner>,
+    events: EventLog,
+    /// Latest published snapshot, built only while someone is subscribed.
+    published: watch::Sender<Option<Arc<StatusSnapshot>>>,
 }
 
 impl StatusState {
@@ -59,7 +90,6 @@ impl StatusState {
 
     fn with_max_sample_age(metadata: StatusMetadata, max_sample_age: Duration) -> Self {
         Self {
-            metadata,
             started_at: Instant::now(),
             max_sample_age,
             inner: RwLock::new(StatusInner {
@@ -69,16 +99,253 @@ impl StatusState {
                 totals: CumulativeTotals::default(),
                 backend_totals: BTreeMap::new(),
                 backend_observed_at: BTreeMap::new(),
+                health_checks: BTreeMap::new(),
+                outliers: BTreeMap::new(),
+                slow_start: BTreeMap::new(),
+                drains: BTreeMap::new(),
+                admin_overrides: BTreeMap::new(),
+                priority_tiers: None,
+                load_balancer: None,
+                provider_kind: metadata.provider,
+                ports: metadata.ports.clone(),
+                config_reload: None,
+                provider_error: None,
+                provider_topology: None,
+                provider_watches: Vec::new(),
+                readiness: ReadinessReason::Starting,
+                discovered: None,
             }),
+            events: EventLog::default(),
+            published: watch::Sender::new(None),
+            metadata,
         }
     }
 
+    /// Lifecycle events recorded by this instance.
+    pub fn events(&self) -> &EventLog {
+        &self.events
+    }
+
     pub fn mark_running(&self) {
-        self.inner.write().expect("status lock poisoned").lifecycle = Lifecycle::Running;
+        let mut inner = self.inner.write().expect("status lock poisoned");
+        inner.lifecycle = Lifecycle::Running;
+        self.record_readiness_change(&mut inner, Instant::now());
     }
 
     pub fn begin_shutdown(&self) {
-        self.inner.write().expect("status lock poisoned").lifecycle = Lifecycle::ShuttingDown;
+        self.events.record(
+            EventKind::ShutdownStarted,
+            EventSeverity::Info,
+            "Graceful shutdown started",
+            None,
+        );
+        let mut inner = self.inner.write().expect("status lock poisoned");
+        inner.lifecycle = Lifecycle::ShuttingDown;
+        self.record_readiness_change(&mut inner, Instant::now());
+        drop(inner);
+        // Subscribers stop following an instance once it shuts down.
+        self.published
+            .send_replace(Some(Arc::new(self.snapshot_at(Instant::now()))));
+    }
+
+    /// Follow the snapshots published by the maintenance loop. The receiver
+    /// only holds the latest one, so a slow subscriber skips intermediate
+    /// snapshots rather than queueing them.
+    pub fn subscribe(&self) -> watch::Receiver<Option<Arc<StatusSnapshot>>> {
+        self.published.subscribe()
+    }
+
+    /// Record an event when readiness differs from the last reported
+    /// reason. Readiness is evaluated whenever the lifecycle changes or a
+    /// sample is published.
+    fn record_readiness_change(&self, inner: &mut StatusInner, now: Instant) {
+        let readiness = readiness(
+            inner.lifecycle,
+            inner.sample.as_ref(),
+            sample_age(inner, now),
+            self.max_sample_age,
+        );
+        if readiness.reason == inner.readiness {
+            return;
+        }
+        let message = if readiness.ready {
+            format!("Ready (was {})", inner.readiness.as_str())
+        } else {
+            format!(
+                "Not ready: {} (was {})",
+                readiness.reason.as_str(),
+                inner.readiness.as_str()
+            )
+        };
+        let severity = match readiness.reason {
+            ReadinessReason::DataplaneSampleStale
+            | ReadinessReason::BackendProviderUnhealthy
+            | ReadinessReason::NoRoutableBackends => EventSeverity::Warning,
+            _ => EventSeverity::Info,
+        };
+        inner.readiness = readiness.reason;
+        self.events
+            .record(EventKind::ReadinessChanged, severity, message, None);
+    }
+
+    /// Record backends added to or removed from discovery since the last
+    /// published sample.
+    fn record_discovery_changes(&self, inner: &mut StatusInner, discovered_hosts: &[Host]) {
+        let discovered: BTreeMap<IpAddr, String> = discovered_hosts
+            .iter()
+            .map(|host| (host.ip, host.name.clone()))
+            .collect();
+        if let Some(previous) = &inner.discovered {
+            for (address, name) in &discovered {
+                if !previous.contains_key(address) {
+                    self.events.record(
+                        EventKind::BackendAdded,
+                        EventSeverity::Info,
+                        format!("Discovered backend {name} ({address})"),
+                        Some(*address),
+                    );
+                }
+            }
+            for (address, name) in previous {
+                if !discovered.contains_key(address) {
+                    self.events.record(
+                        EventKind::BackendRemoved,
+                        EventSeverity::Info,
+                        format!("Backend {name} ({address}) left discovery"),
+                        Some(*address),
+                    );
+                }
+            }
+        }
+        inner.discovered = Some(discovered);
+    }
+
+    /// Replace the active health-check results attached to the next published
+    /// sample. Backends absent from `checks` report no health-check state.
+    pub fn record_health_checks(&self, checks: BTreeMap<IpAddr, HealthCheckStatus>) {
+        self.inner
+            .write()
+            .expect("status lock poisoned")
+            .health_checks = checks;
+    }
+
+    /// Replace the passive outlier-detection state attached to the next
+    /// published sample.
+    pub fn record_outliers(&self, outliers: BTreeMap<IpAddr, OutlierStatus>) {
+        self.inner.write().expect("status lock poisoned").outliers = outliers;
+    }
+
+    /// Replace the slow-start ramp state attached to the next published sample.
+    pub fn record_slow_start(&self, slow_start: BTreeMap<IpAddr, SlowStartStatus>) {
+        self.inner.write().expect("status lock poisoned").slow_start = slow_start;
+    }
+
+    /// Replace the drain progress attached to the next published sample.
+    pub fn record_drains(&self, drains: BTreeMap<IpAddr, DrainStatus>) {
+        self.inner.write().expect("status lock poisoned").drains = drains;
+    }
+
+    /// Report the result of a configuration reload.
+    pub fn record_config_reload(&self, reload: ConfigReloadStatus) {
+        let (kind, severity, message) = match reload.outcome {
+            ConfigReloadOutcome::Applied => (
+                EventKind::ConfigReloaded,
+                EventSeverity::Info,
+                format!("Reloaded configuration: {}", reload.changed.join(", ")),
+            ),
+            ConfigReloadOutcome::Unchanged => (
+                EventKind::ConfigReloaded,
+                EventSeverity::Info,
+                "Reloaded configuration without changes".to_string(),
+            ),
+            ConfigReloadOutcome::Rejected | ConfigReloadOutcome::Failed => (
+                EventKind::ConfigReloadFailed,
+                EventSeverity::Warning,
+                format!(
+                    "Configuration reload failed: {}",
+                    reload.error.as_deref().unwrap_or("unknown error")
+                ),
+            ),
+        };
+
+        {
+            let mut inner = self.inner.write().expect("status lock poisoned");
+            inner.config_reload = Some(reload);
+        }
+        self.events.record(kind, severity, message, None);
+    }
+
+    /// Report the provider now feeding the pool after a reload swapped it.
+    pub fn record_provider_kind(&self, kind: ProviderKind) {
+        self.inner
+            .write()
+            .expect("status lock poisoned")
+            .provider_kind = kind;
+    }
+
+    /// Report the port mappings now programmed into the dataplane.
+    pub fn record_ports(&self, ports: Vec<PortStatus>) {
+        self.inner.write().expect("status lock poisoned").ports = ports;
+    }
+
+    /// Replace the admin overrides attached to the next published sample.
+    pub fn record_admin_overrides(&self, overrides: BTreeMap<IpAddr, AdminOverrideStatus>) {
+        self.inner
+            .write()
+            .expect("status lock poisoned")
+            .admin_overrides = overrides;
+    }
+
+    /// Replace the priority tier selection attached to the next published
+    /// sample.
+    pub fn record_priority_tiers(&self, priority_tiers: PriorityTierStatus) {
+        self.inner
+            .write()
+            .expect("status lock poisoned")
+            .priority_tiers = Some(priority_tiers);
+    }
+
+    /// Replace the load balancer controller state attached to the next
+    /// published sample.
+    pub fn record_load_balancer(&self, load_balancer: LoadBalancerStatus) {
+        self.inner
+            .write()
+            .expect("status lock poisoned")
+            .load_balancer = Some(load_balancer);
+    }
+
+    pub fn record_provider_error(&self, error: Option<String>) {
+        self.inner
+            .write()
+            .expect("status lock poisoned")
+            .provider_error = error;
+    }
+
+    pub fn record_provider_topology(&self, topology: Option<TopologyStatus>) {
+        self.inner
+            .write()
+            .expect("status lock poisoned")
+            .provider_topology = topology;
+    }
+
+    pub fn record_provider_watches(&self, watches: Vec<WatchStatus>) {
+        self.inner
+            .write()
+            .expect("status lock poisoned")
+            .provider_watches = watches;
+    }
+
+    /// Time each tracked backend has spent in the pool as of `now`, measured
+    /// from the first observation reported as `time_in_pool_seconds`.
+    /// Backends first seen by the next sample are absent.
+    pub fn time_in_pool(&self, now: Instant) -> BTreeMap<IpAddr, Duration> {
+        self.inner
+            .read()
+            .expect("status lock poisoned")
+            .backend_observed_at
+            .iter()
+            .map(|(address, observed_at)| (*address, now.saturating_duration_since(*observed_at)))
+            .collect()
     }
 
     pub fn publish(
@@ -156,6 +423,13 @@ impl StatusState {
             &inner.backend_totals,
         );
         apply_backend_pool_durations(&mut inner.backend_observed_at, &mut backends, sampled_at);
+        for backend in &mut backends {
+            backend.health_check = inner.health_checks.get(&backend.address).cloned();
+            backend.outlier = inner.outliers.get(&backend.address).cloned();
+            backend.slow_start = inner.slow_start.get(&backend.address).cloned();
+            backend.drain = inner.drains.get(&backend.address).cloned();
+            backend.admin_override = inner.admin_overrides.get(&backend.address).cloned();
+        }
         let discovered_backends = backends.iter().filter(|backend| backend.discovered).count();
         let routable_backend_count = backends
             .iter()
@@ -165,20 +439,43 @@ impl StatusState {
         inner.sample = Some(Sample {
             sampled_at_unix_ms,
             provider: ProviderStatus {
-                kind: self.metadata.provider,
+                kind: inner.provider_kind,
                 healthy: provider_healthy,
+                last_error: inner.provider_error.clone(),
                 discovered_backends,
                 routable_backends: routable_backend_count,
+                topology: inner.provider_topology.clone(),
+                watches: inner.provider_watches.clone(),
             },
             directional_flow_entries: stats.flow_map_entries,
             flow_map_complete: stats.flow_map_complete,
+            packet_outcomes: counter_statuses(
+                &stats.dataplane.outcomes,
+                PacketOutcome::ALL.map(PacketOutcome::label),
+                sample_seconds,
+            ),
+            dataplane_errors: counter_statuses(
+                &stats.dataplane.errors,
+                XlbErr::ALL.map(XlbErr::label),
+                sample_seconds,
+            ),
             connections,
             ingress,
             egress,
             resources: stats.resource_utilization.into(),
+            priority_tiers: inner.priority_tiers.clone(),
+            load_balancer: inner.load_balancer.clone(),
             backends,
         });
         inner.sampled_at = Some(sampled_at);
+
+        self.record_discovery_changes(&mut inner, discovered_hosts);
+        self.record_readiness_change(&mut inner, sampled_at);
+        drop(inner);
+        if self.published.receiver_count() > 0 {
+            self.published
+                .send_replace(Some(Arc::new(self.snapshot_at(sampled_at))));
+        }
     }
 
     pub fn readiness(&self) -> ReadinessStatus {
@@ -229,10 +526,13 @@ impl StatusState {
             .as_ref()
             .map(|sample| sample.provider.clone())
             .unwrap_or(ProviderStatus {
-                kind: self.metadata.provider,
+                kind: inner.provider_kind,
                 healthy: false,
+                last_error: None,
                 discovered_backends: 0,
                 routable_backends: 0,
+                topology: None,
+                watches: Vec::new(),
             });
 
         StatusSnapshot {
@@ -258,13 +558,21 @@ impl StatusState {
                 xdp_attachments: self.metadata.xdp_attachments.clone(),
                 protocol: self.metadata.protocol,
                 routing_mode: self.metadata.routing_mode,
-                ports: self.metadata.ports.clone(),
+                ports: inner.ports.clone(),
                 directional_flow_entries: sample
                     .as_ref()
                     .map_or(0, |sample| sample.directional_flow_entries),
                 flow_map_complete: sample
                     .as_ref()
                     .is_some_and(|sample| sample.flow_map_complete),
+                packet_outcomes: sample
+                    .as_ref()
+                    .map(|sample| sample.packet_outcomes.clone())
+                    .unwrap_or_default(),
+                errors: sample
+                    .as_ref()
+                    .map(|sample| sample.dataplane_errors.clone())
+                    .unwrap_or_default(),
             },
             connections: sample
                 .as_ref()
@@ -282,11 +590,57 @@ impl StatusState {
                 .as_ref()
                 .map(|sample| sample.resources.clone())
                 .unwrap_or_default(),
+            priority_tiers: sample
+                .as_ref()
+                .and_then(|sample| sample.priority_tiers.clone()),
+            load_balancer: sample
+                .as_ref()
+                .and_then(|sample| sample.load_balancer.clone()),
+            config_reload: inner.config_reload.clone(),
             backends: sample.map_or_else(Vec::new, |sample| sample.backends),
         }
     }
 }
 
+/// The backends of `next` that changed since `previous`, and the addresses
+/// of those it no longer lists. A backend whose only change is
+/// `time_in_pool_seconds` is left out; that grows with the wall clock.
+pub fn status_delta(previous: &StatusSnapshot, next: &StatusSnapshot) -> StatusDelta {
+    let previous_backends: BTreeMap<IpAddr, &BackendStatus> = previous
+        .backends
+        .iter()
+        .map(|backend| (backend.address, backend))
+        .collect();
+    let mut status = next.clone();
+    status
+        .backends
+        .retain_mut(|backend| match previous_backends.get(&backend.address) {
+            Some(previous) => {
+                let time_in_pool = std::mem::replace(
+                    &mut backend.time_in_pool_seconds,
+                    previous.time_in_pool_seconds,
+                );
+                let changed = *backend != **previous;
+                backend.time_in_pool_seconds = time_in_pool;
+                changed
+            }
+            None => true,
+        });
+    let next_addresses: HashSet<IpAddr> = next
+        .backends
+        .iter()
+        .map(|backend| backend.address)
+        .collect();
+    let removed_backends = previous_backends
+        .into_keys()
+        .filter(|address| !next_addresses.contains(address))
+        .collect();
+    StatusDelta {
+        status,
+        removed_backends,
+    }
+}
+
 fn sample_age(inner: &StatusInner, now: Instant) -> Option<Duration> {
     inner
         .sampled_at
@@ -372,6 +726,13 @@ fn backend_statuses(
             connections: ConnectionStatus::default(),
             ingress: TrafficStatus::default(),
             egress: TrafficStatus::default(),
+            health_check: None,
+            outlier: None,
+            slow_start: None,
+            drain: None,
+            admin_override: None,
+            priority: host.priority,
+            handshake_latency: None,
         });
     }
 
@@ -386,6 +747,13 @@ fn backend_statuses(
             connections: ConnectionStatus::default(),
             ingress: TrafficStatus::default(),
             egress: TrafficStatus::default(),
+            health_check: None,
+            outlier: None,
+            slow_start: None,
+            drain: None,
+            admin_override: None,
+            priority: 0,
+            handshake_latency: None,
         });
         backend.connections =
             connection_status(aggregate, sample_seconds, &CumulativeTotals::default());
@@ -400,11 +768,24 @@ fn backend_statuses(
         if let Some(totals) = backend_totals.get(address) {
             apply_cumulative_totals(backend, totals);
         }
+        backend.handshake_latency = stats
+            .handshake_latency
+            .get(&pack_ip(*address))
+            .and_then(handshake_latency_status);
     }
 
     backends.into_values().collect()
 }
 
+fn handshake_latency_status(sample: &HandshakeLatencySample) -> Option<HandshakeLatencyStatus> {
+    Some(HandshakeLatencyStatus {
+        p50_ms: sample.recent_percentile_ms(0.50)?,
+        p95_ms: sample.recent_percentile_ms(0.95)?,
+        p99_ms: sample.recent_percentile_ms(0.99)?,
+        samples: sample.recent_count(),
+    })
+}
+
 fn apply_backend_pool_durations(
     observed_at: &mut BTreeMap<IpAddr, Instant>,
     backends: &mut [BackendStatus],
@@ -473,6 +854,22 @@ fn traffic_status(metrics: &Metrics, sample_seconds: f64, bytes_total: u64) -> T
     }
 }
 
+fn counter_statuses<const N: usize>(
+    samples: &[CounterSample; N],
+    names: [&'static str; N],
+    sample_seconds: f64,
+) -> Vec<DataplaneCounterStatus> {
+    samples
+        .iter()
+        .zip(names)
+        .map(|(sample, name)| DataplaneCounterStatus {
+            name,
+            per_second: rate(sample.delta, sample_seconds),
+            total: sample.total,
+        })
+        .collect()
+}
+
 fn rate(value: u64, seconds: f64) -> f64 {
     finite_or_zero(value as f64 / positive_finite(seconds))
 }
@@ -489,15 +886,7 @@ fn finite_or_zero(value: f64) -> f64 {
     if value.is_finite() { value } else { 0.0 }
 }
 
-fn packed_ip(ip: u128) -> IpAddr {
-    if ip <= u128::from(u32::MAX) {
-        IpAddr::V4(Ipv4Addr::from(ip as u32))
-    } else {
-        IpAddr::V6(Ipv6Addr::from(ip))
-    }
-}
-
-fn unix_time_ms() -> u64 {
+pub(crate) fn unix_time_ms() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
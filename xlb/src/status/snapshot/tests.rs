@@ -1,5 +1,6 @@
 use super::*;
 use crate::r#loop::utils::LbFlowStats;
+use crate::status::{EventKind, EventQuery, EventSeverity};
 use xlb_common::net::IpVersion;
 
 fn metadata() -> StatusMetadata {
@@ -17,6 +18,7 @@ fn metadata() -> StatusMetadata {
         ports: vec![PortStatus {
             listen: 80,
             backend: 8080,
+            backend_name: None,
         }],
     }
 }
@@ -25,6 +27,9 @@ fn host(name: &str, ip: &str) -> Host {
     Host {
         name: name.into(),
         ip: ip.parse().expect("valid IP"),
+        weight: None,
+        priority: 0,
+        ports: BTreeMap::new(),
     }
 }
 
@@ -117,6 +122,179 @@ fn readiness_requires_running_fresh_provider_and_routable_backend() {
     assert!(state.health().healthy);
 }
 
+#[test]
+fn discovery_and_readiness_changes_are_recorded_as_events() {
+    let state = StatusState::new(metadata());
+    state.mark_running();
+    state.publish(
+        &stats(),
+        &[host("backend-a", "10.0.0.1"), host("backend-b", "10.0.0.2")],
+        &[backend("10.0.0.1")],
+        true,
+    );
+    state.publish(
+        &stats(),
+        &[host("backend-b", "10.0.0.2"), host("backend-c", "10.0.0.3")],
+        &[backend("10.0.0.2")],
+        true,
+    );
+    state.publish(&stats(), &[host("backend-c", "10.0.0.3")], &[], true);
+    state.begin_shutdown();
+
+    let events: Vec<(EventKind, Option<IpAddr>)> = state
+        .events()
+        .page(EventQuery::default())
+        .events
+        .into_iter()
+        .map(|event| (event.kind, event.backend))
+        .collect();
+    let address = |ip: &str| Some(ip.parse().expect("valid IP"));
+    assert_eq!(
+        events,
+        vec![
+            // The first sample is a baseline, not a burst of additions.
+            (EventKind::ReadinessChanged, None),
+            (EventKind::ReadinessChanged, None),
+            (EventKind::BackendAdded, address("10.0.0.3")),
+            (EventKind::BackendRemoved, address("10.0.0.1")),
+            (EventKind::BackendRemoved, address("10.0.0.2")),
+            (EventKind::ReadinessChanged, None),
+            (EventKind::ShutdownStarted, None),
+            (EventKind::ReadinessChanged, None),
+        ]
+    );
+    let page = state.events().page(EventQuery::default());
+    assert_eq!(page.events[5].severity, EventSeverity::Warning);
+    assert_eq!(
+        page.events[5].message,
+        "Not ready: no_routable_backends (was ready)"
+    );
+}
+
+#[test]
+fn provider_error_is_reported_with_the_next_sample() {
+    let state = StatusState::new(metadata());
+    state.mark_running();
+
+    state.record_provider_error(Some("invalid backend file".into()));
+    state.publish(&stats(), &[], &[], false);
+    let provider = state.snapshot().provider;
+    assert!(!provider.healthy);
+    assert_eq!(provider.last_error.as_deref(), Some("invalid backend file"));
+
+    state.record_provider_error(None);
+    state.publish(&stats(), &[], &[], true);
+    assert_eq!(state.snapshot().provider.last_error, None);
+}
+
+#[test]
+fn config_reloads_update_the_snapshot_and_record_events() {
+    let state = StatusState::new(metadata());
+    state.mark_running();
+    assert_eq!(state.snapshot().config_reload, None);
+
+    state.record_provider_kind(ProviderKind::Dns);
+    state.record_ports(vec![PortStatus {
+        listen: 443,
+        backend: 8443,
+        backend_name: None,
+    }]);
+    state.record_config_reload(ConfigReloadStatus {
+        at_unix_ms: 1,
+        outcome: ConfigReloadOutcome::Applied,
+        changed: vec!["ports".into(), "provider".into()],
+        error: None,
+    });
+    let snapshot = state.snapshot();
+    assert_eq!(snapshot.provider.kind, ProviderKind::Dns);
+    assert_eq!(snapshot.dataplane.ports[0].listen, 443);
+    assert_eq!(
+        snapshot.config_reload.map(|reload| reload.outcome),
+        Some(ConfigReloadOutcome::Applied)
+    );
+    state.publish(&stats(), &[], &[], true);
+    assert_eq!(state.snapshot().provider.kind, ProviderKind::Dns);
+
+    state.record_config_reload(ConfigReloadStatus {
+        at_unix_ms: 2,
+        outcome: ConfigReloadOutcome::Rejected,
+        changed: vec!["listen".into()],
+        error: Some("Changes to listen require a restart; nothing was applied".into()),
+    });
+    let events = state.events().page(EventQuery::default()).events;
+    let reloads: Vec<_> = events
+        .iter()
+        .filter(|event| {
+            matches!(
+                event.kind,
+                EventKind::ConfigReloaded | EventKind::ConfigReloadFailed
+            )
+        })
+        .map(|event| (event.kind, event.severity, event.message.as_str()))
+        .collect();
+    assert_eq!(
+        reloads,
+        vec![
+            (
+                EventKind::ConfigReloaded,
+                EventSeverity::Info,
+                "Reloaded configuration: ports, provider"
+            ),
+            (
+                EventKind::ConfigReloadFailed,
+                EventSeverity::Warning,
+                "Configuration reload failed: Changes to listen require a restart; nothing was applied"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn priority_tiers_are_reported_with_the_next_sample() {
+    let state = StatusState::new(metadata());
+    state.mark_running();
+    let standby = Host {
+        priority: 1,
+        ports: BTreeMap::new(),
+        ..host("standby", "10.0.0.2")
+    };
+    let tiers = PriorityTierStatus {
+        active: Some(1),
+        min_eligible_backends: 1,
+        tiers: vec![
+            TierCount {
+                priority: 0,
+                eligible: 0,
+            },
+            TierCount {
+                priority: 1,
+                eligible: 1,
+            },
+        ],
+        transitions_total: 1,
+        recent_transitions: vec![TierTransition {
+            from: Some(0),
+            to: Some(1),
+            at_unix_ms: 1_000,
+        }],
+    };
+
+    state.record_priority_tiers(tiers.clone());
+    assert_eq!(state.snapshot().priority_tiers, None);
+    state.publish(
+        &stats(),
+        &[host("primary", "10.0.0.1"), standby],
+        &[backend("10.0.0.2")],
+        true,
+    );
+
+    let snapshot = state.snapshot();
+    assert_eq!(snapshot.priority_tiers, Some(tiers));
+    assert_eq!(backend_status(&snapshot, "10.0.0.1").priority, 0);
+    assert_eq!(backend_status(&snapshot, "10.0.0.2").priority, 1);
+    assert!(backend_status(&snapshot, "10.0.0.2").available_for_new_connections);
+}
+
 #[test]
 fn stale_samples_make_running_instance_unready() {
     let state = StatusState::with_max_sample_age(metadata(), Duration::from_secs(5));
@@ -173,6 +351,35 @@ fn snapshot_reports_rates_totals_and_unavailable_draining_backends() {
     assert_eq!(snapshot.backends[1].connections.active, 2);
 }
 
+#[test]
+fn snapshot_reports_dataplane_outcomes_and_errors_by_name() {
+    let state = StatusState::new(metadata());
+    assert!(state.snapshot().dataplane.packet_outcomes.is_empty());
+
+    let mut stats = stats();
+    stats.dataplane.outcomes[PacketOutcome::Forward as usize] = CounterSample {
+        total: 900,
+        delta: 40,
+    };
+    stats.dataplane.errors[XlbErr::ErrNoEphemeralPorts as usize] =
+        CounterSample { total: 7, delta: 3 };
+    state.publish(&stats, &[], &[], true);
+    let dataplane = state.snapshot().dataplane;
+
+    let forward = &dataplane.packet_outcomes[PacketOutcome::Forward as usize];
+    assert_eq!(forward.name, "forward");
+    assert_eq!(forward.per_second, 20.0);
+    assert_eq!(forward.total, 900);
+    let exhausted = dataplane
+        .errors
+        .iter()
+        .find(|counter| counter.name == "no_ephemeral_ports")
+        .expect("every error is reported");
+    assert_eq!(exhausted.per_second, 1.5);
+    assert_eq!(exhausted.total, 7);
+    assert_eq!(dataplane.errors.len(), XlbErr::COUNT as usize);
+}
+
 #[test]
 fn backend_time_in_pool_survives_draining_and_resets_after_removal() {
     let state = StatusState::new(metadata());
@@ -425,3 +632,81 @@ fn non_finite_values_never_reach_status_json() {
     assert_eq!(snapshot.resources.overall_percent, None);
     serde_json::to_string(&snapshot).expect("status snapshot is valid JSON");
 }
+
+#[test]
+fn backends_report_recent_handshake_percentiles() {
+    let state = StatusState::new(metadata());
+    let mut stats = stats();
+    let mut sample = HandshakeLatencySample::default();
+    // 1,000 handshakes between 512 µs and 1,024 µs.
+    sample.recent[10] = 1_000;
+    stats
+        .handshake_latency
+        .insert(backend("10.0.0.1").ip, sample);
+    state.publish(
+        &stats,
+        &[host("a", "10.0.0.1"), host("b", "10.0.0.2")],
+        &[],
+        true,
+    );
+    let snapshot = state.snapshot();
+
+    let latency = backend_status(&snapshot, "10.0.0.1")
+        .handshake_latency
+        .as_ref()
+        .expect("recent handshakes are reported");
+    assert_eq!(latency.samples, 1_000);
+    assert_eq!(latency.p50_ms, 0.768);
+    assert_eq!(latency.p99_ms, 1.01888);
+    assert!(
+        backend_status(&snapshot, "10.0.0.2")
+            .handshake_latency
+            .is_none()
+    );
+}
+
+#[test]
+fn deltas_list_changed_and_removed_backends_but_not_time_in_pool() {
+    let state = StatusState::new(metadata());
+    let first_at = Instant::now();
+    let hosts = [host("backend-a", "10.0.0.1"), host("backend-b", "10.0.0.2")];
+    let complete = LbFlowStats {
+        sample_duration_seconds: 1.0,
+        flow_map_complete: true,
+        ..Default::default()
+    };
+    state.publish_at(
+        &complete,
+        &hosts,
+        &[backend("10.0.0.1"), backend("10.0.0.2")],
+        true,
+        first_at,
+        1,
+    );
+    let first = state.snapshot_at(first_at);
+
+    let second_at = first_at + Duration::from_secs(5);
+    state.publish_at(&complete, &hosts[..1], &[], true, second_at, 2);
+    let second = state.snapshot_at(second_at);
+    let delta = status_delta(&first, &second);
+    assert_eq!(delta.status.backends.len(), 1);
+    assert!(!delta.status.backends[0].available_for_new_connections);
+    assert_eq!(
+        delta.removed_backends,
+        vec!["10.0.0.2".parse::<IpAddr>().expect("valid IP")]
+    );
+    let value = serde_json::to_value(&delta).expect("delta serializes");
+    assert_eq!(value["service"], "test-lb");
+    assert_eq!(value["removed_backends"][0], "10.0.0.2");
+
+    let third_at = second_at + Duration::from_secs(5);
+    state.publish_at(&complete, &hosts[..1], &[], true, third_at, 3);
+    let third = state.snapshot_at(third_at);
+    assert_ne!(
+        backend_status(&third, "10.0.0.1").time_in_pool_seconds,
+        backend_status(&second, "10.0.0.1").time_in_pool_seconds
+    );
+    let unchanged = status_delta(&second, &third);
+    assert!(unchanged.status.backends.is_empty());
+    assert!(unchanged.removed_backends.is_empty());
+}
@@ -0,0 +1,575 @@
+use crate::config::AdminTlsConfig;
+use anyhow::{Context, Result, bail};
+use axum::extract::connect_info::Connected;
+use axum::serve::{IncomingStream, Listener};
+use futures::StreamExt;
+use inotify::{Inotify, WatchMask};
+use log::{debug, info, warn};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use std::collections::BTreeSet;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+use webpki::EndEntityCert;
+
+/// Directory events after which the certificate files are read again, as
+/// the file provider does for its backend file.
+const RELOAD_EVENTS: WatchMask = WatchMask::CLOSE_WRITE
+    .union(WatchMask::MOVED_TO)
+    .union(WatchMask::MOVED_FROM)
+    .union(WatchMask::DELETE);
+/// A client that has not finished its handshake by then is disconnected.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Pause after a failed accept, which usually means the process is out of
+/// file descriptors.
+const ACCEPT_RETRY: Duration = Duration::from_secs(1);
+/// Completed handshakes waiting for the HTTP server to pick them up.
+const ACCEPTED_BACKLOG: usize = 64;
+/// DER encoding of the commonName attribute type, 2.5.4.3.
+const COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+/// DER tags of the string types a commonName is encoded with in practice:
+/// UTF8String, PrintableString, and IA5String.
+const NAME_STRING_TAGS: [u8; 3] = [0x0c, 0x13, 0x16];
+
+/// Server side of the HTTPS admin listener. The configuration is rebuilt
+/// whenever the certificate, key, or client CA file changes; a change that
+/// fails to load keeps the previous configuration.
+#[derive(Debug)]
+pub struct AdminTls {
+    config: AdminTlsConfig,
+    current: Arc<RwLock<Arc<ServerConfig>>>,
+}
+
+impl AdminTls {
+    pub fn load(config: &AdminTlsConfig) -> Result<Self> {
+        Ok(Self {
+            current: Arc::new(RwLock::new(Arc::new(server_config(config)?))),
+            config: config.clone(),
+        })
+    }
+
+    /// Whether certificates issued by `client_ca` authenticate clients.
+    pub fn client_certificates(&self) -> bool {
+        self.config.client_ca.is_some()
+    }
+
+    fn watch(&self) -> Result<JoinHandle<()>> {
+        let directories: BTreeSet<&Path> = [
+            Some(&self.config.cert),
+            Some(&self.config.key),
+            self.config.client_ca.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|path| match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        })
+        .collect();
+        let inotify = Inotify::init().context("Failed to initialize inotify")?;
+        for directory in directories {
+            inotify
+                .watches()
+                .add(directory, RELOAD_EVENTS)
+                .with_context(|| format!("Failed to watch {}", directory.display()))?;
+        }
+        let mut events = inotify
+            .into_event_stream([0u8; 4096])
+            .context("Failed to read inotify events")?;
+
+        let config = self.config.clone();
+        let current = self.current.clone();
+        Ok(tokio::spawn(async move {
+            while let Some(event) = events.next().await {
+                match event {
+                    Ok(event) => {
+                        debug!(
+                            "Admin TLS directory event {:?} on {:?}",
+                            event.mask, event.name
+                        );
+                        reload(&config, &current);
+                    }
+                    Err(watch_error) => {
+                        warn!("Admin TLS certificate watch error: {watch_error}");
+                    }
+                }
+            }
+
+            warn!("Admin TLS certificate watch ended unexpectedly");
+        }))
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(
+            self.current
+                .read()
+                .expect("TLS config lock poisoned")
+                .clone(),
+        )
+    }
+}
+
+fn reload(config: &AdminTlsConfig, current: &RwLock<Arc<ServerConfig>>) {
+    match server_config(config) {
+        Ok(next) => {
+            *current.write().expect("TLS config lock poisoned") = Arc::new(next);
+            info!("Reloaded admin TLS certificate {}", config.cert.display());
+        }
+        Err(error) => {
+            warn!("Failed to reload admin TLS certificates, keeping the previous ones: {error:#}")
+        }
+    }
+}
+
+fn server_config(config: &AdminTlsConfig) -> Result<ServerConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let certs = read_certificates(&config.cert, "certificate")?;
+    let key = PrivateKeyDer::from_pem_file(&config.key)
+        .with_context(|| format!("Failed to read admin TLS key {}", config.key.display()))?;
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .context("Failed to configure admin TLS protocol versions")?;
+    let builder = match &config.client_ca {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_certificates(path, "client CA")? {
+                roots
+                    .add(cert)
+                    .with_context(|| format!("Invalid admin TLS client CA {}", path.display()))?;
+            }
+            // Clients without a certificate may still use Basic auth, and
+            // probes reach /healthz and /readyz without one.
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .allow_unauthenticated()
+                .build()
+                .context("Failed to configure admin TLS client verification")?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut server = builder.with_single_cert(certs, key).with_context(|| {
+        format!(
+            "Admin TLS key {} does not match certificate {}",
+            config.key.display(),
+            config.cert.display()
+        )
+    })?;
+    server.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(server)
+}
+
+fn read_certificates(path: &Path, kind: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read admin TLS {kind} {}", path.display()))?;
+    if certs.is_empty() {
+        bail!(
+            "Admin TLS {kind} {} contains no certificates",
+            path.display()
+        );
+    }
+    Ok(certs)
+}
+
+/// Client of an HTTPS admin connection, available to handlers as
+/// `ConnectInfo<TlsClient>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsClient {
+    pub address: SocketAddr,
+    /// Name of the certificate the client presented, which was issued by
+    /// `client_ca`; the handshake fails for any other certificate. `None`
+    /// when the client presented no certificate.
+    pub certificate: Option<Arc<str>>,
+}
+
+impl Connected<IncomingStream<'_, TlsListener>> for TlsClient {
+    fn connect_info(stream: IncomingStream<'_, TlsListener>) -> Self {
+        stream.remote_addr().clone()
+    }
+}
+
+/// Names a client certificate by its subject common name, else its first DNS
+/// or URI subject alternative name, else its serial number.
+fn certificate_name(cert: &CertificateDer<'_>) -> String {
+    let Ok(cert) = EndEntityCert::try_from(cert) else {
+        return "an unparseable certificate".to_string();
+    };
+    common_name(cert.subject())
+        .or_else(|| cert.valid_dns_names().next())
+        .or_else(|| cert.valid_uri_names().next())
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            let serial: String = cert
+                .serial()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect();
+            format!("serial {serial}")
+        })
+}
+
+/// First commonName in a DER `Name`, given without its outer SEQUENCE.
+fn common_name(mut name: &[u8]) -> Option<&str> {
+    while let Some((0x31, mut attributes, rest)) = der_element(name) {
+        while let Some((0x30, attribute, next)) = der_element(attributes) {
+            if let Some((0x06, COMMON_NAME, value)) = der_element(attribute)
+                && let Some((tag, value, _)) = der_element(value)
+                && NAME_STRING_TAGS.contains(&tag)
+            {
+                return std::str::from_utf8(value).ok();
+            }
+            attributes = next;
+        }
+        name = rest;
+    }
+    None
+}
+
+/// Splits one DER element off `input` as its tag, contents, and the bytes
+/// that follow it.
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&length, input) = input.split_first()?;
+    let (length, input) = if length < 0x80 {
+        (usize::from(length), input)
+    } else {
+        let octets = usize::from(length & 0x7f);
+        if octets == 0 || octets > size_of::<u32>() || input.len() < octets {
+            return None;
+        }
+        let (length, input) = input.split_at(octets);
+        let length = length
+            .iter()
+            .fold(0, |length, &octet| length << 8 | usize::from(octet));
+        (length, input)
+    };
+    if input.len() < length {
+        return None;
+    }
+    let (contents, rest) = input.split_at(length);
+    Some((tag, contents, rest))
+}
+
+/// Accepts TCP connections and hands them to the HTTP server once their
+/// TLS handshake completes. Handshakes run in their own tasks so a slow
+/// client cannot hold up the others.
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    accepted: mpsc::Receiver<(TlsStream<TcpStream>, TlsClient)>,
+    accept_task: JoinHandle<()>,
+    watch_task: JoinHandle<()>,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, tls: AdminTls) -> Result<Self> {
+        let local_addr = listener
+            .local_addr()
+            .context("Failed to read admin HTTPS server address")?;
+        let watch_task = tls.watch()?;
+        let (accepted_tx, accepted) = mpsc::channel(ACCEPTED_BACKLOG);
+        let accept_task =
+            tokio::spawn(async move {
+                loop {
+                    let (stream, address) = match listener.accept().await {
+                        Ok(connection) => connection,
+                        Err(error) => {
+                            warn!("Admin HTTPS server failed to accept a connection: {error}");
+                            tokio::time::sleep(ACCEPT_RETRY).await;
+                            continue;
+                        }
+                    };
+                    let acceptor = tls.acceptor();
+                    let accepted_tx = accepted_tx.clone();
+                    tokio::spawn(async move {
+                        let stream =
+                            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream))
+                                .await
+                            {
+                                Ok(Ok(stream)) => stream,
+                                Ok(Err(error)) => {
+                                    debug!("Admin TLS handshake with {address} failed: {error}");
+                                    return;
+                                }
+                                Err(_) => {
+                                    debug!("Admin TLS handshake with {address} timed out");
+                                    return;
+                                }
+                            };
+                        let certificate = stream
+                            .get_ref()
+                            .1
+                            .peer_certificates()
+                            .and_then(<[_]>::first)
+                            .map(|cert| certificate_name(cert).into());
+                        let client = TlsClient {
+                            address,
+                            certificate,
+                        };
+                        let _ = accepted_tx.send((stream, client)).await;
+                    });
+                }
+            });
+        Ok(Self {
+            local_addr,
+            accepted,
+            accept_task,
+            watch_task,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = TlsClient;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.accepted.recv().await {
+            Some(connection) => connection,
+            // The accept task only ends when this listener is dropped.
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(TlsClient {
+            address: self.local_addr,
+            certificate: None,
+        })
+    }
+}
+
+impl Drop for TlsListener {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+        self.watch_task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::extract::ConnectInfo;
+    use axum::routing::get;
+    use rcgen::{
+        BasicConstraints, CertificateParams, CertifiedIssuer, DistinguishedName, DnType,
+        ExtendedKeyUsagePurpose, IsCa, KeyPair,
+    };
+    use std::path::PathBuf;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("xlb-admin-tls-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create test directory");
+        dir
+    }
+
+    fn certificate_authority(name: &str) -> CertifiedIssuer<'static, KeyPair> {
+        let mut params = CertificateParams::default();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.distinguished_name.push(DnType::CommonName, name);
+        CertifiedIssuer::self_signed(params, KeyPair::generate().expect("generate CA key"))
+            .expect("sign CA certificate")
+    }
+
+    /// Issues a certificate, returning its PEM certificate, PEM key, and DER.
+    fn issue(
+        ca: &CertifiedIssuer<'static, KeyPair>,
+        common_name: Option<&str>,
+        dns_names: &[&str],
+        usage: ExtendedKeyUsagePurpose,
+    ) -> (String, String, CertificateDer<'static>) {
+        let mut params = CertificateParams::new(
+            dns_names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .expect("valid subject alternative names");
+        params.distinguished_name = DistinguishedName::new();
+        if let Some(common_name) = common_name {
+            params
+                .distinguished_name
+                .push(DnType::CommonName, common_name);
+        }
+        params.extended_key_usages = vec![usage];
+        let key = KeyPair::generate().expect("generate key");
+        let cert = params.signed_by(&key, ca).expect("sign certificate");
+        (cert.pem(), key.serialize_pem(), cert.der().clone())
+    }
+
+    fn client(
+        ca: &CertifiedIssuer<'static, KeyPair>,
+        identity: Option<(&str, &str)>,
+    ) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder()
+            .add_root_certificate(
+                reqwest::Certificate::from_pem(ca.pem().as_bytes()).expect("valid CA"),
+            )
+            .tls_info(true)
+            .timeout(Duration::from_secs(5));
+        if let Some((cert, key)) = identity {
+            builder = builder.identity(
+                reqwest::Identity::from_pem(format!("{cert}{key}").as_bytes())
+                    .expect("valid identity"),
+            );
+        }
+        builder.build().expect("build client")
+    }
+
+    /// Serves the name of the client certificate, and whether the client
+    /// presented none, from the returned port.
+    async fn serve(tls: AdminTls) -> u16 {
+        let listener = TlsListener::new(TcpListener::bind("127.0.0.1:0").await.expect("bind"), tls)
+            .expect("start TLS listener");
+        let port = listener.local_addr().expect("local address").address.port();
+        let app = Router::new().route(
+            "/",
+            get(|ConnectInfo(client): ConnectInfo<TlsClient>| async move {
+                client
+                    .certificate
+                    .map_or_else(|| "anonymous".to_string(), |name| name.to_string())
+            }),
+        );
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<TlsClient>(),
+            )
+            .await
+        });
+        port
+    }
+
+    #[test]
+    fn certificate_name_prefers_the_common_name() {
+        let ca = certificate_authority("XLB test CA");
+        let client = ExtendedKeyUsagePurpose::ClientAuth;
+        let name = |common_name, dns_names| {
+            certificate_name(&issue(&ca, common_name, dns_names, client.clone()).2)
+        };
+
+        assert_eq!(name(Some("deploy-bot"), &["bot.example.com"]), "deploy-bot");
+        assert_eq!(name(None, &["bot.example.com"]), "bot.example.com");
+        assert!(name(None, &[]).starts_with("serial "));
+        assert_eq!(
+            certificate_name(&CertificateDer::from(vec![0x30, 0x00])),
+            "an unparseable certificate"
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_https_and_authenticates_client_certificates() {
+        let dir = test_dir("round-trip");
+        let ca = certificate_authority("XLB test CA");
+        let other_ca = certificate_authority("Other CA");
+        let server = |dir: &Path| {
+            let (cert, key, der) = issue(
+                &ca,
+                Some("localhost"),
+                &["localhost"],
+                ExtendedKeyUsagePurpose::ServerAuth,
+            );
+            std::fs::write(dir.join("tls.crt"), cert).expect("write certificate");
+            std::fs::write(dir.join("tls.key"), key).expect("write key");
+            der
+        };
+        let first = server(&dir);
+        std::fs::write(dir.join("ca.crt"), ca.pem()).expect("write client CA");
+        let tls = AdminTls::load(&AdminTlsConfig {
+            cert: dir.join("tls.crt"),
+            key: dir.join("tls.key"),
+            client_ca: Some(dir.join("ca.crt")),
+        })
+        .expect("load admin TLS");
+        let url = format!("https://localhost:{}/", serve(tls).await);
+        let get = |client: reqwest::Client| {
+            let url = url.clone();
+            async move {
+                let response = client.get(url).send().await?;
+                let peer = response
+                    .extensions()
+                    .get::<reqwest::tls::TlsInfo>()
+                    .and_then(|info| info.peer_certificate())
+                    .map(<[u8]>::to_vec);
+                Ok::<_, reqwest::Error>((response.text().await?, peer))
+            }
+        };
+
+        let (body, peer) = get(client(&ca, None)).await.expect("anonymous request");
+        assert_eq!(body, "anonymous");
+        assert_eq!(peer.as_deref(), Some(first.as_ref()));
+
+        let bot = issue(
+            &ca,
+            Some("deploy-bot"),
+            &[],
+            ExtendedKeyUsagePurpose::ClientAuth,
+        );
+        let (body, _) = get(client(&ca, Some((&bot.0, &bot.1))))
+            .await
+            .expect("request with a client certificate");
+        assert_eq!(body, "deploy-bot");
+
+        let intruder = issue(
+            &other_ca,
+            Some("deploy-bot"),
+            &[],
+            ExtendedKeyUsagePurpose::ClientAuth,
+        );
+        get(client(&ca, Some((&intruder.0, &intruder.1))))
+            .await
+            .expect_err("a certificate from another CA is refused");
+
+        // Renewed certificates apply to new connections without a restart.
+        let renewed = server(&dir);
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        loop {
+            let (_, peer) = get(client(&ca, None)).await.expect("request after renewal");
+            if peer.as_deref() == Some(renewed.as_ref()) {
+                break;
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "renewed certificate was not loaded"
+            );
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        std::fs::remove_dir_all(dir).expect("remove test directory");
+    }
+
+    #[test]
+    fn load_reports_the_file_it_cannot_use() {
+        let dir = test_dir("invalid");
+        let missing = AdminTlsConfig {
+            cert: dir.join("missing.crt"),
+            key: dir.join("missing.key"),
+            client_ca: None,
+        };
+        let error = AdminTls::load(&missing).expect_err("a missing certificate cannot load");
+        assert!(format!("{error:#}").contains("missing.crt"), "{error:#}");
+
+        let empty = dir.join("empty.crt");
+        std::fs::write(&empty, "not a certificate\n").expect("write certificate");
+        let error = AdminTls::load(&AdminTlsConfig {
+            cert: empty,
+            ..missing
+        })
+        .expect_err("a file without certificates cannot load");
+        assert!(
+            error.to_string().contains("contains no certificates"),
+            "{error:#}"
+        );
+        std::fs::remove_dir_all(dir).expect("remove test directory");
+    }
+}